@@ -0,0 +1,96 @@
+use std::{
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Ends something once, e.g. a shutdown countdown, however many parties race to end it. Clones
+/// share the same state and can be sent to other threads.
+///
+/// Whoever ends the countdown cancels the token, including the countdown itself when it runs out.
+/// Only the first [`cancel`](Self::cancel) returns true, so only the first of a cancelling user,
+/// a command and an expiry acts.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<(Mutex<bool>, Condvar)>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token and wakes everyone waiting for it. Returns false if it was cancelled
+    /// already.
+    pub fn cancel(&self) -> bool {
+        let (cancelled, changed) = &*self.0;
+        let mut cancelled = cancelled.lock().unwrap_or_else(PoisonError::into_inner);
+        if *cancelled {
+            return false;
+        }
+        *cancelled = true;
+        changed.notify_all();
+        true
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0 .0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Blocks until the token is cancelled.
+    pub fn wait(&self) {
+        let (cancelled, changed) = &*self.0;
+        let cancelled = cancelled.lock().unwrap_or_else(PoisonError::into_inner);
+        let _cancelled = changed
+            .wait_while(cancelled, |cancelled| !*cancelled)
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+
+    /// Blocks until the token is cancelled or `timeout` passed. Returns whether it was cancelled.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let (cancelled, changed) = &*self.0;
+        let mut cancelled = cancelled.lock().unwrap_or_else(PoisonError::into_inner);
+        //wakeups can be spurious
+        while !*cancelled {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            cancelled = changed
+                .wait_timeout(cancelled, left)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn only_first_cancel_wins() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        assert!(clone.cancel());
+        assert!(!token.cancel());
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn waiters_wake_on_cancel() {
+        let token = CancellationToken::new();
+        assert!(!token.wait_timeout(Duration::from_millis(10)));
+        let waiter = {
+            let token = token.clone();
+            thread::spawn(move || token.wait_timeout(Duration::from_secs(10)))
+        };
+        thread::sleep(Duration::from_millis(20));
+        token.cancel();
+        assert!(waiter.join().unwrap());
+        //already cancelled tokens don't block
+        token.wait();
+    }
+}
@@ -0,0 +1,10 @@
+use crate::Heartbeat;
+
+/// Source of the clock readings stored as heartbeat and compared with it.
+///
+/// The monitor reads the clocks of the system. Tests supply fixed readings to simulate reboots
+/// without waiting for one.
+pub trait Clock {
+    /// Returns the current readings.
+    fn now(&self) -> Heartbeat;
+}
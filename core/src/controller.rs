@@ -0,0 +1,257 @@
+use std::{collections::VecDeque, str::FromStr, sync::Mutex};
+
+/// What happens when a shutdown prompt is requested while another one is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConcurrentPolicy {
+    /// The request joins the active prompt. It is cancelled or carried out together with it.
+    #[default]
+    Merge,
+    /// The request waits and is prompted for once the active prompt is cancelled.
+    Queue,
+    /// The request is dropped.
+    Ignore,
+}
+
+impl FromStr for ConcurrentPolicy {
+    type Err = ParsePolicyError;
+
+    /// Parses `merge`, `queue` or `ignore`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "merge" => Ok(ConcurrentPolicy::Merge),
+            "queue" => Ok(ConcurrentPolicy::Queue),
+            "ignore" => Ok(ConcurrentPolicy::Ignore),
+            other => Err(ParsePolicyError(other.to_owned())),
+        }
+    }
+}
+
+/// Error returned when a [`ConcurrentPolicy`] name is not recognized.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown concurrent prompt policy `{0}`(expected merge, queue or ignore)")]
+pub struct ParsePolicyError(String);
+
+/// Identifies a prompt admitted by [`ShutdownController`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PromptId(u64);
+
+/// How [`ShutdownController::request`] handled a request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Admission<T> {
+    /// No prompt was active. The caller shows the prompt for `payload`.
+    Start(PromptId, T),
+    /// The request joined the active prompt.
+    Merged(PromptId),
+    /// The request waits for the active prompt to be cancelled.
+    Queued,
+    /// The request was dropped.
+    Ignored,
+}
+
+/// Makes sure only one shutdown prompt is shown at a time and that the shutdown is signalled at
+/// most once, however many times a restart is detected.
+///
+/// `T` is whatever the caller needs to show a prompt later, e.g. its timeout and action. The
+/// controller doesn't show prompts itself. The caller reports back with
+/// [`cancelled`](Self::cancelled) or [`expired`](Self::expired) when the prompt it started ends.
+pub struct ShutdownController<T> {
+    policy: ConcurrentPolicy,
+    state: Mutex<State<T>>,
+}
+
+struct State<T> {
+    active: Option<PromptId>,
+    queue: VecDeque<T>,
+    next_id: u64,
+    //shutdown was signalled. no prompt is started afterwards.
+    expired: bool,
+}
+
+impl<T> ShutdownController<T> {
+    pub fn new(policy: ConcurrentPolicy) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(State {
+                active: None,
+                queue: VecDeque::new(),
+                next_id: 0,
+                expired: false,
+            }),
+        }
+    }
+
+    pub fn policy(&self) -> ConcurrentPolicy {
+        self.policy
+    }
+
+    /// Asks for a prompt for `payload`.
+    pub fn request(&self, payload: T) -> Admission<T> {
+        let mut state = self.state.lock().unwrap();
+        if state.expired {
+            return Admission::Ignored;
+        }
+        let Some(active) = state.active else {
+            return Admission::Start(state.start(), payload);
+        };
+        match self.policy {
+            ConcurrentPolicy::Merge => Admission::Merged(active),
+            ConcurrentPolicy::Queue => {
+                state.queue.push_back(payload);
+                Admission::Queued
+            }
+            ConcurrentPolicy::Ignore => Admission::Ignored,
+        }
+    }
+
+    /// Reports that prompt `id` was cancelled. Returns the queued request to prompt for next, if
+    /// any.
+    pub fn cancelled(&self, id: PromptId) -> Option<(PromptId, T)> {
+        let mut state = self.state.lock().unwrap();
+        if state.active != Some(id) {
+            return None;
+        }
+        state.active = None;
+        let payload = state.queue.pop_front()?;
+        Some((state.start(), payload))
+    }
+
+    /// Reports that prompt `id` expired. Returns true if the caller should go on with the
+    /// shutdown, which is the case only once. Queued requests are dropped.
+    pub fn expired(&self, id: PromptId) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.active != Some(id) || state.expired {
+            return false;
+        }
+        state.active = None;
+        state.expired = true;
+        state.queue.clear();
+        true
+    }
+
+    /// Returns true while a prompt is shown.
+    pub fn is_active(&self) -> bool {
+        self.state.lock().unwrap().active.is_some()
+    }
+
+    /// Number of requests waiting for the active prompt to be cancelled.
+    pub fn queued(&self) -> usize {
+        self.state.lock().unwrap().queue.len()
+    }
+}
+
+impl<T> State<T> {
+    fn start(&mut self) -> PromptId {
+        let id = PromptId(self.next_id);
+        self.next_id += 1;
+        self.active = Some(id);
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(controller: &ShutdownController<&'static str>, payload: &'static str) -> PromptId {
+        match controller.request(payload) {
+            Admission::Start(id, started) => {
+                assert_eq!(started, payload);
+                id
+            }
+            other => panic!("prompt was not started: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn first_request_starts_prompt() {
+        let controller = ShutdownController::new(ConcurrentPolicy::Merge);
+        assert!(!controller.is_active());
+        start(&controller, "first");
+        assert!(controller.is_active());
+    }
+
+    #[test]
+    fn merge_joins_active_prompt() {
+        let controller = ShutdownController::new(ConcurrentPolicy::Merge);
+        let id = start(&controller, "first");
+        assert_eq!(controller.request("second"), Admission::Merged(id));
+        assert_eq!(controller.cancelled(id), None);
+        assert!(!controller.is_active());
+    }
+
+    #[test]
+    fn ignore_drops_request() {
+        let controller = ShutdownController::new(ConcurrentPolicy::Ignore);
+        let id = start(&controller, "first");
+        assert_eq!(controller.request("second"), Admission::Ignored);
+        assert_eq!(controller.queued(), 0);
+        assert_eq!(controller.cancelled(id), None);
+    }
+
+    #[test]
+    fn queue_prompts_again_after_cancel() {
+        let controller = ShutdownController::new(ConcurrentPolicy::Queue);
+        let first = start(&controller, "first");
+        assert_eq!(controller.request("second"), Admission::Queued);
+        assert_eq!(controller.request("third"), Admission::Queued);
+        assert_eq!(controller.queued(), 2);
+
+        let (second, payload) = controller.cancelled(first).unwrap();
+        assert_eq!(payload, "second");
+        assert_ne!(second, first);
+        assert!(controller.is_active());
+
+        let (_, payload) = controller.cancelled(second).unwrap();
+        assert_eq!(payload, "third");
+        assert_eq!(controller.queued(), 0);
+    }
+
+    #[test]
+    fn queue_is_dropped_on_expiry() {
+        let controller = ShutdownController::new(ConcurrentPolicy::Queue);
+        let id = start(&controller, "first");
+        assert_eq!(controller.request("second"), Admission::Queued);
+        assert!(controller.expired(id));
+        assert_eq!(controller.queued(), 0);
+        assert!(!controller.is_active());
+    }
+
+    #[test]
+    fn shutdown_is_signalled_once() {
+        let controller = ShutdownController::new(ConcurrentPolicy::Merge);
+        let id = start(&controller, "first");
+        assert!(controller.expired(id));
+        assert!(!controller.expired(id));
+        assert_eq!(controller.request("second"), Admission::Ignored);
+    }
+
+    #[test]
+    fn stale_prompt_is_ignored() {
+        let controller = ShutdownController::new(ConcurrentPolicy::Queue);
+        let first = start(&controller, "first");
+        controller.request("second");
+        let (second, _) = controller.cancelled(first).unwrap();
+        //first prompt reporting late must not affect the second one
+        assert_eq!(controller.cancelled(first), None);
+        assert!(!controller.expired(first));
+        assert!(controller.is_active());
+        assert!(controller.expired(second));
+    }
+
+    #[test]
+    fn parses_policy_names() {
+        assert_eq!(
+            "merge".parse::<ConcurrentPolicy>().unwrap(),
+            ConcurrentPolicy::Merge
+        );
+        assert_eq!(
+            " queue ".parse::<ConcurrentPolicy>().unwrap(),
+            ConcurrentPolicy::Queue
+        );
+        assert_eq!(
+            "ignore".parse::<ConcurrentPolicy>().unwrap(),
+            ConcurrentPolicy::Ignore
+        );
+        assert!("both".parse::<ConcurrentPolicy>().is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use std::time::Duration;
+
+use crate::{Clock, Heartbeat, HeartbeatStorage};
+
+/// Allowed disagreement between clock readings. Covers rounding of the stored timestamp and the
+/// time it takes to read the clocks.
+const CLOCK_TOLERANCE: Duration = Duration::from_secs(5);
+
+/// Outcome of comparing the last heartbeat with the current time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Computer was restarted within the threshold. Shutdown sequence should be initiated.
+    Restarted { elapsed: Duration },
+    /// Last heartbeat is older than the threshold.
+    Normal { elapsed: Duration },
+    /// Last heartbeat was written in the current boot. The program was restarted, not the computer.
+    SameBoot { elapsed: Duration },
+    /// Wall clock contradicts the uptime, so the wall clock was changed since the last heartbeat
+    /// (NTP correction, manual change, wrong time zone of the hardware clock). A restart can't be
+    /// confirmed and must not be acted on.
+    ClockChanged { elapsed: Duration },
+}
+
+impl Decision {
+    /// Wall clock time since the last heartbeat.
+    pub fn elapsed(&self) -> Duration {
+        match *self {
+            Decision::Restarted { elapsed }
+            | Decision::Normal { elapsed }
+            | Decision::SameBoot { elapsed }
+            | Decision::ClockChanged { elapsed } => elapsed,
+        }
+    }
+}
+
+/// Decides whether the computer was restarted unexpectedly.
+///
+/// If the duration since the last heartbeat is less than the threshold, the program was stopped
+/// and started again in a short time, which indicates an unexpected restart or a similar event.
+///
+/// If both heartbeats carry a boot id, an unchanged boot id proves that there was no reboot in
+/// between. Wall clock time alone is not trusted. It is cross-checked against the uptime:
+/// - if uptime hasn't started over since the last heartbeat, the uptime difference must agree
+///   with the wall clock difference;
+/// - otherwise the computer was booted after the last heartbeat, so the wall clock difference
+///   can't be shorter than the current uptime.
+pub struct RestartDetector {
+    threshold: Duration,
+}
+
+impl RestartDetector {
+    pub fn new(threshold: Duration) -> Self {
+        Self { threshold }
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// Compares the heartbeat kept in `storage` with the current readings of `clock`.
+    pub fn check(
+        &self,
+        storage: &mut impl HeartbeatStorage,
+        clock: &impl Clock,
+    ) -> anyhow::Result<Decision> {
+        let last_heartbeat = storage.read()?;
+        Ok(self.evaluate(last_heartbeat, clock.now()))
+    }
+
+    #[tracing::instrument(level = "debug", skip(self), fields(threshold_ms = self.threshold.as_millis() as u64), ret)]
+    pub fn evaluate(&self, last_heartbeat: Heartbeat, now: Heartbeat) -> Decision {
+        let wall = now.time - last_heartbeat.time;
+        let elapsed = wall.abs().to_std().unwrap_or(Duration::MAX);
+        //last heartbeat is in the future. clock was set backwards.
+        if wall < chrono::Duration::zero() && elapsed > CLOCK_TOLERANCE {
+            return Decision::ClockChanged { elapsed };
+        }
+
+        if let (Some(last_boot), Some(boot)) = (last_heartbeat.boot_id, now.boot_id) {
+            if last_boot == boot {
+                return Decision::SameBoot { elapsed };
+            }
+        }
+
+        match (last_heartbeat.uptime, now.uptime) {
+            (Some(last_uptime), Some(uptime)) if uptime >= last_uptime => {
+                //no real reboot in between. restart is confirmed only if both clocks agree.
+                let monotonic = uptime - last_uptime;
+                if (monotonic < self.threshold) != (elapsed < self.threshold)
+                    && monotonic.abs_diff(elapsed) > CLOCK_TOLERANCE
+                {
+                    return Decision::ClockChanged { elapsed };
+                }
+            }
+            (Some(_), Some(uptime)) if elapsed + CLOCK_TOLERANCE < uptime => {
+                //heartbeat is dated after the current boot started
+                return Decision::ClockChanged { elapsed };
+            }
+            _ => {}
+        }
+
+        if elapsed < self.threshold {
+            Decision::Restarted { elapsed }
+        } else {
+            Decision::Normal { elapsed }
+        }
+    }
+
+    /// Re-evaluates `decision` after the wall clock was corrected between the two heartbeats,
+    /// e.g. by the Windows Time service. `correction` is the sum of new minus old time of the
+    /// corrections. A clock set back makes the time between the heartbeats look shorter than it
+    /// was, so a restart that only appears to be within the threshold can't be acted on.
+    pub fn corrected(&self, decision: Decision, correction: chrono::Duration) -> Decision {
+        let Decision::Restarted { elapsed } = decision else {
+            return decision;
+        };
+        let measured =
+            chrono::Duration::from_std(elapsed).unwrap_or_else(|_| chrono::Duration::max_value());
+        match (measured - correction).to_std() {
+            Ok(actual) if actual < self.threshold => decision,
+            _ => Decision::ClockChanged { elapsed },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::*;
+    use crate::{HeartbeatWriter, MemoryHeartbeat};
+
+    const THRESHOLD: Duration = Duration::from_secs(100);
+
+    struct FixedClock(Heartbeat);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> Heartbeat {
+            self.0
+        }
+    }
+
+    fn time(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn heartbeat(secs: i64, uptime: u64, boot_id: u32) -> Heartbeat {
+        Heartbeat {
+            time: time(secs),
+            uptime: Some(Duration::from_secs(uptime)),
+            boot_id: Some(boot_id),
+        }
+    }
+
+    fn check(last: Heartbeat, now: Heartbeat) -> Decision {
+        RestartDetector::new(THRESHOLD)
+            .check(&mut MemoryHeartbeat(Some(last)), &FixedClock(now))
+            .unwrap()
+    }
+
+    #[test]
+    fn reboot_within_threshold_is_restart() {
+        let decision = check(heartbeat(0, 3600, 1), heartbeat(50, 20, 2));
+        assert_eq!(
+            decision,
+            Decision::Restarted {
+                elapsed: Duration::from_secs(50)
+            }
+        );
+    }
+
+    #[test]
+    fn sub_second_threshold_is_exact() {
+        let last = Heartbeat {
+            time: time(0),
+            uptime: Some(Duration::from_secs(3600)),
+            boot_id: Some(1),
+        };
+        let at = |millis: i64, uptime_ms: u64| Heartbeat {
+            time: time(0) + chrono::Duration::milliseconds(millis),
+            uptime: Some(Duration::from_millis(uptime_ms)),
+            boot_id: Some(2),
+        };
+        let detector = RestartDetector::new(Duration::from_millis(500));
+        assert_eq!(
+            detector.evaluate(last, at(300, 100)),
+            Decision::Restarted {
+                elapsed: Duration::from_millis(300)
+            }
+        );
+        assert!(matches!(
+            detector.evaluate(last, at(700, 100)),
+            Decision::Normal { .. }
+        ));
+    }
+
+    #[test]
+    fn reboot_after_threshold_is_normal() {
+        let decision = check(heartbeat(0, 3600, 1), heartbeat(600, 30, 2));
+        assert!(matches!(decision, Decision::Normal { .. }));
+    }
+
+    #[test]
+    fn same_boot_is_not_restart() {
+        let decision = check(heartbeat(0, 3600, 1), heartbeat(50, 3650, 1));
+        assert!(matches!(decision, Decision::SameBoot { .. }));
+    }
+
+    #[test]
+    fn heartbeat_dated_after_boot_is_clock_change() {
+        //computer has been up for 10 minutes, yet the last heartbeat is 50 seconds old
+        let decision = check(heartbeat(0, 3600, 1), heartbeat(50, 600, 2));
+        assert!(matches!(decision, Decision::ClockChanged { .. }));
+    }
+
+    #[test]
+    fn heartbeat_set_in_future_is_clock_change() {
+        let decision = check(heartbeat(600, 3600, 1), heartbeat(50, 20, 2));
+        assert!(matches!(decision, Decision::ClockChanged { .. }));
+    }
+
+    #[test]
+    fn restart_straddling_clock_set_back_is_clock_change() {
+        let detector = RestartDetector::new(THRESHOLD);
+        let decision = detector.evaluate(heartbeat(0, 3600, 1), heartbeat(50, 20, 2));
+        //clock was set back by two minutes while the computer was off
+        let corrected = detector.corrected(decision, chrono::Duration::seconds(-120));
+        assert!(matches!(corrected, Decision::ClockChanged { .. }));
+    }
+
+    #[test]
+    fn small_clock_correction_keeps_restart() {
+        let detector = RestartDetector::new(THRESHOLD);
+        let decision = detector.evaluate(heartbeat(0, 3600, 1), heartbeat(50, 20, 2));
+        assert_eq!(
+            detector.corrected(decision, chrono::Duration::seconds(-10)),
+            decision
+        );
+        let normal = detector.evaluate(heartbeat(0, 3600, 1), heartbeat(600, 30, 2));
+        assert_eq!(
+            detector.corrected(normal, chrono::Duration::seconds(-120)),
+            normal
+        );
+    }
+
+    #[test]
+    fn missing_heartbeat_is_error() {
+        let detector = RestartDetector::new(THRESHOLD);
+        let clock = FixedClock(heartbeat(50, 20, 2));
+        assert!(detector.check(&mut MemoryHeartbeat(None), &clock).is_err());
+    }
+
+    #[test]
+    fn written_heartbeat_is_read_back() {
+        let mut writer = HeartbeatWriter::new(Cursor::new(Vec::new()));
+        writer.write(heartbeat(0, 3600, 1)).unwrap();
+        let decision = RestartDetector::new(THRESHOLD)
+            .check(&mut writer, &FixedClock(heartbeat(50, 20, 2)))
+            .unwrap();
+        assert!(matches!(decision, Decision::Restarted { .. }));
+    }
+}
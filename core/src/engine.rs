@@ -0,0 +1,194 @@
+use std::{
+    sync::{
+        atomic::{self, AtomicBool, AtomicI64},
+        mpsc, Mutex,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::{CancellationToken, ConcurrentPolicy, Decision, ShutdownController};
+
+/// Something that happened in an [`Engine`]. Delivered to every subscriber.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// The last heartbeat was compared with the current one on startup.
+    Evaluated(Decision),
+    /// A heartbeat was written.
+    HeartbeatWritten(DateTime<Utc>),
+    Paused,
+    Resumed,
+    /// A shutdown countdown was started. `timeout` is `None` if the prompt waits for the user to
+    /// confirm instead of running out.
+    CountdownStarted {
+        timeout: Option<Duration>,
+    },
+    /// The countdown was cancelled by the user, a command or another session.
+    CountdownCancelled,
+    /// The user postponed the prompted action until `until`. The prompt is shown again then.
+    CountdownSnoozed {
+        until: DateTime<Utc>,
+    },
+    /// The countdown ran out or the user confirmed. The prompted action is carried out.
+    CountdownExpired,
+}
+
+/// Request issued to an [`Engine`] by a frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Stop writing heartbeats and cancel the pending countdown.
+    Pause,
+    Resume,
+    /// Cancel the pending countdown.
+    CancelCountdown,
+}
+
+/// Snapshot of the state of an [`Engine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineState {
+    pub paused: bool,
+    pub countdown_pending: bool,
+    /// Time of the last heartbeat written since the engine was created.
+    pub last_heartbeat: Option<DateTime<Utc>>,
+}
+
+/// Runtime state of the monitor shared between heartbeat, prompts and frontends.
+///
+/// Frontends [`subscribe`](Self::subscribe) to [`Event`]s, query the [`state`](Self::state) and
+/// issue [`Command`]s, while the host reports what happens through the other methods. `P` is the
+/// payload of prompts admitted by the [`ShutdownController`].
+pub struct Engine<P> {
+    paused: AtomicBool,
+    //unix timestamp of last heartbeat. 0 if nothing is written yet.
+    last_updated: AtomicI64,
+    //token of the running countdown
+    countdown: Mutex<Option<CancellationToken>>,
+    controller: ShutdownController<P>,
+    subscribers: Mutex<Vec<mpsc::Sender<Event>>>,
+}
+
+impl<P> Engine<P> {
+    pub fn new(concurrent_prompts: ConcurrentPolicy) -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            last_updated: AtomicI64::new(0),
+            countdown: Mutex::new(None),
+            controller: ShutdownController::new(concurrent_prompts),
+            subscribers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns a receiver of all events published from now on. Dropping it unsubscribes.
+    pub fn subscribe(&self) -> mpsc::Receiver<Event> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Delivers `event` to every subscriber.
+    pub fn publish(&self, event: Event) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn state(&self) -> EngineState {
+        EngineState {
+            paused: self.is_paused(),
+            countdown_pending: self.is_countdown_pending(),
+            last_heartbeat: self.last_updated(),
+        }
+    }
+
+    /// Carries out `command`. Returns false if it had nothing to act on, e.g. cancelling without
+    /// a pending countdown.
+    pub fn execute(&self, command: Command) -> bool {
+        match command {
+            Command::Pause => {
+                self.set_paused(true);
+                //pending shutdown must not fire while paused
+                self.cancel_countdown();
+                true
+            }
+            Command::Resume => {
+                self.set_paused(false);
+                true
+            }
+            Command::CancelCountdown => self.cancel_countdown(),
+        }
+    }
+
+    pub fn controller(&self) -> &ShutdownController<P> {
+        &self.controller
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(atomic::Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, atomic::Ordering::SeqCst) != paused {
+            self.publish(if paused {
+                Event::Paused
+            } else {
+                Event::Resumed
+            });
+        }
+    }
+
+    pub fn last_updated(&self) -> Option<DateTime<Utc>> {
+        match self.last_updated.load(atomic::Ordering::SeqCst) {
+            0 => None,
+            time => Utc.timestamp_opt(time, 0).single(),
+        }
+    }
+
+    pub fn set_last_updated(&self, time: DateTime<Utc>) {
+        self.last_updated
+            .store(time.timestamp(), atomic::Ordering::SeqCst);
+        self.publish(Event::HeartbeatWritten(time));
+    }
+
+    /// Registers a new countdown of `timeout`, or one that never runs out if `None`. The returned
+    /// token ends it, and the host reports its end with [`countdown_ended`](Self::countdown_ended).
+    pub fn start_countdown(&self, timeout: Option<Duration>) -> CancellationToken {
+        let token = CancellationToken::new();
+        *self.countdown.lock().unwrap() = Some(token.clone());
+        self.publish(Event::CountdownStarted { timeout });
+        token
+    }
+
+    /// Reports that the countdown was cancelled, or expired or was confirmed.
+    pub fn countdown_ended(&self, cancelled: bool) {
+        self.publish(if cancelled {
+            Event::CountdownCancelled
+        } else {
+            Event::CountdownExpired
+        });
+    }
+
+    /// Reports that the user snoozed the countdown until `until`.
+    pub fn countdown_snoozed(&self, until: DateTime<Utc>) {
+        self.publish(Event::CountdownSnoozed { until });
+    }
+
+    /// Countdown is pending until its token is cancelled, which expiring does as well.
+    pub fn is_countdown_pending(&self) -> bool {
+        self.countdown
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|token| !token.is_cancelled())
+    }
+
+    /// Returns false if there was no pending countdown.
+    pub fn cancel_countdown(&self) -> bool {
+        self.countdown
+            .lock()
+            .unwrap()
+            .take()
+            .is_some_and(|token| token.cancel())
+    }
+}
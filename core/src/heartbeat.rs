@@ -0,0 +1,641 @@
+use std::{
+    io::{Read, Seek, SeekFrom, Write},
+    str::FromStr,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+/// Clock readings taken when a heartbeat is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Heartbeat {
+    /// Wall clock time.
+    pub time: DateTime<Utc>,
+    /// Time since the computer was started. Unlike wall clock time it is never adjusted, but it
+    /// starts over on every boot. Missing in heartbeats written by older versions.
+    pub uptime: Option<Duration>,
+    /// Identifier of the boot the heartbeat was written in. Missing in heartbeats written by older
+    /// versions.
+    pub boot_id: Option<u32>,
+}
+
+/// Encoding of the wall clock time of a heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+    /// Unix timestamp in whole seconds. Readable by every version.
+    #[default]
+    Seconds,
+    /// Unix timestamp in seconds with three fractional digits.
+    Milliseconds,
+    /// RFC 3339 in UTC with millisecond precision, e.g. `2023-10-01T12:00:00.250Z`.
+    Rfc3339,
+}
+
+impl TimestampFormat {
+    fn format(self, time: DateTime<Utc>) -> String {
+        match self {
+            TimestampFormat::Seconds => time.timestamp().to_string(),
+            TimestampFormat::Milliseconds => {
+                let millis = time.timestamp_millis();
+                format!("{}.{:03}", millis.div_euclid(1000), millis.rem_euclid(1000))
+            }
+            TimestampFormat::Rfc3339 => time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        }
+    }
+}
+
+impl FromStr for TimestampFormat {
+    type Err = ParseFormatError;
+
+    /// Parses `seconds`, `milliseconds` or `rfc3339`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "seconds" => Ok(TimestampFormat::Seconds),
+            "milliseconds" => Ok(TimestampFormat::Milliseconds),
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            other => Err(ParseFormatError(other.to_owned())),
+        }
+    }
+}
+
+/// Error returned when a [`TimestampFormat`] name is not recognized.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown timestamp format `{0}`(expected seconds, milliseconds or rfc3339)")]
+pub struct ParseFormatError(String);
+
+/// Reads and writes the heartbeat, the clock readings of the last time the program was alive.
+///
+/// The heartbeat is stored as timestamp in [`TimestampFormat`] followed by uptime in milliseconds
+/// and boot id. Missing readings are stored as `-`.
+pub struct HeartbeatWriter<F> {
+    file: F,
+    format: TimestampFormat,
+}
+
+impl<F: Read + Write + Seek> HeartbeatWriter<F> {
+    pub fn new(file: F) -> Self {
+        Self::with_format(file, TimestampFormat::default())
+    }
+
+    /// Creates a writer storing the time in `format`. Heartbeats are read in any format.
+    pub fn with_format(file: F, format: TimestampFormat) -> Self {
+        Self { file, format }
+    }
+
+    /// Returns the underlying file, e.g. to sync it to disk.
+    pub fn get_ref(&self) -> &F {
+        &self.file
+    }
+
+    /// Returns the last recorded heartbeat.
+    pub fn read(&mut self) -> anyhow::Result<Heartbeat> {
+        let mut heartbeat = String::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_string(&mut heartbeat)?;
+        parse_heartbeat(&heartbeat)
+    }
+
+    /// Records `heartbeat` as the last heartbeat.
+    pub fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        //file is never truncated, so pad the record to overwrite longer previous ones
+        let record = format!("{:<64}", format_heartbeat(heartbeat, self.format));
+        overwrite(&mut self.file, &record)
+    }
+}
+
+/// Keeps the heartbeat in two files written in turn, so the previous heartbeat stays readable
+/// when a write is torn by power loss.
+///
+/// Each record is a line holding a sequence number, the heartbeat as JSON document and a checksum
+/// of the rest of the line. The valid record with the highest sequence number is the last
+/// heartbeat. Records may also carry the [`Origin`] of the heartbeat.
+///
+/// With a key, an HMAC-SHA256 replaces the checksum, so records written by anyone who doesn't know
+/// the key are rejected.
+pub struct DoubleBufferedHeartbeat<F> {
+    files: [F; 2],
+    format: TimestampFormat,
+    key: Option<Vec<u8>>,
+    origin: Option<Origin>,
+    /// Origin of the newest record read.
+    last_origin: Option<Origin>,
+    /// Sequence number of the newest record, once the files were read.
+    seq: Option<u64>,
+}
+
+/// Process that wrote a heartbeat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Origin {
+    pub pid: u32,
+    /// Version of the program.
+    pub version: String,
+    pub hostname: String,
+}
+
+/// Version of the records written by [`DoubleBufferedHeartbeat`]. Records of older versions are
+/// migrated forward when read, so upgrading never turns the last heartbeat into a parse error.
+///
+/// 1. Bare unix timestamp of the earliest versions.
+/// 2. Timestamp, uptime and boot id separated by spaces, as written by [`HeartbeatWriter`].
+/// 3. JSON document, optionally with the [`Origin`].
+pub const RECORD_VERSION: u32 = 3;
+
+/// Forward migrations of records. The one at index `i` migrates version `i + 1` to `i + 2`.
+const MIGRATIONS: [fn(Value) -> anyhow::Result<Value>; 2] = [v1_to_v2, v2_to_v3];
+
+/// Heartbeat record as stored by [`DoubleBufferedHeartbeat`].
+#[derive(Serialize, Deserialize)]
+struct Record {
+    /// [`RECORD_VERSION`] the record was written with. Missing in the first records of version 3.
+    #[serde(default)]
+    v: u32,
+    /// Wall clock time in [`TimestampFormat`].
+    time: String,
+    uptime_ms: Option<u64>,
+    boot_id: Option<u32>,
+    #[serde(flatten)]
+    origin: Option<Origin>,
+}
+
+impl<F: Read + Write + Seek> DoubleBufferedHeartbeat<F> {
+    /// Creates a storage writing the time in `format` to `a` and `b` in turn.
+    pub fn new(a: F, b: F, format: TimestampFormat) -> Self {
+        Self {
+            files: [a, b],
+            format,
+            key: None,
+            origin: None,
+            last_origin: None,
+            seq: None,
+        }
+    }
+
+    /// Authenticates records with `key`. Records without a valid HMAC are no longer read.
+    pub fn with_key(mut self, key: Vec<u8>) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Records `origin` along with every heartbeat.
+    pub fn with_origin(mut self, origin: Origin) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Returns where the heartbeat returned by the last [`read`](Self::read) came from, if it was
+    /// recorded.
+    pub fn last_origin(&self) -> Option<&Origin> {
+        self.last_origin.as_ref()
+    }
+
+    /// Returns both underlying files, e.g. to sync them to disk.
+    pub fn files(&self) -> &[F; 2] {
+        &self.files
+    }
+
+    /// Returns the newest valid heartbeat of both files.
+    pub fn read(&mut self) -> anyhow::Result<Heartbeat> {
+        let mut newest: Option<(u64, Heartbeat, Option<Origin>)> = None;
+        let mut error = None;
+        for file in &mut self.files {
+            match read_record(file, self.key.as_deref()) {
+                Ok((seq, heartbeat, origin))
+                    if newest.as_ref().is_none_or(|(newest, ..)| seq > *newest) =>
+                {
+                    newest = Some((seq, heartbeat, origin));
+                }
+                Ok(_) => {}
+                Err(e) => error = Some(e),
+            }
+        }
+        self.seq = Some(newest.as_ref().map_or(0, |(seq, ..)| *seq));
+        match (newest, error) {
+            (Some((_, heartbeat, origin)), _) => {
+                self.last_origin = origin;
+                Ok(heartbeat)
+            }
+            (None, Some(e)) => Err(e.context("no valid heartbeat in either file")),
+            (None, None) => Err(anyhow!("no valid heartbeat in either file")),
+        }
+    }
+
+    /// Records `heartbeat` in the file not holding the newest heartbeat.
+    pub fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        if self.seq.is_none() {
+            //continue the sequence, or the older file would outrank new records
+            let _ = self.read();
+        }
+        let seq = self.seq.unwrap_or_default() + 1;
+        let record = Record {
+            v: RECORD_VERSION,
+            time: self.format.format(heartbeat.time),
+            uptime_ms: heartbeat.uptime.map(|uptime| uptime.as_millis() as u64),
+            boot_id: heartbeat.boot_id,
+            origin: self.origin.clone(),
+        };
+        let body = format!("{seq} {}", serde_json::to_string(&record)?);
+        //line ends the record. padding overwrites shorter earlier ones, anything after is ignored.
+        let record = format!(
+            "{:<256}",
+            format!("{body} {}\n", seal(&body, self.key.as_deref()))
+        );
+        overwrite(&mut self.files[(seq % 2) as usize], &record)?;
+        self.seq = Some(seq);
+        Ok(())
+    }
+}
+
+/// Formats `heartbeat` as [`HeartbeatWriter`] stores it, readable by [`parse_heartbeat`].
+pub fn format_heartbeat(heartbeat: Heartbeat, format: TimestampFormat) -> String {
+    let uptime = heartbeat
+        .uptime
+        .map_or_else(|| "-".to_owned(), |uptime| uptime.as_millis().to_string());
+    let boot_id = heartbeat
+        .boot_id
+        .map_or_else(|| "-".to_owned(), |boot_id| boot_id.to_string());
+    format!("{} {uptime} {boot_id}", format.format(heartbeat.time))
+}
+
+fn overwrite(file: &mut (impl Write + Seek), record: &str) -> anyhow::Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(record.as_bytes())?;
+    file.flush()?;
+    Ok(())
+}
+
+fn read_record(
+    file: &mut (impl Read + Seek),
+    key: Option<&[u8]>,
+) -> anyhow::Result<(u64, Heartbeat, Option<Origin>)> {
+    let mut record = String::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_string(&mut record)?;
+    let (body, sum) = record
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim_end()
+        .rsplit_once(' ')
+        .ok_or_else(|| anyhow!("heartbeat record has no checksum"))?;
+    if !sealed(body, sum, key) {
+        return Err(match key {
+            Some(_) => anyhow!("heartbeat record is not authenticated(torn write or spoofed)"),
+            None => anyhow!("checksum of heartbeat record doesn't match(torn write)"),
+        });
+    }
+    let (seq, heartbeat) = body
+        .split_once(' ')
+        .ok_or_else(|| anyhow!("heartbeat record has no sequence number"))?;
+    let (heartbeat, origin) = decode_record(heartbeat)?;
+    Ok((seq.parse()?, heartbeat, origin))
+}
+
+/// Parses a heartbeat record of any version up to [`RECORD_VERSION`], including the bare
+/// timestamps of the earliest versions.
+pub fn parse_record(record: &str) -> anyhow::Result<Heartbeat> {
+    Ok(decode_record(record)?.0)
+}
+
+fn decode_record(record: &str) -> anyhow::Result<(Heartbeat, Option<Origin>)> {
+    let record: Record = serde_json::from_value(migrate_record(record)?)?;
+    let heartbeat = Heartbeat {
+        time: parse_timestamp(&record.time)?,
+        uptime: record.uptime_ms.map(Duration::from_millis),
+        boot_id: record.boot_id,
+    };
+    Ok((heartbeat, record.origin))
+}
+
+/// Brings `record` from the version it was written in to [`RECORD_VERSION`]. Records of a newer
+/// version, written by a newer program before a downgrade, are an error rather than guessed at.
+fn migrate_record(record: &str) -> anyhow::Result<Value> {
+    let record = record.trim();
+    let (version, mut value) = if record.starts_with('{') {
+        let value: Value = serde_json::from_str(record)?;
+        let version = match value.get("v") {
+            None => 3,
+            Some(v) => v
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .filter(|v| *v >= 3)
+                .ok_or_else(|| anyhow!("invalid heartbeat record version {v}"))?,
+        };
+        (version, value)
+    } else if record.split_whitespace().nth(1).is_none() {
+        (1, Value::String(record.to_owned()))
+    } else {
+        (2, Value::String(record.to_owned()))
+    };
+    if version > RECORD_VERSION {
+        return Err(anyhow!(
+            "heartbeat record version {version} is newer than {RECORD_VERSION}(written by a newer version)"
+        ));
+    }
+    for migrate in &MIGRATIONS[(version - 1) as usize..] {
+        value = migrate(value)?;
+    }
+    Ok(value)
+}
+
+/// Bare timestamp gains unknown uptime and boot id.
+fn v1_to_v2(value: Value) -> anyhow::Result<Value> {
+    let time = value
+        .as_str()
+        .ok_or_else(|| anyhow!("heartbeat record of version 1 is not a timestamp"))?;
+    Ok(Value::String(format!("{time} - -")))
+}
+
+/// Space separated fields become the JSON document.
+fn v2_to_v3(value: Value) -> anyhow::Result<Value> {
+    let fields = value
+        .as_str()
+        .ok_or_else(|| anyhow!("heartbeat record of version 2 is not text"))?;
+    let heartbeat = parse_heartbeat(fields)?;
+    let time = fields.split_whitespace().next().unwrap_or_default();
+    Ok(json!({
+        "v": 3,
+        "time": time,
+        "uptime_ms": heartbeat.uptime.map(|uptime| uptime.as_millis() as u64),
+        "boot_id": heartbeat.boot_id,
+    }))
+}
+
+/// Hex HMAC-SHA256 of `body` with `key`, or 32-bit FNV-1a without key. The checksum only guards
+/// against torn writes, not against tampering.
+fn seal(body: &str, key: Option<&[u8]>) -> String {
+    match key {
+        Some(key) => mac(key, body)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+        None => fnv(body),
+    }
+}
+
+//whether `sum` is the seal of `body`. macs are compared in constant time, so a spoofed record
+//can't be completed byte by byte.
+fn sealed(body: &str, sum: &str, key: Option<&[u8]>) -> bool {
+    match key {
+        Some(key) => {
+            let Some(sum) = decode_hex(sum) else {
+                return false;
+            };
+            mac(key, body).verify_slice(&sum).is_ok()
+        }
+        None => sum == fnv(body),
+    }
+}
+
+fn mac(key: &[u8], body: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac takes keys of any length");
+    mac.update(body.as_bytes());
+    mac
+}
+
+fn fnv(body: &str) -> String {
+    let hash = body.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    });
+    format!("{hash:08x}")
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Keeps the last heartbeat. [`HeartbeatWriter`] keeps it in a file.
+pub trait HeartbeatStorage {
+    /// Returns the last recorded heartbeat.
+    fn read(&mut self) -> anyhow::Result<Heartbeat>;
+
+    /// Records `heartbeat` as the last heartbeat.
+    fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()>;
+}
+
+impl<F: Read + Write + Seek> HeartbeatStorage for HeartbeatWriter<F> {
+    fn read(&mut self) -> anyhow::Result<Heartbeat> {
+        HeartbeatWriter::read(self)
+    }
+
+    fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        HeartbeatWriter::write(self, heartbeat)
+    }
+}
+
+impl<F: Read + Write + Seek> HeartbeatStorage for DoubleBufferedHeartbeat<F> {
+    fn read(&mut self) -> anyhow::Result<Heartbeat> {
+        DoubleBufferedHeartbeat::read(self)
+    }
+
+    fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        DoubleBufferedHeartbeat::write(self, heartbeat)
+    }
+}
+
+/// Keeps the heartbeat in memory only, e.g. for tests and for trying out the configuration. Nothing
+/// survives a restart, so no restart is ever detected with it.
+#[derive(Debug, Default)]
+pub struct MemoryHeartbeat(pub Option<Heartbeat>);
+
+impl HeartbeatStorage for MemoryHeartbeat {
+    fn read(&mut self) -> anyhow::Result<Heartbeat> {
+        self.0.ok_or_else(|| anyhow!("no heartbeat recorded yet"))
+    }
+
+    fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        self.0 = Some(heartbeat);
+        Ok(())
+    }
+}
+
+/// Parses a heartbeat written by [`HeartbeatWriter`] or a bare timestamp of older versions.
+pub fn parse_heartbeat(heartbeat: &str) -> anyhow::Result<Heartbeat> {
+    let mut fields = heartbeat.split_whitespace();
+    let time = parse_timestamp(fields.next().unwrap_or_default())?;
+    let uptime = fields
+        .next()
+        .filter(|uptime| *uptime != "-")
+        .map(|uptime| uptime.parse().map(Duration::from_millis))
+        .transpose()?;
+    let boot_id = fields
+        .next()
+        .filter(|boot_id| *boot_id != "-")
+        .map(str::parse)
+        .transpose()?;
+    Ok(Heartbeat {
+        time,
+        uptime,
+        boot_id,
+    })
+}
+
+/// Parses a timestamp in any [`TimestampFormat`].
+pub fn parse_timestamp(time: &str) -> anyhow::Result<DateTime<Utc>> {
+    let time = time.trim();
+    if time.contains('T') {
+        return Ok(DateTime::parse_from_rfc3339(time)?.with_timezone(&Utc));
+    }
+    let (secs, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let secs = secs.parse::<i64>()?;
+    let nanos = if fraction.is_empty() {
+        0
+    } else if fraction.len() <= 9 && fraction.bytes().all(|c| c.is_ascii_digit()) {
+        //"25" is 250ms
+        format!("{fraction:0<9}").parse::<u32>()?
+    } else {
+        return Err(anyhow!("Invalid timestamp: {time}"));
+    };
+    Utc.timestamp_opt(secs, nanos)
+        .single()
+        .ok_or_else(|| anyhow!("Invalid timestamp: {time}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn heartbeat(secs: i64) -> Heartbeat {
+        Heartbeat {
+            time: Utc.timestamp_opt(secs, 0).unwrap(),
+            uptime: Some(Duration::from_secs(secs as u64)),
+            boot_id: Some(7),
+        }
+    }
+
+    fn storage() -> DoubleBufferedHeartbeat<Cursor<Vec<u8>>> {
+        DoubleBufferedHeartbeat::new(
+            Cursor::new(Vec::new()),
+            Cursor::new(Vec::new()),
+            TimestampFormat::Seconds,
+        )
+    }
+
+    #[test]
+    fn newest_record_is_read() {
+        let mut storage = storage();
+        for secs in 100..105 {
+            storage.write(heartbeat(secs)).unwrap();
+        }
+        assert_eq!(storage.read().unwrap(), heartbeat(104));
+
+        //sequence continues when reopened
+        let [a, b] = storage.files;
+        let mut reopened = DoubleBufferedHeartbeat::new(a, b, TimestampFormat::Seconds);
+        reopened.write(heartbeat(105)).unwrap();
+        assert_eq!(reopened.read().unwrap(), heartbeat(105));
+    }
+
+    #[test]
+    fn torn_write_falls_back_to_previous_record() {
+        let mut storage = storage();
+        storage.write(heartbeat(100)).unwrap();
+        storage.write(heartbeat(101)).unwrap();
+        //power was cut halfway through the newest record
+        let newest = storage.files[0].get_mut();
+        newest[10..].fill(b' ');
+        assert_eq!(storage.read().unwrap(), heartbeat(100));
+
+        storage.files[1].get_mut().clear();
+        assert!(storage.read().is_err());
+    }
+
+    #[test]
+    fn origin_is_read_back() {
+        let origin = Origin {
+            pid: 4242,
+            version: "1.2.3".to_owned(),
+            hostname: "DESKTOP-1".to_owned(),
+        };
+        let mut storage = storage().with_origin(origin.clone());
+        storage.write(heartbeat(100)).unwrap();
+        assert_eq!(storage.read().unwrap(), heartbeat(100));
+        assert_eq!(storage.last_origin(), Some(&origin));
+    }
+
+    #[test]
+    fn records_of_older_versions_are_migrated() {
+        let expected = Heartbeat {
+            time: Utc.timestamp_opt(100, 0).unwrap(),
+            uptime: None,
+            boot_id: None,
+        };
+        assert_eq!(parse_record("100").unwrap(), expected);
+        assert_eq!(parse_record("100 - -").unwrap(), expected);
+        assert_eq!(parse_record("100 100000 7").unwrap(), heartbeat(100));
+        assert_eq!(
+            parse_record(r#"{"time":"100","uptime_ms":100000,"boot_id":7}"#).unwrap(),
+            heartbeat(100)
+        );
+
+        //double buffered record from before the json document
+        let mut storage = storage();
+        let body = "4 100 100000 7";
+        let record = format!("{body} {}\n", seal(body, None));
+        storage.files[0] = Cursor::new(record.into_bytes());
+        assert_eq!(storage.read().unwrap(), heartbeat(100));
+    }
+
+    #[test]
+    fn records_of_unknown_versions_are_rejected() {
+        assert!(parse_record(r#"{"v":4,"time":"100"}"#).is_err());
+        assert!(parse_record(r#"{"v":1,"time":"100"}"#).is_err());
+
+        let mut storage = storage();
+        storage.write(heartbeat(100)).unwrap();
+        let written = String::from_utf8(storage.files[1].get_ref().clone()).unwrap();
+        assert!(written.contains(&format!(r#""v":{RECORD_VERSION}"#)));
+    }
+
+    #[test]
+    fn seal_matches_rfc_4231() {
+        //test case 2
+        let sum = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+        assert_eq!(seal("what do ya want for nothing?", Some(b"Jefe")), sum);
+        assert!(sealed("what do ya want for nothing?", sum, Some(b"Jefe")));
+        //test case 6, key longer than a block
+        let sum = "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54";
+        let body = "Test Using Larger Than Block-Size Key - Hash Key First";
+        assert_eq!(seal(body, Some(&[0xaa; 131])), sum);
+        assert!(!sealed(body, &sum.replace('6', "7"), Some(&[0xaa; 131])));
+        assert!(!sealed(body, "not hex", Some(&[0xaa; 131])));
+    }
+
+    #[test]
+    fn records_without_key_are_rejected() {
+        let mut storage = storage().with_key(b"machine secret".to_vec());
+        storage.write(heartbeat(100)).unwrap();
+        storage.write(heartbeat(101)).unwrap();
+        assert_eq!(storage.read().unwrap(), heartbeat(101));
+
+        //forged with the plain checksum
+        let [a, b] = storage.files;
+        let mut forger = DoubleBufferedHeartbeat::new(a, b, TimestampFormat::Seconds);
+        forger.write(heartbeat(200)).unwrap();
+        let [a, b] = forger.files;
+        let mut storage = DoubleBufferedHeartbeat::new(a, b, TimestampFormat::Seconds)
+            .with_key(b"machine secret".to_vec());
+        assert_eq!(storage.read().unwrap(), heartbeat(101));
+    }
+
+    #[test]
+    fn formatted_heartbeat_is_parsed_back() {
+        let heartbeat = heartbeat(1_700_000_000);
+        let formatted = format_heartbeat(heartbeat, TimestampFormat::Rfc3339);
+        assert_eq!(parse_heartbeat(&formatted).unwrap(), heartbeat);
+    }
+}
@@ -0,0 +1,37 @@
+//! Platform independent restart detection logic of restart-fix.
+//!
+//! [`HeartbeatWriter`] periodically records the current time and uptime, and [`RestartDetector`]
+//! compares the last recorded [`Heartbeat`] with the current one on startup to decide whether the
+//! computer was restarted unexpectedly. [`HeartbeatWorker`] runs the heartbeat in background, or in
+//! [`HeartbeatTicks`] driven by a timer of the host, and can be stopped through [`Stopper`] handles. [`DoubleBufferedHeartbeat`] keeps the last complete
+//! heartbeat readable through torn writes. The readings come from a [`Clock`] and are kept in a
+//! [`HeartbeatStorage`], so both can be replaced in tests, e.g. by a [`MemoryHeartbeat`]. [`ShutdownController`] keeps repeated detections
+//! from showing more than one prompt at a time.
+//!
+//! [`Engine`] holds the runtime state of the monitor. Frontends subscribe to its [`Event`]s, query
+//! its [`EngineState`] and issue [`Command`]s without depending on how the prompt is shown. The
+//! running countdown is ended through a [`CancellationToken`], by whoever gets there first.
+
+mod cancel;
+mod clock;
+mod controller;
+mod detector;
+mod engine;
+mod heartbeat;
+mod tick;
+mod worker;
+
+pub use cancel::CancellationToken;
+pub use clock::Clock;
+pub use controller::{Admission, ConcurrentPolicy, ParsePolicyError, PromptId, ShutdownController};
+pub use detector::{Decision, RestartDetector};
+pub use engine::{Command, Engine, EngineState, Event};
+pub use heartbeat::{
+    format_heartbeat, parse_heartbeat, parse_record, parse_timestamp, DoubleBufferedHeartbeat,
+    Heartbeat, HeartbeatStorage, HeartbeatWriter, MemoryHeartbeat, Origin, ParseFormatError,
+    TimestampFormat, RECORD_VERSION,
+};
+pub use worker::{
+    HeartbeatSource, HeartbeatTicks, HeartbeatWorker, RestartPolicy, StopReason, Stopper,
+    WorkerError, WorkerOptions,
+};
@@ -0,0 +1,189 @@
+#[cfg(windows)]
+use std::sync::mpsc::TryRecvError;
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::StopReason;
+
+//wakes the worker out of its wait once a stop is requested or a stopper is dropped. on windows an
+//event the worker waits on along with its timer, elsewhere the stop channel alone does.
+pub(crate) struct Wake {
+    #[cfg(windows)]
+    event: windows::Event,
+}
+
+impl Wake {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            #[cfg(windows)]
+            event: windows::Event::new(),
+        })
+    }
+
+    pub(crate) fn set(&self) {
+        #[cfg(windows)]
+        self.event.set();
+    }
+}
+
+//what ended a wait of Ticker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Waited {
+    Deadline,
+    Stop(StopReason),
+}
+
+//waits of the worker for its next tick. on windows they are timed by a high resolution waitable
+//timer, which fires on time instead of at the next tick of the system clock(up to 15.6 ms late)
+//and doesn't keep the processor out of idle states meanwhile. it is given up for waiting on the stop
+//channel if it can't be created or set.
+pub(crate) struct Ticker {
+    stop_rx: mpsc::Receiver<StopReason>,
+    #[cfg(windows)]
+    wake: Arc<Wake>,
+    #[cfg(windows)]
+    timer: Option<windows::Timer>,
+}
+
+impl Ticker {
+    pub(crate) fn new(stop_rx: mpsc::Receiver<StopReason>, wake: Arc<Wake>) -> Self {
+        #[cfg(not(windows))]
+        let _ = wake;
+        Self {
+            stop_rx,
+            #[cfg(windows)]
+            wake,
+            #[cfg(windows)]
+            timer: windows::Timer::new()
+                .map_err(|e| tracing::warn!(error = %e, "unable to create waitable timer"))
+                .ok(),
+        }
+    }
+
+    //block until `deadline` or a stop request, whichever comes first
+    pub(crate) fn wait_until(&mut self, deadline: Instant) -> Waited {
+        #[cfg(windows)]
+        loop {
+            //every stopper is gone, so nothing can stop the worker anymore but closing
+            match self.stop_rx.try_recv() {
+                Ok(reason) => return Waited::Stop(reason),
+                Err(TryRecvError::Disconnected) => return Waited::Stop(StopReason::Close),
+                Err(TryRecvError::Empty) => {}
+            }
+            let Some(timer) = &self.timer else {
+                break;
+            };
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Waited::Deadline;
+            }
+            match timer.wait(left, &self.wake.event) {
+                Ok(windows::Fired::Timer) => return Waited::Deadline,
+                //stop request or a dropped stopper. told apart by the channel above.
+                Ok(windows::Fired::Event) => {}
+                Err(e) => {
+                    tracing::warn!(error = %e, "waitable timer failed. using the stop channel");
+                    self.timer = None;
+                }
+            }
+        }
+        match self
+            .stop_rx
+            .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        {
+            Ok(reason) => Waited::Stop(reason),
+            Err(RecvTimeoutError::Disconnected) => Waited::Stop(StopReason::Close),
+            Err(RecvTimeoutError::Timeout) => Waited::Deadline,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{io, ptr, time::Duration};
+
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0},
+        System::Threading::{
+            CreateEventW, CreateWaitableTimerExW, SetEvent, SetWaitableTimer,
+            WaitForMultipleObjects, CREATE_WAITABLE_TIMER_HIGH_RESOLUTION, INFINITE,
+            TIMER_ALL_ACCESS,
+        },
+    };
+
+    //auto-reset event. a null handle, if it can't be created, is never signalled, which leaves
+    //stop requests to be noticed on the next tick.
+    pub(super) struct Event(HANDLE);
+
+    impl Event {
+        pub(super) fn new() -> Self {
+            Self(unsafe { CreateEventW(ptr::null(), 0, 0, ptr::null()) })
+        }
+
+        pub(super) fn set(&self) {
+            if self.0 != 0 {
+                unsafe { SetEvent(self.0) };
+            }
+        }
+    }
+
+    impl Drop for Event {
+        fn drop(&mut self) {
+            if self.0 != 0 {
+                unsafe { CloseHandle(self.0) };
+            }
+        }
+    }
+
+    pub(super) enum Fired {
+        Timer,
+        Event,
+    }
+
+    pub(super) struct Timer(HANDLE);
+
+    impl Timer {
+        //high resolution timers need windows 10 1803. older versions get a regular one.
+        pub(super) fn new() -> io::Result<Self> {
+            let create = |flags| unsafe {
+                CreateWaitableTimerExW(ptr::null(), ptr::null(), flags, TIMER_ALL_ACCESS)
+            };
+            let mut timer = create(CREATE_WAITABLE_TIMER_HIGH_RESOLUTION);
+            if timer == 0 {
+                timer = create(0);
+            }
+            if timer == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self(timer))
+        }
+
+        //wait until `after` passed or `event` is signalled. the timer doesn't wake the computer
+        //from sleep, so a tick due during standby fires on resume.
+        pub(super) fn wait(&self, after: Duration, event: &Event) -> io::Result<Fired> {
+            //negative due time is relative, in 100 ns units
+            let due = -((after.as_nanos() / 100).clamp(1, i64::MAX as u128) as i64);
+            if unsafe { SetWaitableTimer(self.0, &due, 0, None, ptr::null(), 0) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let handles = [self.0, event.0];
+            let count = if event.0 == 0 { 1 } else { 2 };
+            match unsafe { WaitForMultipleObjects(count, handles.as_ptr(), 0, INFINITE) } {
+                WAIT_OBJECT_0 => Ok(Fired::Timer),
+                signalled if signalled == WAIT_OBJECT_0 + 1 => Ok(Fired::Event),
+                _ => Err(io::Error::last_os_error()),
+            }
+        }
+    }
+
+    impl Drop for Timer {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+}
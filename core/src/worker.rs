@@ -0,0 +1,596 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    tick::{Ticker, Waited, Wake},
+    Heartbeat, HeartbeatStorage,
+};
+
+/// Why [`HeartbeatWorker`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// Shutdown countdown expired and the computer should be shut down. All resources, including
+    /// the heartbeat file, must be released before shutting down.
+    Shutdown,
+    /// Countdown expired and the computer should be restarted into its firmware setup. Resources
+    /// are released as for [`StopReason::Shutdown`].
+    RebootToFirmware,
+    /// Program is closing.
+    Close,
+}
+
+/// Errors that stop [`HeartbeatWorker`] or waiting for it.
+#[derive(Debug, thiserror::Error)]
+pub enum WorkerError {
+    #[error("heartbeat can't be written after {attempts} attempts: {source:#}")]
+    WriteFailed {
+        attempts: u32,
+        source: anyhow::Error,
+    },
+    #[error("background worker stopped unexpectedly")]
+    Panicked,
+    #[error("background worker didn't stop in time")]
+    Timeout,
+}
+
+/// Supplies heartbeats written by [`HeartbeatWorker`] and is told how writing went.
+pub trait HeartbeatSource: Send + 'static {
+    /// Returns the heartbeat to write now, or `None` to skip this tick (e.g. while paused).
+    fn next(&mut self) -> Option<Heartbeat>;
+
+    /// Called after `heartbeat` was written.
+    fn written(&mut self, _heartbeat: Heartbeat) {}
+
+    /// Called after writing failed `attempts` times in a row.
+    fn failed(&mut self, _attempts: u32, _error: &anyhow::Error) {}
+}
+
+/// Settings of [`HeartbeatWorker`].
+#[derive(Debug, Clone, Copy)]
+pub struct WorkerOptions {
+    /// Time between heartbeats.
+    pub interval: Duration,
+    /// Consecutive write failures tolerated before the worker gives up.
+    pub max_failures: u32,
+}
+
+/// How a supervised [`HeartbeatWorker`] is restarted after it failed.
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    /// Restarts before the worker stops for good.
+    pub max_restarts: u32,
+    /// Wait before the first restart. It doubles with every restart, up to `max_backoff`.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// Failed worker stays stopped.
+    pub const NEVER: Self = Self {
+        max_restarts: 0,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+    };
+
+    /// Wait before restart number `restart`, counted from 1.
+    pub fn backoff(&self, restart: u32) -> Duration {
+        let factor = 2u32.saturating_pow(restart.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Requests a [`HeartbeatWorker`] to stop. Can be cloned and sent to other threads.
+#[derive(Clone)]
+pub struct Stopper(StopTarget);
+
+#[derive(Clone)]
+enum StopTarget {
+    Thread {
+        stop_tx: mpsc::Sender<StopReason>,
+        wake: Arc<Wake>,
+    },
+    Ticks(Arc<Outcome>),
+}
+
+impl Stopper {
+    /// Asks the worker to stop. The first request wins. Does nothing if the worker is already
+    /// stopped.
+    pub fn stop(&self, reason: StopReason) {
+        match &self.0 {
+            StopTarget::Thread { stop_tx, wake } => {
+                let _ = stop_tx.send(reason);
+                wake.set();
+            }
+            StopTarget::Ticks(outcome) => outcome.send(Ok(reason)),
+        }
+    }
+}
+
+impl Drop for Stopper {
+    //the worker closes once the last stopper is gone, which it has to wake up for
+    fn drop(&mut self) {
+        if let StopTarget::Thread { wake, .. } = &self.0 {
+            wake.set();
+        }
+    }
+}
+
+//result of a ticked worker, sent by a stopper or by the tick that gave up, whichever comes first.
+//ticks hold it while they write, so a stop waits for the write in flight and nothing is written
+//after it.
+struct Outcome(Mutex<Option<mpsc::Sender<Result<StopReason, WorkerError>>>>);
+
+impl Outcome {
+    fn send(&self, result: Result<StopReason, WorkerError>) {
+        if let Some(result_tx) = self.0.lock().unwrap_or_else(PoisonError::into_inner).take() {
+            let _ = result_tx.send(result);
+        }
+    }
+}
+
+/// Background thread that periodically writes heartbeats, or the ticks of a host timer that do
+/// (see [`ticked`](Self::ticked)).
+pub struct HeartbeatWorker {
+    stopper: Stopper,
+    result: mpsc::Receiver<Result<StopReason, WorkerError>>,
+}
+
+impl HeartbeatWorker {
+    /// Spawns a worker that stops for good once writing fails [`WorkerOptions::max_failures`]
+    /// times in a row.
+    pub fn spawn<W, S>(writer: Arc<Mutex<W>>, options: WorkerOptions, source: S) -> Self
+    where
+        W: HeartbeatStorage + Send + 'static,
+        S: HeartbeatSource,
+    {
+        Self::supervise(writer, options, source, RestartPolicy::NEVER, |_| Ok(()))
+    }
+
+    /// Spawns a worker that is restarted after it failed or panicked, as `restart` allows.
+    /// `reopen` is given the writer before each restart, e.g. to open the heartbeat file again.
+    /// Stoppers handed out before a restart keep working.
+    pub fn supervise<W, S, R>(
+        writer: Arc<Mutex<W>>,
+        options: WorkerOptions,
+        mut source: S,
+        restart: RestartPolicy,
+        mut reopen: R,
+    ) -> Self
+    where
+        W: HeartbeatStorage + Send + 'static,
+        S: HeartbeatSource,
+        R: FnMut(&mut W) -> anyhow::Result<()> + Send + 'static,
+    {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let (result_tx, result_rx) = mpsc::channel();
+        let wake = Wake::new();
+        let stopper = Stopper(StopTarget::Thread {
+            stop_tx,
+            wake: Arc::clone(&wake),
+        });
+        thread::spawn(move || {
+            let _span = tracing::debug_span!(
+                "heartbeat_worker",
+                interval_ms = options.interval.as_millis() as u64
+            )
+            .entered();
+            let mut ticker = Ticker::new(stop_rx, wake);
+            let mut restarts = 0;
+            let result = loop {
+                let run = panic::catch_unwind(AssertUnwindSafe(|| {
+                    write_heartbeats(&writer, options, &mut source, &mut ticker)
+                }));
+                let error = match run {
+                    Ok(Ok(reason)) => break Ok(reason),
+                    Ok(Err(e)) => e,
+                    Err(_) => WorkerError::Panicked,
+                };
+                if restarts >= restart.max_restarts {
+                    break Err(error);
+                }
+                restarts += 1;
+                let backoff = restart.backoff(restarts);
+                tracing::error!(
+                    %error,
+                    restarts,
+                    backoff_ms = backoff.as_millis() as u64,
+                    "restarting background worker"
+                );
+                //stop requests during the backoff are still followed
+                if let Waited::Stop(reason) = ticker.wait_until(Instant::now() + backoff) {
+                    break Ok(reason);
+                }
+                //a panic while writing leaves the lock poisoned, but the writer is reopened anyway
+                let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+                if let Err(e) = reopen(&mut writer) {
+                    tracing::error!(
+                        error = format!("{e:#}"),
+                        "unable to reopen heartbeat storage"
+                    );
+                }
+            };
+            let _ = result_tx.send(result);
+        });
+        Self {
+            stopper,
+            result: result_rx,
+        }
+    }
+
+    /// Like [`supervise`](Self::supervise), but without a thread of its own: the host calls
+    /// [`HeartbeatTicks::tick`] an interval from now and then whenever the previous tick asks it
+    /// to, e.g. from a thread-pool timer.
+    pub fn ticked<W, S, R>(
+        writer: Arc<Mutex<W>>,
+        options: WorkerOptions,
+        source: S,
+        restart: RestartPolicy,
+        reopen: R,
+    ) -> (Self, HeartbeatTicks<W, S, R>)
+    where
+        W: HeartbeatStorage + Send + 'static,
+        S: HeartbeatSource,
+        R: FnMut(&mut W) -> anyhow::Result<()> + Send + 'static,
+    {
+        let (result_tx, result_rx) = mpsc::channel();
+        let outcome = Arc::new(Outcome(Mutex::new(Some(result_tx))));
+        let worker = Self {
+            stopper: Stopper(StopTarget::Ticks(Arc::clone(&outcome))),
+            result: result_rx,
+        };
+        let ticks = HeartbeatTicks {
+            writer,
+            options,
+            source,
+            restart,
+            reopen,
+            outcome,
+            failures: 0,
+            restarts: 0,
+            reopening: false,
+        };
+        (worker, ticks)
+    }
+
+    pub fn stopper(&self) -> Stopper {
+        self.stopper.clone()
+    }
+
+    /// Blocks until the worker stops for any reason.
+    pub fn wait(self) -> Result<StopReason, WorkerError> {
+        //sender is dropped without result only if worker thread panicked
+        self.result.recv().unwrap_or(Err(WorkerError::Panicked))
+    }
+
+    /// Asks the worker to close and waits up to `timeout` for it. If it already stopped for
+    /// another reason, that reason is returned.
+    pub fn stop(self, timeout: Duration) -> Result<StopReason, WorkerError> {
+        self.stopper.stop(StopReason::Close);
+        match self.result.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => Err(WorkerError::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(WorkerError::Panicked),
+        }
+    }
+}
+
+/// Ticks of a [`HeartbeatWorker`] created by [`ticked`](HeartbeatWorker::ticked). Failures and
+/// restarts are handled as by a supervised worker, with the backoff being the wait until the next
+/// tick.
+pub struct HeartbeatTicks<W, S, R> {
+    writer: Arc<Mutex<W>>,
+    options: WorkerOptions,
+    source: S,
+    restart: RestartPolicy,
+    reopen: R,
+    outcome: Arc<Outcome>,
+    failures: u32,
+    restarts: u32,
+    //storage is reopened by the tick after a restart backoff
+    reopening: bool,
+}
+
+impl<W, S, R> HeartbeatTicks<W, S, R>
+where
+    W: HeartbeatStorage,
+    S: HeartbeatSource,
+    R: FnMut(&mut W) -> anyhow::Result<()>,
+{
+    /// Writes the heartbeat due now. Returns the time until the next tick, or `None` once the
+    /// worker stopped.
+    pub fn tick(&mut self) -> Option<Duration> {
+        let started = Instant::now();
+        let mut outcome = self
+            .outcome
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        outcome.as_ref()?;
+        let _span = tracing::debug_span!("heartbeat_tick").entered();
+        if std::mem::take(&mut self.reopening) {
+            self.failures = 0;
+            let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Err(e) = (self.reopen)(&mut writer) {
+                tracing::error!(
+                    error = format!("{e:#}"),
+                    "unable to reopen heartbeat storage"
+                );
+            }
+        }
+        let written = panic::catch_unwind(AssertUnwindSafe(|| {
+            write_heartbeat(
+                &self.writer,
+                self.options.max_failures,
+                &mut self.source,
+                &mut self.failures,
+            )
+        }));
+        let error = match written {
+            //time spent writing doesn't stretch the interval
+            Ok(Ok(())) => return Some(self.options.interval.saturating_sub(started.elapsed())),
+            Ok(Err(e)) => e,
+            Err(_) => WorkerError::Panicked,
+        };
+        if self.restarts >= self.restart.max_restarts {
+            if let Some(result_tx) = outcome.take() {
+                let _ = result_tx.send(Err(error));
+            }
+            return None;
+        }
+        self.restarts += 1;
+        let backoff = self.restart.backoff(self.restarts);
+        tracing::error!(
+            %error,
+            restarts = self.restarts,
+            backoff_ms = backoff.as_millis() as u64,
+            "restarting background worker"
+        );
+        self.reopening = true;
+        Some(backoff)
+    }
+}
+
+//writes heartbeats until a stop is requested or writing fails too often in a row
+fn write_heartbeats<W, S>(
+    writer: &Mutex<W>,
+    options: WorkerOptions,
+    source: &mut S,
+    ticker: &mut Ticker,
+) -> Result<StopReason, WorkerError>
+where
+    W: HeartbeatStorage,
+    S: HeartbeatSource,
+{
+    let mut failures = 0;
+    let mut next_tick = Instant::now() + options.interval;
+    loop {
+        //thread sleeps until the tick or a stop request. waiting for a deadline keeps time spent
+        //writing from stretching the interval.
+        if let Waited::Stop(reason) = ticker.wait_until(next_tick) {
+            tracing::debug!(?reason, "stop requested");
+            return Ok(reason);
+        }
+        next_tick += options.interval;
+        //ticks missed during standby or a stalled write are skipped, not caught up in a burst
+        let now = Instant::now();
+        if next_tick <= now {
+            next_tick = now + options.interval;
+        }
+        write_heartbeat(writer, options.max_failures, source, &mut failures)?;
+    }
+}
+
+//writes the heartbeat of `source`, if it has one. fails once writing failed `max_failures` times
+//in a row.
+fn write_heartbeat<W, S>(
+    writer: &Mutex<W>,
+    max_failures: u32,
+    source: &mut S,
+    failures: &mut u32,
+) -> Result<(), WorkerError>
+where
+    W: HeartbeatStorage,
+    S: HeartbeatSource,
+{
+    let Some(heartbeat) = source.next() else {
+        tracing::debug!("no heartbeat to write");
+        return Ok(());
+    };
+    tracing::debug!(?heartbeat, "writing heartbeat");
+    let written = writer
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .write(heartbeat);
+    match written {
+        Ok(()) => {
+            *failures = 0;
+            source.written(heartbeat);
+            Ok(())
+        }
+        Err(e) => {
+            *failures += 1;
+            source.failed(*failures, &e);
+            if *failures >= max_failures {
+                return Err(WorkerError::WriteFailed {
+                    attempts: *failures,
+                    source: e,
+                });
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    //storage failing until it is reopened
+    #[derive(Default)]
+    struct Flaky {
+        broken: bool,
+        written: u32,
+    }
+
+    impl HeartbeatStorage for Flaky {
+        fn read(&mut self) -> anyhow::Result<Heartbeat> {
+            anyhow::bail!("not readable")
+        }
+
+        fn write(&mut self, _heartbeat: Heartbeat) -> anyhow::Result<()> {
+            if self.broken {
+                anyhow::bail!("broken");
+            }
+            self.written += 1;
+            Ok(())
+        }
+    }
+
+    struct Ticks;
+
+    impl HeartbeatSource for Ticks {
+        fn next(&mut self) -> Option<Heartbeat> {
+            Some(Heartbeat {
+                time: Utc::now(),
+                uptime: None,
+                boot_id: None,
+            })
+        }
+    }
+
+    static OPTIONS: WorkerOptions = WorkerOptions {
+        interval: Duration::from_millis(5),
+        max_failures: 2,
+    };
+
+    fn restart(max_restarts: u32) -> RestartPolicy {
+        RestartPolicy {
+            max_restarts,
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(20),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let restart = restart(10);
+        assert_eq!(restart.backoff(1), Duration::from_millis(5));
+        assert_eq!(restart.backoff(2), Duration::from_millis(10));
+        assert_eq!(restart.backoff(3), Duration::from_millis(20));
+        assert_eq!(restart.backoff(40), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn failed_worker_is_restarted_with_reopened_storage() {
+        let writer = Arc::new(Mutex::new(Flaky {
+            broken: true,
+            written: 0,
+        }));
+        let worker =
+            HeartbeatWorker::supervise(Arc::clone(&writer), OPTIONS, Ticks, restart(1), |w| {
+                w.broken = false;
+                Ok(())
+            });
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            worker.stop(Duration::from_secs(1)).unwrap(),
+            StopReason::Close
+        );
+        assert!(writer.lock().unwrap().written > 0);
+    }
+
+    #[test]
+    fn worker_gives_up_after_max_restarts() {
+        let writer = Arc::new(Mutex::new(Flaky {
+            broken: true,
+            written: 0,
+        }));
+        let worker = HeartbeatWorker::supervise(writer, OPTIONS, Ticks, restart(2), |_| Ok(()));
+        assert!(matches!(
+            worker.wait(),
+            Err(WorkerError::WriteFailed { attempts: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn ticked_worker_restarts_and_stops() {
+        let writer = Arc::new(Mutex::new(Flaky {
+            broken: true,
+            written: 0,
+        }));
+        let (worker, mut ticks) =
+            HeartbeatWorker::ticked(Arc::clone(&writer), OPTIONS, Ticks, restart(1), |w| {
+                w.broken = false;
+                Ok(())
+            });
+        //first failure is tolerated, the second restarts after the backoff
+        assert!(ticks.tick().is_some_and(|next| next <= OPTIONS.interval));
+        assert_eq!(ticks.tick(), Some(Duration::from_millis(5)));
+        assert!(ticks.tick().is_some());
+        assert_eq!(writer.lock().unwrap().written, 1);
+        worker.stopper().stop(StopReason::Shutdown);
+        assert_eq!(ticks.tick(), None);
+        assert_eq!(writer.lock().unwrap().written, 1);
+        assert_eq!(
+            worker.stop(Duration::from_secs(1)).unwrap(),
+            StopReason::Shutdown
+        );
+    }
+
+    #[test]
+    fn ticked_worker_gives_up_after_max_restarts() {
+        let writer = Arc::new(Mutex::new(Flaky {
+            broken: true,
+            written: 0,
+        }));
+        let (worker, mut ticks) =
+            HeartbeatWorker::ticked(writer, OPTIONS, Ticks, RestartPolicy::NEVER, |_| Ok(()));
+        assert!(ticks.tick().is_some());
+        assert_eq!(ticks.tick(), None);
+        assert!(matches!(
+            worker.wait(),
+            Err(WorkerError::WriteFailed { attempts: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn panicking_source_is_restarted() {
+        struct PanicsOnce(bool);
+
+        impl HeartbeatSource for PanicsOnce {
+            fn next(&mut self) -> Option<Heartbeat> {
+                if !self.0 {
+                    self.0 = true;
+                    panic!("first tick");
+                }
+                Ticks.next()
+            }
+        }
+
+        let writer = Arc::new(Mutex::new(Flaky::default()));
+        let worker = HeartbeatWorker::supervise(
+            Arc::clone(&writer),
+            OPTIONS,
+            PanicsOnce(false),
+            restart(1),
+            |_| Ok(()),
+        );
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            worker.stop(Duration::from_secs(1)).unwrap(),
+            StopReason::Close
+        );
+        assert!(writer.lock().unwrap().written > 0);
+    }
+}
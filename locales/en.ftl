@@ -0,0 +1,219 @@
+# Messages of restart-fix in English. Every other catalog falls back to this one for messages it
+# doesn't have. Lines of multiline messages are shown as separate lines.
+
+## Durations
+
+duration-hours = { $count ->
+    [one] { $count } hour
+   *[other] { $count } hours
+}
+duration-minutes = { $count ->
+    [one] { $count } minute
+   *[other] { $count } minutes
+}
+duration-seconds = { $count ->
+    [one] { $count } second
+   *[other] { $count } seconds
+}
+
+## Shutdown countdown
+## $action is "hibernate", "firmware" or "shutdown".
+
+shutdown-title = Shutdown notice
+countdown-text = { $action ->
+    [hibernate] Automatic restart detected. The computer will hibernate in { $timeout }.
+    [firmware] Automatic restart detected. The computer will restart into firmware(UEFI) setup in { $timeout }.
+   *[shutdown] Automatic restart detected. The computer will shut down in { $timeout }.
+}
+    { $hint }
+announcement = { $action ->
+    [hibernate] The computer will hibernate in { $timeout }.
+    [firmware] The computer will restart into firmware setup in { $timeout }.
+   *[shutdown] The computer will shut down in { $timeout }.
+}
+other-session-hint = Press Yes to cancel it for everyone, or No to let it go on.
+other-session-warning = Another user started it. Save your work.
+apps-open-text = Shutdown was cancelled because { $apps } didn't close. Save your work and close it to let the computer shut down next time.
+confirm-text = { $action ->
+    [hibernate] Automatic restart detected. Hibernate the computer now?
+    [firmware] Automatic restart detected. Restart the computer into firmware(UEFI) setup now?
+   *[shutdown] Automatic restart detected. Shut down the computer now?
+}
+    Press No to keep working.
+
+# Buttons of the enlarged countdown. Letters after & are their keyboard shortcuts.
+cancel-button = &Cancel shutdown
+snooze-button = &Snooze { $duration }
+extend-button = &Postpone { $duration }
+act-now-button = { $action ->
+    [hibernate] &Hibernate now
+    [firmware] &Restart now
+   *[shutdown] Shut &down now
+}
+time-left = Time left: { $left }
+
+cancel-hint-window = Press Enter or Escape to cancel.
+cancel-hint-ok = Press OK to cancel.
+cancel-hint-snooze = Press Yes to cancel, or No to snooze for { $snooze }.
+cancel-hint-extend = Press Yes to cancel, No to snooze for { $snooze }, or Cancel to postpone by { $extend }.
+
+## Notices
+
+shutdown-block-reason = Saving restart-detection state
+restart-notice-title = Restart notice
+restart-notice-text = The computer was restarted automatically.
+pending-reboot-title = Restart pending
+pending-reboot-text = Windows intends to restart soon to finish installing updates. Save your work, or restart at a time that suits you.
+scheduled-task-text = The restart was initiated by scheduled task { $task }.
+peer-notice-text = { $from } detected an unattended restart.
+dry-run-text = { $action ->
+    [hibernate] Dry run: the computer would have hibernated now.
+    [firmware] Dry run: the computer would have restarted into firmware setup now.
+   *[shutdown] Dry run: the computer would have shut down now.
+}
+config-tampered-title = restart-fix settings were changed
+config-tampered-text =
+    The config file no longer matches the one sealed by an administrator. The sealed settings are used instead.
+
+    To apply the changes, run "restart-fix config seal" from an elevated prompt.
+
+## Notification area
+
+tray-tip = restart-fix is monitoring restarts
+show-status = Show status
+show-history = Show history
+
+## History window
+
+history-title = restart-fix history
+history-filter = Filter
+history-column-started = Started
+history-column-boot = Boot
+history-column-cause = Cause
+history-column-uptime = Uptime before
+history-column-action = Action
+history-column-reliability = Reliability Monitor
+# $kind is "update-installed", "update-failed", "system-crash", "app-crash" or "app-hang".
+reliability-event = { $kind ->
+    [update-installed] { $count ->
+        [one] { $count } update installed
+       *[other] { $count } updates installed
+    }
+    [update-failed] { $count ->
+        [one] { $count } update failed
+       *[other] { $count } updates failed
+    }
+    [system-crash] { $count ->
+        [one] { $count } Windows crash
+       *[other] { $count } Windows crashes
+    }
+    [app-crash] { $count ->
+        [one] { $count } app crash
+       *[other] { $count } app crashes
+    }
+   *[app-hang] { $count ->
+        [one] { $count } app stopped responding
+       *[other] { $count } apps stopped responding
+    }
+}
+history-action = { $acted ->
+    [yes] { $action }(carried out)
+   *[no] { $action }(not carried out)
+}
+
+## Status window
+
+status-title = restart-fix status
+agent-status-connected = Restarts are detected by the restart-fix service. Its shutdown prompts are shown here.
+agent-status-disconnected = The restart-fix service can't be reached. Connecting again shortly.
+# $state is "paused", "countdown" or "armed".
+status-monitoring = { $state ->
+    [paused] paused until next boot
+    [countdown] shutdown countdown running
+   *[armed] armed
+}
+status-no-heartbeat = none yet
+status-detection = { $classification }{ $cause } at { $time }
+status-detection-cause = (cause: { $cause })
+status-not-detected = not run yet(waiting for startup grace)
+# $state is "now", "not-now", "unset" or "ignored".
+status-active-hours = { $state ->
+    [now] { $hours }, in effect now
+    [not-now] { $hours }, not in effect now
+    [unset] not configured
+   *[ignored] not respected
+}
+# $state is "on", "off" or "unknown".
+status-fast-startup = { $state ->
+    [on] on(shutting down hibernates the kernel)
+    [off] off
+   *[unknown] unknown
+}
+status-text =
+    Monitoring: { $monitoring }
+    Uptime: { $uptime }
+    Last heartbeat: { $heartbeat }
+    Restart detection: { $detection }
+    Active hours(no automatic shutdown): { $active_hours }
+    Fast startup: { $fast_startup }
+
+## Setup wizard
+
+wizard-title = restart-fix setup
+wizard-intro =
+    restart-fix watches for restarts you didn't ask for, e.g. by Windows Update while the computer was left on. If the computer restarts shortly after it was last running, restart-fix shuts it down again after a countdown that you can cancel.
+
+    Choose how it should behave. Every setting can be changed later in restart-fix.toml.
+wizard-threshold = Restart counts if within:
+wizard-action = When a restart is detected:
+wizard-action-shutdown = Shut down
+wizard-action-hibernate = Hibernate
+wizard-action-notify = Only notify me
+wizard-autostart = Start restart-fix with Windows
+wizard-telemetry = Send anonymous weekly statistics(number of restarts, causes, cancellations)
+wizard-start = Start monitoring
+wizard-exit = Exit
+
+## Errors
+
+autostart-error-title = Autostart error
+autostart-error-text =
+    Unable to repair autostart registration. Restart detection won't work from the next boot.
+    { $error }
+watchdog-error-title = Watchdog error
+watchdog-error-text =
+    Unable to repair the scheduled task that relaunches restart-fix. restart-fix won't be relaunched if it stops.
+    { $error }
+fatal-error-title = restart-fix error
+fatal-error-text =
+    Restart detection has stopped because of an error.
+    { $error }
+panic-text =
+    restart-fix has crashed and restart detection has stopped.
+    { $panic }
+recovery-title = restart-fix recovery mode
+recovery-text =
+    restart-fix failed to start { $failed ->
+        [one] once
+       *[other] { $failed } times in a row
+    }. It runs in recovery mode: the configuration is ignored and restarts are only notified.
+    Fix or change the configuration to leave recovery mode.
+
+## Updates and summaries
+
+update-title = restart-fix update
+update-text = { $installed ->
+    [yes] restart-fix { $version } has been downloaded and is installed the next time restart-fix starts.
+   *[no] restart-fix { $version } is available.
+}
+    { $url }
+weekly-summary-title = restart-fix weekly summary
+weekly-summary-text = { $restarts ->
+    [0] No unexpected restarts this week({ $previous } the week before).
+   *[other] { $restarts ->
+        [one] 1 unexpected restart
+       *[other] { $restarts } unexpected restarts
+    } this week({ $previous } the week before).
+    Causes: { $causes }
+    Shut down automatically: { $shutdowns }, cancelled or notified only: { $notified }
+}
@@ -0,0 +1,182 @@
+# Messages of restart-fix in Korean. Messages missing here are shown in English.
+
+## Durations. Korean has no plural forms.
+
+duration-hours = { $count }시간
+duration-minutes = { $count }분
+duration-seconds = { $count }초
+
+## Shutdown countdown
+
+shutdown-title = 컴퓨터 종료 알림
+countdown-text = { $action ->
+    [hibernate] 자동 재시작을 감지했습니다. { $timeout } 후 컴퓨터가 최대 절전 모드로 전환됩니다.
+    [firmware] 자동 재시작을 감지했습니다. { $timeout } 후 컴퓨터가 펌웨어(UEFI) 설정으로 재시작됩니다.
+   *[shutdown] 자동 재시작을 감지했습니다. { $timeout } 후 컴퓨터가 종료됩니다.
+}
+    { $hint }
+announcement = { $action ->
+    [hibernate] { $timeout } 후 컴퓨터가 최대 절전 모드로 전환됩니다.
+    [firmware] { $timeout } 후 컴퓨터가 펌웨어 설정으로 재시작됩니다.
+   *[shutdown] { $timeout } 후 컴퓨터가 종료됩니다.
+}
+other-session-hint = 모든 사용자에 대해 취소하려면 예를, 계속 진행하려면 아니요를 누르세요.
+other-session-warning = 다른 사용자가 시작했습니다. 작업을 저장하세요.
+apps-open-text = { $apps }이(가) 닫히지 않아 종료가 취소되었습니다. 다음에 컴퓨터가 종료될 수 있도록 작업을 저장하고 닫으세요.
+confirm-text = { $action ->
+    [hibernate] 자동 재시작을 감지했습니다. 지금 컴퓨터를 최대 절전 모드로 전환할까요?
+    [firmware] 자동 재시작을 감지했습니다. 지금 컴퓨터를 펌웨어(UEFI) 설정으로 재시작할까요?
+   *[shutdown] 자동 재시작을 감지했습니다. 지금 컴퓨터를 종료할까요?
+}
+    계속 사용하려면 아니요를 누르세요.
+
+cancel-button = 종료 취소(&C)
+snooze-button = { $duration } 미루기(&S)
+extend-button = { $duration } 연장(&P)
+act-now-button = { $action ->
+    [hibernate] 지금 최대 절전(&H)
+    [firmware] 지금 재시작(&R)
+   *[shutdown] 지금 종료(&D)
+}
+time-left = 남은 시간: { $left }
+
+cancel-hint-window = 취소하려면 Enter 또는 Esc를 누르세요.
+cancel-hint-ok = 취소하려면 확인을 누르세요.
+cancel-hint-snooze = 취소하려면 예를, { $snooze } 동안 미루려면 아니요를 누르세요.
+cancel-hint-extend = 취소하려면 예를, { $snooze } 동안 미루려면 아니요를, { $extend } 연장하려면 취소를 누르세요.
+
+## Notices
+
+shutdown-block-reason = 재시작 감지 상태를 저장하는 중
+restart-notice-title = 재시작 알림
+restart-notice-text = 컴퓨터가 자동으로 재시작되었습니다.
+pending-reboot-title = 재시작 대기 중
+pending-reboot-text = Windows가 업데이트 설치를 마치기 위해 곧 재시작하려고 합니다. 작업을 저장하거나 편한 시간에 재시작하세요.
+scheduled-task-text = 예약된 작업 { $task }이(가) 재시작을 시작했습니다.
+peer-notice-text = { $from }에서 무인 재시작이 감지되었습니다.
+dry-run-text = { $action ->
+    [hibernate] Dry run: 실제 모드였다면 지금 컴퓨터가 최대 절전 모드로 전환되었습니다.
+    [firmware] Dry run: 실제 모드였다면 지금 컴퓨터가 펌웨어 설정으로 재시작되었습니다.
+   *[shutdown] Dry run: 실제 모드였다면 지금 컴퓨터가 종료되었습니다.
+}
+config-tampered-title = restart-fix 설정이 변경됨
+config-tampered-text =
+    설정 파일이 관리자가 봉인한 파일과 다릅니다. 봉인된 설정이 대신 사용됩니다.
+
+    변경 사항을 적용하려면 관리자 권한으로 "restart-fix config seal"을 실행하세요.
+
+## Notification area
+
+tray-tip = restart-fix가 재시작을 감시하는 중
+show-status = 상태 보기
+show-history = 기록 보기
+
+## History window
+
+history-title = restart-fix 기록
+history-filter = 필터
+history-column-started = 시작 시각
+history-column-boot = 부팅
+history-column-cause = 원인
+history-column-uptime = 재시작 전 가동 시간
+history-column-action = 조치
+history-column-reliability = 안정성 모니터
+reliability-event = { $kind ->
+    [update-installed] 업데이트 { $count }개 설치
+    [update-failed] 업데이트 { $count }개 실패
+    [system-crash] Windows 오류 { $count }회
+    [app-crash] 앱 충돌 { $count }회
+   *[app-hang] 앱 응답 없음 { $count }회
+}
+history-action = { $acted ->
+    [yes] { $action }(실행됨)
+   *[no] { $action }(실행 안 됨)
+}
+
+## Status window
+
+status-title = restart-fix 상태
+agent-status-connected = restart-fix 서비스가 재시작을 감지합니다. 서비스의 종료 안내가 여기에 표시됩니다.
+agent-status-disconnected = restart-fix 서비스에 연결할 수 없습니다. 곧 다시 연결합니다.
+status-monitoring = { $state ->
+    [paused] 다음 부팅까지 일시 중지됨
+    [countdown] 종료 카운트다운 진행 중
+   *[armed] 감시 중
+}
+status-no-heartbeat = 아직 없음
+status-detection = { $classification }{ $cause }, { $time }
+status-detection-cause = (원인: { $cause })
+status-not-detected = 아직 실행되지 않음(시작 유예 시간 대기 중)
+status-active-hours = { $state ->
+    [now] { $hours }, 현재 적용 중
+    [not-now] { $hours }, 현재 적용 안 됨
+    [unset] 설정되지 않음
+   *[ignored] 사용하지 않음
+}
+status-fast-startup = { $state ->
+    [on] 켜짐(종료 시 커널을 최대 절전 모드로 저장)
+    [off] 꺼짐
+   *[unknown] 알 수 없음
+}
+status-text =
+    감시 상태: { $monitoring }
+    가동 시간: { $uptime }
+    마지막 하트비트: { $heartbeat }
+    재시작 감지: { $detection }
+    사용 시간(자동 종료 안 함): { $active_hours }
+    빠른 시작: { $fast_startup }
+
+## Setup wizard
+
+wizard-title = restart-fix 설정
+wizard-intro =
+    restart-fix는 켜 둔 컴퓨터가 Windows 업데이트 등으로 원하지 않게 재시작되는 것을 감시합니다. 컴퓨터가 마지막으로 실행된 직후에 재시작되면, 취소할 수 있는 카운트다운 후 컴퓨터를 다시 종료합니다.
+
+    동작 방식을 선택하세요. 모든 설정은 나중에 restart-fix.toml에서 바꿀 수 있습니다.
+wizard-threshold = 다음 시간 안의 재시작을 감지:
+wizard-action = 재시작이 감지되면:
+wizard-action-shutdown = 시스템 종료
+wizard-action-hibernate = 최대 절전
+wizard-action-notify = 알림만 표시
+wizard-autostart = Windows 시작 시 restart-fix 실행
+wizard-telemetry = 익명 주간 통계 전송(재시작 횟수, 원인, 취소 횟수)
+wizard-start = 감시 시작
+wizard-exit = 종료
+
+## Errors
+
+autostart-error-title = 자동 시작 등록 오류
+autostart-error-text =
+    자동 시작 등록을 복구하지 못했습니다. 다음 부팅부터 재시작 감지가 동작하지 않습니다.
+    { $error }
+watchdog-error-title = 감시 작업 오류
+watchdog-error-text =
+    restart-fix를 다시 실행하는 예약 작업을 복구하지 못했습니다. restart-fix가 중단되어도 다시 실행되지 않습니다.
+    { $error }
+fatal-error-title = restart-fix 오류
+fatal-error-text =
+    오류로 인해 재시작 감지가 중단되었습니다.
+    { $error }
+panic-text =
+    restart-fix가 비정상 종료되어 재시작 감지가 중단되었습니다.
+    { $panic }
+recovery-title = restart-fix 복구 모드
+recovery-text =
+    restart-fix가 { $failed }번 연속으로 시작하지 못했습니다. 설정을 무시하고 재시작을 알리기만 하는 복구 모드로 동작합니다.
+    복구 모드를 끝내려면 설정을 고치거나 변경하세요.
+
+## Updates and summaries
+
+update-title = restart-fix 업데이트
+update-text = { $installed ->
+    [yes] restart-fix { $version } 버전을 내려받았습니다. 다음에 restart-fix가 시작될 때 설치됩니다.
+   *[no] restart-fix { $version } 버전을 사용할 수 있습니다.
+}
+    { $url }
+weekly-summary-title = restart-fix 주간 요약
+weekly-summary-text = { $restarts ->
+    [0] 이번 주에는 예기치 않은 재시작이 없었습니다(지난주 { $previous }회).
+   *[other] 이번 주 예기치 않은 재시작: { $restarts }회(지난주 { $previous }회)
+    원인: { $causes }
+    자동 종료: { $shutdowns }회, 취소 또는 알림만: { $notified }회
+}
@@ -0,0 +1,1247 @@
+use std::{
+    env,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use restart_fix_core::{Admission, CancellationToken, PromptId, StopReason, Stopper};
+use winsafe::{co, prelude::*, HWND};
+
+use crate::{
+    active_hours::ActiveHours,
+    alert,
+    app_state::AppState,
+    audit, boot, close_apps, countdown_window, dialog_placement,
+    email::{self, Email},
+    error::{self, Error},
+    hook,
+    i18n::Locale,
+    metrics, notifier, overlay_window,
+    policy::{
+        Action, Battery, Cause, DialogButton, LockedSession, OtherSessions, PromptMode,
+        RemoteSession, ShutdownLimit,
+    },
+    pool_timer::{self, Next},
+    power, process_list, session,
+    session_arbiter::SessionArbiter,
+    session_lock,
+    shutdown_log::ShutdownLog,
+    shutdown_privilege,
+    snooze::{Snooze, SNOOZE_DURATION},
+    taskbar,
+    template::{self, Template},
+    toast,
+    webhook::{self, Webhook},
+    windows_update,
+};
+
+// Time between checks for input during the countdown. Short enough to feel like a reaction to it.
+static INPUT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+//how restart detected within threshold is acted on
+#[derive(Clone)]
+pub struct ActionSettings {
+    //cause of the detected restart. logged when acting without asking.
+    pub cause: Cause,
+    //scheduled task that initiated the restart. named in the built-in dialog text.
+    pub task: Option<String>,
+    pub prompt_mode: PromptMode,
+    //countdown of the shutdown dialog
+    pub timeout: Duration,
+    pub silent_delay: Duration,
+    //"Extend" choice of the dialog. zero removes it.
+    pub extend_by: Duration,
+    //buttons of the countdown dialog, cancel first
+    pub dialog_buttons: Vec<DialogButton>,
+    //countdown lasts at least this long while a screen reader is running
+    pub screen_reader_timeout: Duration,
+    pub locked_session: LockedSession,
+    //played and spoken when the countdown starts
+    pub alert_sound: Option<String>,
+    pub announce: bool,
+    //organization's wording of the countdown dialog
+    pub dialog_title: Option<Template>,
+    pub dialog_text: Option<Template>,
+    //command run before the computer is shut down or hibernated
+    pub pre_shutdown_cmd: Option<String>,
+    pub pre_shutdown_timeout: Duration,
+    //applications asked to close before shutdown. shutdown is aborted if they don't with
+    //abort_if_apps_open.
+    pub close_apps: Vec<String>,
+    pub close_apps_timeout: Duration,
+    pub abort_if_apps_open: bool,
+    pub battery: Battery,
+    pub respect_active_hours: bool,
+    pub quiet_hours: Option<ActiveHours>,
+    pub remote_session: RemoteSession,
+    pub other_sessions: OtherSessions,
+    //user who gave input within this window is only notified. zero disables it.
+    pub active_input_window: Duration,
+    //input during the countdown cancels it
+    pub cancel_on_input: bool,
+    //countdown is held while the user gave input this recently. zero disables it.
+    pub pause_on_input: Duration,
+    //persisted "Snooze 1 hour" choice of the dialog
+    pub snooze: Snooze,
+    pub shutdown_limit: ShutdownLimit,
+    //automatic shutdowns carried out so far, counted against the limit
+    pub shutdowns: ShutdownLog,
+    //admins notified when the computer is shut down or hibernated
+    pub email: Email,
+    pub webhooks: Vec<Webhook>,
+    pub dry_run: bool,
+}
+
+//where the user is told and asked. the monitor shows its prompts in its own session, the service
+//in those of its agents.
+pub trait Frontend {
+    fn lock_screen(&self, dry_run: bool);
+
+    fn show_restart_notice(&self);
+
+    //asks before `action` is carried out as `settings` say. may block until it is answered.
+    fn prompt(
+        &self,
+        action: Action,
+        settings: &ActionSettings,
+        state: &Arc<AppState>,
+        stopper: Stopper,
+    );
+
+    //countdown of `timeout` that shuts nothing down
+    fn test_prompt(&self, timeout: Duration, pause_on_input: Duration, state: &AppState);
+}
+
+//session restart-fix runs in
+pub struct Desktop;
+
+impl Frontend for Desktop {
+    fn lock_screen(&self, dry_run: bool) {
+        lock_screen(dry_run);
+    }
+
+    fn show_restart_notice(&self) {
+        show_restart_notice();
+    }
+
+    fn prompt(
+        &self,
+        action: Action,
+        settings: &ActionSettings,
+        state: &Arc<AppState>,
+        stopper: Stopper,
+    ) {
+        prompt(action, settings, state, stopper);
+    }
+
+    fn test_prompt(&self, timeout: Duration, pause_on_input: Duration, state: &AppState) {
+        let locale = Locale::current();
+        show_shutdown_dialog(DialogOptions {
+            timeout,
+            pause_on_input,
+            title: locale.shutdown_title(),
+            text: locale.countdown_text(Action::Shutdown, timeout, None, None),
+            countdown: state.start_countdown(Some(timeout)),
+            on_expired: None,
+            on_cancelled: None,
+            choices: None,
+            arbiter: None,
+        });
+    }
+}
+
+pub fn take(
+    requested: Action,
+    settings: &ActionSettings,
+    state: &Arc<AppState>,
+    stopper: Stopper,
+    frontend: &dyn Frontend,
+) {
+    let action = adjust(requested, settings);
+    match action {
+        Action::Nothing => {}
+        Action::LockScreen => frontend.lock_screen(settings.dry_run),
+        Action::NotifyOnly => frontend.show_restart_notice(),
+        Action::Shutdown | Action::Hibernate | Action::RebootToFirmware => {
+            frontend.prompt(action, settings, state, stopper)
+        }
+    }
+}
+
+//prompt for shutdown, hibernation or restart into firmware setup as `settings` say, regardless of
+//the circumstances of the moment
+pub fn prompt(action: Action, settings: &ActionSettings, state: &Arc<AppState>, stopper: Stopper) {
+    let prompt = Prompt {
+        action,
+        settings: settings.clone(),
+        stopper,
+    };
+    request_prompt(prompt, state, |id, prompt| {
+        show_prompt(id, prompt, Arc::clone(state))
+    });
+}
+
+//ask to prompt as `concurrent_prompts` says. `show` is given the prompt if none is shown already,
+//and reports its end to the controller of `state`.
+pub fn request_prompt(prompt: Prompt, state: &AppState, show: impl FnOnce(PromptId, Prompt)) {
+    match state.controller().request(prompt) {
+        Admission::Start(id, prompt) => show(id, prompt),
+        Admission::Merged(_) => log::info!("shutdown prompt is already shown. merged"),
+        Admission::Queued => log::info!("shutdown prompt is already shown. queued"),
+        Admission::Ignored => log::info!("shutdown prompt is already shown. ignored"),
+    }
+}
+
+//action carried out for `requested` under the circumstances of the moment(battery, hours, input,
+//remote sessions and the shutdown limit)
+pub fn adjust(requested: Action, settings: &ActionSettings) -> Action {
+    let action = adjust_for_battery(requested, settings.battery);
+    let action = adjust_for_active_hours(action, settings.respect_active_hours);
+    let action = adjust_for_quiet_hours(action, settings.quiet_hours);
+    let action = adjust_for_input(action, settings.active_input_window);
+    let action = adjust_for_remote_session(action, settings.remote_session);
+    let action = adjust_for_limit(action, settings);
+    audit::action(requested, action);
+    action
+}
+
+//power off once nobody cancelled. pre-shutdown command may still abort it.
+pub fn carry_out(action: Action, settings: &ActionSettings, stopper: &Stopper) {
+    if !run_pre_shutdown(settings) {
+        return;
+    }
+    //hibernation keeps applications open
+    if action != Action::Hibernate && !ask_apps_to_close(settings) {
+        return;
+    }
+    if !settings.dry_run {
+        if let Err(e) = settings.shutdowns.record(Utc::now()) {
+            log::warn!("unable to record automatic shutdown: {e:#}");
+        }
+        metrics::shutdown_performed();
+        email::send_shutdown(&settings.email, settings.cause, action);
+        webhook::send_shutdown(&settings.webhooks, settings.cause, action);
+    }
+    match action {
+        Action::Hibernate => hibernate(settings.dry_run),
+        Action::RebootToFirmware => stopper.stop(StopReason::RebootToFirmware),
+        _ => stopper.stop(StopReason::Shutdown),
+    }
+}
+
+//shutdown or hibernation waiting to be prompted for
+#[derive(Clone)]
+pub struct Prompt {
+    pub action: Action,
+    pub settings: ActionSettings,
+    pub stopper: Stopper,
+}
+
+fn show_prompt(id: PromptId, prompt: Prompt, state: Arc<AppState>) {
+    let Prompt {
+        mut action,
+        settings,
+        stopper,
+    } = prompt.clone();
+    let mut prompt_mode = settings.prompt_mode;
+    if session_lock::is_locked() {
+        match settings.locked_session {
+            LockedSession::Dialog => {}
+            LockedSession::Wait => {
+                log::info!("workstation is locked. prompt is shown once it is unlocked");
+                session_lock::wait_until_unlocked();
+                if state.is_paused() {
+                    log::info!("monitoring was paused while locked. shutdown prompt is dropped");
+                    show_next_prompt(id, state);
+                    return;
+                }
+            }
+            LockedSession::Silent => {
+                log::info!("workstation is locked. acting without dialog");
+                prompt_mode = PromptMode::Silent;
+            }
+            LockedSession::Hibernate => {
+                log::info!("workstation is locked. hibernating without dialog");
+                action = Action::Hibernate;
+                prompt_mode = PromptMode::Silent;
+            }
+        }
+    }
+    let arbiter = open_session_arbiter();
+    if arbiter
+        .as_ref()
+        .is_some_and(|arbiter| arbiter.is_cancelled())
+    {
+        log::info!("shutdown was already cancelled in other session");
+        show_next_prompt(id, state);
+        return;
+    }
+    let cause = settings.cause;
+    let extend_by = Some(settings.extend_by).filter(|extend_by| !extend_by.is_zero());
+    let (dialog_title, dialog_text) = (settings.dialog_title.clone(), settings.dialog_text.clone());
+    let task = settings.task.clone();
+    let pause_on_input = settings.pause_on_input;
+    let timeout = match prompt_mode {
+        PromptMode::Countdown => Some(countdown_timeout(&settings)),
+        PromptMode::Silent => Some(settings.silent_delay),
+        PromptMode::Confirm => None,
+    };
+    let countdown = state.start_countdown(timeout);
+    if let Some(timeout) = timeout {
+        alert(&settings, action, timeout);
+        if settings.cancel_on_input {
+            watch_input(countdown.clone(), arbiter.clone());
+        }
+        if prompt_mode == PromptMode::Countdown && settings.other_sessions != OtherSessions::Ignore
+        {
+            warn_other_sessions(
+                (action, timeout),
+                settings.other_sessions,
+                countdown.clone(),
+                arbiter.clone(),
+            );
+        }
+    }
+    let on_expired: OnEnded = {
+        let state = Arc::clone(&state);
+        Box::new(move || expire(id, action, &settings, &stopper, &state))
+    };
+    let on_cancelled: OnEnded = {
+        let state = Arc::clone(&state);
+        Box::new(move || {
+            state.countdown_ended(true);
+            show_next_prompt(id, state);
+        })
+    };
+    let locale = Locale::current();
+    match (prompt_mode, timeout) {
+        //only confirm mode has no timeout
+        (_, None) => {
+            let text = locale.confirm_text(action);
+            show_confirm_dialog(text, countdown, on_expired, on_cancelled, arbiter);
+        }
+        (PromptMode::Silent, Some(delay)) => {
+            //nobody is there to read a dialog. decision is logged for auditing instead.
+            log::warn!(
+                "silent mode: {action:?} in {}s without dialog(cause: {})",
+                delay.as_secs(),
+                cause.as_str()
+            );
+            if let Some(arbiter) = arbiter {
+                watch_arbiter(arbiter, countdown.clone());
+            }
+            Countdown::start(CountdownSpec {
+                timeout: delay,
+                pause_on_input: Duration::ZERO,
+                deadline: Arc::new(Mutex::new(Instant::now() + delay)),
+                token: countdown,
+                response: Arc::new(Mutex::new(None)),
+                on_expired: Some(on_expired),
+                on_cancelled: Some(on_cancelled),
+                on_answered: None,
+            });
+        }
+        (_, Some(timeout)) => {
+            let values = template::Values {
+                timeout,
+                cause,
+                boot_time: boot::boot_time(),
+            };
+            let title =
+                dialog_title.map_or_else(|| locale.shutdown_title(), |title| title.render(&values));
+            //task is named only in the built-in wording, which has no placeholder for it
+            let task = task.filter(|_| dialog_text.is_none());
+            //hint is kept so that the buttons are always explained
+            let text = match dialog_text {
+                Some(text) => format!(
+                    "{}\r\n{}",
+                    text.render(&values),
+                    locale.cancel_hint(Some(SNOOZE_DURATION), extend_by)
+                ),
+                None => locale.countdown_text(action, timeout, Some(SNOOZE_DURATION), extend_by),
+            };
+            let text = match task {
+                Some(task) => format!("{}\r\n{text}", locale.scheduled_task_text(&task)),
+                None => text,
+            };
+            let buttons = dialog_buttons(&prompt.settings.dialog_buttons, action, extend_by);
+            let choices = Choices {
+                on_answered: Box::new(move |answer| match answer {
+                    Answer::Postpone(postpone) => postpone_prompt(id, prompt, postpone, state),
+                    Answer::Instead(instead) => {
+                        log::info!("user chose {instead:?} instead of {action:?}");
+                        expire(id, instead, &prompt.settings, &prompt.stopper, &state)
+                    }
+                }),
+                extend_by,
+                buttons,
+            };
+            show_shutdown_dialog(DialogOptions {
+                timeout,
+                pause_on_input,
+                title,
+                text,
+                countdown,
+                on_expired: Some(on_expired),
+                on_cancelled: Some(on_cancelled),
+                choices: Some(choices),
+                arbiter,
+            });
+        }
+    }
+}
+
+//countdown ended by expiring or by the user acting right away. only the first expired prompt
+//shuts the computer down.
+fn expire(
+    id: PromptId,
+    action: Action,
+    settings: &ActionSettings,
+    stopper: &Stopper,
+    state: &AppState,
+) {
+    state.countdown_ended(false);
+    if state.controller().expired(id) {
+        carry_out(action, settings, stopper);
+    }
+}
+
+//configured buttons with their labels. those that wouldn't do anything different are left out.
+fn dialog_buttons(
+    configured: &[DialogButton],
+    action: Action,
+    extend_by: Option<Duration>,
+) -> Vec<(DialogButton, String)> {
+    let locale = Locale::current();
+    configured
+        .iter()
+        .filter_map(|&button| {
+            let label = match button {
+                DialogButton::Cancel => locale.cancel_button(),
+                DialogButton::Snooze => locale.snooze_button(SNOOZE_DURATION),
+                DialogButton::Extend => locale.extend_button(extend_by?),
+                DialogButton::ActNow => locale.act_now_button(action),
+                DialogButton::Hibernate if action == Action::Hibernate => return None,
+                DialogButton::Hibernate => locale.act_now_button(Action::Hibernate),
+            };
+            Some((button, label))
+        })
+        .collect()
+}
+
+//listening to the dialog and finding its buttons takes longer than reading it
+fn countdown_timeout(settings: &ActionSettings) -> Duration {
+    if settings.timeout < settings.screen_reader_timeout && countdown_window::screen_reader_active()
+    {
+        log::info!(
+            "screen reader is running. countdown is extended to {}s",
+            settings.screen_reader_timeout.as_secs()
+        );
+        return settings.screen_reader_timeout;
+    }
+    settings.timeout
+}
+
+//users who aren't looking at the screen get a warning as well
+fn alert(settings: &ActionSettings, action: Action, timeout: Duration) {
+    if let Some(sound) = &settings.alert_sound {
+        if let Err(e) = alert::play(sound) {
+            log::warn!("unable to play {sound}: {e}");
+        }
+    }
+    if settings.announce {
+        alert::speak(Locale::current().announcement(action, timeout));
+    }
+}
+
+//prompt stays active while postponed, so restarts detected meanwhile are merged or queued into it
+fn postpone_prompt(id: PromptId, prompt: Prompt, postpone: Postpone, state: Arc<AppState>) {
+    let duration = match postpone {
+        Postpone::Snooze => SNOOZE_DURATION,
+        Postpone::Extend(extend_by) => extend_by,
+    };
+    let until = Utc::now() + chrono::Duration::seconds(duration.as_secs() as i64);
+    if postpone == Postpone::Snooze {
+        //snooze still ends in this process, it just isn't remembered by the next one
+        if let Err(e) = prompt.settings.snooze.set(until) {
+            log::warn!("unable to persist snooze: {e:#}");
+        }
+    }
+    log::info!("shutdown postponed until {}", until.to_rfc3339());
+    state.countdown_snoozed(until);
+    thread::spawn(move || {
+        thread::sleep(duration);
+        if postpone == Postpone::Snooze {
+            if let Err(e) = prompt.settings.snooze.clear() {
+                log::warn!("unable to clear snooze: {e:#}");
+            }
+        }
+        if state.is_paused() {
+            log::info!("monitoring is paused. postponed shutdown prompt is dropped");
+            show_next_prompt(id, state);
+            return;
+        }
+        log::info!("postponement ended. showing shutdown prompt again");
+        show_prompt(id, prompt, state);
+    });
+}
+
+//prompt queued while `cancelled` was shown
+fn show_next_prompt(cancelled: PromptId, state: Arc<AppState>) {
+    if let Some((id, prompt)) = next_prompt(cancelled, &state) {
+        show_prompt(id, prompt, state);
+    }
+}
+
+//report that prompt `cancelled` ended without shutting down. returns the queued prompt to show
+//next, if any. those queued while paused are dropped.
+pub fn next_prompt(mut cancelled: PromptId, state: &AppState) -> Option<(PromptId, Prompt)> {
+    while let Some((id, prompt)) = state.controller().cancelled(cancelled) {
+        if !state.is_paused() {
+            log::info!("showing queued shutdown prompt");
+            return Some((id, prompt));
+        }
+        log::info!("monitoring is paused. queued shutdown prompt is dropped");
+        cancelled = id;
+    }
+    None
+}
+
+//forced power-off on the go discards unsaved work
+fn adjust_for_battery(action: Action, battery: Battery) -> Action {
+    if !action.powers_off() {
+        return action;
+    }
+    let status = match power::status() {
+        Ok(status) if status.on_battery => status,
+        Ok(_) => return action,
+        Err(e) => {
+            log::warn!("unable to query power status: {e}");
+            return action;
+        }
+    };
+    if status
+        .battery_percent
+        .is_some_and(|percent| percent < battery.min_percent)
+    {
+        log::info!(
+            "running on battery below {}%. notifying only",
+            battery.min_percent
+        );
+        return Action::NotifyOnly;
+    }
+    if action == Action::Shutdown && battery.prefer_hibernate {
+        log::info!("running on battery. hibernating instead of shutting down");
+        return Action::Hibernate;
+    }
+    action
+}
+
+//user said the computer is in use during active hours
+fn adjust_for_active_hours(action: Action, respect: bool) -> Action {
+    if !respect || !action.powers_off() {
+        return action;
+    }
+    match ActiveHours::read() {
+        Ok(Some(hours)) if hours.contains_now() => {
+            log::info!("inside active hours({hours}). notifying only");
+            Action::NotifyOnly
+        }
+        Ok(_) => action,
+        Err(e) => {
+            log::warn!("unable to read active hours: {e:#}");
+            action
+        }
+    }
+}
+
+//hours the organization set aside, e.g. nights when nobody should find the computer off
+fn adjust_for_quiet_hours(action: Action, quiet_hours: Option<ActiveHours>) -> Action {
+    match quiet_hours {
+        Some(hours) if hours.contains_now() && action.powers_off() => {
+            log::info!("inside quiet hours({hours}). notifying only");
+            Action::NotifyOnly
+        }
+        _ => action,
+    }
+}
+
+//user at the keyboard must not have the computer pulled out from under them
+fn adjust_for_input(action: Action, window: Duration) -> Action {
+    if window.is_zero() || !action.powers_off() {
+        return action;
+    }
+    match session::time_since_input() {
+        Ok(idle) if idle < window => {
+            log::info!("user gave input {}s ago. notifying only", idle.as_secs());
+            Action::NotifyOnly
+        }
+        Ok(_) => action,
+        Err(e) => {
+            log::warn!("unable to query last input: {e}");
+            action
+        }
+    }
+}
+
+fn adjust_for_remote_session(action: Action, remote_session: RemoteSession) -> Action {
+    if remote_session == RemoteSession::Dialog || !action.powers_off() {
+        return action;
+    }
+    match session::active_remote_session() {
+        Ok(Some(id)) => {
+            log::info!("remote desktop session {id} is active. {remote_session:?} applies");
+            if remote_session == RemoteSession::Skip {
+                Action::Nothing
+            } else {
+                Action::NotifyOnly
+            }
+        }
+        Ok(None) => action,
+        Err(e) => {
+            log::warn!("unable to list remote desktop sessions: {e}");
+            action
+        }
+    }
+}
+
+//computer already powered off automatically as often as allowed
+fn adjust_for_limit(action: Action, settings: &ActionSettings) -> Action {
+    let limit = settings.shutdown_limit;
+    if limit.count == 0 || !action.powers_off() {
+        return action;
+    }
+    let since = chrono::Duration::from_std(limit.window)
+        .ok()
+        .and_then(|window| Utc::now().checked_sub_signed(window))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    match settings.shutdowns.since(since) {
+        Ok(count) if count >= limit.count as usize => {
+            log::warn!(
+                "{count} automatic shutdowns within {}s already. notifying only",
+                limit.window.as_secs()
+            );
+            Action::NotifyOnly
+        }
+        Ok(_) => action,
+        Err(e) => {
+            log::warn!("unable to read automatic shutdowns: {e:#}");
+            action
+        }
+    }
+}
+
+fn show_restart_notice() {
+    thread::spawn(|| {
+        let locale = Locale::current();
+        notifier::notify(
+            &locale.restart_notice_title(),
+            &locale.restart_notice_text(),
+        );
+    });
+}
+
+pub fn lock_screen(dry_run: bool) {
+    if dry_run {
+        log::info!("dry run: would have locked the workstation and turned the displays off now");
+        return;
+    }
+    log::info!("locking the workstation and turning the displays off");
+    if let Err(e) = power::lock_and_turn_off_displays() {
+        log::error!("unable to lock the workstation: {e}");
+    }
+}
+
+//unlike shutdown, monitoring goes on after resume. so it is done without stopping background worker.
+pub fn hibernate(dry_run: bool) {
+    if dry_run {
+        log::info!("dry run: would have hibernated now");
+        let locale = Locale::current();
+        notifier::notify(
+            &locale.shutdown_title(),
+            &locale.dry_run_text(Action::Hibernate),
+        );
+        return;
+    }
+    log::info!("hibernating");
+    if let Err(e) = shutdown_privilege::enable() {
+        error::report_action(&Error::ShutdownPrivilege(e));
+        return;
+    }
+    if let Err(e) = system_shutdown::hibernate() {
+        log::error!("unable to hibernate the computer: {e}");
+    }
+}
+
+//without arbiter, countdown is only cancelled from this session
+fn open_session_arbiter() -> Option<Arc<SessionArbiter>> {
+    let arbiter = boot::boot_id().and_then(|boot_id| Ok(SessionArbiter::open(boot_id)?));
+    match arbiter {
+        Ok(arbiter) => Some(Arc::new(arbiter)),
+        Err(e) => {
+            log::warn!("unable to share shutdown prompt with other sessions: {e:#}");
+            None
+        }
+    }
+}
+
+//returns false if shutdown must be aborted
+fn run_pre_shutdown(settings: &ActionSettings) -> bool {
+    if !settings.dry_run {
+        windows_update::wait_until_installed();
+    } else if let Some(installer) = windows_update::running_installer() {
+        log::info!("dry run: would have waited for updates to finish installing({installer})");
+    }
+    let Some(command) = &settings.pre_shutdown_cmd else {
+        return true;
+    };
+    if settings.dry_run {
+        log::info!("dry run: would have run pre-shutdown command: {command}");
+        return true;
+    }
+    match hook::run_pre_shutdown(command, settings.pre_shutdown_timeout) {
+        Ok(()) => true,
+        Err(e) => {
+            log::error!("shutdown is aborted: {e:#}");
+            false
+        }
+    }
+}
+
+//returns false if shutdown must be aborted
+fn ask_apps_to_close(settings: &ActionSettings) -> bool {
+    if settings.close_apps.is_empty() {
+        return true;
+    }
+    if settings.dry_run {
+        log::info!(
+            "dry run: would have asked {} to close",
+            settings.close_apps.join(", ")
+        );
+        return true;
+    }
+    let open = match close_apps::close(&settings.close_apps, settings.close_apps_timeout) {
+        Ok(open) => open,
+        Err(e) => {
+            log::warn!("unable to close applications: {e}");
+            return true;
+        }
+    };
+    if open.is_empty() {
+        return true;
+    }
+    let open = open.join(", ");
+    if !settings.abort_if_apps_open {
+        log::warn!("{open} didn't close. shutting down anyway");
+        return true;
+    }
+    log::error!("shutdown is aborted: {open} didn't close");
+    thread::spawn(move || {
+        let locale = Locale::current();
+        notifier::notify(&locale.shutdown_title(), &locale.apps_open_text(&open));
+    });
+    false
+}
+
+//what happens when countdown expires or is cancelled
+type OnEnded = Box<dyn FnOnce() + Send>;
+
+//how the user put the countdown off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Postpone {
+    //"Snooze 1 hour". persisted across restarts.
+    Snooze,
+    //"Extend by N minutes"
+    Extend(Duration),
+}
+
+//answer of the dialog other than cancelling, read by the countdown once it is cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Response {
+    Answer(Answer),
+    //"Shut down now". acts as if the countdown expired.
+    Now,
+}
+
+//answer acted on by whoever offered the choices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Postpone(Postpone),
+    //e.g. "Hibernate now" instead of shutting down
+    Instead(Action),
+}
+
+type OnAnswered = Box<dyn FnOnce(Answer) + Send>;
+
+//choices of the dialog besides cancelling
+pub struct Choices {
+    on_answered: OnAnswered,
+    //"Extend" is offered along with "Snooze" if set
+    extend_by: Option<Duration>,
+    //buttons of the countdown window with their labels, cancel first
+    buttons: Vec<(DialogButton, String)>,
+}
+
+//countdown dialog shown by show_shutdown_dialog. nothing happens when it ends without the handlers.
+pub struct DialogOptions {
+    pub timeout: Duration,
+    //countdown is held while the user gave input this recently. zero never holds it.
+    pub pause_on_input: Duration,
+    pub title: String,
+    pub text: String,
+    pub countdown: CancellationToken,
+    pub on_expired: Option<OnEnded>,
+    pub on_cancelled: Option<OnEnded>,
+    pub choices: Option<Choices>,
+    //countdowns in other sessions, cancelled along with this one
+    pub arbiter: Option<Arc<SessionArbiter>>,
+}
+
+pub fn show_shutdown_dialog(options: DialogOptions) {
+    let DialogOptions {
+        timeout,
+        pause_on_input,
+        title,
+        text,
+        countdown,
+        on_expired,
+        on_cancelled,
+        choices,
+        arbiter,
+    } = options;
+    //dialog offers postponing only if someone acts on it
+    let postponable = choices.is_some();
+    let (on_answered, extend_by, buttons) = match choices {
+        Some(Choices {
+            on_answered,
+            extend_by,
+            buttons,
+        }) => (Some(on_answered), extend_by, buttons),
+        None => (None, None, Vec::new()),
+    };
+    let response = Arc::new(Mutex::new(None));
+    if let Some(arbiter) = arbiter.clone() {
+        watch_arbiter(arbiter, countdown.clone());
+    }
+    thread::spawn(move || {
+        let _span = tracing::debug_span!(
+            "shutdown_dialog",
+            timeout_secs = timeout.as_secs(),
+            postponable
+        )
+        .entered();
+        let deadline = Arc::new(Mutex::new(Instant::now() + timeout));
+        Countdown::start(CountdownSpec {
+            timeout,
+            pause_on_input,
+            deadline: Arc::clone(&deadline),
+            token: countdown.clone(),
+            response: Arc::clone(&response),
+            on_expired,
+            on_cancelled,
+            on_answered,
+        });
+        //sleep or blank display would let the countdown expire unseen as well
+        let _awake = power::KeepAwake::new();
+        {
+            let deadline = Arc::clone(&deadline);
+            dialog_placement::on_shown(move |hwnd| taskbar::track(hwnd, deadline, timeout));
+        }
+        //without choices cancel is the only button
+        let buttons = if buttons.is_empty() {
+            vec![(DialogButton::Cancel, Locale::current().cancel_button())]
+        } else {
+            buttons
+        };
+        let toast_buttons = buttons.clone();
+        //countdown is useless if the dialog is hidden behind other windows until it expires
+        //enter picks yes, which cancels. escape picks cancel where the dialog has it.
+        let shown = dialog_placement::in_front(|| match (postponable, extend_by) {
+            //cancel is the only choice, so the warning can't be misread
+            _ if overlay_window::is_enabled() => overlay_window::show(&title, &text, deadline)
+                .map(|()| None)
+                .map_err(|e| format!("{e:#}")),
+            _ if countdown_window::is_used() => {
+                countdown_window::show(&title, &text, deadline, buttons)
+                    .map(|button| button_response(button, extend_by))
+                    .map_err(|e| format!("{e:#}"))
+            }
+            //yes cancels, no snoozes and cancel(or closing the dialog) extends
+            (true, Some(extend_by)) => HWND::NULL
+                .MessageBox(
+                    &text,
+                    &title,
+                    co::MB::YESNOCANCEL
+                        | co::MB::DEFBUTTON1
+                        | co::MB::ICONINFORMATION
+                        | co::MB::TOPMOST
+                        | co::MB::SETFOREGROUND,
+                )
+                .map(|answer| match answer {
+                    co::DLGID::YES => None,
+                    co::DLGID::NO => Some(Response::Answer(Answer::Postpone(Postpone::Snooze))),
+                    _ => Some(Response::Answer(Answer::Postpone(Postpone::Extend(
+                        extend_by,
+                    )))),
+                })
+                .map_err(|e| e.to_string()),
+            (true, None) => HWND::NULL
+                .MessageBox(
+                    &text,
+                    &title,
+                    co::MB::YESNO
+                        | co::MB::DEFBUTTON1
+                        | co::MB::ICONINFORMATION
+                        | co::MB::TOPMOST
+                        | co::MB::SETFOREGROUND,
+                )
+                .map(|answer| {
+                    (answer == co::DLGID::NO)
+                        .then_some(Response::Answer(Answer::Postpone(Postpone::Snooze)))
+                })
+                .map_err(|e| e.to_string()),
+            (false, _) => notifier::message_box(&title, &text)
+                .map(|()| None)
+                .map_err(|e| e.to_string()),
+        });
+        tracing::debug!(?shown, "shutdown dialog closed");
+        match shown {
+            Ok(answer) => answer_countdown(answer, &countdown, &response, arbiter.as_deref()),
+            Err(e) => {
+                log::error!("unable to display shutdown dialog: {e}");
+                let toast = CountdownToast {
+                    buttons: toast_buttons,
+                    extend_by,
+                    countdown: countdown.clone(),
+                    response,
+                    arbiter,
+                };
+                //never shut down without warning the user. cancel countdown if neither dialog nor
+                //toast can be shown.
+                if !toast.show(&title, &text) {
+                    countdown.cancel();
+                }
+            }
+        }
+    });
+}
+
+//what pressing `button` of the countdown answers. None cancels.
+fn button_response(button: DialogButton, extend_by: Option<Duration>) -> Option<Response> {
+    match (button, extend_by) {
+        (DialogButton::Snooze, _) => Some(Response::Answer(Answer::Postpone(Postpone::Snooze))),
+        (DialogButton::Extend, Some(extend_by)) => Some(Response::Answer(Answer::Postpone(
+            Postpone::Extend(extend_by),
+        ))),
+        (DialogButton::ActNow, _) => Some(Response::Now),
+        (DialogButton::Hibernate, _) => Some(Response::Answer(Answer::Instead(Action::Hibernate))),
+        _ => None,
+    }
+}
+
+//end the countdown with the answer of the user, whichever way it was given. `None` cancels.
+fn answer_countdown(
+    answer: Option<Response>,
+    countdown: &CancellationToken,
+    response: &Mutex<Option<Response>>,
+    arbiter: Option<&SessionArbiter>,
+) {
+    if let Some(answer) = answer {
+        //read by the countdown on its tick after cancelling below
+        *response.lock().unwrap() = Some(answer);
+        if countdown.cancel() {
+            match answer {
+                Response::Answer(Answer::Postpone(_)) => log::info!("shutdown postponed by user"),
+                Response::Answer(Answer::Instead(_)) | Response::Now => {
+                    log::info!("shutdown confirmed by user")
+                }
+            }
+        }
+        //postponing is local to this session. other sessions go on with their countdowns.
+        return;
+    }
+    //countdown may already be expired or cancelled over ipc
+    if countdown.cancel() {
+        log::info!("shutdown cancelled by user");
+        //first response wins. countdowns in other sessions are cancelled as well.
+        if let Some(arbiter) = arbiter {
+            arbiter.cancel();
+        }
+    }
+}
+
+//toast standing in for a countdown dialog that can't be shown. its buttons answer like those of
+//the dialog while restart-fix runs.
+struct CountdownToast {
+    buttons: Vec<(DialogButton, String)>,
+    extend_by: Option<Duration>,
+    countdown: CancellationToken,
+    response: Arc<Mutex<Option<Response>>>,
+    arbiter: Option<Arc<SessionArbiter>>,
+}
+
+impl CountdownToast {
+    //blocks until the countdown ended, then hides the toast. returns false if toasts are
+    //unavailable as well.
+    fn show(self, title: &str, text: &str) -> bool {
+        let labels: Vec<String> = self
+            .buttons
+            .iter()
+            .map(|(_, label)| label.clone())
+            .collect();
+        let countdown = self.countdown.clone();
+        let on_button = move |index: usize| {
+            if let Some(&(button, _)) = self.buttons.get(index) {
+                let answer = button_response(button, self.extend_by);
+                answer_countdown(
+                    answer,
+                    &self.countdown,
+                    &self.response,
+                    self.arbiter.as_deref(),
+                );
+            }
+        };
+        match toast::show(title, text, &labels, on_button) {
+            Ok(toast) => {
+                log::info!("countdown is shown as a toast instead");
+                countdown.wait();
+                toast.hide();
+                true
+            }
+            Err(e) => {
+                log::error!("unable to show countdown toast: {e:#}");
+                false
+            }
+        }
+    }
+}
+
+//nothing happens unless the user answers yes. there is no timeout that defaults to shutdown.
+fn show_confirm_dialog(
+    text: String,
+    countdown: CancellationToken,
+    on_confirmed: OnEnded,
+    on_cancelled: OnEnded,
+    arbiter: Option<Arc<SessionArbiter>>,
+) {
+    if let Some(arbiter) = arbiter.clone() {
+        watch_arbiter(arbiter, countdown.clone());
+    }
+    let response = Arc::new(Mutex::new(None));
+    {
+        let (countdown, response) = (countdown.clone(), Arc::clone(&response));
+        thread::spawn(move || {
+            //once cancelled, a late answer can't act anymore
+            countdown.wait();
+            if *response.lock().unwrap() == Some(Response::Now) {
+                log::info!("shutdown confirmed by user");
+                on_confirmed();
+            } else {
+                on_cancelled();
+            }
+        });
+    }
+    thread::spawn(move || {
+        let shown = dialog_placement::in_front(|| {
+            HWND::NULL
+                .MessageBox(
+                    &text,
+                    &Locale::current().shutdown_title(),
+                    co::MB::YESNO | co::MB::ICONQUESTION,
+                )
+                .map(|answer| answer == co::DLGID::YES)
+        });
+        match shown {
+            Ok(true) => {
+                *response.lock().unwrap() = Some(Response::Now);
+                countdown.cancel();
+            }
+            Ok(false) => {
+                if countdown.cancel() {
+                    log::info!("shutdown declined by user");
+                    if let Some(arbiter) = arbiter {
+                        arbiter.cancel();
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("unable to display shutdown dialog: {e}");
+                countdown.cancel();
+            }
+        }
+    });
+}
+
+//cancel countdown on the first keyboard or mouse input after it started. polling ends with the
+//countdown.
+fn watch_input(countdown: CancellationToken, arbiter: Option<Arc<SessionArbiter>>) {
+    let started = Instant::now();
+    pool_timer::start(INPUT_POLL_INTERVAL, Duration::ZERO, move || {
+        if countdown.is_cancelled() {
+            return Next::Stop;
+        }
+        let touched = session::time_since_input().is_ok_and(|idle| idle < started.elapsed());
+        if touched && countdown.cancel() {
+            log::info!("shutdown cancelled by user input");
+            if let Some(arbiter) = &arbiter {
+                arbiter.cancel();
+            }
+            return Next::Stop;
+        }
+        Next::After(INPUT_POLL_INTERVAL)
+    });
+}
+
+//show countdown in active sessions of other users, which restart-fix of their own doesn't
+//prompt. with `OtherSessions::Object` answering yes in any of them cancels the shutdown.
+fn warn_other_sessions(
+    (action, timeout): (Action, Duration),
+    other_sessions: OtherSessions,
+    countdown: CancellationToken,
+    arbiter: Option<Arc<SessionArbiter>>,
+) {
+    let sessions = match session::other_user_sessions() {
+        Ok(sessions) => sessions,
+        Err(e) => {
+            log::warn!("unable to list sessions of other users: {e}");
+            return;
+        }
+    };
+    let prompting = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_name()?.to_str().map(str::to_owned))
+        .and_then(|name| process_list::sessions_running(&name).ok())
+        .unwrap_or_default();
+    let can_cancel = other_sessions == OtherSessions::Object;
+    let locale = Locale::current();
+    let text = locale.other_session_text(action, timeout, can_cancel);
+    for session_id in sessions {
+        if prompting.contains(&session_id) {
+            continue;
+        }
+        log::info!("countdown is shown in session {session_id} of another user");
+        let (text, countdown, arbiter) = (text.clone(), countdown.clone(), arbiter.clone());
+        thread::spawn(move || {
+            let title = locale.shutdown_title();
+            match session::send_message(session_id, &title, &text, timeout, can_cancel) {
+                Ok(true) => {
+                    if countdown.cancel() {
+                        log::info!("shutdown cancelled by user of session {session_id}");
+                        if let Some(arbiter) = arbiter {
+                            arbiter.cancel();
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("unable to show countdown in session {session_id}: {e}"),
+            }
+        });
+    }
+}
+
+//cancel countdown once it is cancelled in other session
+fn watch_arbiter(arbiter: Arc<SessionArbiter>, countdown: CancellationToken) {
+    thread::spawn(move || {
+        arbiter.wait_cancelled();
+        if countdown.cancel() {
+            log::info!("shutdown cancelled in other session");
+        }
+    });
+}
+
+//countdown of a prompt. it is over once `token` is cancelled, by whoever gets there first.
+struct CountdownSpec {
+    timeout: Duration,
+    //countdown is held while the user gave input this recently, so nobody typing has to race it.
+    //zero never holds it.
+    pause_on_input: Duration,
+    //moved on while the countdown is held
+    deadline: Arc<Mutex<Instant>>,
+    token: CancellationToken,
+    //answer of the dialog, set before it cancels the token
+    response: Arc<Mutex<Option<Response>>>,
+    on_expired: Option<OnEnded>,
+    on_cancelled: Option<OnEnded>,
+    on_answered: Option<OnAnswered>,
+}
+
+//running countdown, ticked on the thread pool every INPUT_POLL_INTERVAL. each tick holds it, runs
+//it out or finds it ended, and the tick that ends it hands what follows to a thread of its own.
+struct Countdown {
+    spec: CountdownSpec,
+    //time left, not counting the time it was held
+    remaining: Duration,
+    last_tick: Instant,
+    held: bool,
+}
+
+impl Countdown {
+    fn start(spec: CountdownSpec) {
+        let mut countdown = Self {
+            remaining: spec.timeout,
+            last_tick: Instant::now(),
+            held: false,
+            spec,
+        };
+        let first = INPUT_POLL_INTERVAL.min(countdown.remaining);
+        pool_timer::start(first, Duration::ZERO, move || countdown.tick());
+    }
+
+    fn tick(&mut self) -> Next {
+        if self.spec.token.is_cancelled() {
+            return self.end(false);
+        }
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick);
+        self.last_tick = now;
+        let pause_on_input = self.spec.pause_on_input;
+        let typing = !pause_on_input.is_zero()
+            && session::time_since_input().is_ok_and(|idle| idle < pause_on_input);
+        if typing != self.held {
+            self.held = typing;
+            match typing {
+                true => log::info!("user is active. countdown is held"),
+                false => log::info!("user is idle. countdown goes on"),
+            }
+        }
+        if !self.held {
+            self.remaining = self.remaining.saturating_sub(elapsed);
+        }
+        *self.spec.deadline.lock().unwrap() = now + self.remaining;
+        if !self.remaining.is_zero() {
+            return Next::After(INPUT_POLL_INTERVAL.min(self.remaining));
+        }
+        //expiring ends the countdown like cancelling does. whoever is first wins.
+        let expired = self.spec.token.cancel();
+        self.end(expired)
+    }
+
+    fn end(&mut self, expired: bool) -> Next {
+        let response = *self.spec.response.lock().unwrap();
+        let on_expired = self.spec.on_expired.take();
+        let on_cancelled = self.spec.on_cancelled.take();
+        let on_answered = self.spec.on_answered.take();
+        //what follows may take minutes, e.g. waiting for updates, closing apps or waiting for the
+        //workstation to be unlocked for the next prompt, which must not hold up the thread pool
+        thread::spawn(move || {
+            let on_ended = match (expired, response, on_answered) {
+                (true, _, _) => {
+                    log::info!("shutdown countdown expired");
+                    on_expired
+                }
+                (false, Some(Response::Now), _) => on_expired,
+                (false, Some(Response::Answer(answer)), Some(on_answered)) => {
+                    on_answered(answer);
+                    return;
+                }
+                _ => on_cancelled,
+            };
+            if let Some(on_ended) = on_ended {
+                on_ended();
+            }
+        });
+        Next::Stop
+    }
+}
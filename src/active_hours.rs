@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use chrono::{Local, Timelike};
+use winsafe::{prelude::*, RegistryValue, HKEY};
+
+//set by the user in Settings > Windows Update > Advanced options
+const SETTINGS_KEY: &str = r"SOFTWARE\Microsoft\WindowsUpdate\UX\Settings";
+//set by group policy "Turn off auto-restart for updates during active hours". takes precedence.
+const POLICY_KEY: &str = r"SOFTWARE\Policies\Microsoft\Windows\WindowsUpdate";
+
+//hours of the day the user said the computer is in use. end is exclusive and may wrap past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveHours {
+    start: u32,
+    end: u32,
+}
+
+impl ActiveHours {
+    //None if active hours are not configured
+    pub fn read() -> anyhow::Result<Option<Self>> {
+        if matches!(dword(POLICY_KEY, "SetActiveHours"), Ok(Some(1))) {
+            if let (Some(start), Some(end)) = (
+                dword(POLICY_KEY, "ActiveHoursStart")?,
+                dword(POLICY_KEY, "ActiveHoursEnd")?,
+            ) {
+                return Ok(Some(Self { start, end }));
+            }
+        }
+        match (
+            dword(SETTINGS_KEY, "ActiveHoursStart")?,
+            dword(SETTINGS_KEY, "ActiveHoursEnd")?,
+        ) {
+            (Some(start), Some(end)) => Ok(Some(Self { start, end })),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&hour)
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+
+    pub fn contains_now(&self) -> bool {
+        self.contains(Local::now().hour())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid hours `{0}`(expected e.g. 22:00-07:00)")]
+pub struct ParseHoursError(String);
+
+//"22:00-07:00" or "22-7". only whole hours are supported, like windows update active hours.
+impl FromStr for ActiveHours {
+    type Err = ParseHoursError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseHoursError(s.to_owned());
+        let hour = |hour: &str| {
+            let hour = hour.trim();
+            let hour = hour.strip_suffix(":00").unwrap_or(hour);
+            hour.parse::<u32>().ok().filter(|hour| *hour <= 24)
+        };
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let (start, end) = (
+            hour(start).ok_or_else(invalid)?,
+            hour(end).ok_or_else(invalid)?,
+        );
+        Ok(Self {
+            start: start % 24,
+            end: end % 24,
+        })
+    }
+}
+
+impl std::fmt::Display for ActiveHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:00-{:02}:00", self.start, self.end)
+    }
+}
+
+//missing key or value is None
+fn dword(key: &str, name: &str) -> anyhow::Result<Option<u32>> {
+    match HKEY::LOCAL_MACHINE.RegGetValue(Some(key), Some(name)) {
+        Ok(RegistryValue::Dword(value)) => Ok(Some(value)),
+        Ok(_) => anyhow::bail!("{name} is not a DWORD value"),
+        Err(e) if e == winsafe::co::ERROR::FILE_NOT_FOUND => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
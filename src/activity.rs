@@ -0,0 +1,115 @@
+use std::{
+    io, mem, thread,
+    time::{Duration, Instant},
+};
+
+use windows_sys::Win32::{Foundation::FILETIME, System::Threading::GetSystemTimes};
+
+use crate::{policy::Activity, wmi};
+
+// Time over which usage is measured. Long enough that a short burst, e.g. of an application
+// starting, doesn't count as work in progress.
+static SAMPLE_DURATION: Duration = Duration::from_secs(10);
+// Time between measurements while the computer is busy. Backups and renders last for a while.
+static RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+//average usage of the whole computer over a sample
+#[derive(Debug, Clone, Copy)]
+struct Usage {
+    cpu_percent: f64,
+    disk_mb_per_sec: f64,
+    network_mbit_per_sec: f64,
+}
+
+//usage over the next SAMPLE_DURATION. blocks meanwhile.
+fn measure() -> anyhow::Result<Usage> {
+    let (cpu, io, started) = (cpu_times()?, wmi::io_counters()?, Instant::now());
+    thread::sleep(SAMPLE_DURATION);
+    let (cpu_after, io_after) = (cpu_times()?, wmi::io_counters()?);
+    let secs = started.elapsed().as_secs_f64();
+    let idle = cpu_after.0.saturating_sub(cpu.0);
+    let total = cpu_after.1.saturating_sub(cpu.1);
+    Ok(Usage {
+        cpu_percent: if total == 0 {
+            0.0
+        } else {
+            100.0 * (total - idle) as f64 / total as f64
+        },
+        disk_mb_per_sec: io_after.disk_bytes.saturating_sub(io.disk_bytes) as f64 / 1e6 / secs,
+        network_mbit_per_sec: io_after.network_bytes.saturating_sub(io.network_bytes) as f64 * 8.0
+            / 1e6
+            / secs,
+    })
+}
+
+//what keeps the computer busy according to `activity`, e.g. "disk at 45 MB/s"
+fn busy(usage: Usage, activity: &Activity) -> Option<String> {
+    let over = |value: f64, threshold: u32| threshold > 0 && value >= threshold.into();
+    if over(usage.cpu_percent, activity.cpu_percent.into()) {
+        return Some(format!("cpu at {:.0}%", usage.cpu_percent));
+    }
+    if over(usage.disk_mb_per_sec, activity.disk_mb_per_sec) {
+        return Some(format!("disk at {:.1} MB/s", usage.disk_mb_per_sec));
+    }
+    if over(usage.network_mbit_per_sec, activity.network_mbit_per_sec) {
+        return Some(format!(
+            "network at {:.1} Mbit/s",
+            usage.network_mbit_per_sec
+        ));
+    }
+    None
+}
+
+//block while the computer is busy above the thresholds of `activity`, for at most its deadline.
+//failure to measure usage counts as idle.
+pub fn wait_until_idle(activity: &Activity) {
+    if activity.deadline.is_zero() {
+        return;
+    }
+    let started = Instant::now();
+    let mut logged = false;
+    loop {
+        match measure() {
+            Ok(usage) => match busy(usage, activity) {
+                Some(what) if started.elapsed() < activity.deadline => {
+                    if !logged {
+                        log::info!(
+                            "computer is busy({what}). action is deferred for up to {}s",
+                            activity.deadline.as_secs()
+                        );
+                        logged = true;
+                    }
+                    thread::sleep(
+                        RECHECK_INTERVAL.min(activity.deadline.saturating_sub(started.elapsed())),
+                    );
+                }
+                Some(what) => {
+                    log::warn!("computer is still busy({what}). deferral has ended");
+                    return;
+                }
+                None => {
+                    if logged {
+                        log::info!("computer is no longer busy");
+                    }
+                    return;
+                }
+            },
+            Err(e) => {
+                log::warn!("unable to measure usage of the computer: {e:#}");
+                return;
+            }
+        }
+    }
+}
+
+//idle and total(kernel and user, which includes idle) time of all processors, in 100 ns units
+fn cpu_times() -> io::Result<(u64, u64)> {
+    let (mut idle, mut kernel, mut user): (FILETIME, FILETIME, FILETIME) =
+        unsafe { (mem::zeroed(), mem::zeroed(), mem::zeroed()) };
+    if unsafe { GetSystemTimes(&mut idle, &mut kernel, &mut user) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let ticks =
+        |time: FILETIME| (u64::from(time.dwHighDateTime) << 32) | u64::from(time.dwLowDateTime);
+    Ok((ticks(idle), ticks(kernel) + ticks(user)))
+}
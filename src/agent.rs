@@ -0,0 +1,126 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    action, app_close_handler::AppCloseHandler, i18n::Locale, notifier, policy::Action, service,
+};
+
+// Time between attempts to reach the service, e.g. while it is restarted.
+static RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Whether the service can be reached right now, shown from the tray icon.
+static CONNECTED: AtomicBool = AtomicBool::new(false);
+
+//per-user side of the service. prompts of the service are shown in the session of the user, who can
+//cancel them. the countdown is kept by the service, which also powers off. blocks until the app is
+//closed.
+pub fn run() {
+    thread::spawn(|| loop {
+        if let Ok(pipe) = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(service::AGENT_PIPE)
+        {
+            CONNECTED.store(true, Ordering::Relaxed);
+            show_prompts(pipe);
+            CONNECTED.store(false, Ordering::Relaxed);
+            log::info!("disconnected from restart-fix service");
+        }
+        thread::sleep(RECONNECT_DELAY);
+    });
+    let (exit_tx, exit_rx) = flume::bounded(1);
+    AppCloseHandler::new()
+        .with_tray(show_status)
+        .on_app_close(move || {
+            let _ = exit_tx.send(());
+        });
+    let _ = exit_rx.recv();
+}
+
+//until the service disconnects
+fn show_prompts(pipe: File) {
+    let Ok(reader) = pipe.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(reader).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("prompt"), Some(action), Some(timeout)) => {
+                let (Some(action), Ok(timeout)) = (parse_action(action), timeout.parse()) else {
+                    continue;
+                };
+                let Ok(pipe) = pipe.try_clone() else {
+                    continue;
+                };
+                thread::spawn(move || prompt(action, Duration::from_secs(timeout), pipe));
+            }
+            (Some("confirm"), Some(action), _) => {
+                let (Some(action), Ok(pipe)) = (parse_action(action), pipe.try_clone()) else {
+                    continue;
+                };
+                thread::spawn(move || confirm(action, pipe));
+            }
+            //the service can't lock a desktop it doesn't run on
+            (Some("lock"), _, _) => action::lock_screen(false),
+            (Some("notice"), _, _) => {
+                thread::spawn(|| {
+                    let locale = Locale::current();
+                    notifier::notify(
+                        &locale.restart_notice_title(),
+                        &locale.restart_notice_text(),
+                    );
+                });
+            }
+            //countdown of the service ended. dialog answered afterwards cancels nothing.
+            _ => {}
+        }
+    }
+}
+
+//pressing OK cancels the countdown of the service
+fn prompt(action: Action, timeout: Duration, mut pipe: File) {
+    let locale = Locale::current();
+    let text = locale.countdown_text(action, timeout, None, None);
+    //answered, not only seen, so it doesn't fall back to a balloon
+    let _ = notifier::message_box(&locale.shutdown_title(), &text);
+    if writeln!(pipe, "cancel").is_ok() {
+        log::info!("shutdown of the service cancelled");
+    }
+}
+
+//only yes lets the service carry out the action. no cancels it, and so does closing the dialog.
+fn confirm(action: Action, mut pipe: File) {
+    let locale = Locale::current();
+    let answer = match notifier::ask(&locale.shutdown_title(), &locale.confirm_text(action)) {
+        Ok(true) => "confirm",
+        Ok(false) => "cancel",
+        Err(e) => {
+            log::warn!("unable to ask for confirmation: {e}");
+            return;
+        }
+    };
+    if writeln!(pipe, "{answer}").is_ok() {
+        log::info!("shutdown of the service answered: {answer}");
+    }
+}
+
+//only actions that power off are prompted for
+fn parse_action(name: &str) -> Option<Action> {
+    Action::parse(name).filter(|action| action.powers_off())
+}
+
+fn show_status() {
+    let locale = Locale::current();
+    notifier::notify(
+        &locale.status_title(),
+        &locale.agent_status_text(CONNECTED.load(Ordering::Relaxed)),
+    );
+}
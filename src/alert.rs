@@ -0,0 +1,44 @@
+use std::{io, os::windows::process::CommandExt, process::Command, thread};
+
+use windows_sys::Win32::Media::Audio::{
+    PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME, SND_NODEFAULT,
+};
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+//play .wav file or system sound(e.g. "SystemExclamation") without waiting for it to finish
+pub fn play(sound: &str) -> io::Result<()> {
+    let kind = if sound.to_ascii_lowercase().ends_with(".wav") {
+        SND_FILENAME
+    } else {
+        SND_ALIAS
+    };
+    let sound: Vec<u16> = sound.encode_utf16().chain(Some(0)).collect();
+    //without nodefault, a missing sound would silently play the default beep instead
+    if unsafe { PlaySoundW(sound.as_ptr(), 0, kind | SND_ASYNC | SND_NODEFAULT) } == 0 {
+        return Err(io::Error::other("sound not found"));
+    }
+    Ok(())
+}
+
+//read `text` aloud with the speech synthesizer of windows(SAPI). there are no sapi bindings, so
+//it goes through System.Speech of powershell, which runs in background until it has finished.
+pub fn speak(text: String) {
+    thread::spawn(move || {
+        let script = format!(
+            "Add-Type -AssemblyName System.Speech; (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak('{}')",
+            text.replace('\'', "''")
+        );
+        let spoken = Command::new("powershell.exe")
+            .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+            .creation_flags(CREATE_NO_WINDOW)
+            .status();
+        match spoken {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                log::warn!("unable to announce shutdown: powershell exited with {status}")
+            }
+            Err(e) => log::warn!("unable to announce shutdown: {e}"),
+        }
+    });
+}
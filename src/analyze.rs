@@ -0,0 +1,318 @@
+use std::{cmp::Reverse, collections::BTreeMap, fmt::Write, fs, path::Path};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    config::Config,
+    crash,
+    history::History,
+    paths,
+    policy::Cause,
+    report::by_count,
+    shutdown_reason,
+    wmi::{self, ReliabilityRecord},
+};
+
+//number of events of each kind looked at
+const MAX_EVENTS: usize = 500;
+//source of update installations in reliability records
+const UPDATE_CLIENT: &str = "Microsoft-Windows-WindowsUpdateClient";
+//reliability record of a successfully installed update
+const UPDATE_INSTALLED: u32 = 19;
+//bugcheck is reported shortly after the boot that logs the dirty shutdown
+const BUGCHECK_MATCH_WINDOW: i64 = 10 * 60;
+
+//probable cause of restarts with the evidence found for it
+struct Finding {
+    cause: Cause,
+    //restarts detected by restart-fix and attributed to the cause
+    detections: usize,
+    //matching shutdowns and crashes in the System event log
+    events: usize,
+    last: Option<DateTime<Utc>>,
+    evidence: Vec<String>,
+}
+
+impl Finding {
+    fn new(cause: Cause) -> Self {
+        Self {
+            cause,
+            detections: 0,
+            events: 0,
+            last: None,
+            evidence: Vec::new(),
+        }
+    }
+
+    fn seen(&mut self, time: DateTime<Utc>) {
+        self.last = self.last.max(Some(time));
+    }
+
+    fn title(&self) -> &'static str {
+        match self.cause {
+            Cause::WindowsUpdate => "Windows Update",
+            Cause::Crash => "Blue screen",
+            Cause::UserInitiated => "Restarted by a user",
+            Cause::ScheduledTask => "Restarted by a scheduled task",
+            Cause::PowerLoss => "Power loss or hang",
+            Cause::Unknown => "Restarted by a program",
+        }
+    }
+
+    fn next_steps(&self) -> &'static str {
+        match self.cause {
+            Cause::WindowsUpdate => {
+                "Set Active Hours(Settings > Windows Update > Advanced options) or enable the \
+                \"No auto-restart with logged on users\" policy. Updates still need a restart, so \
+                schedule one instead of postponing it forever."
+            }
+            Cause::Crash => {
+                "Look up the stop codes above, update drivers and firmware, and inspect the dumps \
+                in C:\\Windows\\Minidump. Frequent different stop codes often point to faulty memory."
+            }
+            Cause::UserInitiated => {
+                "Someone restarted the computer from the Start menu or with shutdown.exe. Check the \
+                users above and scripts running `shutdown /r`."
+            }
+            Cause::ScheduledTask => {
+                "A scheduled task started the program that restarted the computer. Look it up in \
+                Task Scheduler and change its trigger or remove the restart from its action."
+            }
+            Cause::PowerLoss => {
+                "Check power supply, cables, UPS and temperatures, and the firmware setting that \
+                powers on after AC loss. A hang reset by a hardware watchdog is logged the same way."
+            }
+            Cause::Unknown => {
+                "Check the programs above, scheduled tasks and device management agents \
+                (e.g. Intune, Configuration Manager) for restart deadlines."
+            }
+        }
+    }
+}
+
+//probable causes of restarts, most likely first, and sources that couldn't be read
+pub struct Analysis {
+    findings: Vec<Finding>,
+    notes: Vec<String>,
+}
+
+//correlate detections of restart-fix with event log and reliability records.
+//unreadable sources are reported as notes instead of failing the whole analysis.
+pub fn analyze() -> Analysis {
+    let mut findings: BTreeMap<&str, Finding> = BTreeMap::new();
+    let mut notes = Vec::new();
+
+    let config = Config::load(paths::config_path()).unwrap_or_default();
+    match History::new(config.state_file("history")).read() {
+        Ok(detections) => {
+            //history is oldest first, so the detection kept last is the latest one
+            let mut latest = BTreeMap::new();
+            for detection in detections {
+                let finding = entry(&mut findings, detection.cause);
+                finding.detections += 1;
+                finding.seen(detection.time);
+                latest.insert(detection.cause.as_str(), detection);
+            }
+            for (cause, detection) in latest {
+                //local time as seen by the user back then, which may differ from today's timezone
+                let time = detection
+                    .local
+                    .map_or_else(|| detection.time.to_rfc3339(), |local| local.to_rfc3339());
+                let evidence = if detection.seq == 0 {
+                    format!("last detected at {time}")
+                } else {
+                    format!("last detected as #{} at {time}", detection.seq)
+                };
+                findings.get_mut(cause).unwrap().evidence.push(evidence);
+            }
+        }
+        Err(e) => notes.push(format!("unable to read history: {e:#}")),
+    }
+
+    let mut initiators: BTreeMap<&str, BTreeMap<String, usize>> = BTreeMap::new();
+    match shutdown_reason::recent_shutdowns(MAX_EVENTS) {
+        Ok(shutdowns) => {
+            for record in shutdowns {
+                let cause = Cause::of_shutdown(Some(&record));
+                let finding = entry(&mut findings, cause);
+                finding.events += 1;
+                finding.seen(record.time);
+                //process is logged as "C:\Windows\system32\winlogon.exe (HOSTNAME)"
+                let process = record.process.split(" (").next().unwrap_or_default();
+                let initiator = if cause == Cause::UserInitiated {
+                    format!("{} via {process}", record.user)
+                } else {
+                    process.to_owned()
+                };
+                *initiators
+                    .entry(cause.as_str())
+                    .or_default()
+                    .entry(initiator)
+                    .or_default() += 1;
+            }
+        }
+        Err(e) => notes.push(format!("unable to read shutdown events: {e:#}")),
+    }
+    for (cause, initiators) in &initiators {
+        let finding = findings.get_mut(cause).unwrap();
+        for (count, initiator) in
+            by_count(initiators.iter().map(|(k, v)| (k.as_str(), *v)).collect())
+        {
+            finding
+                .evidence
+                .push(format!("initiated by {initiator} ({count} times)"));
+        }
+    }
+
+    let bugchecks = crash::recent_bugchecks(MAX_EVENTS).unwrap_or_else(|e| {
+        notes.push(format!("unable to read crash reports: {e:#}"));
+        Vec::new()
+    });
+    if !bugchecks.is_empty() {
+        let mut codes: BTreeMap<String, usize> = BTreeMap::new();
+        let crash = entry(&mut findings, Cause::Crash);
+        for bugcheck in &bugchecks {
+            crash.events += 1;
+            crash.seen(bugcheck.time);
+            *codes.entry(format!("{:#010x}", bugcheck.code)).or_default() += 1;
+        }
+        for (count, code) in by_count(codes.iter().map(|(k, v)| (k.as_str(), *v)).collect()) {
+            crash
+                .evidence
+                .push(format!("stop code {code} ({count} times)"));
+        }
+    }
+
+    match shutdown_reason::recent_dirty_shutdowns(MAX_EVENTS) {
+        Ok(dirty) => {
+            //dirty shutdowns without a crash report nearby are power losses or hangs
+            let power_losses: Vec<_> = dirty
+                .into_iter()
+                .filter(|time| {
+                    !bugchecks.iter().any(|bugcheck| {
+                        (bugcheck.time - *time).num_seconds().abs() < BUGCHECK_MATCH_WINDOW
+                    })
+                })
+                .collect();
+            if !power_losses.is_empty() {
+                let finding = entry(&mut findings, Cause::PowerLoss);
+                for time in &power_losses {
+                    finding.events += 1;
+                    finding.seen(*time);
+                }
+                finding.evidence.push(format!(
+                    "{} unexpected shutdowns without crash report",
+                    power_losses.len()
+                ));
+            }
+        }
+        Err(e) => notes.push(format!("unable to read dirty shutdown events: {e:#}")),
+    }
+
+    match wmi::reliability_records(UPDATE_CLIENT) {
+        Ok(records) => {
+            let installed: Vec<ReliabilityRecord> = records
+                .into_iter()
+                .filter(|record| record.event_id == UPDATE_INSTALLED)
+                .collect();
+            if let Some(newest) = installed.iter().max_by_key(|record| record.time) {
+                let count = installed.len();
+                if let Some(updates) = findings.get_mut(Cause::WindowsUpdate.as_str()) {
+                    updates.evidence.push(format!(
+                        "{count} updates installed, last at {}: {}",
+                        newest.time.to_rfc3339(),
+                        newest.product
+                    ));
+                }
+            }
+        }
+        Err(e) => notes.push(format!("unable to read reliability records: {e:#}")),
+    }
+
+    let mut findings: Vec<Finding> = findings.into_values().collect();
+    findings.sort_by_key(|finding| Reverse((finding.detections, finding.events, finding.last)));
+    Analysis { findings, notes }
+}
+
+impl Analysis {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if self.findings.is_empty() {
+            text.push_str("no restarts found in history or event log\n");
+        }
+        for (rank, finding) in self.findings.iter().enumerate() {
+            let _ = writeln!(
+                text,
+                "{}. {}: {} detected by restart-fix, {} in event log{}",
+                rank + 1,
+                finding.title(),
+                finding.detections,
+                finding.events,
+                last_seen(finding)
+            );
+            for evidence in &finding.evidence {
+                let _ = writeln!(text, "   - {evidence}");
+            }
+            let _ = writeln!(text, "   next steps: {}", finding.next_steps());
+        }
+        for note in &self.notes {
+            let _ = writeln!(text, "note: {note}");
+        }
+        text
+    }
+
+    pub fn write_html(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>restart-fix analysis</title></head>\n<body>\n<h1>Why is this computer restarting?</h1>\n",
+        );
+        if self.findings.is_empty() {
+            html.push_str("<p>No restarts found in history or event log.</p>\n");
+        } else {
+            html.push_str("<table border=\"1\" cellpadding=\"4\">\n<tr><th>#</th><th>Cause</th><th>Detected</th><th>Events</th><th>Last</th><th>Evidence</th><th>Next steps</th></tr>\n");
+            for (rank, finding) in self.findings.iter().enumerate() {
+                let evidence: String = finding
+                    .evidence
+                    .iter()
+                    .map(|evidence| format!("<li>{}</li>", escape(evidence)))
+                    .collect();
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td><ul>{evidence}</ul></td><td>{}</td></tr>",
+                    rank + 1,
+                    escape(finding.title()),
+                    finding.detections,
+                    finding.events,
+                    finding.last.map(|time| time.to_rfc3339()).unwrap_or_default(),
+                    escape(finding.next_steps())
+                );
+            }
+            html.push_str("</table>\n");
+        }
+        for note in &self.notes {
+            let _ = writeln!(html, "<p><i>{}</i></p>", escape(note));
+        }
+        html.push_str("</body>\n</html>\n");
+        fs::write(path, html)?;
+        Ok(())
+    }
+}
+
+fn entry<'a>(findings: &'a mut BTreeMap<&'static str, Finding>, cause: Cause) -> &'a mut Finding {
+    findings
+        .entry(cause.as_str())
+        .or_insert_with(|| Finding::new(cause))
+}
+
+fn last_seen(finding: &Finding) -> String {
+    finding
+        .last
+        .map(|time| format!(", last at {}", time.to_rfc3339()))
+        .unwrap_or_default()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -4,10 +4,35 @@ use std::{
     thread::{self, JoinHandle},
 };
 
+use windows_sys::Win32::{Foundation::HWND, UI::WindowsAndMessaging::ENDSESSION_LOGOFF};
 use winsafe::{co, gui, prelude::*};
 
+use crate::{i18n::Locale, session_lock, tray};
+
+//why the session is ending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndSession {
+    Shutdown,
+    Logoff,
+}
+
+impl EndSession {
+    //from lparam of wm_queryendsession and wm_endsession
+    fn of(flags: u32) -> Self {
+        if flags & ENDSESSION_LOGOFF != 0 {
+            EndSession::Logoff
+        } else {
+            EndSession::Shutdown
+        }
+    }
+}
+
 pub struct AppCloseHandler {
     wnd: gui::WindowMain,
+    track_session_lock: bool,
+    on_show_status: Option<Box<dyn Fn() + Send>>,
+    on_show_history: Option<Box<dyn Fn() + Send>>,
+    on_session_ended: Option<Box<dyn Fn(EndSession) + Send>>,
 }
 
 impl AppCloseHandler {
@@ -16,29 +41,181 @@ impl AppCloseHandler {
             style: co::WS::OVERLAPPED, //required for processing wm_close and wm_endsession message
             ..Default::default()
         });
-        Self { wnd }
+        Self {
+            wnd,
+            track_session_lock: false,
+            on_show_status: None,
+            on_show_history: None,
+            on_session_ended: None,
+        }
+    }
+
+    //keep session_lock informed about lock and unlock of the workstation
+    pub fn track_session_lock(mut self) -> Self {
+        self.track_session_lock = true;
+        self.wnd.on().wm(co::WM::WTSSESSION_CHANGE, |msg| {
+            session_lock::changed(msg.wparam);
+            Ok(Some(0))
+        });
+        self
+    }
+
+    //show icon in the notification area. `handler` runs when the user asks for the status window.
+    pub fn with_tray<F>(mut self, handler: F) -> Self
+    where
+        F: Fn() + Send + 'static,
+    {
+        self.on_show_status = Some(Box::new(handler));
+        self
+    }
+
+    //offer the history window from the menu of the tray icon
+    pub fn with_history<F>(mut self, handler: F) -> Self
+    where
+        F: Fn() + Send + 'static,
+    {
+        self.on_show_history = Some(Box::new(handler));
+        self
+    }
+
+    //window only exists once it is created on the thread running it
+    fn on_create(self) -> gui::WindowMain {
+        let Self {
+            wnd,
+            track_session_lock,
+            on_show_status,
+            on_show_history,
+            on_session_ended: _,
+        } = self;
+        let tray = on_show_status.is_some();
+        //changes are recognized on the next start from the event log. logged to explain the gap.
+        wnd.on().wm(co::WM::TIMECHANGE, |_| {
+            log::info!("system time was changed");
+            Ok(Some(0))
+        });
+        let hwnd = wnd.clone();
+        wnd.on().wm_create(move |_| {
+            let hwnd = hwnd.hwnd().ptr() as HWND;
+            if track_session_lock {
+                if let Err(e) = session_lock::register(hwnd) {
+                    log::warn!("unable to register for session notifications: {e}");
+                }
+            }
+            if tray {
+                add_tray_icon(hwnd);
+            }
+            Ok(0)
+        });
+        if let Some(handler) = on_show_status {
+            let hwnd = wnd.clone();
+            wnd.on()
+                .wm(unsafe { co::WM::from_raw(tray::WM_TRAY) }, move |msg| {
+                    let hwnd = hwnd.hwnd().ptr() as HWND;
+                    let locale = Locale::current();
+                    let show_history = on_show_history.as_ref().map(|_| locale.show_history());
+                    match tray::clicked(
+                        hwnd,
+                        msg.lparam as u32,
+                        &locale.show_status(),
+                        show_history.as_deref(),
+                    ) {
+                        Some(tray::Command::ShowStatus) => handler(),
+                        Some(tray::Command::ShowHistory) => {
+                            if let Some(show_history) = &on_show_history {
+                                show_history();
+                            }
+                        }
+                        None => {}
+                    }
+                    Ok(Some(0))
+                });
+            //icons are gone when explorer restarts
+            match winsafe::RegisterWindowMessage("TaskbarCreated") {
+                Ok(taskbar_created) => {
+                    let hwnd = wnd.clone();
+                    wnd.on()
+                        .wm(unsafe { co::WM::from_raw(taskbar_created) }, move |_| {
+                            add_tray_icon(hwnd.hwnd().ptr() as HWND);
+                            Ok(Some(0))
+                        });
+                }
+                Err(e) => log::warn!("unable to watch for explorer restarts: {e}"),
+            }
+            let hwnd = wnd.clone();
+            wnd.on().wm_destroy(move || {
+                tray::remove(hwnd.hwnd().ptr() as HWND);
+                Ok(())
+            });
+        }
+        wnd
     }
 
-    pub fn on_app_close<F>(self, handler: F) -> JoinHandle<()>
+    //run `handler` with the hidden window when windows asks whether the session may end.
+    //session goes on ending once it returns.
+    pub fn on_query_end_session<F>(self, handler: F) -> Self
+    where
+        F: Fn(HWND, EndSession) + 'static,
+    {
+        let wnd = self.wnd.clone();
+        self.wnd.on().wm(co::WM::QUERYENDSESSION, move |msg| {
+            let end = EndSession::of(msg.lparam as u32);
+            tracing::debug!(?end, "wm_queryendsession");
+            handler(wnd.hwnd().ptr() as _, end);
+            Ok(Some(1))
+        });
+        self
+    }
+
+    //run `handler` once the session really ends. unlike the query before, which another
+    //application can still veto, nothing can stop it anymore then.
+    pub fn on_session_ended<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(EndSession) + Send + 'static,
+    {
+        self.on_session_ended = Some(Box::new(handler));
+        self
+    }
+
+    pub fn on_app_close<F>(mut self, handler: F) -> JoinHandle<()>
     where
         F: FnOnce() + Send + 'static,
     {
         thread::spawn(move || {
+            let _span = tracing::debug_span!("close_handler").entered();
+            let on_session_ended = self.on_session_ended.take();
+            let wnd = self.on_create();
             let handler = Rc::new(Cell::new(Some(handler)));
             let handler_1 = Rc::clone(&handler);
-            self.wnd.on().wm_close(move || {
+            wnd.on().wm_close(move || {
+                tracing::debug!("wm_close");
                 if let Some(handler) = handler.take() {
                     handler();
                 }
                 Ok(())
             });
-            self.wnd.on().wm_end_session(move |_| {
+            //only the last handler of a message is run, so both are run from here
+            wnd.on().wm_end_session(move |msg| {
+                tracing::debug!(ending = msg.is_session_being_ended, "wm_endsession");
+                //false if the ending was cancelled after all
+                if let Some(on_session_ended) = &on_session_ended {
+                    if msg.is_session_being_ended {
+                        on_session_ended(EndSession::of(msg.event.raw()));
+                    }
+                }
                 if let Some(handler) = handler_1.take() {
                     handler();
                 }
                 Ok(())
             });
-            self.wnd.run_main(Some(co::SW::HIDE)).unwrap();
+            if let Err(e) = wnd.run_main(Some(co::SW::HIDE)) {
+                log::error!("close handler window stopped: {e}");
+            }
         })
     }
 }
+
+fn add_tray_icon(hwnd: HWND) {
+    if let Err(e) = tray::add(hwnd, &Locale::current().tray_tip()) {
+        log::warn!("unable to add notification area icon: {e}");
+    }
+}
@@ -0,0 +1,6 @@
+use restart_fix_core::Engine;
+
+use crate::action::Prompt;
+
+//runtime state shared between main, background worker, prompts and ipc server
+pub type AppState = Engine<Prompt>;
@@ -0,0 +1,263 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use restart_fix_core::{Decision, Event, Heartbeat};
+use serde_json::{json, Value};
+
+use crate::{
+    app_state::AppState,
+    boot::BootClassification,
+    policy::{Action, Cause},
+};
+
+//file is moved aside to {name}.old once it grows past this, so it can't fill the disk
+const MAX_SIZE: u64 = 1024 * 1024;
+
+//audit file in the state directory. set once on startup, records before are dropped.
+static PATH: OnceLock<PathBuf> = OnceLock::new();
+
+//answers "why did/didn't my pc shut down" from one machine-readable file, separate from the debug
+//log. each line is a json object with "time" and "record", which is one of
+//"evaluation", "detection", "action" or "response".
+pub fn init(path: PathBuf, state: &AppState) {
+    if PATH.set(path).is_err() {
+        return;
+    }
+    //response of the user to the shutdown prompt
+    let events = state.subscribe();
+    thread::spawn(move || {
+        for event in events {
+            let response = match event {
+                Event::CountdownStarted { timeout } => {
+                    json!({"response": "prompted", "timeout": timeout.map(|t| t.as_secs())})
+                }
+                Event::CountdownCancelled => json!({"response": "cancelled"}),
+                Event::CountdownSnoozed { until } => {
+                    json!({"response": "snoozed", "until": until.to_rfc3339()})
+                }
+                Event::CountdownExpired => json!({"response": "expired"}),
+                _ => continue,
+            };
+            write("response", response);
+        }
+    });
+}
+
+//last heartbeat compared with the current one on startup
+pub fn evaluation(
+    last_heartbeat: Heartbeat,
+    now: Heartbeat,
+    threshold: Duration,
+    decision: Decision,
+    classification: BootClassification,
+) {
+    let result = match decision {
+        Decision::Restarted { .. } => "restarted",
+        Decision::Normal { .. } => "normal",
+        Decision::SameBoot { .. } => "same-boot",
+        Decision::ClockChanged { .. } => "clock-changed",
+    };
+    write(
+        "evaluation",
+        json!({
+            "last_heartbeat": heartbeat(&last_heartbeat),
+            "now": heartbeat(&now),
+            "threshold": threshold.as_secs(),
+            "elapsed": decision.elapsed().as_secs(),
+            "result": result,
+            "classification": classification,
+        }),
+    );
+}
+
+//nothing to compare with, so no restart can be detected
+pub fn unreadable_heartbeat(error: &anyhow::Error) {
+    write(
+        "evaluation",
+        json!({
+            "result": "unreadable",
+            "error": format!("{error:#}"),
+            "classification": BootClassification::Unknown,
+        }),
+    );
+}
+
+//restart within threshold and action the policy assigns to its cause
+pub fn detection(cause: Cause, action: Action) {
+    write(
+        "detection",
+        json!({"cause": cause.as_str(), "action": action.as_str()}),
+    );
+}
+
+//action carried out after battery, active hours, input and other conditions were considered
+pub fn action(requested: Action, action: Action) {
+    write(
+        "action",
+        json!({"requested": requested.as_str(), "action": action.as_str()}),
+    );
+}
+
+//startup as told by its evaluation record and the records that followed it
+#[derive(Debug, Clone)]
+pub struct Boot {
+    pub time: DateTime<Utc>,
+    //as BootClassification is serialized, e.g. "unexpected-restart"
+    pub classification: String,
+    //uptime of the last heartbeat, i.e. how long the computer ran before it went down
+    pub uptime_before: Option<Duration>,
+    pub last_heartbeat: Option<DateTime<Utc>>,
+    //time of the evaluation less the uptime then
+    pub boot_time: Option<DateTime<Utc>>,
+    //time between the last heartbeat and the evaluation
+    pub elapsed: Option<Duration>,
+    pub threshold: Option<Duration>,
+    //result of the evaluation, e.g. "restarted" or "unreadable"
+    pub result: String,
+    //why the last heartbeat couldn't be read
+    pub error: Option<String>,
+    pub cause: Option<String>,
+    //action the policy assigns to the cause
+    pub rule_action: Option<String>,
+    //action carried out
+    pub action: Option<String>,
+    //last response to the prompt, e.g. "cancelled"
+    pub response: Option<String>,
+}
+
+impl Boot {
+    //computer was powered off by restart-fix
+    pub fn acted(&self) -> bool {
+        let powers_off = [
+            Action::Shutdown,
+            Action::Hibernate,
+            Action::RebootToFirmware,
+        ]
+        .iter()
+        .any(|action| self.action.as_deref() == Some(action.as_str()));
+        powers_off
+            && !matches!(
+                self.response.as_deref(),
+                Some("prompted" | "cancelled" | "snoozed")
+            )
+    }
+}
+
+//startups recorded in the audit file, oldest first. the moved aside part is read as well.
+pub fn boots() -> anyhow::Result<Vec<Boot>> {
+    match PATH.get() {
+        Some(path) => boots_in(path),
+        None => Ok(Vec::new()),
+    }
+}
+
+//startups recorded in the audit file at `path`, e.g. for commands that run without init
+pub fn boots_in(path: &Path) -> anyhow::Result<Vec<Boot>> {
+    let mut boots = Vec::new();
+    for path in [path.with_extension("jsonl.old"), path.to_owned()] {
+        read_boots(&path, &mut boots)?;
+    }
+    Ok(boots)
+}
+
+//missing file has no records. unreadable lines(e.g. cut off by power loss) are skipped.
+fn read_boots(path: &Path, boots: &mut Vec<Boot>) -> anyhow::Result<()> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for line in text.lines() {
+        let Ok(record) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let text = |name: &str| record[name].as_str().map(str::to_owned);
+        let time = |value: &Value| {
+            value
+                .as_str()
+                .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.with_timezone(&Utc))
+        };
+        let secs = |value: &Value| value.as_u64().map(Duration::from_secs);
+        match record["record"].as_str() {
+            Some("evaluation") => {
+                let Some(boot_time) = time(&record["time"]) else {
+                    continue;
+                };
+                let now = &record["now"];
+                boots.push(Boot {
+                    time: boot_time,
+                    classification: text("classification").unwrap_or_default(),
+                    uptime_before: secs(&record["last_heartbeat"]["uptime"]),
+                    last_heartbeat: time(&record["last_heartbeat"]["time"]),
+                    boot_time: time(&now["time"]).zip(secs(&now["uptime"])).and_then(
+                        |(time, uptime)| Some(time - chrono::Duration::from_std(uptime).ok()?),
+                    ),
+                    elapsed: secs(&record["elapsed"]),
+                    threshold: secs(&record["threshold"]),
+                    result: text("result").unwrap_or_default(),
+                    error: text("error"),
+                    cause: None,
+                    rule_action: None,
+                    action: None,
+                    response: None,
+                });
+            }
+            //records before the first evaluation belong to a start that wasn't recorded
+            Some("detection") => {
+                if let Some(boot) = boots.last_mut() {
+                    boot.cause = text("cause");
+                    boot.rule_action = text("action");
+                }
+            }
+            Some("action") => {
+                if let Some(boot) = boots.last_mut() {
+                    boot.action = text("action");
+                }
+            }
+            Some("response") => {
+                if let Some(boot) = boots.last_mut() {
+                    boot.response = text("response");
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn heartbeat(heartbeat: &Heartbeat) -> Value {
+    json!({
+        "time": heartbeat.time.to_rfc3339(),
+        "uptime": heartbeat.uptime.map(|uptime| uptime.as_secs()),
+        "boot_id": heartbeat.boot_id,
+    })
+}
+
+fn write(record: &'static str, fields: Value) {
+    let Some(path) = PATH.get() else {
+        return;
+    };
+    let mut line = json!({"time": Utc::now().to_rfc3339(), "record": record});
+    if let (Value::Object(line), Value::Object(fields)) = (&mut line, fields) {
+        line.extend(fields);
+    }
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() > MAX_SIZE) {
+        let _ = fs::rename(path, path.with_extension("jsonl.old"));
+    }
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{line}"));
+    if let Err(e) = written {
+        log::warn!("unable to write audit record: {e}");
+    }
+}
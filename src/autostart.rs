@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use winsafe::{co, prelude::*, RegistryValue, HKEY};
+
+use crate::{i18n::Locale, notifier, pool_timer, registry};
+
+const RUN_KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Run";
+const RUN_VALUE: &str = "restart-fix";
+const INSTALLED_FLAG: &str = "Autostart";
+
+// Interval between autostart registration checks. Imaging tools, cleanup utilities or users
+// may remove the registration at any time, and protection that silently stops starting with
+// windows is the worst failure mode of this program.
+static CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub enum Registration {
+    Valid,
+    Missing,
+    //registered command doesn't point to current executable
+    Mismatch(String),
+}
+
+//register current executable to start with windows
+pub fn register() -> anyhow::Result<()> {
+    registry::set_value(
+        &HKEY::CURRENT_USER,
+        RUN_KEY,
+        RUN_VALUE,
+        RegistryValue::Sz(command_line()?),
+    )?;
+    registry::set_flag(INSTALLED_FLAG)?;
+    Ok(())
+}
+
+pub fn unregister() -> anyhow::Result<()> {
+    registry::delete_value(&HKEY::CURRENT_USER, RUN_KEY, RUN_VALUE)
+}
+
+pub fn is_installed() -> bool {
+    registry::is_flag_set(INSTALLED_FLAG)
+}
+
+pub fn check() -> anyhow::Result<Registration> {
+    let expected = command_line()?;
+    match HKEY::CURRENT_USER.RegGetValue(Some(RUN_KEY), Some(RUN_VALUE)) {
+        Ok(RegistryValue::Sz(command)) | Ok(RegistryValue::ExpandSz(command)) => {
+            if command.eq_ignore_ascii_case(&expected) {
+                Ok(Registration::Valid)
+            } else {
+                Ok(Registration::Mismatch(command))
+            }
+        }
+        Ok(_) => Ok(Registration::Mismatch("<not a string value>".to_owned())),
+        Err(co::ERROR::FILE_NOT_FOUND) => Ok(Registration::Missing),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//verify autostart registration once a day and repair it if needed
+pub fn spawn_watchdog() {
+    pool_timer::every(CHECK_INTERVAL, || {
+        //nothing to watch if autostart was never installed
+        if is_installed() {
+            verify();
+        }
+    });
+}
+
+fn verify() {
+    let problem = match check() {
+        Ok(Registration::Valid) => return,
+        Ok(Registration::Missing) => "autostart registration is missing".to_owned(),
+        Ok(Registration::Mismatch(command)) => {
+            format!("autostart registration points to other executable: {command}")
+        }
+        Err(e) => {
+            log::warn!("unable to verify autostart registration: {e}");
+            return;
+        }
+    };
+    log::warn!("{problem}");
+    match register() {
+        Ok(()) => log::info!("autostart registration repaired"),
+        Err(e) => {
+            log::error!("unable to repair autostart registration: {e}");
+            let locale = Locale::current();
+            notifier::notify(
+                &locale.autostart_error_title(),
+                &locale.autostart_error_text(&e),
+            );
+        }
+    }
+}
+
+//quoted path of current executable
+pub fn command_line() -> anyhow::Result<String> {
+    Ok(format!("\"{}\"", std::env::current_exe()?.display()))
+}
@@ -0,0 +1,101 @@
+use std::{sync::OnceLock, time::Duration};
+
+use chrono::{DateTime, Utc};
+use restart_fix_core::{Clock, Heartbeat};
+use serde::Serialize;
+use winsafe::{prelude::*, RegistryValue, HKEY};
+
+const BOOT_ID_KEY: &str =
+    r"SYSTEM\CurrentControlSet\Control\Session Manager\Memory Management\PrefetchParameters";
+
+//time the computer was started, derived from wall clock and tick count.
+//readings taken during the same boot may differ slightly because of clock adjustments.
+pub fn boot_time() -> DateTime<Utc> {
+    Utc::now() - chrono::Duration::from_std(uptime()).unwrap_or_else(|_| chrono::Duration::zero())
+}
+
+//time since the computer was started. includes time spent in sleep, so it advances
+//like wall clock time but is never adjusted.
+pub fn uptime() -> Duration {
+    Duration::from_millis(winsafe::GetTickCount64())
+}
+
+//clocks of the system, read for heartbeats to be stored or compared with the stored one
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Heartbeat {
+        Heartbeat {
+            time: Utc::now(),
+            uptime: Some(uptime()),
+            boot_id: current_boot_id(),
+        }
+    }
+}
+
+//system clocks with readings replaced on the command line, to try out detection without rebooting
+pub struct SimulatedClock {
+    //wall clock time instead of the actual one
+    pub now: Option<DateTime<Utc>>,
+    //readings as taken right after a boot
+    pub restart: bool,
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> Heartbeat {
+        let mut heartbeat = SystemClock.now();
+        if let Some(now) = self.now {
+            heartbeat.time = now;
+        }
+        if self.restart {
+            heartbeat.uptime = Some(Duration::ZERO);
+            //boot id of the next boot is unknown, so only wall clock time is compared
+            heartbeat.boot_id = None;
+        }
+        heartbeat
+    }
+}
+
+//boot id doesn't change until next boot, so registry is read only once
+fn current_boot_id() -> Option<u32> {
+    static BOOT_ID: OnceLock<Option<u32>> = OnceLock::new();
+    *BOOT_ID.get_or_init(|| match boot_id() {
+        Ok(boot_id) => Some(boot_id),
+        Err(e) => {
+            log::warn!("unable to read boot id: {e:#}");
+            None
+        }
+    })
+}
+
+//how the current boot was classified on startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BootClassification {
+    //restarted within threshold. shutdown sequence is initiated.
+    UnexpectedRestart,
+    //restarted within threshold after a blue screen
+    Crash,
+    //restarted within threshold after power loss or hang(dirty shutdown without bugcheck)
+    PowerLoss,
+    //restarted within threshold while monitoring was paused
+    Paused,
+    //restarted within threshold, but reason of previous shutdown is in bypass list
+    Bypassed,
+    Normal,
+    //monitor was restarted without reboot(e.g. relaunched by watchdog task)
+    MonitorRestarted,
+    //wall clock was changed since last heartbeat. restart can't be confirmed.
+    ClockChanged,
+    //last heartbeat can't be read
+    Unknown,
+}
+
+//boot counter maintained by the kernel. incremented on every boot, so it identifies current boot
+//across sessions and processes, unlike boot_time().
+pub fn boot_id() -> anyhow::Result<u32> {
+    match HKEY::LOCAL_MACHINE.RegGetValue(Some(BOOT_ID_KEY), Some("BootId"))? {
+        RegistryValue::Dword(id) => Ok(id),
+        _ => anyhow::bail!("BootId is not a DWORD value"),
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::boot::boot_time;
+
+// Boot time is derived from wall clock and tick count, so two readings taken during the
+// same boot may differ slightly. Markers whose boot time is within this tolerance are
+// considered to be written during the current boot.
+static BOOT_TIME_TOLERANCE: Duration = Duration::from_secs(60);
+
+//written when windows ends the session, so a restart can be told apart from restart-fix merely
+//being killed and relaunched. marker file stores boot time of the session that ended.
+pub struct CleanShutdownMarker {
+    path: PathBuf,
+}
+
+impl CleanShutdownMarker {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    pub fn set(&self) -> anyhow::Result<()> {
+        fs::write(&self.path, boot_time().timestamp().to_string())?;
+        Ok(())
+    }
+
+    //returns true if a session of an earlier boot ended cleanly since the marker was last taken.
+    //marker is removed, so it only vouches for the restart that follows it.
+    pub fn take(&self) -> bool {
+        let ended_boot = self.read();
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                log::warn!("unable to remove clean shutdown marker: {e}")
+            }
+            _ => {}
+        }
+        match ended_boot {
+            //session ended without reboot(e.g. logoff)
+            Ok(ended_boot) => (boot_time() - ended_boot)
+                .abs()
+                .to_std()
+                .is_ok_and(|delta| delta >= BOOT_TIME_TOLERANCE),
+            Err(_) => false,
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<DateTime<Utc>> {
+        let time = fs::read_to_string(&self.path)?.trim().parse::<i64>()?;
+        Utc.timestamp_opt(time, 0)
+            .single()
+            .ok_or_else(|| anyhow!("Invalid timestamp: {time}"))
+    }
+}
@@ -0,0 +1,377 @@
+use std::path::PathBuf;
+
+use anyhow::bail;
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use restart_fix_core::parse_timestamp;
+use serde_json::json;
+use windows_sys::Win32::{
+    System::Console::{AttachConsole, ATTACH_PARENT_PROCESS},
+    UI::WindowsAndMessaging::{AllowSetForegroundWindow, ASFW_ANY},
+};
+
+use crate::{
+    analyze, autostart,
+    config::{self, Config},
+    diag, doctor, elevation,
+    error::ErrorFormat,
+    explain, fast_startup, ipc, paths,
+    peers::{self, Request},
+    policy::Cause,
+    prevention, provision, report, self_test, service, uninstall, watchdog_task,
+};
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Detects unintended restarts and shuts the computer down"
+)]
+pub struct Cli {
+    /// Detect restarts and show dialog as usual, but never shut down
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Treat this start as the first one after a reboot. Implies --dry-run
+    #[arg(long)]
+    pub simulate_restart: bool,
+
+    /// Time seen by restart detection on startup, as unix timestamp or RFC 3339. Implies --dry-run
+    #[arg(long, value_name = "TIMESTAMP", value_parser = parse_fake_now)]
+    pub fake_now: Option<DateTime<Utc>>,
+
+    /// Skip startup and logon grace, so the outcome is shown right away. Implies --dry-run
+    #[arg(long)]
+    pub fast_forward: bool,
+
+    /// Log verbosely to a console and stop cleanly on Ctrl+C, for interactive debugging
+    #[arg(long)]
+    pub console: bool,
+
+    /// How an error ending restart-fix is printed to stderr, for wrapper scripts
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub error_format: ErrorFormat,
+
+    //set when relaunched elevated by a command that needs administrator rights
+    #[arg(long = "elevated-output", hide = true, value_name = "FILE")]
+    pub elevated_output: Option<PathBuf>,
+
+    //set by the service control manager when it starts the service
+    #[arg(long, hide = true)]
+    pub service: bool,
+
+    //without subcommand, program runs as monitor
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+impl Cli {
+    //detection is fed synthetic times to try out the configuration
+    pub fn is_simulated(&self) -> bool {
+        self.simulate_restart || self.fake_now.is_some() || self.fast_forward
+    }
+
+    //simulated restarts must never shut the computer down
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run || self.is_simulated()
+    }
+}
+
+fn parse_fake_now(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_timestamp(value).map_err(|e| e.to_string())
+}
+
+#[derive(Subcommand, Clone)]
+pub enum CliCommand {
+    /// Register restart-fix to start with windows
+    Install {
+        /// Also create a scheduled task that relaunches restart-fix if it exits or crashes
+        #[arg(long)]
+        watchdog: bool,
+    },
+    /// Stop restart-fix and remove everything it has set up or written: autostart, watchdog task,
+    /// service, state and log files and the event log source. Asks for administrator rights if the
+    /// service or event log source is there
+    Uninstall {
+        /// Keep the history of detected restarts
+        #[arg(long)]
+        keep_history: bool,
+        //set when relaunched elevated for the service and the event log source
+        #[arg(long, hide = true)]
+        machine_wide: bool,
+    },
+    /// Register event log source. Asks for administrator rights if needed
+    Provision,
+    /// Set Windows Update policies that keep it from restarting while a user is logged on. Asks for
+    /// administrator rights if needed
+    Prevent {
+        /// Remove the policies again, restoring the defaults of Windows Update
+        #[arg(long)]
+        restore: bool,
+    },
+    /// Turn off Fast Startup, so shutting down powers the computer off completely. Asks for
+    /// administrator rights if needed
+    DisableFastStartup,
+    /// Install and start the system service, which detects restarts before anyone logs on. Asks
+    /// for administrator rights if needed
+    InstallService,
+    /// Stop and remove the system service
+    UninstallService,
+    /// Check configuration and environment for problems
+    Doctor,
+    /// Show the configuration merged from the machine-wide and per-user settings
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Show statistics of recent shutdowns and restarts by cause and initiator
+    Report,
+    /// Rank probable causes of restarts with suggested next steps
+    Analyze {
+        /// Write the analysis to an HTML file instead of printing it
+        #[arg(long, value_name = "FILE")]
+        html: Option<PathBuf>,
+    },
+    /// Explain how the last start was evaluated and what was done about it, from the audit file
+    Explain,
+    /// Collect config, logs, state and related System events into a zip for bug reports
+    Diag {
+        /// Zip file to write
+        #[arg(value_name = "FILE", default_value = "restart-fix-diag.zip")]
+        output: PathBuf,
+    },
+    /// Show state of the running instance
+    Status {
+        /// Show recent log lines instead
+        #[arg(long)]
+        tail: bool,
+        /// Print status as JSON, including whether an instance is running
+        #[arg(long, conflicts_with = "tail")]
+        json: bool,
+    },
+    /// Pause monitoring
+    Pause,
+    /// Resume monitoring
+    Resume,
+    /// Cancel pending shutdown countdown
+    CancelShutdown,
+    /// Show shutdown dialog without shutting down
+    TriggerTest,
+    /// Show the notice of a detected restart as configured, without a running instance
+    TestNotification,
+    /// Show the shutdown prompt of a detected restart as configured, without a running instance,
+    /// and never shut down
+    TestShutdownDialog,
+    /// Check whether this account can take each action
+    TestAction {
+        /// Only check, without taking any action. test-action never acts, so it is required
+        #[arg(long, required = true)]
+        dry: bool,
+    },
+    /// Ask peers configured in [peers] to shut down or notify their users
+    TellPeers {
+        #[arg(value_enum)]
+        request: Request,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ConfigCommand {
+    /// Show where settings are taken from and the settings they give, merged
+    Show {
+        /// Show every setting in effect, with defaults and environment variables applied
+        #[arg(long)]
+        effective: bool,
+    },
+    /// Keep the config file as it is now in HKLM and use that copy while the file differs from it,
+    /// so other users can't change the settings. Needs an elevated prompt
+    Seal,
+    /// Let the config file be changed again. Needs an elevated prompt
+    Unseal,
+}
+
+//program is built for windows subsystem and has no console of its own.
+//attach to console of the parent process(if any) so output of client mode is visible.
+pub fn attach_parent_console() {
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+//output of the command, printed by the caller
+pub fn run(command: CliCommand) -> anyhow::Result<String> {
+    let response = match command {
+        CliCommand::Install { watchdog } => {
+            autostart::register()?;
+            if watchdog {
+                watchdog_task::register()?;
+                "autostart and watchdog task registered".to_owned()
+            } else {
+                "autostart registered".to_owned()
+            }
+        }
+        CliCommand::Uninstall {
+            keep_history,
+            machine_wide: false,
+        } => uninstall::run(keep_history)?,
+        CliCommand::Uninstall {
+            keep_history,
+            machine_wide: true,
+        } => uninstall::run_machine_wide(keep_history)?,
+        CliCommand::Provision => {
+            if let Some(output) = elevation::require(&["provision"])? {
+                return Ok(output);
+            }
+            provision::register_event_source()?;
+            "event log source registered".to_owned()
+        }
+        CliCommand::Prevent { restore } => {
+            let args: &[&str] = if restore {
+                &["prevent", "--restore"]
+            } else {
+                &["prevent"]
+            };
+            if let Some(output) = elevation::require(args)? {
+                return Ok(output);
+            }
+            if restore {
+                prevention::restore()?;
+                "windows update policies restored to defaults".to_owned()
+            } else {
+                prevention::apply()?;
+                "windows update no longer restarts while a user is logged on".to_owned()
+            }
+        }
+        CliCommand::DisableFastStartup => {
+            if let Some(output) = elevation::require(&["disable-fast-startup"])? {
+                return Ok(output);
+            }
+            fast_startup::disable()?;
+            "fast startup turned off".to_owned()
+        }
+        CliCommand::InstallService => {
+            if let Some(output) = elevation::require(&["install-service"])? {
+                return Ok(output);
+            }
+            service::install()?;
+            "service installed and started. restart-fix started at logon shows its prompts"
+                .to_owned()
+        }
+        CliCommand::UninstallService => {
+            if let Some(output) = elevation::require(&["uninstall-service"])? {
+                return Ok(output);
+            }
+            service::uninstall()?;
+            "service removed".to_owned()
+        }
+        CliCommand::Doctor => doctor::diagnose()
+            .iter()
+            .map(|finding| finding.to_string())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        CliCommand::Config {
+            command: ConfigCommand::Show { effective: false },
+        } => {
+            let path = paths::config_path();
+            let mut files = vec![path.clone()];
+            //config file of the service is the machine-wide one
+            if paths::machine_config_path() != path {
+                files.push(paths::machine_config_path());
+            }
+            let mut response = format!(
+                "settings in order of precedence:\n  HKLM\\{0}, HKCU\\{0} (group policy)\n  \
+                 RESTART_FIX_* environment variables\n",
+                config::POLICY_KEY
+            );
+            for file in files {
+                let state = if file.exists() { "" } else { " (missing)" };
+                response.push_str(&format!("  {}{state}\n", file.display()));
+            }
+            response.push_str(&format!("  HKLM\\{}\n\n", config::MACHINE_KEY));
+            response.push_str(&toml::to_string_pretty(&Config::merged(&path)?)?);
+            let policies = Config::policies()?;
+            if !policies.is_empty() {
+                response.push_str("\nlocked by group policy:\n");
+                response.push_str(&toml::to_string_pretty(&policies)?);
+            }
+            response
+        }
+        CliCommand::Config {
+            command: ConfigCommand::Show { effective: true },
+        } => format!("{:#?}", Config::load(paths::config_path())?),
+        CliCommand::Config {
+            command: ConfigCommand::Seal,
+        } => {
+            let path = paths::config_path();
+            let hash = Config::seal(&path)?;
+            format!("{} is sealed(sha-256 {hash})", path.display())
+        }
+        CliCommand::Config {
+            command: ConfigCommand::Unseal,
+        } => {
+            Config::unseal()?;
+            "config file is unsealed".to_owned()
+        }
+        CliCommand::TestNotification => {
+            self_test::notification(&Config::load(paths::config_path())?)
+        }
+        CliCommand::TestShutdownDialog => {
+            self_test::shutdown_dialog(&Config::load(paths::config_path())?)
+        }
+        CliCommand::TestAction { .. } => {
+            let findings = doctor::check_actions();
+            let response = findings
+                .iter()
+                .map(|finding| finding.to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+            if findings.iter().any(doctor::Finding::is_error) {
+                bail!("{response}");
+            }
+            response
+        }
+        CliCommand::Report => report::shutdown_statistics()?,
+        CliCommand::Analyze { html: None } => analyze::analyze().to_text(),
+        CliCommand::Analyze { html: Some(path) } => {
+            analyze::analyze().write_html(&path)?;
+            format!("analysis written to {}", path.display())
+        }
+        CliCommand::Explain => explain::last_boot(&Config::load(paths::config_path())?)?,
+        CliCommand::Diag { output } => {
+            let missing = diag::export(&output)?;
+            let mut response = format!("diagnostics written to {}", output.display());
+            for part in missing {
+                response.push_str(&format!("\nnot included: {part}"));
+            }
+            response
+        }
+        CliCommand::TellPeers { request } => {
+            let peers = Config::load(paths::config_path())?.peers;
+            if peers.addresses.is_empty() {
+                bail!("no peers are configured");
+            }
+            let mut response = String::new();
+            for (address, result) in peers::send(&peers, request, Cause::Unknown) {
+                match result {
+                    Ok(()) => response.push_str(&format!("{address}: ok\n")),
+                    Err(e) => response.push_str(&format!("{address}: {e:#}\n")),
+                }
+            }
+            response
+        }
+        //remaining commands are forwarded to the running instance over the control pipe
+        //scripts get an answer even if nothing is running
+        CliCommand::Status { json: true, .. } => ipc::send(ipc::Command::StatusJson)
+            .unwrap_or_else(|_| json!({ "running": false }).to_string()),
+        CliCommand::Status { tail: false, .. } => ipc::send(ipc::Command::Status)?,
+        CliCommand::Status { tail: true, .. } => ipc::send(ipc::Command::Tail)?,
+        CliCommand::Pause => ipc::send(ipc::Command::Pause)?,
+        CliCommand::Resume => ipc::send(ipc::Command::Resume)?,
+        CliCommand::CancelShutdown => ipc::send(ipc::Command::CancelShutdown)?,
+        CliCommand::TriggerTest => {
+            //the dialog is shown by the running instance, which may not take the foreground on its own
+            unsafe { AllowSetForegroundWindow(ASFW_ANY) };
+            ipc::send(ipc::Command::TriggerTest)?
+        }
+    };
+    Ok(response)
+}
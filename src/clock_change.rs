@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::event_log;
+
+//Kernel-General logs event 1 whenever the system time is changed, by the windows time service,
+//the user or the hardware clock on boot
+const TIME_CHANGED_QUERY: &str =
+    "*[System[Provider[@Name='Microsoft-Windows-Kernel-General'] and EventID=1]]";
+const MAX_EVENTS: usize = 50;
+
+// Corrections shorter than this are the regular adjustments of the time service.
+static MIN_CORRECTION: Duration = Duration::from_secs(2);
+
+//change of the system time
+#[derive(Debug)]
+pub struct Correction {
+    pub old_time: DateTime<Utc>,
+    pub new_time: DateTime<Utc>,
+}
+
+impl Correction {
+    //negative if the clock was set back
+    pub fn shift(&self) -> chrono::Duration {
+        self.new_time - self.old_time
+    }
+}
+
+//corrections of the wall clock since `since`, oldest first. the time before each correction is
+//compared, since the event itself is dated by the corrected clock.
+pub fn corrections_since(since: DateTime<Utc>) -> anyhow::Result<Vec<Correction>> {
+    let events = event_log::query_newest("System", TIME_CHANGED_QUERY, MAX_EVENTS)?;
+    let mut corrections: Vec<_> = events
+        .iter()
+        .filter_map(|event| {
+            Some(Correction {
+                old_time: parse_time(&event.data("OldTime")?)?,
+                new_time: parse_time(&event.data("NewTime")?)?,
+            })
+        })
+        .filter(|correction| correction.old_time >= since)
+        .filter(|correction| {
+            correction.shift().abs().to_std().unwrap_or_default() >= MIN_CORRECTION
+        })
+        .collect();
+    corrections.reverse();
+    Ok(corrections)
+}
+
+//windows writes 7 fraction digits, e.g. "2024-05-01T03:00:00.5000000Z"
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time.trim())
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
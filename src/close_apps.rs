@@ -0,0 +1,65 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, BOOL, HANDLE, HWND, LPARAM, WAIT_OBJECT_0},
+    System::Threading::{OpenProcess, WaitForSingleObject, PROCESS_SYNCHRONIZE},
+    UI::WindowsAndMessaging::{
+        EnumWindows, GetWindow, GetWindowThreadProcessId, IsWindowVisible, PostMessageW, GW_OWNER,
+        WM_CLOSE,
+    },
+};
+
+use crate::process_list;
+
+//ask running applications named in `names`(e.g. "WINWORD.EXE") to close, as if the user closed
+//their windows, and wait up to `timeout` for them to exit. they can save documents or ask to, which
+//a power-off doesn't give them a chance to. returns the names of those still running.
+pub fn close(names: &[String], timeout: Duration) -> io::Result<Vec<String>> {
+    //windows of other sessions can't be reached from this one
+    let processes = process_list::running_in_session(names)?;
+    if processes.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pids: Vec<u32> = processes.iter().map(|(pid, _)| *pid).collect();
+    unsafe { EnumWindows(Some(close_window), &pids as *const Vec<u32> as LPARAM) };
+    let deadline = Instant::now() + timeout;
+    let mut open = Vec::new();
+    for (pid, name) in processes {
+        log::info!("asked {name}(pid {pid}) to close");
+        let left = deadline.saturating_duration_since(Instant::now());
+        if !exits_within(pid, left) {
+            open.push(name);
+        }
+    }
+    open.sort();
+    open.dedup();
+    Ok(open)
+}
+
+//post WM_CLOSE to top-level windows of the processes in `pids`(a *const Vec<u32>)
+unsafe extern "system" fn close_window(hwnd: HWND, pids: LPARAM) -> BOOL {
+    let pids = &*(pids as *const Vec<u32>);
+    let mut pid = 0;
+    GetWindowThreadProcessId(hwnd, &mut pid);
+    //owned windows(dialogs, tool windows) close along with their owner
+    if pids.contains(&pid) && IsWindowVisible(hwnd) != 0 && GetWindow(hwnd, GW_OWNER) == 0 {
+        PostMessageW(hwnd, WM_CLOSE, 0, 0);
+    }
+    //go on with the next window
+    1
+}
+
+//a process that can't be opened has exited already
+fn exits_within(pid: u32, timeout: Duration) -> bool {
+    let process: HANDLE = unsafe { OpenProcess(PROCESS_SYNCHRONIZE, 0, pid) };
+    if process == 0 {
+        return true;
+    }
+    let millis = timeout.as_millis().min(u32::MAX as u128 - 1) as u32;
+    let exited = unsafe { WaitForSingleObject(process, millis) } == WAIT_OBJECT_0;
+    unsafe { CloseHandle(process) };
+    exited
+}
@@ -0,0 +1,1074 @@
+use std::{
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Local};
+use log::LevelFilter;
+use restart_fix_core::{ConcurrentPolicy, TimestampFormat};
+use serde::{de, Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+use winsafe::{prelude::*, RegistryValue, HKEY};
+
+use crate::{
+    active_hours::ActiveHours,
+    email::Email,
+    hotkey::Hotkey,
+    i18n::Locale,
+    mqtt::Broker,
+    notifier, paths,
+    peers::Peers,
+    policy::{
+        Accessibility, Action, Activity, AppClose, Battery, DialogButton, HeartbeatBackend,
+        LockedSession, OtherSessions, Policy, PromptMode, RemoteSession, RestartLoop, Rule,
+        Schedule, ShutdownLimit, ShutdownType, Tier, UpdateCheck, WarningStyle,
+    },
+    registry, remote_assist,
+    responder::Response,
+    template::Template,
+    wake_on_lan::MacAddress,
+    webhook::Webhook,
+};
+
+//machine-wide settings set by administrators, e.g. through group policy preferences, beneath the
+//config files
+pub const MACHINE_KEY: &str = r"Software\restart-fix";
+//settings enforced through group policy, under HKLM and HKCU. they override every other source.
+pub const POLICY_KEY: &str = r"Software\Policies\RestartFix";
+//sha-256 of the sealed config file and its text, in HKLM where only administrators can write. a
+//subkey, since values of MACHINE_KEY are taken as settings. a config file that doesn't match is
+//replaced by the sealed text.
+const SEAL_KEY: &str = r"Software\restart-fix\Seal";
+const SEAL_HASH_VALUE: &str = "Sha256";
+const SEALED_CONFIG_VALUE: &str = "Config";
+
+// Define a threshold duration used to determine if the system should initiate a shutdown sequence.
+// This constant sets a time limit of 100 seconds. If the duration since the last recorded update
+// (as read from a file) is less than this threshold, it indicates an unexpected restart or a similar
+// event. In such a case, the system will consider initiating a shutdown sequence to handle this situation.
+
+static DEFAULT_THRESHOLD: Duration = Duration::from_secs(100);
+
+// Specify the timeout duration for the shutdown process. This constant defines a period of 20 seconds
+// during which the application will wait after notifying the user of an impending shutdown. If the
+// user does not cancel the shutdown within this timeframe, the system will proceed to shut down.
+// This timeout provides a brief window for any last-minute user intervention or to abort the shutdown
+// process if it was triggered unintentionally.
+static DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(20);
+
+// Time a pre-shutdown command may take. A hanging script must not keep the computer from shutting
+// down forever, and shutdown is aborted rather than cutting the script off halfway.
+static DEFAULT_PRE_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Time applications in close_apps get to save and exit. Long enough to answer a question about
+// unsaved changes.
+static DEFAULT_CLOSE_APPS_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Combination unlikely to be taken by other applications, yet easy to press in a hurry.
+static DEFAULT_CANCEL_HOTKEY: &str = "Ctrl+Alt+Shift+C";
+
+// Time offered by "Extend" in the shutdown dialog. Enough to save work or finish a short task,
+// without putting the shutdown off as far as snoozing does.
+static DEFAULT_EXTEND_BY: Duration = Duration::from_secs(10 * 60);
+
+// Time between pings of `ping_url`. Dead man's switch services expect a ping every few minutes
+// at most, and a ping per heartbeat would flood them.
+static DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(60);
+
+// Time between heartbeats. A restart is noticed if it took longer than the threshold, and the last
+// heartbeat is at most this old when the computer goes down.
+static DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Heartbeats more frequent than this would only keep the disk busy. Shorter intervals are raised
+// to it.
+pub static MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(10);
+
+// Time applications get to close on a graceful shutdown. Saving a document takes seconds, so this
+// only waits so long for someone to answer a "save changes?" prompt.
+static DEFAULT_APP_CLOSE_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Longest deferral for a full-screen application. Covers a meeting or a gaming session, yet a
+// machine left with a game running still goes down eventually.
+static DEFAULT_FULLSCREEN_DEADLINE: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    //in seconds
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub threshold: Duration,
+    //in seconds
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub shutdown_timeout: Duration,
+    //in seconds. restart detection begins this long after restart-fix was started. 0 disables it.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub startup_grace: Duration,
+    //in seconds. no action is taken until this long after the user logged on. 0 disables it.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub logon_grace: Duration,
+    //what happens when a prompt is requested while another one is shown: "merge", "queue" or "ignore"
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub concurrent_prompts: ConcurrentPolicy,
+    //in seconds. user who gave input this recently is only notified instead of prompted. 0 disables it.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub active_input_window: Duration,
+    //any keyboard or mouse input during the countdown cancels it, since someone is at the computer
+    pub cancel_on_input: bool,
+    //in seconds. countdown is held while the user gave input this recently. 0 disables it.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub pause_on_input: Duration,
+    //global key combination cancelling the countdown. empty disables it.
+    #[serde(deserialize_with = "deserialize_optional")]
+    pub cancel_hotkey: Option<Hotkey>,
+    //.wav file or system sound(e.g. "SystemExclamation") played when the countdown starts
+    pub alert_sound: Option<String>,
+    //announce the countdown with text-to-speech
+    pub announce: bool,
+    //title and text of the countdown dialog replacing the built-in wording. see template.rs for placeholders.
+    #[serde(deserialize_with = "deserialize_optional")]
+    pub dialog_title: Option<Template>,
+    #[serde(deserialize_with = "deserialize_optional")]
+    pub dialog_text: Option<Template>,
+    //"countdown" shows the shutdown dialog. "silent" skips it for unattended machines.
+    pub prompt_mode: PromptMode,
+    //"overlay" shows the countdown over the whole screen instead of in a dialog
+    pub warning_style: WarningStyle,
+    //prompt while the workstation is locked: "dialog", "wait", "silent" or "hibernate"
+    pub locked_session: LockedSession,
+    //in seconds. delay before silent shutdown, during which it can still be cancelled over ipc.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub silent_delay: Duration,
+    //in seconds. "Extend" choice of the shutdown dialog postpones the countdown this long. 0 disables it.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub extend_by: Duration,
+    //"Shut down now" choice of the shutdown dialog, which is then shown in a window of its own
+    pub act_now_button: bool,
+    //buttons of the shutdown dialog, in this order. cancel is always offered. empty offers those
+    //of the message box, and "act-now" with act_now_button.
+    pub dialog_buttons: Vec<DialogButton>,
+    //command run before shutdown or hibernation. shutdown is aborted if it exits with non-zero code.
+    pub pre_shutdown_cmd: Option<String>,
+    //in seconds. pre-shutdown command is killed and shutdown aborted after this long.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub pre_shutdown_timeout: Duration,
+    //executable names of applications asked to close before shutdown, so they can save documents
+    pub close_apps: Vec<String>,
+    //in seconds. time they get to exit.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub close_apps_timeout: Duration,
+    //shutdown is aborted if any of them is still running after close_apps_timeout
+    pub abort_if_apps_open: bool,
+    //encoding of heartbeat time: "seconds", "milliseconds" or "rfc3339". every format can be read back.
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub timestamp_format: TimestampFormat,
+    //directory holding heartbeat, pause marker, history and log file. defaults to %LOCALAPPDATA%\restart-fix.
+    //on write-filtered(UWF/EWF) systems this must be on an excluded or unprotected volume.
+    pub state_dir: PathBuf,
+    //SHTDN_REASON_* codes that, when found as reason of the previous shutdown, always bypass detection.
+    //gives deployment tooling a sanctioned way to reboot machines without triggering shutdown.
+    pub bypass_reason_codes: Vec<u32>,
+    //action for each cause of restart(e.g. [policy.windows-update])
+    pub policy: Policy,
+    //threshold, countdown and action for some days and hours([[schedule]] sections). first match applies.
+    pub schedule: Vec<Schedule>,
+    //responses by time the restart took to come back([[tiers]] sections). detects restarts up to the
+    //longest `within` even past threshold.
+    pub tiers: Vec<Tier>,
+    //never shut down inside active hours configured for windows update. user is only notified then.
+    pub respect_active_hours: bool,
+    //warn the user once per boot when windows waits for a restart, before it restarts on its own
+    pub warn_pending_reboot: bool,
+    //abort restarts windows update initiates while restart-fix is running
+    pub abort_update_restarts: bool,
+    //sum up the restarts detected each week, shown to the user and emailed if [email] is set up
+    pub weekly_summary: bool,
+    //send anonymous weekly counters(detections, causes, cancellations). opt-in only.
+    pub telemetry: bool,
+    //endpoint of telemetry. empty uses the one of the build.
+    pub telemetry_url: String,
+    //hours during which the user is only notified, e.g. "22:00-07:00". empty disables it.
+    #[serde(deserialize_with = "deserialize_optional")]
+    pub quiet_hours: Option<ActiveHours>,
+    //behavior on battery([battery] section)
+    pub battery: Battery,
+    //deferral while the computer is busy([activity] section)
+    pub activity: Activity,
+    //escalation on boot loop([restart_loop] section)
+    pub restart_loop: RestartLoop,
+    //cap on automatic shutdowns([shutdown_limit] section)
+    pub shutdown_limit: ShutdownLimit,
+    //countdown for people using assistive technology([accessibility] section)
+    pub accessibility: Accessibility,
+    //"full"(cold boot next time) or "hybrid"(fast startup)
+    pub shutdown_type: ShutdownType,
+    //"force-if-hung", "force" or "graceful"
+    pub app_close: AppClose,
+    //in seconds. applications still open this long after a graceful shutdown began are closed
+    //forcibly. 0 waits until they are closed.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub app_close_timeout: Duration,
+    //action while a remote desktop session is active: "skip", "notify" or "dialog"
+    pub remote_session: RemoteSession,
+    //countdown shown to users of other sessions: "ignore", "warn" or "object"
+    pub other_sessions: OtherSessions,
+    //executable names of remote assistance tools. action is deferred while one of them is running.
+    pub remote_assist_processes: Vec<String>,
+    //in seconds. action is deferred while a full-screen application or presentation runs, for at
+    //most this long. 0 disables it.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub fullscreen_deadline: Duration,
+    //discard state written on another machine. for clones of a golden image in virtual desktop pools.
+    pub vdi_mode: bool,
+    //authenticate heartbeat records with a secret only this user can read
+    pub heartbeat_hmac: bool,
+    //write heartbeat through the disk cache
+    pub durable_heartbeat: bool,
+    //"file", "registry" or "memory"
+    pub heartbeat_store: HeartbeatBackend,
+    //in seconds, fractions allowed. time between heartbeats.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub heartbeat_interval: Duration,
+    //port of localhost websocket endpoint broadcasting state changes and accepting commands
+    pub websocket_port: Option<u16>,
+    //optional port of a prometheus endpoint on localhost(/metrics)
+    pub metrics_port: Option<u16>,
+    //check github for newer releases once a day: "off", "notify" or "install"
+    pub update_check: UpdateCheck,
+    //base url of central reporting server. events are posted as json to {fleet_endpoint}/events.
+    pub fleet_endpoint: Option<String>,
+    //email sent to admins([email] section)
+    pub email: Email,
+    //discord, slack or generic incoming webhooks([[webhooks]] sections)
+    pub webhooks: Vec<Webhook>,
+    //scripts and plugins run for detected restarts([[responses]] sections)
+    pub responses: Vec<Response>,
+    //other restart-fix instances on the network([peers] section)
+    pub peers: Peers,
+    //mac addresses sent wake-on-lan packets when a restart is detected
+    pub wake_on_lan: Vec<MacAddress>,
+    //directory heartbeats are copied to, e.g. on a share of a home server
+    pub heartbeat_mirror: Option<PathBuf>,
+    //url requested after heartbeats, for dead man's switch services like healthchecks.io
+    pub ping_url: Option<String>,
+    //in seconds. url is requested at most this often.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub ping_interval: Duration,
+    //mqtt broker receiving liveness, detections and shutdown actions, as mqtt://[user:password@]host[:port]
+    #[serde(deserialize_with = "deserialize_optional")]
+    pub mqtt_broker: Option<Broker>,
+    //topic messages are published under. defaults to restart-fix/{computer name}.
+    pub mqtt_topic: Option<String>,
+    //announce the machine to home assistant through the mqtt broker
+    pub mqtt_discovery: bool,
+    //records up to this level are logged: "error", "warn", "info", "debug" or "trace"
+    #[serde(deserialize_with = "deserialize_from_str")]
+    pub log_level: LevelFilter,
+    //actions restarts may be responded with. others are replaced by a notice. all if empty.
+    pub allowed_actions: Vec<Action>,
+    //settings locked by group policy, which environment variables and the fleet endpoint can't
+    //override either
+    #[serde(skip)]
+    pub locked: Vec<String>,
+    //config file doesn't match the sealed one, whose settings are used instead
+    #[serde(skip)]
+    pub tampered: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            startup_grace: Duration::ZERO,
+            logon_grace: Duration::ZERO,
+            concurrent_prompts: ConcurrentPolicy::default(),
+            active_input_window: Duration::ZERO,
+            cancel_on_input: false,
+            pause_on_input: Duration::ZERO,
+            cancel_hotkey: DEFAULT_CANCEL_HOTKEY.parse().ok(),
+            alert_sound: None,
+            announce: false,
+            dialog_title: None,
+            dialog_text: None,
+            prompt_mode: PromptMode::default(),
+            warning_style: WarningStyle::default(),
+            silent_delay: Duration::ZERO,
+            extend_by: DEFAULT_EXTEND_BY,
+            act_now_button: false,
+            dialog_buttons: Vec::new(),
+            locked_session: LockedSession::default(),
+            pre_shutdown_cmd: None,
+            pre_shutdown_timeout: DEFAULT_PRE_SHUTDOWN_TIMEOUT,
+            close_apps: Vec::new(),
+            close_apps_timeout: DEFAULT_CLOSE_APPS_TIMEOUT,
+            abort_if_apps_open: false,
+            timestamp_format: TimestampFormat::Milliseconds,
+            state_dir: paths::data_dir(),
+            bypass_reason_codes: Vec::new(),
+            policy: Policy::default(),
+            schedule: Vec::new(),
+            tiers: Vec::new(),
+            respect_active_hours: true,
+            warn_pending_reboot: true,
+            abort_update_restarts: false,
+            weekly_summary: false,
+            telemetry: false,
+            telemetry_url: String::new(),
+            quiet_hours: None,
+            battery: Battery::default(),
+            activity: Activity::default(),
+            restart_loop: RestartLoop::default(),
+            shutdown_limit: ShutdownLimit::default(),
+            accessibility: Accessibility::default(),
+            shutdown_type: ShutdownType::default(),
+            app_close: AppClose::default(),
+            app_close_timeout: DEFAULT_APP_CLOSE_TIMEOUT,
+            remote_session: RemoteSession::default(),
+            other_sessions: OtherSessions::default(),
+            update_check: UpdateCheck::default(),
+            remote_assist_processes: remote_assist::default_processes(),
+            fullscreen_deadline: DEFAULT_FULLSCREEN_DEADLINE,
+            vdi_mode: false,
+            heartbeat_hmac: false,
+            durable_heartbeat: false,
+            heartbeat_store: HeartbeatBackend::default(),
+            heartbeat_interval: DEFAULT_HEARTBEAT_INTERVAL,
+            websocket_port: None,
+            metrics_port: None,
+            fleet_endpoint: None,
+            email: Email::default(),
+            webhooks: Vec::new(),
+            responses: Vec::new(),
+            peers: Peers::default(),
+            wake_on_lan: Vec::new(),
+            heartbeat_mirror: None,
+            ping_url: None,
+            ping_interval: DEFAULT_PING_INTERVAL,
+            mqtt_broker: None,
+            mqtt_topic: None,
+            mqtt_discovery: false,
+            log_level: LevelFilter::Info,
+            allowed_actions: Vec::new(),
+            locked: Vec::new(),
+            tampered: false,
+        }
+    }
+}
+
+impl Config {
+    //settings are taken from, in order of precedence: group policy, RESTART_FIX_* environment
+    //variables, `path`, the machine-wide config file, values of the machine-wide registry key and
+    //defaults. missing files are not an error.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let policies = Self::policies()?;
+        let mut merged = Self::merged(path)?;
+        merge(&mut merged, policies.clone());
+        let mut config: Self = toml::Value::Table(merged).try_into()?;
+        config.locked = policies.keys().cloned().collect();
+        config.tampered = Self::broken_seal(path)?.is_some();
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    //machine-wide settings overlaid with `path`, before group policy, defaults and environment
+    //variables apply. tables are merged key by key. any other setting, lists included, replaces
+    //the one beneath.
+    pub fn merged(path: impl AsRef<Path>) -> anyhow::Result<toml::Table> {
+        let path = path.as_ref();
+        let mut merged = registry_table(&HKEY::LOCAL_MACHINE, MACHINE_KEY)
+            .with_context(|| format!(r"unable to read HKLM\{MACHINE_KEY}"))?;
+        let machine = paths::machine_config_path();
+        if machine != path {
+            merge(&mut merged, read_table(&machine)?);
+        }
+        let file = match Self::broken_seal(path)? {
+            Some(sealed) => toml::from_str(&sealed).context("sealed config")?,
+            None => read_table(path)?,
+        };
+        merge(&mut merged, file);
+        Ok(merged)
+    }
+
+    //protect the config file at `path` from being changed by anyone but an administrator. it is
+    //kept in HKLM as it is now, and used in place of the file while they differ. needs elevation.
+    //returns the sha-256 of the sealed file.
+    pub fn seal(path: impl AsRef<Path>) -> anyhow::Result<String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("unable to read {}", path.display()))?;
+        let _: Self = toml::from_str(&text).with_context(|| format!("{}", path.display()))?;
+        let hash = sha256(text.as_bytes());
+        for (name, value) in [(SEALED_CONFIG_VALUE, &text), (SEAL_HASH_VALUE, &hash)] {
+            registry::set_value(
+                &HKEY::LOCAL_MACHINE,
+                SEAL_KEY,
+                name,
+                RegistryValue::Sz(value.clone()),
+            )
+            .context("unable to seal config file. sealing needs an elevated prompt")?;
+        }
+        Ok(hash)
+    }
+
+    //lets the config file be changed freely again. needs elevation.
+    pub fn unseal() -> anyhow::Result<()> {
+        for name in [SEAL_HASH_VALUE, SEALED_CONFIG_VALUE] {
+            registry::delete_value(&HKEY::LOCAL_MACHINE, SEAL_KEY, name)
+                .context("unable to unseal config file. unsealing needs an elevated prompt")?;
+        }
+        Ok(())
+    }
+
+    //sealed text if the file at `path` was changed or removed since it was sealed. None if it
+    //matches or nothing is sealed.
+    fn broken_seal(path: &Path) -> anyhow::Result<Option<String>> {
+        let Some(hash) = registry::string_value(&HKEY::LOCAL_MACHINE, SEAL_KEY, SEAL_HASH_VALUE)?
+        else {
+            return Ok(None);
+        };
+        if fs::read(path).is_ok_and(|text| sha256(&text) == hash) {
+            return Ok(None);
+        }
+        let sealed = registry::string_value(&HKEY::LOCAL_MACHINE, SEAL_KEY, SEALED_CONFIG_VALUE)?
+            .filter(|sealed| sha256(sealed.as_bytes()) == hash)
+            .context("sealed config is missing or doesn't match its hash")?;
+        Ok(Some(sealed))
+    }
+
+    //settings of group policy. policies of the computer take precedence over those of the user.
+    pub fn policies() -> anyhow::Result<toml::Table> {
+        let mut policies = registry_table(&HKEY::CURRENT_USER, POLICY_KEY)
+            .with_context(|| format!(r"unable to read HKCU\{POLICY_KEY}"))?;
+        merge(
+            &mut policies,
+            registry_table(&HKEY::LOCAL_MACHINE, POLICY_KEY)
+                .with_context(|| format!(r"unable to read HKLM\{POLICY_KEY}"))?,
+        );
+        Ok(policies)
+    }
+
+    //buttons the shutdown dialog offers, cancel first
+    pub fn dialog_buttons(&self) -> Vec<DialogButton> {
+        let mut buttons = vec![DialogButton::Cancel];
+        let configured = if self.dialog_buttons.is_empty() {
+            let mut default = vec![DialogButton::Snooze, DialogButton::Extend];
+            if self.act_now_button {
+                default.push(DialogButton::ActNow);
+            }
+            default
+        } else {
+            self.dialog_buttons.clone()
+        };
+        for button in configured {
+            if !buttons.contains(&button) {
+                buttons.push(button);
+            }
+        }
+        buttons
+    }
+
+    //shutdown dialog needs buttons of its own instead of those of the message box
+    pub fn has_custom_buttons(&self) -> bool {
+        self.act_now_button || !self.dialog_buttons.is_empty()
+    }
+
+    pub fn is_locked(&self, setting: &str) -> bool {
+        self.locked.iter().any(|locked| locked == setting)
+    }
+
+    //`rule` with an action outside allowed_actions replaced by a notice, or by nothing if notices
+    //aren't allowed either
+    pub fn allowed(&self, mut rule: Rule) -> Rule {
+        let allowed =
+            |action| self.allowed_actions.is_empty() || self.allowed_actions.contains(&action);
+        if !allowed(rule.action) {
+            let replacement = if allowed(Action::NotifyOnly) {
+                Action::NotifyOnly
+            } else {
+                Action::Nothing
+            };
+            log::warn!(
+                "action {} is not allowed. {} instead",
+                rule.action.as_str(),
+                replacement.as_str()
+            );
+            rule.action = replacement;
+        }
+        rule
+    }
+
+    //environment variable `name` setting `setting`, unless it is locked by group policy
+    fn env_var(&self, name: &str, setting: &str) -> Option<String> {
+        let value = env_var(name)?;
+        if self.is_locked(setting) {
+            log::warn!("{name} is ignored. {setting} is locked by group policy");
+            return None;
+        }
+        Some(value)
+    }
+
+    fn apply_env(&mut self) -> anyhow::Result<()> {
+        if let Some(value) = self.env_var("RESTART_FIX_THRESHOLD", "threshold") {
+            self.threshold = parse_env_secs("RESTART_FIX_THRESHOLD", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_TIMEOUT", "shutdown_timeout") {
+            self.shutdown_timeout = parse_env_secs("RESTART_FIX_TIMEOUT", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_STARTUP_GRACE", "startup_grace") {
+            self.startup_grace = parse_env_secs("RESTART_FIX_STARTUP_GRACE", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_LOGON_GRACE", "logon_grace") {
+            self.logon_grace = parse_env_secs("RESTART_FIX_LOGON_GRACE", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_CONCURRENT_PROMPTS", "concurrent_prompts") {
+            self.concurrent_prompts = parse_env("RESTART_FIX_CONCURRENT_PROMPTS", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_ACTIVE_INPUT_WINDOW", "active_input_window")
+        {
+            self.active_input_window = parse_env_secs("RESTART_FIX_ACTIVE_INPUT_WINDOW", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_CANCEL_ON_INPUT", "cancel_on_input") {
+            self.cancel_on_input = parse_env("RESTART_FIX_CANCEL_ON_INPUT", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_PAUSE_ON_INPUT", "pause_on_input") {
+            self.pause_on_input = parse_env_secs("RESTART_FIX_PAUSE_ON_INPUT", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_ALERT_SOUND", "alert_sound") {
+            self.alert_sound = Some(value);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_ANNOUNCE", "announce") {
+            self.announce = parse_env("RESTART_FIX_ANNOUNCE", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_CANCEL_HOTKEY", "cancel_hotkey") {
+            self.cancel_hotkey = Some(parse_env("RESTART_FIX_CANCEL_HOTKEY", &value)?);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_DIALOG_TITLE", "dialog_title") {
+            self.dialog_title = Some(parse_env("RESTART_FIX_DIALOG_TITLE", &value)?);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_DIALOG_TEXT", "dialog_text") {
+            self.dialog_text = Some(parse_env("RESTART_FIX_DIALOG_TEXT", &value)?);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_SILENT_DELAY", "silent_delay") {
+            self.silent_delay = parse_env_secs("RESTART_FIX_SILENT_DELAY", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_EXTEND_BY", "extend_by") {
+            self.extend_by = parse_env_secs("RESTART_FIX_EXTEND_BY", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_ACT_NOW_BUTTON", "act_now_button") {
+            self.act_now_button = parse_env("RESTART_FIX_ACT_NOW_BUTTON", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_CLOSE_APPS", "close_apps") {
+            //comma separated
+            self.close_apps = value
+                .split(',')
+                .map(str::trim)
+                .filter(|app| !app.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_CLOSE_APPS_TIMEOUT", "close_apps_timeout") {
+            self.close_apps_timeout = parse_env_secs("RESTART_FIX_CLOSE_APPS_TIMEOUT", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_ABORT_IF_APPS_OPEN", "abort_if_apps_open") {
+            self.abort_if_apps_open = parse_env("RESTART_FIX_ABORT_IF_APPS_OPEN", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_APP_CLOSE_TIMEOUT", "app_close_timeout") {
+            self.app_close_timeout = parse_env_secs("RESTART_FIX_APP_CLOSE_TIMEOUT", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_FULLSCREEN_DEADLINE", "fullscreen_deadline")
+        {
+            self.fullscreen_deadline = parse_env_secs("RESTART_FIX_FULLSCREEN_DEADLINE", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_TIMESTAMP_FORMAT", "timestamp_format") {
+            self.timestamp_format = parse_env("RESTART_FIX_TIMESTAMP_FORMAT", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_STATE_DIR", "state_dir") {
+            self.state_dir = PathBuf::from(value);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_BYPASS_REASON_CODES", "bypass_reason_codes")
+        {
+            //comma separated, decimal or 0x prefixed hex
+            self.bypass_reason_codes = value
+                .split(',')
+                .map(str::trim)
+                .filter(|code| !code.is_empty())
+                .map(|code| match code.strip_prefix("0x") {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => code.parse(),
+                })
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("invalid RESTART_FIX_BYPASS_REASON_CODES: {value}"))?;
+        }
+        if let Some(value) =
+            self.env_var("RESTART_FIX_RESPECT_ACTIVE_HOURS", "respect_active_hours")
+        {
+            self.respect_active_hours = parse_env("RESTART_FIX_RESPECT_ACTIVE_HOURS", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_WARN_PENDING_REBOOT", "warn_pending_reboot")
+        {
+            self.warn_pending_reboot = parse_env("RESTART_FIX_WARN_PENDING_REBOOT", &value)?;
+        }
+        if let Some(value) =
+            self.env_var("RESTART_FIX_ABORT_UPDATE_RESTARTS", "abort_update_restarts")
+        {
+            self.abort_update_restarts = parse_env("RESTART_FIX_ABORT_UPDATE_RESTARTS", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_WEEKLY_SUMMARY", "weekly_summary") {
+            self.weekly_summary = parse_env("RESTART_FIX_WEEKLY_SUMMARY", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_TELEMETRY", "telemetry") {
+            self.telemetry = parse_env("RESTART_FIX_TELEMETRY", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_TELEMETRY_URL", "telemetry_url") {
+            self.telemetry_url = value;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_QUIET_HOURS", "quiet_hours") {
+            self.quiet_hours = Some(parse_env("RESTART_FIX_QUIET_HOURS", &value)?);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_VDI_MODE", "vdi_mode") {
+            self.vdi_mode = parse_env("RESTART_FIX_VDI_MODE", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_HEARTBEAT_HMAC", "heartbeat_hmac") {
+            self.heartbeat_hmac = parse_env("RESTART_FIX_HEARTBEAT_HMAC", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_DURABLE_HEARTBEAT", "durable_heartbeat") {
+            self.durable_heartbeat = parse_env("RESTART_FIX_DURABLE_HEARTBEAT", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_HEARTBEAT_INTERVAL", "heartbeat_interval") {
+            self.heartbeat_interval = parse_env_secs("RESTART_FIX_HEARTBEAT_INTERVAL", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_WEBSOCKET_PORT", "websocket_port") {
+            self.websocket_port = Some(parse_env("RESTART_FIX_WEBSOCKET_PORT", &value)?);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_METRICS_PORT", "metrics_port") {
+            self.metrics_port = Some(parse_env("RESTART_FIX_METRICS_PORT", &value)?);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_FLEET_ENDPOINT", "fleet_endpoint") {
+            self.fleet_endpoint = Some(value);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_WAKE_ON_LAN", "wake_on_lan") {
+            //comma separated
+            self.wake_on_lan = value
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .with_context(|| format!("invalid RESTART_FIX_WAKE_ON_LAN: {value}"))?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_HEARTBEAT_MIRROR", "heartbeat_mirror") {
+            self.heartbeat_mirror = Some(PathBuf::from(value));
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_PING_URL", "ping_url") {
+            self.ping_url = Some(value);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_PING_INTERVAL", "ping_interval") {
+            self.ping_interval = parse_env_secs("RESTART_FIX_PING_INTERVAL", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_MQTT_BROKER", "mqtt_broker") {
+            self.mqtt_broker = Some(parse_env("RESTART_FIX_MQTT_BROKER", &value)?);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_MQTT_TOPIC", "mqtt_topic") {
+            self.mqtt_topic = Some(value);
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_MQTT_DISCOVERY", "mqtt_discovery") {
+            self.mqtt_discovery = parse_env("RESTART_FIX_MQTT_DISCOVERY", &value)?;
+        }
+        if let Some(value) = self.env_var("RESTART_FIX_LOG_LEVEL", "log_level") {
+            self.log_level = parse_env("RESTART_FIX_LOG_LEVEL", &value)?;
+        }
+        Ok(())
+    }
+
+    //configuration of recovery mode. file and environment are ignored, since either of them may be
+    //what keeps the monitor from starting. nothing is shut down and nothing is sent over network.
+    pub fn recovery() -> Self {
+        Self {
+            policy: Policy::notify_only(),
+            ..Self::default()
+        }
+    }
+
+    //sha-256 of config file. lets fleet dashboards spot machines running with unexpected settings.
+    //returns None if config file doesn't exist.
+    pub fn file_hash(path: impl AsRef<Path>) -> Option<String> {
+        Some(sha256(&fs::read(path).ok()?))
+    }
+
+    //settings only applied when monitor starts are taken from `running`.
+    //returns names of those that were changed, which take effect after a restart.
+    pub fn keep_startup_settings(&mut self, running: &Config) -> Vec<&'static str> {
+        let mut ignored = Vec::new();
+        keep(
+            "startup_grace",
+            &mut self.startup_grace,
+            &running.startup_grace,
+            &mut ignored,
+        );
+        keep(
+            "warning_style",
+            &mut self.warning_style,
+            &running.warning_style,
+            &mut ignored,
+        );
+        keep(
+            "concurrent_prompts",
+            &mut self.concurrent_prompts,
+            &running.concurrent_prompts,
+            &mut ignored,
+        );
+        keep(
+            "cancel_hotkey",
+            &mut self.cancel_hotkey,
+            &running.cancel_hotkey,
+            &mut ignored,
+        );
+        keep(
+            "timestamp_format",
+            &mut self.timestamp_format,
+            &running.timestamp_format,
+            &mut ignored,
+        );
+        keep(
+            "state_dir",
+            &mut self.state_dir,
+            &running.state_dir,
+            &mut ignored,
+        );
+        keep(
+            "vdi_mode",
+            &mut self.vdi_mode,
+            &running.vdi_mode,
+            &mut ignored,
+        );
+        keep(
+            "heartbeat_hmac",
+            &mut self.heartbeat_hmac,
+            &running.heartbeat_hmac,
+            &mut ignored,
+        );
+        keep(
+            "durable_heartbeat",
+            &mut self.durable_heartbeat,
+            &running.durable_heartbeat,
+            &mut ignored,
+        );
+        keep(
+            "heartbeat_store",
+            &mut self.heartbeat_store,
+            &running.heartbeat_store,
+            &mut ignored,
+        );
+        keep(
+            "heartbeat_interval",
+            &mut self.heartbeat_interval,
+            &running.heartbeat_interval,
+            &mut ignored,
+        );
+        keep(
+            "websocket_port",
+            &mut self.websocket_port,
+            &running.websocket_port,
+            &mut ignored,
+        );
+        keep(
+            "metrics_port",
+            &mut self.metrics_port,
+            &running.metrics_port,
+            &mut ignored,
+        );
+        keep(
+            "fleet_endpoint",
+            &mut self.fleet_endpoint,
+            &running.fleet_endpoint,
+            &mut ignored,
+        );
+        keep(
+            "peers.listen_port",
+            &mut self.peers.listen_port,
+            &running.peers.listen_port,
+            &mut ignored,
+        );
+        keep(
+            "warn_pending_reboot",
+            &mut self.warn_pending_reboot,
+            &running.warn_pending_reboot,
+            &mut ignored,
+        );
+        keep(
+            "accessibility.text_scale",
+            &mut self.accessibility.text_scale,
+            &running.accessibility.text_scale,
+            &mut ignored,
+        );
+        keep(
+            "act_now_button",
+            &mut self.act_now_button,
+            &running.act_now_button,
+            &mut ignored,
+        );
+        keep(
+            "dialog_buttons",
+            &mut self.dialog_buttons,
+            &running.dialog_buttons,
+            &mut ignored,
+        );
+        keep(
+            "weekly_summary",
+            &mut self.weekly_summary,
+            &running.weekly_summary,
+            &mut ignored,
+        );
+        keep(
+            "telemetry",
+            &mut self.telemetry,
+            &running.telemetry,
+            &mut ignored,
+        );
+        keep(
+            "telemetry_url",
+            &mut self.telemetry_url,
+            &running.telemetry_url,
+            &mut ignored,
+        );
+        keep(
+            "update_check",
+            &mut self.update_check,
+            &running.update_check,
+            &mut ignored,
+        );
+        keep(
+            "heartbeat_mirror",
+            &mut self.heartbeat_mirror,
+            &running.heartbeat_mirror,
+            &mut ignored,
+        );
+        keep(
+            "ping_url",
+            &mut self.ping_url,
+            &running.ping_url,
+            &mut ignored,
+        );
+        keep(
+            "ping_interval",
+            &mut self.ping_interval,
+            &running.ping_interval,
+            &mut ignored,
+        );
+        keep(
+            "mqtt_broker",
+            &mut self.mqtt_broker,
+            &running.mqtt_broker,
+            &mut ignored,
+        );
+        keep(
+            "mqtt_topic",
+            &mut self.mqtt_topic,
+            &running.mqtt_topic,
+            &mut ignored,
+        );
+        keep(
+            "mqtt_discovery",
+            &mut self.mqtt_discovery,
+            &running.mqtt_discovery,
+            &mut ignored,
+        );
+        ignored
+    }
+
+    //[[schedule]] section in effect at `now`
+    pub fn schedule_at(&self, now: DateTime<Local>) -> Option<&Schedule> {
+        self.schedule.iter().find(|schedule| schedule.contains(now))
+    }
+
+    //settings with the [[schedule]] section in effect at `now` applied
+    pub fn scheduled(&self, now: DateTime<Local>) -> Config {
+        let mut config = self.clone();
+        if let Some(schedule) = self.schedule_at(now) {
+            config.threshold = schedule.threshold.unwrap_or(self.threshold);
+            config.shutdown_timeout = schedule.shutdown_timeout.unwrap_or(self.shutdown_timeout);
+            if let Some(action) = schedule.action {
+                config.policy = config.policy.with_action(action);
+            }
+        }
+        config
+    }
+
+    //restarts coming back within this are detected
+    pub fn detection_threshold(&self) -> Duration {
+        self.tiers
+            .iter()
+            .map(|tier| tier.within)
+            .fold(self.threshold, Duration::max)
+    }
+
+    //[[tiers]] section of the shortest `within` that a restart taking `elapsed` fits in
+    pub fn tier_for(&self, elapsed: Duration) -> Option<&Tier> {
+        self.tiers
+            .iter()
+            .filter(|tier| elapsed <= tier.within)
+            .min_by_key(|tier| tier.within)
+    }
+
+    //settings with the [[tiers]] section for a restart taking `elapsed` applied
+    pub fn tiered(&self, elapsed: Duration) -> Config {
+        let mut config = self.clone();
+        if let Some(tier) = self.tier_for(elapsed) {
+            config.prompt_mode = tier.prompt_mode.unwrap_or(self.prompt_mode);
+            config.shutdown_timeout = tier.shutdown_timeout.unwrap_or(self.shutdown_timeout);
+            if let Some(action) = tier.action {
+                config.policy = config.policy.with_action(action);
+            }
+        }
+        config
+    }
+
+    pub fn state_file(&self, name: &str) -> PathBuf {
+        self.state_dir.join(name)
+    }
+}
+
+fn keep<T: PartialEq + Clone>(
+    name: &'static str,
+    reloaded: &mut T,
+    running: &T,
+    changed: &mut Vec<&'static str>,
+) {
+    if reloaded != running {
+        changed.push(name);
+        *reloaded = running.clone();
+    }
+}
+
+//empty variable is treated as unset so that an override can be cleared with `set NAME=`
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+
+//tell the user the config file was changed behind the seal, without blocking the caller
+pub fn warn_tampered() {
+    log::warn!("config file doesn't match the sealed one. sealed settings are used instead");
+    thread::spawn(|| {
+        let locale = Locale::current();
+        notifier::notify(
+            &locale.config_tampered_title(),
+            &locale.config_tampered_text(),
+        );
+    });
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+//config file as table. empty if it doesn't exist.
+fn read_table(path: &Path) -> anyhow::Result<toml::Table> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(toml::from_str(&text).with_context(|| format!("{}", path.display()))?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(toml::Table::new()),
+        Err(e) => Err(e).with_context(|| format!("unable to read {}", path.display())),
+    }
+}
+
+//values of `key` named after settings, e.g. `threshold`(REG_DWORD) or `quiet_hours`(REG_SZ).
+//strings holding a toml value, like `true` or `{ action = "nothing" }`, are taken as that value.
+fn registry_table(root: &HKEY, key: &str) -> anyhow::Result<toml::Table> {
+    let mut table = toml::Table::new();
+    for (name, value) in registry::values(root, key)? {
+        let value = match value {
+            RegistryValue::Dword(value) => toml::Value::Integer(value.into()),
+            RegistryValue::Qword(value) => toml::Value::Integer(value as i64),
+            RegistryValue::Sz(text) | RegistryValue::ExpandSz(text) => {
+                toml::from_str::<toml::Table>(&format!("value = {text}"))
+                    .ok()
+                    .and_then(|mut parsed| parsed.remove("value"))
+                    .unwrap_or(toml::Value::String(text))
+            }
+            RegistryValue::MultiSz(texts) => {
+                toml::Value::Array(texts.into_iter().map(toml::Value::String).collect())
+            }
+            _ => {
+                log::warn!(r"registry value {name} of {key} has unsupported type");
+                continue;
+            }
+        };
+        table.insert(name, value);
+    }
+    Ok(table)
+}
+
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str, value: &str) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid {name}: {value}"))
+}
+
+//seconds with fraction, e.g. "0.25"
+fn parse_env_secs(name: &str, value: &str) -> anyhow::Result<Duration> {
+    let secs = parse_env(name, value)?;
+    secs_to_duration(secs).with_context(|| format!("invalid {name}: {value}"))
+}
+
+//whole or fractional seconds, e.g. 100 or 0.25. kept to the millisecond.
+pub fn deserialize_secs<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    secs_to_duration(f64::deserialize(deserializer)?).map_err(de::Error::custom)
+}
+
+pub fn secs_to_duration(secs: f64) -> anyhow::Result<Duration> {
+    if !secs.is_finite() || secs < 0.0 {
+        anyhow::bail!("{secs} is not a number of seconds");
+    }
+    Ok(Duration::from_millis((secs * 1000.0).round() as u64))
+}
+
+//empty string means the setting is turned off
+pub fn deserialize_optional<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    value.parse().map(Some).map_err(de::Error::custom)
+}
+
+fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(de::Error::custom)
+}
@@ -0,0 +1,186 @@
+use std::{
+    ffi::OsString,
+    io, mem,
+    os::windows::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Arc, Mutex, RwLock},
+    thread,
+    time::Duration,
+};
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, INVALID_HANDLE_VALUE},
+    Storage::FileSystem::{
+        CreateFileW, ReadDirectoryChangesW, FILE_FLAG_BACKUP_SEMANTICS, FILE_LIST_DIRECTORY,
+        FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_INFORMATION,
+        FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    },
+};
+
+use crate::{
+    config::{self, Config},
+    fleet::PushedPolicy,
+    logger, paths,
+};
+
+// Editors often write a file in several steps(truncate, write, rename). Reloading this long after
+// the last change reads the finished file.
+static SETTLE_TIME: Duration = Duration::from_millis(500);
+
+//configuration that is replaced when the config file changes.
+//each use takes the current snapshot, so a decision is made with one consistent configuration.
+pub struct LiveConfig {
+    current: RwLock<Arc<Config>>,
+    //policy pushed by the fleet endpoint. overrides the config file, also after it is reloaded.
+    pushed: Mutex<Option<PushedPolicy>>,
+}
+
+impl LiveConfig {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self {
+            current: RwLock::new(config),
+            pushed: Mutex::new(None),
+        }
+    }
+
+    pub fn push(&self, policy: PushedPolicy) {
+        let mut current = self.current.write().unwrap();
+        let mut config = Config::clone(&current);
+        policy.apply(&mut config);
+        *current = Arc::new(config);
+        *self.pushed.lock().unwrap() = Some(policy);
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        Arc::clone(&self.current.read().unwrap())
+    }
+
+    //load `path` again, keeping settings only applied at startup
+    fn reload(&self, path: &Path) {
+        let mut reloaded = match Config::load(path) {
+            Ok(reloaded) => reloaded,
+            Err(e) => {
+                log::warn!(
+                    "configuration was changed but can't be loaded. previous one is kept: {e:#}"
+                );
+                return;
+            }
+        };
+        let mut current = self.current.write().unwrap();
+        let ignored = reloaded.keep_startup_settings(&current);
+        if let Some(policy) = &*self.pushed.lock().unwrap() {
+            policy.apply(&mut reloaded);
+        }
+        logger::set_level(reloaded.log_level);
+        let tampered = reloaded.tampered && !current.tampered;
+        *current = Arc::new(reloaded);
+        log::info!("configuration reloaded");
+        if tampered {
+            config::warn_tampered();
+        }
+        if !ignored.is_empty() {
+            log::warn!(
+                "changes of {} take effect after restart-fix is restarted",
+                ignored.join(", ")
+            );
+        }
+    }
+}
+
+//reload configuration at `path` whenever it or the machine-wide config file beneath it is changed
+pub fn watch(live: Arc<LiveConfig>, path: PathBuf) {
+    let machine = paths::machine_config_path();
+    //nothing to watch until an administrator creates the machine-wide directory
+    let machine_dir_exists = machine.parent().is_some_and(Path::is_dir);
+    if machine != path && machine_dir_exists {
+        let (live, path) = (Arc::clone(&live), path.clone());
+        thread::spawn(move || {
+            if let Err(e) = watch_changes(&machine, || live.reload(&path)) {
+                log::warn!("unable to watch machine-wide configuration file: {e}");
+            }
+        });
+    }
+    thread::spawn(move || {
+        if let Err(e) = watch_changes(&path, || live.reload(&path)) {
+            log::warn!("unable to watch configuration file. changes apply on next start: {e}");
+        }
+    });
+}
+
+fn watch_changes(path: &Path, mut on_changed: impl FnMut()) -> io::Result<()> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(io::ErrorKind::InvalidInput.into());
+    };
+    let dir_name: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let handle = unsafe {
+        CreateFileW(
+            dir_name.as_ptr(),
+            FILE_LIST_DIRECTORY,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            ptr::null(),
+            OPEN_EXISTING,
+            FILE_FLAG_BACKUP_SEMANTICS,
+            0,
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    //u32 elements keep FILE_NOTIFY_INFORMATION records aligned
+    let mut buffer = vec![0u32; 4096];
+    let result = loop {
+        let mut returned = 0;
+        let read = unsafe {
+            ReadDirectoryChangesW(
+                handle,
+                buffer.as_mut_ptr().cast(),
+                (buffer.len() * mem::size_of::<u32>()) as u32,
+                0,
+                FILE_NOTIFY_CHANGE_FILE_NAME | FILE_NOTIFY_CHANGE_LAST_WRITE,
+                &mut returned,
+                ptr::null_mut(),
+                None,
+            )
+        };
+        if read == 0 {
+            break Err(io::Error::last_os_error());
+        }
+        //0 bytes means the buffer overflowed. the file may have changed.
+        if returned == 0
+            || changed_names(&buffer)
+                .iter()
+                .any(|changed| changed.eq_ignore_ascii_case(name))
+        {
+            thread::sleep(SETTLE_TIME);
+            on_changed();
+        }
+    };
+    unsafe { CloseHandle(handle) };
+    result
+}
+
+fn changed_names(buffer: &[u32]) -> Vec<OsString> {
+    let mut names = Vec::new();
+    let mut offset = 0;
+    loop {
+        let info = unsafe {
+            &*buffer
+                .as_ptr()
+                .cast::<u8>()
+                .add(offset)
+                .cast::<FILE_NOTIFY_INFORMATION>()
+        };
+        let name = unsafe {
+            std::slice::from_raw_parts(
+                info.FileName.as_ptr(),
+                info.FileNameLength as usize / mem::size_of::<u16>(),
+            )
+        };
+        names.push(OsString::from_wide(name));
+        if info.NextEntryOffset == 0 {
+            return names;
+        }
+        offset += info.NextEntryOffset as usize;
+    }
+}
@@ -0,0 +1,48 @@
+use std::sync::OnceLock;
+
+use windows_sys::Win32::{
+    Foundation::BOOL,
+    System::Console::{
+        AllocConsole, AttachConsole, GetConsoleWindow, SetConsoleCtrlHandler,
+        ATTACH_PARENT_PROCESS, CTRL_BREAK_EVENT, CTRL_C_EVENT,
+    },
+};
+
+// Handler run on Ctrl+C. Console control handlers run on a thread of their own.
+static ON_CTRL_C: OnceLock<Box<dyn Fn() + Send + Sync>> = OnceLock::new();
+
+//console for interactive debugging. program is built for windows subsystem, so it uses the console
+//it was started from, or opens one of its own if started from explorer.
+pub fn open() {
+    unsafe {
+        if GetConsoleWindow() == 0 && AttachConsole(ATTACH_PARENT_PROCESS) == 0 {
+            AllocConsole();
+        }
+    }
+}
+
+//run `handler` instead of terminating the process when Ctrl+C or Ctrl+Break is pressed
+pub fn on_ctrl_c(handler: impl Fn() + Send + Sync + 'static) {
+    if ON_CTRL_C.set(Box::new(handler)).is_err() {
+        return;
+    }
+    if unsafe { SetConsoleCtrlHandler(Some(ctrl_handler), 1) } == 0 {
+        log::warn!(
+            "unable to handle Ctrl+C: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+unsafe extern "system" fn ctrl_handler(event: u32) -> BOOL {
+    match event {
+        CTRL_C_EVENT | CTRL_BREAK_EVENT => {
+            if let Some(handler) = ON_CTRL_C.get() {
+                handler();
+            }
+            1
+        }
+        //closing the console window or logoff ends the process as usual
+        _ => 0,
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use windows_sys::Win32::UI::WindowsAndMessaging::{SystemParametersInfoW, SPI_GETSCREENREADER};
+use winsafe::{co, guard::DeleteObjectGuard, gui, msg, prelude::*, HFONT, NONCLIENTMETRICS};
+
+use crate::{dialog_placement, i18n::Locale, policy::DialogButton};
+
+const TIMER_ID: usize = 1;
+
+// Factor the text is enlarged by([accessibility] text_scale). Set on startup.
+static TEXT_SCALE: Mutex<f64> = Mutex::new(1.0);
+
+// Whether the countdown offers other buttons than the message box can(act_now_button,
+// dialog_buttons). Set on startup.
+static CUSTOM_BUTTONS: AtomicBool = AtomicBool::new(false);
+
+//position and size of the buttons, which are laid out in a row
+const BUTTON_WIDTH: u32 = 140;
+const BUTTON_GAP: u32 = 10;
+pub fn set_text_scale(scale: f64) {
+    *TEXT_SCALE.lock().unwrap() = scale.clamp(1.0, 3.0);
+}
+
+pub fn set_custom_buttons(custom: bool) {
+    CUSTOM_BUTTONS.store(custom, Ordering::Relaxed);
+}
+
+//countdown is shown in this window instead of the message box, which can't be enlarged and only
+//has buttons for cancelling and postponing
+pub fn is_used() -> bool {
+    *TEXT_SCALE.lock().unwrap() > 1.0 || CUSTOM_BUTTONS.load(Ordering::Relaxed)
+}
+
+//narrator, nvda, jaws and other screen readers announce themselves with this flag
+pub fn screen_reader_active() -> bool {
+    let mut active = 0;
+    let queried =
+        unsafe { SystemParametersInfoW(SPI_GETSCREENREADER, 0, &mut active as *mut i32 as _, 0) };
+    queried != 0 && active != 0
+}
+
+//countdown with enlarged text and `buttons`, labelled by what they do. returns the one picked. enter picks the focused button,
+//which is "cancel" at first, and escape or closing the window cancels as well, so the computer
+//never goes down by a key pressed by mistake. colors are those of the system, so high contrast
+//themes apply. time left counts down to `deadline`, which moves while the countdown is held.
+//blocks until answered.
+pub fn show(
+    title: &str,
+    text: &str,
+    deadline: Arc<Mutex<Instant>>,
+    buttons: Vec<(DialogButton, String)>,
+) -> anyhow::Result<DialogButton> {
+    let locale = Locale::current();
+    let scale = *TEXT_SCALE.lock().unwrap();
+    let px = |value: u32| (value as f64 * scale) as u32;
+    let pos = |x: i32, y: i32| ((x as f64 * scale) as i32, (y as f64 * scale) as i32);
+    let row = buttons.len().max(1) as u32 * (BUTTON_WIDTH + BUTTON_GAP) - BUTTON_GAP;
+    let width = row.max(440) + 40;
+    let wnd = gui::WindowMain::new(gui::WindowMainOpts {
+        title: title.to_owned(),
+        size: (px(width), px(230)),
+        ex_style: co::WS_EX::TOPMOST,
+        ..Default::default()
+    });
+    let message = gui::Label::new(
+        &wnd,
+        gui::LabelOpts {
+            text: text.to_owned(),
+            position: pos(20, 20),
+            size: (px(width - 40), px(110)),
+            ..Default::default()
+        },
+    );
+    let remaining = gui::Label::new(
+        &wnd,
+        gui::LabelOpts {
+            text: locale.time_left(time_left(&deadline)),
+            position: pos(20, 140),
+            size: (px(width - 40), px(24)),
+            ..Default::default()
+        },
+    );
+    //cancel is the default, so enter never acts
+    let buttons: Vec<_> = buttons
+        .into_iter()
+        .enumerate()
+        .map(|(i, (choice, text))| {
+            let x = 20 + i as u32 * (BUTTON_WIDTH + BUTTON_GAP);
+            let button = gui::Button::new(
+                &wnd,
+                gui::ButtonOpts {
+                    text,
+                    position: pos(x as i32, 180),
+                    width: px(BUTTON_WIDTH),
+                    height: px(30),
+                    button_style: if choice == DialogButton::Cancel {
+                        co::BS::DEFPUSHBUTTON
+                    } else {
+                        co::BS::PUSHBUTTON
+                    },
+                    ..Default::default()
+                },
+            );
+            (choice, button)
+        })
+        .collect();
+
+    let font = scaled_font(scale)?;
+    let answer = Rc::new(Cell::new(DialogButton::Cancel));
+    {
+        let wnd2 = wnd.clone();
+        let (message, remaining) = (message.clone(), remaining.clone());
+        let buttons: Vec<_> = buttons.iter().map(|(_, button)| button.clone()).collect();
+        let hfont = unsafe { font.raw_copy() };
+        wnd.on().wm_create(move |_| {
+            let labels = [message.hwnd(), remaining.hwnd()];
+            for hwnd in labels.into_iter().chain(buttons.iter().map(|b| b.hwnd())) {
+                hwnd.SendMessage(msg::wm::SetFont {
+                    hfont: unsafe { hfont.raw_copy() },
+                    redraw: true,
+                });
+            }
+            buttons[0].focus();
+            wnd2.hwnd().SetTimer(TIMER_ID, 1000, None)?;
+            dialog_placement::bring_to_front(wnd2.hwnd().ptr() as _);
+            Ok(0)
+        });
+    }
+    wnd.on().wm_timer(TIMER_ID, move || {
+        remaining.set_text(&locale.time_left(time_left(&deadline)));
+        Ok(())
+    });
+    for (choice, button) in buttons {
+        let (wnd, answer) = (wnd.clone(), Rc::clone(&answer));
+        button.on().bn_clicked(move || {
+            answer.set(choice);
+            wnd.hwnd().PostMessage(msg::wm::Close {})?;
+            Ok(())
+        });
+    }
+    //escape, sent by the dialog manager as IDCANCEL
+    {
+        let (wnd, answer) = (wnd.clone(), Rc::clone(&answer));
+        wnd.clone()
+            .on()
+            .wm_command_accel_menu(co::DLGID::CANCEL.raw(), move || {
+                answer.set(DialogButton::Cancel);
+                wnd.hwnd().PostMessage(msg::wm::Close {})?;
+                Ok(())
+            });
+    }
+    wnd.run_main(None).map_err(|e| anyhow::anyhow!("{e}"))?;
+    drop(font);
+    Ok(answer.get())
+}
+
+pub fn time_left(deadline: &Mutex<Instant>) -> Duration {
+    deadline
+        .lock()
+        .unwrap()
+        .saturating_duration_since(Instant::now())
+}
+
+//message font of the system, enlarged
+fn scaled_font(scale: f64) -> anyhow::Result<DeleteObjectGuard<HFONT>> {
+    message_font(|height| (height as f64 * scale) as i32)
+}
+
+//message font of the system, `height` pixels tall
+pub fn font_of_height(height: i32) -> anyhow::Result<DeleteObjectGuard<HFONT>> {
+    message_font(|_| -height)
+}
+
+fn message_font(height: impl FnOnce(i32) -> i32) -> anyhow::Result<DeleteObjectGuard<HFONT>> {
+    let mut metrics = NONCLIENTMETRICS::default();
+    unsafe {
+        winsafe::SystemParametersInfo(
+            co::SPI::GETNONCLIENTMETRICS,
+            std::mem::size_of::<NONCLIENTMETRICS>() as _,
+            &mut metrics,
+            co::SPIF::NoValue,
+        )?;
+    }
+    let mut font = metrics.lfMessageFont;
+    font.lfHeight = height(font.lfHeight);
+    Ok(HFONT::CreateFontIndirect(&font)?)
+}
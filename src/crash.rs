@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+
+use crate::event_log;
+
+//Kernel-Power logs event 41 on boot when the previous session ended without a clean shutdown
+const DIRTY_SHUTDOWN_QUERY: &str =
+    "*[System[Provider[@Name='Microsoft-Windows-Kernel-Power'] and EventID=41]]";
+//logged on boot after a bugcheck(blue screen) once the crash dump is processed
+const BUGCHECK_QUERY: &str =
+    "*[System[Provider[@Name='Microsoft-Windows-WER-SystemErrorReporting'] and EventID=1001]]";
+
+//previous session ended by a crash or power loss instead of a shutdown or restart
+#[derive(Debug)]
+pub struct DirtyShutdown {
+    //stop code of the blue screen. None if power was lost or the computer hung.
+    pub bugcheck_code: Option<u32>,
+}
+
+//returns how the previous session ended if it didn't end cleanly
+pub fn previous_dirty_shutdown(boot_time: DateTime<Utc>) -> anyhow::Result<Option<DirtyShutdown>> {
+    let events = event_log::query_newest("System", DIRTY_SHUTDOWN_QUERY, 1)?;
+    let logged_in_this_boot = events
+        .first()
+        .and_then(|event| event.time_created())
+        .is_some_and(|time| time >= boot_time);
+    if !logged_in_this_boot {
+        return Ok(None);
+    }
+    let bugcheck_code = events[0]
+        .data("BugcheckCode")
+        .and_then(|code| code.trim().parse().ok())
+        .filter(|code| *code != 0);
+    let bugcheck_code = match bugcheck_code {
+        Some(code) => Some(code),
+        None => reported_bugcheck(boot_time)?,
+    };
+    Ok(Some(DirtyShutdown { bugcheck_code }))
+}
+
+//bugcheck code reported by windows error reporting in the current boot
+fn reported_bugcheck(boot_time: DateTime<Utc>) -> anyhow::Result<Option<u32>> {
+    Ok(recent_bugchecks(1)?
+        .into_iter()
+        .find(|bugcheck| bugcheck.time >= boot_time)
+        .map(|bugcheck| bugcheck.code))
+}
+
+//blue screen reported by windows error reporting
+#[derive(Debug)]
+pub struct Bugcheck {
+    //time the crash was reported on the next boot
+    pub time: DateTime<Utc>,
+    pub code: u32,
+}
+
+//reported blue screens, newest first.
+//param1 looks like "0x0000009f (0x0000000000000003, ...)".
+pub fn recent_bugchecks(max: usize) -> anyhow::Result<Vec<Bugcheck>> {
+    let events = event_log::query_newest("System", BUGCHECK_QUERY, max)?;
+    Ok(events
+        .iter()
+        .filter_map(|event| {
+            let param = event.data("param1")?;
+            let code = param.split_whitespace().next()?;
+            Some(Bugcheck {
+                time: event.time_created()?,
+                code: u32::from_str_radix(code.trim_start_matches("0x"), 16).ok()?,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,104 @@
+use std::{fs, io, path::Path, time::Duration};
+
+use anyhow::anyhow;
+use chrono::{Datelike, Local, Timelike};
+use rhai::{module_resolvers::DummyModuleResolver, Dynamic, Engine, Scope};
+
+use crate::{
+    policy::{Action, Cause},
+    power, session,
+};
+
+// Operations a script may take. Plenty for a decision, and it ends a script that loops forever.
+static MAX_OPERATIONS: u64 = 100_000;
+
+//what a decide.rhai is told about the detected restart
+pub struct Context {
+    pub cause: Cause,
+    //time since the last heartbeat
+    pub elapsed: Duration,
+    //action of the policy, taken unless the script returns another
+    pub action: Action,
+}
+
+//action the script at `path` returns for `context`. None if there is no script, it returns
+//nothing, or it fails, which leaves the action of the policy in effect.
+pub fn decide(path: &Path, context: &Context) -> Option<Action> {
+    let script = match fs::read_to_string(path) {
+        Ok(script) => script,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            log::warn!("unable to read {}: {e}. policy applies", path.display());
+            return None;
+        }
+    };
+    match run(&script, context) {
+        Ok(Some(action)) => {
+            log::info!("{} decided on {}", path.display(), action.as_str());
+            Some(action)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            log::warn!("{} failed: {e:#}. policy applies", path.display());
+            None
+        }
+    }
+}
+
+fn run(script: &str, context: &Context) -> anyhow::Result<Option<Action>> {
+    let now = Local::now();
+    let status = power::status().ok();
+    let mut scope = Scope::new();
+    scope
+        .push_constant("cause", context.cause.as_str().to_owned())
+        .push_constant("elapsed_secs", context.elapsed.as_secs() as i64)
+        .push_constant("action", context.action.as_str().to_owned())
+        .push_constant("hour", i64::from(now.hour()))
+        .push_constant("minute", i64::from(now.minute()))
+        .push_constant("weekday", now.weekday().to_string().to_lowercase())
+        //unknown, e.g. in the service, which has no input of its own
+        .push_constant(
+            "idle_secs",
+            session::time_since_input()
+                .map_or(Dynamic::UNIT, |idle| Dynamic::from(idle.as_secs() as i64)),
+        )
+        .push_constant(
+            "on_battery",
+            status.as_ref().is_some_and(|status| status.on_battery),
+        )
+        .push_constant(
+            "battery_percent",
+            status
+                .and_then(|status| status.battery_percent)
+                .map_or(Dynamic::UNIT, |percent| Dynamic::from(i64::from(percent))),
+        );
+    let decided = engine()
+        .eval_with_scope::<Dynamic>(&mut scope, script)
+        .map_err(|e| anyhow!("{e}"))?;
+    if decided.is_unit() {
+        return Ok(None);
+    }
+    let name = decided
+        .into_string()
+        .map_err(|kind| anyhow!("returned {kind} instead of the name of an action"))?;
+    Action::parse(&name)
+        .map(Some)
+        .ok_or_else(|| anyhow!("returned unknown action {name:?}"))
+}
+
+//scripts can't reach files, processes or the network, and are bounded in time and memory
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(16)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4096)
+        .set_max_array_size(1024)
+        .set_max_map_size(1024)
+        .on_print(|text| log::info!("decide.rhai: {text}"))
+        .on_debug(|text, _, _| log::debug!("decide.rhai: {text}"));
+    engine
+}
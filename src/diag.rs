@@ -0,0 +1,135 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::ErrorKind,
+    os::windows::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use anyhow::bail;
+use winsafe::{prelude::*, RegistryValue, HKEY};
+
+use crate::{boot, config::Config, doctor, event_log, ipc, paths};
+
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+const VERSION_KEY: &str = r"SOFTWARE\Microsoft\Windows NT\CurrentVersion";
+
+//shutdown initiated(1074), unexpected power off(41), event log started and stopped on boot and
+//shutdown(6005, 6006) and unexpected shutdown(6008)
+const SYSTEM_EVENTS: &str =
+    "*[System[(EventID=1074 or EventID=41 or EventID=6005 or EventID=6006 or EventID=6008)]]";
+const MAX_EVENTS: usize = 200;
+
+//state files worth looking at when restart-fix misfired
+const STATE_FILES: [&str; 8] = [
+    "restart-fix.log",
+    "audit.jsonl",
+    "last_updated.a",
+    "last_updated.b",
+    "history",
+    "shutdowns",
+    "snoozed",
+    "paused",
+];
+
+//collect everything needed to debug a misfire into a zip file at `output`.
+//returns names of the parts that couldn't be collected.
+pub fn export(output: &Path) -> anyhow::Result<Vec<String>> {
+    let dir = std::env::temp_dir().join(format!("restart-fix-diag-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let missing = collect(&dir);
+    let zipped = zip(&dir, output);
+    let _ = fs::remove_dir_all(&dir);
+    zipped?;
+    Ok(missing)
+}
+
+fn collect(dir: &Path) -> Vec<String> {
+    let mut missing = Vec::new();
+    let config_path = paths::config_path();
+    //broken configuration is part of what is debugged, so defaults are used to find the state
+    let config = Config::load(&config_path).unwrap_or_else(|e| {
+        missing.push(format!("configuration: {e:#}"));
+        Config::default()
+    });
+    let mut files: Vec<(String, PathBuf)> = vec![
+        (paths::CONFIG_FILE.to_owned(), config_path),
+        ("crash.log".to_owned(), paths::data_dir().join("crash.log")),
+    ];
+    files.extend(
+        STATE_FILES
+            .into_iter()
+            .map(|name| (name.to_owned(), config.state_file(name))),
+    );
+    for (name, path) in files {
+        match fs::copy(&path, dir.join(&name)) {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => missing.push(format!("{name}: {e}")),
+        }
+    }
+    //running monitor keeps the heartbeat locked, but reports the last one
+    if let Ok(status) = ipc::send(ipc::Command::StatusJson) {
+        if let Err(e) = fs::write(dir.join("status.json"), status) {
+            missing.push(format!("status.json: {e}"));
+        }
+    }
+    match event_log::query_newest("System", SYSTEM_EVENTS, MAX_EVENTS) {
+        Ok(events) => {
+            let xml: Vec<&str> = events.iter().map(|event| event.xml()).collect();
+            let xml = format!("<Events>\r\n{}\r\n</Events>\r\n", xml.join("\r\n"));
+            if let Err(e) = fs::write(dir.join("system-events.xml"), xml) {
+                missing.push(format!("system-events.xml: {e}"));
+            }
+        }
+        Err(e) => missing.push(format!("system event log: {e:#}")),
+    }
+    if let Err(e) = fs::write(dir.join("system.txt"), system_info()) {
+        missing.push(format!("system.txt: {e}"));
+    }
+    if !missing.is_empty() {
+        let _ = fs::write(dir.join("missing.txt"), missing.join("\r\n"));
+    }
+    missing
+}
+
+fn system_info() -> String {
+    let mut info = String::new();
+    let _ = writeln!(info, "restart-fix {}", env!("CARGO_PKG_VERSION"));
+    for name in ["ProductName", "DisplayVersion", "CurrentBuild", "UBR"] {
+        let value = match HKEY::LOCAL_MACHINE.RegGetValue(Some(VERSION_KEY), Some(name)) {
+            Ok(RegistryValue::Sz(value) | RegistryValue::ExpandSz(value)) => value,
+            Ok(RegistryValue::Dword(value)) => value.to_string(),
+            Ok(_) => "?".to_owned(),
+            Err(e) => format!("unavailable({e})"),
+        };
+        let _ = writeln!(info, "{name}: {value}");
+    }
+    let _ = writeln!(info, "boot time: {}", boot::boot_time().to_rfc3339());
+    let _ = writeln!(info, "uptime: {}s", boot::uptime().as_secs());
+    let _ = writeln!(info, "\ndoctor:");
+    for finding in doctor::diagnose() {
+        let _ = writeln!(info, "{finding}");
+    }
+    info
+}
+
+//there is no zip support without another dependency, so it goes through powershell
+fn zip(dir: &Path, output: &Path) -> anyhow::Result<()> {
+    let quote = |path: &Path| format!("'{}'", path.display().to_string().replace('\'', "''"));
+    let script = format!(
+        "Compress-Archive -Path {} -DestinationPath {} -Force",
+        quote(&dir.join("*")),
+        quote(output)
+    );
+    let status = Command::new("powershell.exe")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()?;
+    if !status.success() {
+        bail!("Compress-Archive exited with {status}");
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::{
+    cell::{Cell, RefCell},
+    io, mem, ptr,
+};
+
+use windows_sys::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
+    Graphics::Gdi::{
+        GetMonitorInfoW, MonitorFromPoint, MonitorFromWindow, HMONITOR, MONITORINFO,
+        MONITOR_DEFAULTTONULL, MONITOR_DEFAULTTOPRIMARY,
+    },
+    System::Threading::{AttachThreadInput, GetCurrentThreadId},
+    UI::WindowsAndMessaging::{
+        CallNextHookEx, GetClassNameW, GetCursorPos, GetForegroundWindow, GetWindowRect,
+        GetWindowThreadProcessId, SetForegroundWindow, SetWindowPos, SetWindowsHookExW,
+        UnhookWindowsHookEx, HCBT_ACTIVATE, HWND_TOPMOST, SWP_NOSIZE, WH_CBT,
+    },
+};
+
+type OnShown = Box<dyn FnOnce(HWND)>;
+
+thread_local! {
+    //only the dialog itself is placed, not windows it may open later
+    static PLACED: Cell<bool> = const { Cell::new(false) };
+    //told about the dialog once it is shown
+    static ON_SHOWN: RefCell<Option<OnShown>> = const { RefCell::new(None) };
+}
+
+//show message box of `show` topmost, in the foreground and centered on the monitor the user works on.
+//message box is created on the calling thread, so a thread-local hook catches it before it appears.
+pub fn in_front<T>(show: impl FnOnce() -> T) -> T {
+    PLACED.with(|placed| placed.set(false));
+    let hook = unsafe { SetWindowsHookExW(WH_CBT, Some(cbt_hook), 0, GetCurrentThreadId()) };
+    if hook == 0 {
+        log::warn!(
+            "unable to bring dialog to the front: {}",
+            io::Error::last_os_error()
+        );
+    }
+    let result = show();
+    if hook != 0 {
+        unsafe { UnhookWindowsHookEx(hook) };
+    }
+    ON_SHOWN.with(|on_shown| on_shown.take());
+    result
+}
+
+//call `on_shown` with the window of the next dialog of this thread brought to the front
+pub fn on_shown(on_shown: impl FnOnce(HWND) + 'static) {
+    ON_SHOWN.with(|slot| *slot.borrow_mut() = Some(Box::new(on_shown)));
+}
+
+//same for a window of restart-fix itself, which the hook doesn't recognize
+pub fn bring_to_front(hwnd: HWND) {
+    place(hwnd);
+    set_foreground(hwnd);
+    shown(hwnd);
+}
+
+fn shown(hwnd: HWND) {
+    if let Some(on_shown) = ON_SHOWN.with(|on_shown| on_shown.take()) {
+        on_shown(hwnd);
+    }
+}
+
+unsafe extern "system" fn cbt_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    let hwnd = wparam as HWND;
+    if code == HCBT_ACTIVATE as i32
+        && is_dialog(hwnd)
+        && !PLACED.with(|placed| placed.replace(true))
+    {
+        place(hwnd);
+        set_foreground(hwnd);
+        shown(hwnd);
+    }
+    CallNextHookEx(0, code, wparam, lparam)
+}
+
+//message boxes are of the system dialog class
+fn is_dialog(hwnd: HWND) -> bool {
+    let mut name = [0u16; 16];
+    let len = unsafe { GetClassNameW(hwnd, name.as_mut_ptr(), name.len() as i32) };
+    String::from_utf16_lossy(&name[..len.max(0) as usize]) == "#32770"
+}
+
+//monitor of the active window, or of the cursor if no window is active
+fn target_monitor() -> HMONITOR {
+    let monitor = unsafe { MonitorFromWindow(GetForegroundWindow(), MONITOR_DEFAULTTONULL) };
+    if monitor != 0 {
+        return monitor;
+    }
+    let mut cursor = POINT { x: 0, y: 0 };
+    unsafe {
+        GetCursorPos(&mut cursor);
+        MonitorFromPoint(cursor, MONITOR_DEFAULTTOPRIMARY)
+    }
+}
+
+fn place(hwnd: HWND) {
+    let mut info: MONITORINFO = unsafe { mem::zeroed() };
+    info.cbSize = mem::size_of::<MONITORINFO>() as u32;
+    let mut rect: RECT = unsafe { mem::zeroed() };
+    if unsafe { GetMonitorInfoW(target_monitor(), &mut info) } == 0
+        || unsafe { GetWindowRect(hwnd, &mut rect) } == 0
+    {
+        return;
+    }
+    let work = info.rcWork;
+    let x = work.left + ((work.right - work.left) - (rect.right - rect.left)) / 2;
+    let y = work.top + ((work.bottom - work.top) - (rect.bottom - rect.top)) / 2;
+    unsafe { SetWindowPos(hwnd, HWND_TOPMOST, x, y, 0, 0, SWP_NOSIZE) };
+}
+
+//windows refuses foreground to background processes. sharing input state with the foreground
+//thread for a moment lets the dialog take it anyway.
+fn set_foreground(hwnd: HWND) {
+    unsafe {
+        let foreground = GetWindowThreadProcessId(GetForegroundWindow(), ptr::null_mut());
+        let current = GetCurrentThreadId();
+        let attached = foreground != 0
+            && foreground != current
+            && AttachThreadInput(current, foreground, 1) != 0;
+        SetForegroundWindow(hwnd);
+        if attached {
+            AttachThreadInput(current, foreground, 0);
+        }
+    }
+}
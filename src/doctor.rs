@@ -0,0 +1,252 @@
+use std::{fmt, fs};
+
+use crate::{
+    autostart::{self, Registration},
+    config::Config,
+    fast_startup, paths,
+    policy::ShutdownType,
+    prevention, provision, shutdown_privilege, watchdog_task, write_filter,
+};
+
+pub enum Severity {
+    Ok,
+    Warning,
+    Error,
+}
+
+pub struct Finding {
+    severity: Severity,
+    message: String,
+}
+
+impl Finding {
+    fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Ok => "ok",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "[{severity}] {}", self.message)
+    }
+}
+
+//check installation and environment for problems that would silently break detection
+pub fn diagnose() -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let config_path = paths::config_path();
+    let config = match Config::load(&config_path) {
+        Ok(config) => {
+            findings.push(Finding::new(Severity::Ok, "configuration is valid"));
+            if config.tampered {
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    format!(
+                        "{} doesn't match the sealed config, which is used instead. run `restart-fix config seal` elevated to apply it",
+                        config_path.display()
+                    ),
+                ));
+            }
+            config
+        }
+        Err(e) => {
+            findings.push(Finding::new(
+                Severity::Error,
+                format!("unable to load {}: {e}", config_path.display()),
+            ));
+            Config::default()
+        }
+    };
+    check_state_dir(&config, &mut findings);
+    check_write_filter(&config, &mut findings);
+    check_autostart(&mut findings);
+    check_watchdog_task(&mut findings);
+    check_event_source(&mut findings);
+    check_shutdown_privilege(&mut findings);
+    check_prevention(&mut findings);
+    check_fast_startup(&config, &mut findings);
+    findings
+}
+
+fn check_state_dir(config: &Config, findings: &mut Vec<Finding>) {
+    let probe = config.state_file(".doctor");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            findings.push(Finding::new(
+                Severity::Ok,
+                format!("state directory {} is writable", config.state_dir.display()),
+            ));
+        }
+        Err(e) => findings.push(Finding::new(
+            Severity::Error,
+            format!(
+                "state directory {} is not writable: {e}",
+                config.state_dir.display()
+            ),
+        )),
+    }
+}
+
+fn check_write_filter(config: &Config, findings: &mut Vec<Finding>) {
+    let filters = write_filter::detect();
+    if filters.is_empty() {
+        findings.push(Finding::new(Severity::Ok, "no write filter is active"));
+        return;
+    }
+    for filter in filters {
+        let finding = match write_filter::is_on_system_volume(&config.state_dir) {
+            Ok(true) => Finding::new(
+                Severity::Error,
+                format!(
+                    "{filter} is active and state directory is on the system volume. \
+                    heartbeat won't survive a reboot unless the directory is excluded from the filter. \
+                    set state_dir to an excluded or unprotected volume."
+                ),
+            ),
+            Ok(false) => Finding::new(
+                Severity::Warning,
+                format!(
+                    "{filter} is active. make sure the volume of state directory {} is not protected.",
+                    config.state_dir.display()
+                ),
+            ),
+            Err(e) => Finding::new(
+                Severity::Warning,
+                format!("{filter} is active, but volume of state directory can't be determined: {e}"),
+            ),
+        };
+        findings.push(finding);
+    }
+}
+
+fn check_autostart(findings: &mut Vec<Finding>) {
+    let finding = match autostart::check() {
+        Ok(Registration::Valid) => Finding::new(Severity::Ok, "autostart is registered"),
+        Ok(Registration::Missing) if autostart::is_installed() => Finding::new(
+            Severity::Error,
+            "autostart registration was removed. run `restart-fix install` again.",
+        ),
+        Ok(Registration::Missing) => Finding::new(
+            Severity::Warning,
+            "autostart is not registered by restart-fix. run `restart-fix install` unless it is started by other means.",
+        ),
+        Ok(Registration::Mismatch(command)) => Finding::new(
+            Severity::Error,
+            format!("autostart registration points to other executable: {command}"),
+        ),
+        Err(e) => Finding::new(
+            Severity::Warning,
+            format!("unable to check autostart registration: {e:#}"),
+        ),
+    };
+    findings.push(finding);
+}
+
+fn check_watchdog_task(findings: &mut Vec<Finding>) {
+    if !watchdog_task::is_installed() {
+        return;
+    }
+    let finding = match watchdog_task::exists() {
+        Ok(true) => Finding::new(Severity::Ok, "watchdog task exists"),
+        Ok(false) => Finding::new(
+            Severity::Error,
+            "watchdog task was removed. run `restart-fix install --watchdog` again.",
+        ),
+        Err(e) => Finding::new(
+            Severity::Warning,
+            format!("unable to check watchdog task: {e:#}"),
+        ),
+    };
+    findings.push(finding);
+}
+
+fn check_event_source(findings: &mut Vec<Finding>) {
+    let finding = if provision::is_event_source_registered() {
+        Finding::new(Severity::Ok, "event log source is registered")
+    } else {
+        Finding::new(
+            Severity::Warning,
+            "event log source is not registered. run `restart-fix provision` as administrator.",
+        )
+    };
+    findings.push(finding);
+}
+
+fn check_fast_startup(config: &Config, findings: &mut Vec<Finding>) {
+    let finding = match fast_startup::is_enabled() {
+        Ok(false) => Finding::new(Severity::Ok, "fast startup is off"),
+        Ok(true) if config.shutdown_type == ShutdownType::Hybrid => Finding::new(
+            Severity::Warning,
+            "fast startup is on and shutdown_type is hybrid, so restart-fix doesn't power the computer off completely. set shutdown_type to full or run `restart-fix disable-fast-startup`.",
+        ),
+        Ok(true) => Finding::new(
+            Severity::Ok,
+            "fast startup is on. restart-fix shuts down fully(shutdown_type = full).",
+        ),
+        Err(e) => Finding::new(
+            Severity::Warning,
+            format!("unable to read fast startup setting: {e:#}"),
+        ),
+    };
+    findings.push(finding);
+}
+
+fn check_prevention(findings: &mut Vec<Finding>) {
+    let finding = if prevention::is_applied() {
+        Finding::new(
+            Severity::Ok,
+            "windows update doesn't restart while a user is logged on",
+        )
+    } else {
+        Finding::new(
+            Severity::Warning,
+            "windows update may restart the computer on its own. `restart-fix prevent` sets policies against it.",
+        )
+    };
+    findings.push(finding);
+}
+
+//whether each action can be taken by this account, without taking any
+pub fn check_actions() -> Vec<Finding> {
+    let mut findings = Vec::new();
+    check_shutdown_privilege(&mut findings);
+    let finding = match shutdown_privilege::enable_system_environment() {
+        Ok(()) => Finding::new(Severity::Ok, "reboot-to-firmware restarts into the firmware setup"),
+        Err(e) => Finding::new(
+            Severity::Warning,
+            format!("reboot-to-firmware restarts into the boot options menu instead of the firmware setup({e}). run restart-fix as administrator for the firmware setup."),
+        ),
+    };
+    findings.push(finding);
+    findings.push(Finding::new(
+        Severity::Ok,
+        "lock-screen, notify-only and nothing need no rights",
+    ));
+    findings
+}
+
+impl Finding {
+    pub fn is_error(&self) -> bool {
+        matches!(self.severity, Severity::Error)
+    }
+}
+
+fn check_shutdown_privilege(findings: &mut Vec<Finding>) {
+    let finding = match shutdown_privilege::enable() {
+        Ok(()) => Finding::new(Severity::Ok, "this account may shut down the computer"),
+        Err(e) => Finding::new(
+            Severity::Error,
+            format!("this account may not shut down the computer({e}). grant it the \"Shut down the system\" user right in secpol.msc."),
+        ),
+    };
+    findings.push(finding);
+}
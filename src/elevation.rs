@@ -0,0 +1,124 @@
+use std::{env, ffi::OsStr, fs, mem, os::windows::ffi::OsStrExt, path::Path, process};
+
+use anyhow::{anyhow, bail};
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, ERROR_CANCELLED},
+    Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY},
+    System::Threading::{
+        GetCurrentProcess, GetExitCodeProcess, OpenProcessToken, WaitForSingleObject, INFINITE,
+    },
+    UI::{
+        Shell::{ShellExecuteExW, SEE_MASK_NOASYNC, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW},
+        WindowsAndMessaging::SW_HIDE,
+    },
+};
+
+//hidden argument of the elevated instance. it has no console of its own, so output is written to
+//this file and printed by the instance that launched it.
+const OUTPUT_ARG: &str = "--elevated-output";
+
+//whether this process runs with administrator rights, not only as a member of administrators
+pub fn is_elevated() -> bool {
+    let mut token = 0;
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return false;
+    }
+    let mut elevation = TOKEN_ELEVATION { TokenIsElevated: 0 };
+    let mut length = 0;
+    let queried = unsafe {
+        GetTokenInformation(
+            token,
+            TokenElevation,
+            &mut elevation as *mut _ as *mut _,
+            mem::size_of::<TOKEN_ELEVATION>() as u32,
+            &mut length,
+        )
+    };
+    unsafe { CloseHandle(token) };
+    queried != 0 && elevation.TokenIsElevated != 0
+}
+
+//run `args` of this program elevated through the UAC prompt unless already elevated.
+//returns output of the elevated instance, or None if the caller is elevated and goes on itself.
+pub fn require(args: &[&str]) -> anyhow::Result<Option<String>> {
+    if is_elevated() {
+        return Ok(None);
+    }
+    let output = env::temp_dir().join(format!("restart-fix-elevated-{}.txt", process::id()));
+    let _ = fs::remove_file(&output);
+    let mut parameters: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+    parameters.push(OUTPUT_ARG.to_owned());
+    parameters.push(quote(&output.to_string_lossy()));
+    let exit_code = run_as(&env::current_exe()?, &parameters.join(" "))?;
+    let text = fs::read_to_string(&output).unwrap_or_default();
+    let _ = fs::remove_file(&output);
+    match exit_code {
+        0 => Ok(Some(text)),
+        _ if text.is_empty() => bail!("elevated instance failed with exit code {exit_code}"),
+        _ => Err(anyhow!(text)),
+    }
+}
+
+//result of a command run by the elevated instance, read by `require()` of the launching one
+pub fn write_output(path: &Path, result: &anyhow::Result<String>) {
+    let text = match result {
+        Ok(response) => response.trim_end().to_owned(),
+        Err(e) => format!("{e:#}"),
+    };
+    let _ = fs::write(path, text);
+}
+
+fn run_as(program: &Path, parameters: &str) -> anyhow::Result<u32> {
+    let verb = to_wide("runas");
+    let program = to_wide(program.as_os_str());
+    let parameters = to_wide(parameters);
+    let mut info: SHELLEXECUTEINFOW = unsafe { mem::zeroed() };
+    info.cbSize = mem::size_of::<SHELLEXECUTEINFOW>() as u32;
+    info.fMask = SEE_MASK_NOCLOSEPROCESS | SEE_MASK_NOASYNC;
+    info.lpVerb = verb.as_ptr();
+    info.lpFile = program.as_ptr();
+    info.lpParameters = parameters.as_ptr();
+    info.nShow = SW_HIDE as i32;
+    if unsafe { ShellExecuteExW(&mut info) } == 0 {
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() == Some(ERROR_CANCELLED as i32) {
+            bail!("administrator rights are needed, but the UAC prompt was declined");
+        }
+        return Err(error.into());
+    }
+    let mut exit_code = 0;
+    unsafe {
+        WaitForSingleObject(info.hProcess, INFINITE);
+        GetExitCodeProcess(info.hProcess, &mut exit_code);
+        CloseHandle(info.hProcess);
+    }
+    Ok(exit_code)
+}
+
+//quoted for CommandLineToArgvW, which the elevated instance parses its arguments with
+fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        return arg.to_owned();
+    }
+    let mut quoted = String::from('"');
+    //backslashes are only special in front of a quote, where they are doubled
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes + 1));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        quoted.push(c);
+    }
+    quoted.push_str(&"\\".repeat(backslashes));
+    quoted.push('"');
+    quoted
+}
+
+fn to_wide(s: impl AsRef<OsStr>) -> Vec<u16> {
+    s.as_ref().encode_wide().chain(Some(0)).collect()
+}
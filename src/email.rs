@@ -0,0 +1,316 @@
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context};
+use base64::Engine as _;
+use chrono::{Local, Utc};
+use serde::Deserialize;
+
+use crate::{
+    policy::{Action, Cause},
+    shutdown_reason,
+    weekly_summary::Summary,
+};
+
+const DEFAULT_PORT: u16 = 587;
+//port on which the connection is encrypted from the start instead of with STARTTLS
+const IMPLICIT_TLS_PORT: u16 = 465;
+//server that doesn't answer must not hold up the shutdown for long
+const TIMEOUT: Duration = Duration::from_secs(20);
+//events of the System event log quoted in each message
+const EXCERPT_EVENTS: usize = 3;
+
+//email sent when a restart is detected and when the computer is shut down automatically([email] section)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Email {
+    //"host" or "host:port". port 465 is encrypted from the start, other ports use STARTTLS.
+    pub smtp_server: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    //sender address. defaults to username.
+    pub from: Option<String>,
+    pub to: Vec<String>,
+}
+
+impl Email {
+    fn is_enabled(&self) -> bool {
+        self.smtp_server.is_some() && !self.to.is_empty()
+    }
+}
+
+//notify about restart detected within threshold in background
+pub fn send_detected(email: &Email, cause: Cause, action: Action) {
+    if !email.is_enabled() {
+        return;
+    }
+    let email = email.clone();
+    thread::spawn(move || {
+        let subject = format!("{}: unexpected restart detected", computer_name());
+        let body = format!(
+            "restart-fix detected an unexpected restart of {} at {}.\r\n\r\nCause: {}\r\nAction: {}\r\n\r\n{}",
+            computer_name(),
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            cause.as_str(),
+            action.as_str(),
+            event_excerpt()
+        );
+        match send(&email, &subject, &body) {
+            Ok(()) => log::info!("sent email about detected restart"),
+            Err(e) => log::warn!("unable to send email about detected restart: {e:#}"),
+        }
+    });
+}
+
+//notify about automatic shutdown, hibernation or reboot into firmware setup. blocks until sent,
+//since the computer is about to go down.
+pub fn send_shutdown(email: &Email, cause: Cause, action: Action) {
+    if !email.is_enabled() {
+        return;
+    }
+    let action = match action {
+        Action::Hibernate => "hibernated",
+        Action::RebootToFirmware => "rebooted into firmware setup",
+        _ => "shut down",
+    };
+    let subject = format!("{}: {action} automatically", computer_name());
+    let body = format!(
+        "restart-fix {action} {} at {} after an unexpected restart.\r\n\r\nCause: {}\r\n\r\n{}",
+        computer_name(),
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        cause.as_str(),
+        event_excerpt()
+    );
+    match send(email, &subject, &body) {
+        Ok(()) => log::info!("sent email about automatic {action}"),
+        Err(e) => log::warn!("unable to send email about automatic {action}: {e:#}"),
+    }
+}
+
+//weekly summary of detected restarts in background
+pub fn send_summary(email: &Email, summary: &Summary) {
+    if !email.is_enabled() {
+        return;
+    }
+    let email = email.clone();
+    let subject = format!(
+        "{}: {} unexpected restarts this week",
+        computer_name(),
+        summary.restarts
+    );
+    let mut body = format!(
+        "restart-fix detected {} unexpected restarts of {} in the week until {}({} the week before).\r\n\r\nCauses:\r\n",
+        summary.restarts,
+        computer_name(),
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        summary.previous
+    );
+    if summary.causes.is_empty() {
+        body.push_str("  none\r\n");
+    }
+    for (count, cause) in &summary.causes {
+        let _ = write!(body, "  {count:>5} {cause}\r\n");
+    }
+    let _ = write!(
+        body,
+        "\r\nShut down or hibernated automatically: {}\r\nCancelled or notified only: {}\r\n",
+        summary.shutdowns,
+        summary.restarts.saturating_sub(summary.shutdowns)
+    );
+    thread::spawn(move || match send(&email, &subject, &body) {
+        Ok(()) => log::info!("sent weekly summary email"),
+        Err(e) => log::warn!("unable to send weekly summary email: {e:#}"),
+    });
+}
+
+fn computer_name() -> String {
+    winsafe::GetComputerName().unwrap_or_default()
+}
+
+//recent shutdowns and sessions that ended without one, as recorded by windows
+fn event_excerpt() -> String {
+    let mut excerpt = String::from("Recent shutdowns(System event log, event 1074):\r\n");
+    match shutdown_reason::recent_shutdowns(EXCERPT_EVENTS) {
+        Ok(records) if records.is_empty() => excerpt.push_str("  none\r\n"),
+        Ok(records) => {
+            for record in records {
+                let _ = write!(
+                    excerpt,
+                    "  {} {} by {}({}, reason {:#010x})\r\n",
+                    record
+                        .time
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M:%S"),
+                    record.process,
+                    record.user,
+                    record.reason,
+                    record.reason_code
+                );
+            }
+        }
+        Err(e) => {
+            let _ = write!(excerpt, "  unable to read: {e:#}\r\n");
+        }
+    }
+    excerpt.push_str("Sessions ended without clean shutdown(event 6008):\r\n");
+    match shutdown_reason::recent_dirty_shutdowns(EXCERPT_EVENTS) {
+        Ok(times) if times.is_empty() => excerpt.push_str("  none\r\n"),
+        Ok(times) => {
+            for time in times {
+                let _ = write!(
+                    excerpt,
+                    "  {}\r\n",
+                    time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+                );
+            }
+        }
+        Err(e) => {
+            let _ = write!(excerpt, "  unable to read: {e:#}\r\n");
+        }
+    }
+    excerpt
+}
+
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+//smtp session. password never goes over an unencrypted connection.
+struct Session {
+    stream: BufReader<Box<dyn Stream>>,
+}
+
+impl Session {
+    fn new(stream: impl Stream + 'static) -> Self {
+        Self {
+            stream: BufReader::new(Box::new(stream)),
+        }
+    }
+
+    //send `line` and read the reply, which must have status `expected`. returns text of the reply.
+    fn command(&mut self, line: &str, expected: u16) -> anyhow::Result<String> {
+        let stream = self.stream.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+        self.reply(expected)
+    }
+
+    fn reply(&mut self, expected: u16) -> anyhow::Result<String> {
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                bail!("server closed the connection");
+            }
+            let code: u16 = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| anyhow!("invalid reply: {}", line.trim_end()))?;
+            text.push_str(line.get(4..).unwrap_or_default());
+            //"250-" is followed by more lines, "250 " ends the reply
+            if line.as_bytes().get(3) != Some(&b'-') {
+                if code != expected {
+                    bail!("server answered {}", line.trim_end());
+                }
+                return Ok(text);
+            }
+        }
+    }
+}
+
+fn send(email: &Email, subject: &str, body: &str) -> anyhow::Result<()> {
+    let server = email.smtp_server.as_deref().unwrap_or_default();
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .with_context(|| format!("invalid port of smtp server `{server}`"))?,
+        ),
+        None => (server, DEFAULT_PORT),
+    };
+    let from = email
+        .from
+        .as_deref()
+        .or(email.username.as_deref())
+        .ok_or_else(|| anyhow!("neither from nor username is set"))?;
+
+    let address = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("{host} can't be resolved"))?;
+    let tcp = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+    tcp.set_write_timeout(Some(TIMEOUT))?;
+    let tls = native_tls::TlsConnector::new()?;
+    let hello = format!("EHLO {}", computer_name());
+    let mut session = if port == IMPLICIT_TLS_PORT {
+        let mut session = Session::new(tls.connect(host, tcp)?);
+        session.reply(220)?;
+        session.command(&hello, 250)?;
+        session
+    } else {
+        let mut session = Session::new(tcp.try_clone()?);
+        session.reply(220)?;
+        let capabilities = session.command(&hello, 250)?;
+        if capabilities
+            .lines()
+            .any(|line| line.trim().eq_ignore_ascii_case("STARTTLS"))
+        {
+            session.command("STARTTLS", 220)?;
+            session = Session::new(tls.connect(host, tcp)?);
+            session.command(&hello, 250)?;
+        } else if email.password.is_some() {
+            bail!("{server} doesn't offer STARTTLS. password is not sent unencrypted");
+        }
+        session
+    };
+    if let (Some(username), Some(password)) = (&email.username, &email.password) {
+        let credentials =
+            base64::engine::general_purpose::STANDARD.encode(format!("\0{username}\0{password}"));
+        session.command(&format!("AUTH PLAIN {credentials}"), 235)?;
+    }
+    session.command(&format!("MAIL FROM:<{from}>"), 250)?;
+    for to in &email.to {
+        session.command(&format!("RCPT TO:<{to}>"), 250)?;
+    }
+    session.command("DATA", 354)?;
+    session.command(&message(from, &email.to, subject, body), 250)?;
+    let _ = session.command("QUIT", 221);
+    Ok(())
+}
+
+//headers and body of the message, terminated with a line holding a single dot
+fn message(from: &str, to: &[String], subject: &str, body: &str) -> String {
+    let subject = if subject.is_ascii() {
+        subject.to_owned()
+    } else {
+        format!(
+            "=?utf-8?B?{}?=",
+            base64::engine::general_purpose::STANDARD.encode(subject)
+        )
+    };
+    let mut message = format!(
+        "From: <{from}>\r\nTo: {}\r\nSubject: {subject}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        to.iter()
+            .map(|to| format!("<{to}>"))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Utc::now().to_rfc2822()
+    );
+    for line in body.lines() {
+        //line starting with a dot would end the message early
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push('.');
+    message
+}
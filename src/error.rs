@@ -0,0 +1,132 @@
+use std::{backtrace::Backtrace, io, panic, path::PathBuf, thread};
+
+use restart_fix_core::WorkerError;
+use serde_json::json;
+
+use crate::{event_log, i18n::Locale, notifier};
+
+//errors that stop monitoring. program has no console, so each of them must be reported with report().
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("unable to load configuration: {0:#}")]
+    Config(anyhow::Error),
+    #[error("unable to open log file: {0:#}")]
+    Logger(anyhow::Error),
+    #[error("unable to check for running instance: {0}")]
+    SingleInstance(io::Error),
+    #[error("unable to open {}: {source}", path.display())]
+    StateFile { path: PathBuf, source: io::Error },
+    #[error("unable to lock {}(in use by a backup or sync tool?): {source}", path.display())]
+    StateFileLocked { path: PathBuf, source: io::Error },
+    #[error(transparent)]
+    Worker(WorkerError),
+    #[error("unable to shut down the computer: {0}")]
+    Shutdown(io::Error),
+    #[error("this account is not allowed to shut down the computer({0}). ask an administrator to grant it the \"Shut down the system\" user right(secpol.msc > Local Policies > User Rights Assignment).")]
+    ShutdownPrivilege(io::Error),
+    //not reported to the user. the watchdog task starts restart-fix while it is running.
+    #[error("another instance is already running")]
+    AlreadyRunning,
+}
+
+//exit codes of the process. they are documented and must not change, since scripts and rmm tools
+//depend on them.
+pub const EXIT_FAILURE: i32 = 1;
+//2 is given by clap for invalid arguments
+pub const EXIT_CONFIG: i32 = 3;
+pub const EXIT_STORAGE: i32 = 4;
+pub const EXIT_ALREADY_RUNNING: i32 = 5;
+pub const EXIT_SHUTDOWN: i32 = 6;
+pub const EXIT_MONITOR: i32 = 7;
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => EXIT_CONFIG,
+            Error::Logger(_) | Error::StateFile { .. } | Error::StateFileLocked { .. } => {
+                EXIT_STORAGE
+            }
+            Error::AlreadyRunning => EXIT_ALREADY_RUNNING,
+            Error::Shutdown(_) | Error::ShutdownPrivilege(_) => EXIT_SHUTDOWN,
+            Error::SingleInstance(_) | Error::Worker(_) => EXIT_MONITOR,
+        }
+    }
+
+    //name in machine-readable errors
+    pub fn kind(&self) -> &'static str {
+        match self.exit_code() {
+            EXIT_CONFIG => "config",
+            EXIT_STORAGE => "storage",
+            EXIT_ALREADY_RUNNING => "already-running",
+            EXIT_SHUTDOWN => "shutdown",
+            _ => "monitor",
+        }
+    }
+}
+
+//how errors ending the process are printed to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    //{"error": {"code": 3, "kind": "config", "message": "..."}} on a single line
+    Json,
+}
+
+//print error ending the process with `code` to stderr
+pub fn print(format: ErrorFormat, kind: &str, code: i32, message: &str) {
+    match format {
+        ErrorFormat::Text => eprintln!("error: {message}"),
+        ErrorFormat::Json => eprintln!(
+            "{}",
+            json!({ "error": { "code": code, "kind": kind, "message": message } })
+        ),
+    }
+}
+
+//write error to log and show it to the user
+pub fn report(error: &Error) {
+    log::error!("{error}");
+    report_to_event_log(error);
+    let locale = Locale::current();
+    notifier::notify(&locale.fatal_error_title(), &locale.fatal_error_text(error));
+}
+
+//error of an automatic shutdown or hibernation that doesn't stop monitoring
+pub fn report_action(error: &Error) {
+    log::error!("{error}");
+    report_to_event_log(error);
+    notifier::notify(&Locale::current().shutdown_title(), &error.to_string());
+}
+
+//computer left running after a restart goes unnoticed on unattended machines, so failed shutdowns
+//are also reported where administrators look for them
+fn report_to_event_log(error: &Error) {
+    if matches!(error, Error::Shutdown(_) | Error::ShutdownPrivilege(_)) {
+        let _ = event_log::report_error(&error.to_string());
+    }
+}
+
+//report panics to the event log, where administrators look for them. panic of the main thread
+//ends the program, so it is also shown to the user. panics of other threads may be recovered from
+//(e.g. wmi queries), so they aren't.
+pub fn install_panic_hook() {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let thread = thread::current();
+        let message = format!(
+            "restart-fix panicked in thread {}: {info}\r\n{}",
+            thread.name().unwrap_or("<unnamed>"),
+            Backtrace::force_capture()
+        );
+        let _ = event_log::report_error(&message);
+        previous_hook(info);
+        if thread.name() == Some("main") {
+            let locale = Locale::current();
+            notifier::notify(
+                &locale.fatal_error_title(),
+                &locale.panic_text(&info.to_string()),
+            );
+        }
+    }));
+}
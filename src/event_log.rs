@@ -0,0 +1,247 @@
+use std::{ffi::c_void, io, mem::ManuallyDrop, ptr};
+
+use chrono::{DateTime, Utc};
+use windows_sys::Win32::{
+    Foundation::{ERROR_INSUFFICIENT_BUFFER, ERROR_NO_MORE_ITEMS},
+    System::EventLog::{
+        DeregisterEventSource, EvtClose, EvtNext, EvtQuery, EvtQueryChannelPath,
+        EvtQueryReverseDirection, EvtRender, EvtRenderEventXml, EvtSubscribe,
+        EvtSubscribeActionDeliver, EvtSubscribeToFutureEvents, RegisterEventSourceW, ReportEventW,
+        EVENTLOG_ERROR_TYPE, EVT_HANDLE, EVT_SUBSCRIBE_NOTIFY_ACTION,
+    },
+};
+
+const INFINITE: u32 = u32::MAX;
+
+//source registered by `restart-fix provision`
+const SOURCE: &str = "restart-fix";
+
+//messages of EventCreate.exe render the inserted string as is for ids 1-1000
+const ERROR_EVENT_ID: u32 = 1;
+
+//event rendered as xml by the event log api
+pub struct Event {
+    xml: String,
+}
+
+impl Event {
+    pub fn time_created(&self) -> Option<DateTime<Utc>> {
+        let time = attribute(&self.xml, "TimeCreated", "SystemTime")?;
+        DateTime::parse_from_rfc3339(&time)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    }
+
+    pub fn xml(&self) -> &str {
+        &self.xml
+    }
+
+    //value of <Data Name="{name}"> element of event data
+    pub fn data(&self, name: &str) -> Option<String> {
+        ["'", "\""].into_iter().find_map(|quote| {
+            let open = format!("<Data Name={quote}{name}{quote}>");
+            let start = self.xml.find(&open)? + open.len();
+            let end = self.xml[start..].find("</Data>")? + start;
+            Some(unescape(&self.xml[start..end]))
+        })
+    }
+}
+
+//write error to the application event log. without `restart-fix provision`, event viewer shows
+//the message with a note that the description of the event id can't be found.
+pub fn report_error(message: &str) -> io::Result<()> {
+    let source = to_wide(SOURCE);
+    let handle = unsafe { RegisterEventSourceW(ptr::null(), source.as_ptr()) };
+    if handle == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let message = to_wide(message);
+    let strings = [message.as_ptr()];
+    let reported = unsafe {
+        ReportEventW(
+            handle,
+            EVENTLOG_ERROR_TYPE,
+            0,
+            ERROR_EVENT_ID,
+            ptr::null_mut(),
+            1,
+            0,
+            strings.as_ptr(),
+            ptr::null(),
+        )
+    };
+    let result = if reported == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    };
+    unsafe { DeregisterEventSource(handle) };
+    result
+}
+
+//query events of channel(e.g. "System") matching xpath query, newest first
+pub fn query_newest(channel: &str, xpath: &str, max: usize) -> anyhow::Result<Vec<Event>> {
+    let channel = to_wide(channel);
+    let xpath = to_wide(xpath);
+    let results = Handle(unsafe {
+        EvtQuery(
+            0,
+            channel.as_ptr(),
+            xpath.as_ptr(),
+            EvtQueryChannelPath | EvtQueryReverseDirection,
+        )
+    });
+    if results.0 == 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    let mut events = Vec::new();
+    while events.len() < max {
+        let mut event = 0;
+        let mut returned = 0;
+        if unsafe { EvtNext(results.0, 1, &mut event, INFINITE, 0, &mut returned) } == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(ERROR_NO_MORE_ITEMS as i32) {
+                break;
+            }
+            return Err(err.into());
+        }
+        let event = Handle(event);
+        events.push(Event {
+            xml: render(&event)?,
+        });
+    }
+    Ok(events)
+}
+
+type OnEvent = Box<dyn Fn(Event) + Send + Sync>;
+
+//events of channel matching xpath query are delivered to `on_event` as they are logged, on a
+//thread of the event log api, until this is dropped
+pub struct Subscription {
+    handle: Handle,
+    on_event: *mut OnEvent,
+}
+
+//handler is only called by the event log api, which is done with it once the handle is closed
+unsafe impl Send for Subscription {}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        //closing waits for a callback in progress
+        unsafe { EvtClose(self.handle.0) };
+        self.handle.0 = 0;
+        drop(unsafe { Box::from_raw(self.on_event) });
+    }
+}
+
+pub fn subscribe(
+    channel: &str,
+    xpath: &str,
+    on_event: impl Fn(Event) + Send + Sync + 'static,
+) -> anyhow::Result<Subscription> {
+    let channel = to_wide(channel);
+    let xpath = to_wide(xpath);
+    let on_event: *mut OnEvent = Box::into_raw(Box::new(Box::new(on_event)));
+    let handle = Handle(unsafe {
+        EvtSubscribe(
+            0,
+            0,
+            channel.as_ptr(),
+            xpath.as_ptr(),
+            0,
+            on_event as *const c_void,
+            Some(deliver),
+            EvtSubscribeToFutureEvents,
+        )
+    });
+    if handle.0 == 0 {
+        let error = io::Error::last_os_error();
+        drop(unsafe { Box::from_raw(on_event) });
+        return Err(error.into());
+    }
+    Ok(Subscription { handle, on_event })
+}
+
+unsafe extern "system" fn deliver(
+    action: EVT_SUBSCRIBE_NOTIFY_ACTION,
+    context: *const c_void,
+    event: EVT_HANDLE,
+) -> u32 {
+    //errors are delivered as well(e.g. the channel was cleared), which are of no use here
+    if action != EvtSubscribeActionDeliver {
+        return 0;
+    }
+    let on_event = &*(context as *const OnEvent);
+    //handle of the event belongs to the api and is closed by it
+    let event = ManuallyDrop::new(Handle(event));
+    match render(&event).map(|xml| Event { xml }) {
+        Ok(event) => on_event(event),
+        Err(e) => log::warn!("unable to render subscribed event: {e:#}"),
+    }
+    0
+}
+
+fn render(event: &Handle) -> anyhow::Result<String> {
+    let mut buffer: Vec<u16> = Vec::new();
+    loop {
+        let mut used = 0;
+        let mut property_count = 0;
+        let ok = unsafe {
+            EvtRender(
+                0,
+                event.0,
+                EvtRenderEventXml,
+                (buffer.len() * 2) as u32,
+                buffer.as_mut_ptr().cast(),
+                &mut used,
+                &mut property_count,
+            )
+        };
+        if ok != 0 {
+            //used is in bytes and includes terminating null
+            let len = (used as usize / 2).saturating_sub(1);
+            return Ok(String::from_utf16_lossy(&buffer[..len]));
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERROR_INSUFFICIENT_BUFFER as i32) {
+            return Err(err.into());
+        }
+        buffer.resize((used as usize).div_ceil(2), 0);
+    }
+}
+
+//closes event log handle on drop
+struct Handle(EVT_HANDLE);
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        if self.0 != 0 {
+            unsafe { EvtClose(self.0) };
+        }
+    }
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
+
+fn attribute(xml: &str, element: &str, name: &str) -> Option<String> {
+    let start = xml.find(&format!("<{element} "))?;
+    let end = xml[start..].find('>')? + start;
+    let tag = &xml[start..end];
+    ["'", "\""].into_iter().find_map(|quote| {
+        let key = format!("{name}={quote}");
+        let start = tag.find(&key)? + key.len();
+        let end = tag[start..].find(quote)? + start;
+        Some(unescape(&tag[start..end]))
+    })
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,142 @@
+use std::{fmt::Write, time::Duration};
+
+use chrono::{DateTime, Local, Utc};
+
+use crate::{
+    audit::{self, Boot},
+    config::Config,
+    i18n::Locale,
+    reliability, wmi,
+};
+
+//what happened on the last start and why, told from the audit file, so a question about an
+//unexpected shutdown(or a missing one) can be answered without reading the logs
+pub fn last_boot(config: &Config) -> anyhow::Result<String> {
+    let boots = audit::boots_in(&config.state_file("audit.jsonl"))?;
+    let Some(boot) = boots.last() else {
+        return Ok("no start recorded in the audit file yet".to_owned());
+    };
+    let mut text = String::new();
+    writeln!(
+        text,
+        "last start was evaluated at {}({})",
+        local(boot.time),
+        boot.classification
+    )?;
+    if let Some(boot_time) = boot.boot_time {
+        writeln!(text, "computer was started at {}", local(boot_time))?;
+    }
+    match (boot.last_heartbeat, &boot.error) {
+        (Some(last_heartbeat), _) => {
+            write!(
+                text,
+                "last heartbeat was written at {}",
+                local(last_heartbeat)
+            )?;
+            if let Some(uptime) = boot.uptime_before {
+                write!(
+                    text,
+                    ", after the computer had run for {}",
+                    duration(uptime)
+                )?;
+            }
+            writeln!(text)?;
+        }
+        (None, Some(error)) => {
+            writeln!(
+                text,
+                "last heartbeat couldn't be read({error}), so no restart could be detected"
+            )?;
+        }
+        (None, None) => {}
+    }
+    if let (Some(elapsed), Some(threshold)) = (boot.elapsed, boot.threshold) {
+        let comparison = if elapsed < threshold {
+            "within"
+        } else {
+            "past"
+        };
+        writeln!(
+            text,
+            "{} passed in between, {comparison} the threshold of {}",
+            duration(elapsed),
+            duration(threshold)
+        )?;
+    }
+    writeln!(text, "{}", verdict(boot))?;
+    if let Some(cause) = &boot.cause {
+        writeln!(text, "cause was classified as {cause}")?;
+    }
+    if let Some(rule_action) = &boot.rule_action {
+        writeln!(text, "policy for the cause is {rule_action}")?;
+    }
+    if let Some(action) = &boot.action {
+        if boot.rule_action.as_ref() == Some(action) {
+            writeln!(text, "action taken was {action}")?;
+        } else {
+            writeln!(
+                text,
+                "action taken was {action}, after battery, active hours, quiet hours, input and allowed actions were considered"
+            )?;
+        }
+    }
+    match boot.response.as_deref() {
+        Some("prompted") => writeln!(text, "user was prompted and hasn't answered")?,
+        Some("cancelled") => writeln!(text, "user cancelled the countdown")?,
+        Some("snoozed") => writeln!(text, "user snoozed the countdown")?,
+        Some("expired") => writeln!(text, "countdown expired without an answer")?,
+        Some(response) => writeln!(text, "user response was {response}")?,
+        None if boot.action.is_some() => writeln!(text, "user wasn't prompted")?,
+        None => {}
+    }
+    if boot.acted() {
+        writeln!(text, "restart-fix powered the computer off")?;
+    }
+    let boot_time = boot.boot_time.unwrap_or(boot.time);
+    match reliability::events_since(boot_time) {
+        Ok(events) => {
+            let around: Vec<_> = reliability::around(&events, boot_time).collect();
+            if !around.is_empty() {
+                writeln!(text, "reliability monitor recorded around the start:")?;
+            }
+            for event in around {
+                writeln!(
+                    text,
+                    "  {} {}: {}",
+                    local(event.time),
+                    event.kind.as_str(),
+                    event.product
+                )?;
+            }
+        }
+        Err(e) => writeln!(text, "unable to read reliability records: {e:#}")?,
+    }
+    if let Ok(Some(index)) = wmi::stability_index(boot_time) {
+        writeln!(
+            text,
+            "system stability index was {index:.1} of 10 before the start"
+        )?;
+    }
+    Ok(text.trim_end().to_owned())
+}
+
+fn verdict(boot: &Boot) -> &'static str {
+    match boot.result.as_str() {
+        "restarted" => "so it was taken as an unexpected restart",
+        "normal" => "so it was taken as a normal start",
+        "same-boot" => "computer hadn't restarted since the last heartbeat(restart-fix itself was)",
+        "clock-changed" => "clock was changed in between, so the gap wasn't trusted",
+        "unreadable" => "start wasn't evaluated",
+        _ => "result of the evaluation isn't known",
+    }
+}
+
+fn local(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+fn duration(duration: Duration) -> String {
+    Locale::english().format_duration(duration)
+}
@@ -0,0 +1,36 @@
+use winsafe::{co, prelude::*, RegistryValue, HKEY};
+
+//"Turn on fast startup" of the power options
+const POWER_KEY: &str = r"SYSTEM\CurrentControlSet\Control\Session Manager\Power";
+const HIBERBOOT_ENABLED: &str = "HiberbootEnabled";
+
+//with fast startup, shutting down from the start menu hibernates the kernel, so the next boot
+//resumes it instead of starting cold and uptime may go on across it
+pub fn is_enabled() -> anyhow::Result<bool> {
+    match HKEY::LOCAL_MACHINE.RegGetValue(Some(POWER_KEY), Some(HIBERBOOT_ENABLED)) {
+        Ok(RegistryValue::Dword(enabled)) => Ok(enabled != 0),
+        Ok(_) => anyhow::bail!("{HIBERBOOT_ENABLED} is not a DWORD value"),
+        Err(co::ERROR::FILE_NOT_FOUND) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//turn fast startup off, like unchecking it in the power options. needs administrator rights.
+pub fn disable() -> anyhow::Result<()> {
+    let result = HKEY::LOCAL_MACHINE
+        .RegOpenKeyEx(
+            Some(POWER_KEY),
+            co::REG_OPTION::default(),
+            co::KEY::SET_VALUE,
+        )
+        .and_then(|key| key.RegSetValueEx(Some(HIBERBOOT_ENABLED), RegistryValue::Dword(0)));
+    match result {
+        Ok(()) => Ok(()),
+        Err(co::ERROR::ACCESS_DENIED) => {
+            anyhow::bail!(
+                "access denied. run `restart-fix disable-fast-startup` from an elevated command prompt"
+            )
+        }
+        Err(e) => Err(e.into()),
+    }
+}
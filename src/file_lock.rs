@@ -0,0 +1,27 @@
+use std::{fs::File, io, mem, os::windows::io::AsRawHandle};
+
+use windows_sys::Win32::{
+    Storage::FileSystem::{LockFileEx, LOCKFILE_EXCLUSIVE_LOCK, LOCKFILE_FAIL_IMMEDIATELY},
+    System::IO::OVERLAPPED,
+};
+
+//lock whole `file` for exclusive access through this handle. other processes can neither read nor
+//write it until the handle is closed, which also releases the lock.
+//fails immediately if another handle holds a lock on it.
+pub fn lock_exclusive(file: &File) -> io::Result<()> {
+    let mut overlapped: OVERLAPPED = unsafe { mem::zeroed() };
+    let locked = unsafe {
+        LockFileEx(
+            file.as_raw_handle() as isize,
+            LOCKFILE_EXCLUSIVE_LOCK | LOCKFILE_FAIL_IMMEDIATELY,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if locked == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use restart_fix_core::Event;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    active_hours::ActiveHours,
+    app_state::AppState,
+    boot::BootClassification,
+    config::Config,
+    config_watch::LiveConfig,
+    http,
+    policy::{Action, Cause},
+};
+
+// Heartbeats are written every few seconds. The server only needs to know the machine is alive, so
+// they are forwarded this often at most.
+static HEARTBEAT_REPORT_INTERVAL: Duration = Duration::from_secs(300);
+// Time between fetches of the policy pushed by the server.
+static POLICY_POLL_INTERVAL: Duration = Duration::from_secs(300);
+
+//sent on every startup so a central dashboard can find machines whose monitor never came back
+//after an update or crash
+#[derive(Serialize)]
+struct StartupEvent {
+    event: &'static str,
+    hostname: String,
+    version: &'static str,
+    config_hash: Option<String>,
+    last_boot: BootClassification,
+    time: String,
+}
+
+//report "monitor online" event to the fleet endpoint in background
+pub fn send_online(endpoint: String, config_hash: Option<String>, last_boot: BootClassification) {
+    let event = StartupEvent {
+        event: "monitor-online",
+        hostname: hostname(),
+        version: env!("CARGO_PKG_VERSION"),
+        config_hash,
+        last_boot,
+        time: Utc::now().to_rfc3339(),
+    };
+    thread::spawn(move || match post(&endpoint, &event) {
+        Ok(()) => log::info!("reported startup to fleet endpoint"),
+        Err(e) => log::warn!("unable to report startup to fleet endpoint: {e:#}"),
+    });
+}
+
+//report restart detected within threshold in background
+pub fn send_detection(endpoint: &str, cause: Cause, action: Action) {
+    let endpoint = endpoint.to_owned();
+    let event = AgentEvent {
+        event: "restart-detected",
+        hostname: hostname(),
+        cause: Some(cause.as_str()),
+        action: Some(action.as_str()),
+        time: Utc::now().to_rfc3339(),
+    };
+    thread::spawn(move || match post(&endpoint, &event) {
+        Ok(()) => log::info!("reported detected restart to fleet endpoint"),
+        Err(e) => log::warn!("unable to report detected restart to fleet endpoint: {e:#}"),
+    });
+}
+
+#[derive(Serialize)]
+struct AgentEvent {
+    event: &'static str,
+    hostname: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cause: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    action: Option<&'static str>,
+    time: String,
+}
+
+//settings the server manages for this machine, fetched from {fleet_endpoint}/policy/{hostname}.
+//missing fields leave the setting of the config file as it is.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PushedPolicy {
+    //in seconds
+    pub threshold: Option<u64>,
+    //e.g. "22:00-07:00". empty string turns quiet hours off.
+    pub quiet_hours: Option<String>,
+}
+
+impl PushedPolicy {
+    //policy pushed before restart-fix was last stopped, so it applies before the server is reached
+    pub fn load(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&text) {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                log::warn!("ignoring invalid pushed policy {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    //settings locked by group policy are left as they are
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(threshold) = self.threshold.filter(|_| !config.is_locked("threshold")) {
+            config.threshold = Duration::from_secs(threshold);
+        }
+        match self.quiet_hours.as_deref().map(str::trim) {
+            None => {}
+            Some(_) if config.is_locked("quiet_hours") => {}
+            Some("") => config.quiet_hours = None,
+            Some(hours) => match hours.parse::<ActiveHours>() {
+                Ok(hours) => config.quiet_hours = Some(hours),
+                Err(e) => log::warn!("ignoring quiet hours pushed by fleet endpoint: {e}"),
+            },
+        }
+    }
+}
+
+//stream heartbeats to the fleet endpoint and apply policy it pushes, until the process exits
+pub fn spawn_agent(
+    endpoint: String,
+    state: &AppState,
+    live: Arc<LiveConfig>,
+    policy_path: PathBuf,
+) {
+    let events = state.subscribe();
+    {
+        let endpoint = endpoint.clone();
+        thread::spawn(move || {
+            let mut last_report: Option<Instant> = None;
+            //failure is only logged when it starts, not on every report that follows
+            let mut failing = false;
+            for event in events {
+                if !matches!(event, Event::HeartbeatWritten(_))
+                    || last_report.is_some_and(|at| at.elapsed() < HEARTBEAT_REPORT_INTERVAL)
+                {
+                    continue;
+                }
+                last_report = Some(Instant::now());
+                let event = AgentEvent {
+                    event: "heartbeat",
+                    hostname: hostname(),
+                    cause: None,
+                    action: None,
+                    time: Utc::now().to_rfc3339(),
+                };
+                match post(&endpoint, &event) {
+                    Ok(()) => failing = false,
+                    Err(e) if !failing => {
+                        failing = true;
+                        log::warn!("unable to report heartbeat to fleet endpoint: {e:#}");
+                    }
+                    Err(_) => {}
+                }
+            }
+        });
+    }
+    thread::spawn(move || {
+        let mut current = PushedPolicy::load(&policy_path);
+        loop {
+            match fetch_policy(&endpoint) {
+                Ok(policy) if current.as_ref() != Some(&policy) => {
+                    log::info!("fleet endpoint pushed policy {policy:?}");
+                    if let Err(e) = policy.save(&policy_path) {
+                        log::warn!("unable to save pushed policy: {e:#}");
+                    }
+                    live.push(policy.clone());
+                    current = Some(policy);
+                }
+                Ok(_) => {}
+                Err(e) => log::debug!("unable to fetch policy from fleet endpoint: {e:#}"),
+            }
+            thread::sleep(POLICY_POLL_INTERVAL);
+        }
+    });
+}
+
+fn fetch_policy(endpoint: &str) -> anyhow::Result<PushedPolicy> {
+    let url = format!("{}/policy/{}", endpoint.trim_end_matches('/'), hostname());
+    Ok(http::agent()?.get(&url).call()?.into_json()?)
+}
+
+fn hostname() -> String {
+    winsafe::GetComputerName().unwrap_or_default()
+}
+
+fn post(endpoint: &str, event: &impl Serialize) -> anyhow::Result<()> {
+    let url = format!("{}/events", endpoint.trim_end_matches('/'));
+    http::agent()?.post(&url).send_json(event)?;
+    Ok(())
+}
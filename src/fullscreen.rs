@@ -0,0 +1,66 @@
+use std::{
+    io, thread,
+    time::{Duration, Instant},
+};
+
+use windows_sys::Win32::UI::Shell::{
+    SHQueryUserNotificationState, QUNS_BUSY, QUNS_PRESENTATION_MODE, QUNS_RUNNING_D3D_FULL_SCREEN,
+};
+
+// Time between checks while a full-screen application runs. Games and presentations last for a
+// while, and the prompt comes soon enough after they end.
+static RECHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+//what keeps the user from seeing a prompt right now, as windows tells it to notifications:
+//a full-screen application, a direct3d game in exclusive mode or presentation mode
+pub fn active() -> io::Result<Option<&'static str>> {
+    let mut state = 0;
+    let result = unsafe { SHQueryUserNotificationState(&mut state) };
+    if result < 0 {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(match state {
+        QUNS_BUSY => Some("full-screen application"),
+        QUNS_RUNNING_D3D_FULL_SCREEN => Some("full-screen direct3d application"),
+        QUNS_PRESENTATION_MODE => Some("presentation mode"),
+        _ => None,
+    })
+}
+
+//block while a full-screen application or presentation is running, for at most `deadline`.
+//failure to query the state counts as none.
+pub fn wait_until_inactive(deadline: Duration) {
+    if deadline.is_zero() {
+        return;
+    }
+    let started = Instant::now();
+    let mut logged = false;
+    loop {
+        match active() {
+            Ok(Some(what)) if started.elapsed() < deadline => {
+                if !logged {
+                    log::info!(
+                        "{what} is running. action is deferred for up to {}s",
+                        deadline.as_secs()
+                    );
+                    logged = true;
+                }
+                thread::sleep(RECHECK_INTERVAL.min(deadline.saturating_sub(started.elapsed())));
+            }
+            Ok(Some(what)) => {
+                log::warn!("{what} is still running. deferral has ended");
+                return;
+            }
+            Ok(None) => {
+                if logged {
+                    log::info!("full-screen application has ended");
+                }
+                return;
+            }
+            Err(e) => {
+                log::warn!("unable to check for full-screen applications: {e}");
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use restart_fix_core::Event;
+
+use crate::{app_state::AppState, http};
+
+//ping `url` after heartbeats written, at most once per `interval`, so a dead man's switch
+//(e.g. healthchecks.io) alerts once the machine or restart-fix goes dark.
+//pings stop while monitoring is paused, since no heartbeat is written then.
+pub fn spawn(url: String, interval: Duration, state: Arc<AppState>) {
+    let events = state.subscribe();
+    thread::spawn(move || {
+        let agent = match http::agent() {
+            Ok(agent) => agent,
+            Err(e) => {
+                log::warn!("unable to ping {url}: {e:#}");
+                return;
+            }
+        };
+        let mut last_ping: Option<Instant> = None;
+        //failure is only logged when it starts, not on every ping that follows
+        let mut failing = false;
+        for event in events {
+            if !matches!(event, Event::HeartbeatWritten(_))
+                || last_ping.is_some_and(|at| at.elapsed() < interval)
+            {
+                continue;
+            }
+            last_ping = Some(Instant::now());
+            match agent.get(&url).call() {
+                Ok(_) if failing => {
+                    failing = false;
+                    log::info!("ping of {url} succeeded again");
+                }
+                Ok(_) => {}
+                Err(e) if !failing => {
+                    failing = true;
+                    log::warn!("unable to ping {url}: {e}");
+                }
+                Err(_) => {}
+            }
+        }
+    });
+}
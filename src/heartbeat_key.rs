@@ -0,0 +1,90 @@
+use std::{fs, io, io::ErrorKind, path::Path, ptr, slice};
+
+use windows_sys::Win32::{
+    Security::Cryptography::{
+        BCryptGenRandom, CryptProtectData, CryptUnprotectData, BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+        CRYPTPROTECT_UI_FORBIDDEN, CRYPT_INTEGER_BLOB,
+    },
+    System::Memory::LocalFree,
+};
+
+const KEY_LEN: usize = 32;
+
+//secret authenticating heartbeat records. stored encrypted with dpapi, so only the user account
+//restart-fix runs as can read it, and records written by other users are rejected.
+pub fn load_or_create(path: &Path) -> anyhow::Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(protected) => Ok(unprotect(&protected)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            let mut key = vec![0u8; KEY_LEN];
+            let status = unsafe {
+                BCryptGenRandom(
+                    0,
+                    key.as_mut_ptr(),
+                    key.len() as u32,
+                    BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+                )
+            };
+            if status != 0 {
+                anyhow::bail!("unable to generate heartbeat key(NTSTATUS {status:#x})");
+            }
+            fs::write(path, protect(&key)?)?;
+            Ok(key)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn protect(data: &[u8]) -> io::Result<Vec<u8>> {
+    let input = blob(data);
+    let mut output = blob(&[]);
+    let protected = unsafe {
+        CryptProtectData(
+            &input,
+            ptr::null(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null(),
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+    };
+    if protected == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(take(output))
+}
+
+fn unprotect(data: &[u8]) -> io::Result<Vec<u8>> {
+    let input = blob(data);
+    let mut output = blob(&[]);
+    let unprotected = unsafe {
+        CryptUnprotectData(
+            &input,
+            ptr::null_mut(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null(),
+            CRYPTPROTECT_UI_FORBIDDEN,
+            &mut output,
+        )
+    };
+    if unprotected == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(take(output))
+}
+
+fn blob(data: &[u8]) -> CRYPT_INTEGER_BLOB {
+    CRYPT_INTEGER_BLOB {
+        cbData: data.len() as u32,
+        pbData: data.as_ptr().cast_mut(),
+    }
+}
+
+//copy output of dpapi and free it
+fn take(output: CRYPT_INTEGER_BLOB) -> Vec<u8> {
+    let data = unsafe { slice::from_raw_parts(output.pbData, output.cbData as usize) }.to_vec();
+    unsafe { LocalFree(output.pbData as _) };
+    data
+}
@@ -0,0 +1,78 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use restart_fix_core::{format_heartbeat, Event, Heartbeat, TimestampFormat};
+
+use crate::{app_state::AppState, boot};
+
+// Wait before writing to the share again after it failed. Doubled on every failure that follows.
+static INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+static MAX_BACKOFF: Duration = Duration::from_secs(30 * 60);
+
+//copy heartbeats to `<dir>\<hostname>.heartbeat`, e.g. on a share of a home server, so a central
+//record of when each computer was last alive is kept. the share may be slow or offline at any
+//time, so it is written on a thread of its own. heartbeats written meanwhile wait in the queue of
+//events, and only the newest of them is copied once the share is back.
+pub fn spawn(dir: PathBuf, hostname: &str, format: TimestampFormat, state: Arc<AppState>) {
+    let events = state.subscribe();
+    let path = dir.join(format!("{hostname}.heartbeat"));
+    thread::spawn(move || {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut retry_at: Option<Instant> = None;
+        for event in &events {
+            let Event::HeartbeatWritten(mut time) = event else {
+                continue;
+            };
+            //only the newest heartbeat matters. older ones queued while the share was slow are
+            //skipped.
+            for event in events.try_iter() {
+                if let Event::HeartbeatWritten(newer) = event {
+                    time = newer;
+                }
+            }
+            if retry_at.is_some_and(|at| Instant::now() < at) {
+                continue;
+            }
+            match write(&path, time, format) {
+                Ok(()) if retry_at.is_some() => {
+                    log::info!("heartbeat is copied to {} again", path.display());
+                    retry_at = None;
+                    backoff = INITIAL_BACKOFF;
+                }
+                Ok(()) => {}
+                Err(e) => {
+                    //failure is only logged when it starts, not on every retry that follows
+                    if retry_at.is_none() {
+                        log::warn!(
+                            "unable to copy heartbeat to {}. retrying later: {e:#}",
+                            path.display()
+                        );
+                    } else {
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                    retry_at = Some(Instant::now() + backoff);
+                }
+            }
+        }
+    });
+}
+
+//written next to `path` and renamed over it, so a connection dropped in the middle leaves the
+//previous copy intact rather than a torn one
+fn write(path: &Path, time: DateTime<Utc>, format: TimestampFormat) -> anyhow::Result<()> {
+    let heartbeat = Heartbeat {
+        time,
+        uptime: Some(boot::uptime()),
+        boot_id: boot::boot_id().ok(),
+    };
+    let temp = path.with_extension("heartbeat.tmp");
+    fs::write(&temp, format_heartbeat(heartbeat, format))?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
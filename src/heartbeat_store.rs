@@ -0,0 +1,85 @@
+use std::fs::File;
+
+use restart_fix_core::{
+    format_heartbeat, parse_heartbeat, DoubleBufferedHeartbeat, Heartbeat, HeartbeatStorage,
+    MemoryHeartbeat, Origin, TimestampFormat,
+};
+use winsafe::{prelude::*, RegistryValue, HKEY};
+
+use crate::registry;
+
+//name of the value holding the heartbeat with heartbeat_store = "registry"
+const REGISTRY_VALUE: &str = "heartbeat";
+
+//heartbeat in the store selected by heartbeat_store
+pub enum HeartbeatStore {
+    File(DoubleBufferedHeartbeat<File>),
+    Registry(RegistryHeartbeat),
+    Memory(MemoryHeartbeat),
+}
+
+impl HeartbeatStore {
+    //files the heartbeat is kept in. empty unless it is kept in files.
+    pub fn files(&self) -> &[File] {
+        match self {
+            HeartbeatStore::File(heartbeat) => heartbeat.files(),
+            _ => &[],
+        }
+    }
+
+    //who wrote the heartbeat read last, if it tells
+    pub fn last_origin(&self) -> Option<&Origin> {
+        match self {
+            HeartbeatStore::File(heartbeat) => heartbeat.last_origin(),
+            _ => None,
+        }
+    }
+}
+
+impl HeartbeatStorage for HeartbeatStore {
+    fn read(&mut self) -> anyhow::Result<Heartbeat> {
+        match self {
+            HeartbeatStore::File(heartbeat) => heartbeat.read(),
+            HeartbeatStore::Registry(heartbeat) => heartbeat.read(),
+            HeartbeatStore::Memory(heartbeat) => heartbeat.read(),
+        }
+    }
+
+    fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        match self {
+            HeartbeatStore::File(store) => store.write(heartbeat),
+            HeartbeatStore::Registry(store) => store.write(heartbeat),
+            HeartbeatStore::Memory(store) => store.write(heartbeat),
+        }
+    }
+}
+
+//heartbeat as string value of the per-user key of restart-fix. a value is replaced as a whole, so
+//a write isn't torn like one to a file may be.
+pub struct RegistryHeartbeat {
+    format: TimestampFormat,
+}
+
+impl RegistryHeartbeat {
+    pub fn new(format: TimestampFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl HeartbeatStorage for RegistryHeartbeat {
+    fn read(&mut self) -> anyhow::Result<Heartbeat> {
+        match registry::string_value(&HKEY::CURRENT_USER, registry::APP_KEY, REGISTRY_VALUE)? {
+            Some(heartbeat) => parse_heartbeat(&heartbeat),
+            None => anyhow::bail!("no heartbeat in the registry"),
+        }
+    }
+
+    fn write(&mut self, heartbeat: Heartbeat) -> anyhow::Result<()> {
+        registry::set_value(
+            &HKEY::CURRENT_USER,
+            registry::APP_KEY,
+            REGISTRY_VALUE,
+            RegistryValue::Sz(format_heartbeat(heartbeat, self.format)),
+        )
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, Utc};
+use restart_fix_core::parse_timestamp;
+
+use crate::policy::Cause;
+
+// Number of detections kept in the history file. Older ones are dropped when a detection is
+// recorded, so the file never grows without bound on a machine that restarts all the time.
+static MAX_ENTRIES: usize = 1000;
+
+//restart detected within threshold
+#[derive(Debug, Clone, Copy)]
+pub struct Detection {
+    //increases with every detection recorded, whatever the clock says. 0 for detections recorded
+    //by earlier versions.
+    pub seq: u64,
+    pub time: DateTime<Utc>,
+    //local time at which the detection was recorded. timezone may have changed since.
+    pub local: Option<DateTime<FixedOffset>>,
+    pub cause: Cause,
+}
+
+//detected restarts of this machine, oldest first. each line is "seq unix_timestamp local cause".
+//earlier versions wrote "unix_timestamp cause", which is still read.
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    //records detection of `cause` now
+    pub fn record(&self, cause: Cause) -> anyhow::Result<Detection> {
+        let mut detections = self.read()?;
+        let now = Utc::now();
+        let detection = Detection {
+            seq: detections
+                .iter()
+                .map(|detection| detection.seq)
+                .max()
+                .unwrap_or(0)
+                + 1,
+            time: now,
+            local: Some(now.with_timezone(&Local).fixed_offset()),
+            cause,
+        };
+        if detections.len() >= MAX_ENTRIES {
+            let mut text = String::new();
+            detections.push(detection);
+            for detection in &detections[detections.len() - MAX_ENTRIES..] {
+                text.push_str(&format_line(detection));
+            }
+            fs::write(&self.path, text)?;
+            return Ok(detection);
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(format_line(&detection).as_bytes())?;
+        Ok(detection)
+    }
+
+    //missing history is empty. unreadable lines(e.g. cut off by power loss) are skipped.
+    pub fn read(&self) -> anyhow::Result<Vec<Detection>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(text.lines().filter_map(parse_line).collect())
+    }
+
+    pub fn since(&self, time: DateTime<Utc>) -> anyhow::Result<Vec<Detection>> {
+        let mut detections = self.read()?;
+        detections.retain(|detection| detection.time >= time);
+        Ok(detections)
+    }
+}
+
+fn format_line(detection: &Detection) -> String {
+    let local = detection
+        .local
+        .unwrap_or_else(|| detection.time.fixed_offset())
+        .to_rfc3339_opts(SecondsFormat::Secs, false);
+    format!(
+        "{} {} {local} {}\n",
+        detection.seq,
+        detection.time.timestamp(),
+        detection.cause.as_str()
+    )
+}
+
+fn parse_line(line: &str) -> Option<Detection> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    match fields[..] {
+        [seq, time, local, cause] => Some(Detection {
+            seq: seq.parse().ok()?,
+            time: parse_timestamp(time).ok()?,
+            local: Some(DateTime::parse_from_rfc3339(local).ok()?),
+            cause: Cause::parse(cause)?,
+        }),
+        [time, cause] => Some(Detection {
+            seq: 0,
+            time: parse_timestamp(time).ok()?,
+            local: None,
+            cause: Cause::parse(cause)?,
+        }),
+        _ => None,
+    }
+}
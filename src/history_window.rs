@@ -0,0 +1,156 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use winsafe::{gui, prelude::*, SYSTEMTIME};
+
+use crate::{
+    audit,
+    i18n::{self, Locale},
+    reliability,
+};
+
+// Days shown when the window opens.
+static DEFAULT_DAYS: i64 = 30;
+
+//only one history window is open at a time
+static OPEN: AtomicBool = AtomicBool::new(false);
+
+//list past boots from the audit file, for users who don't use the command line. runs its own
+//message loop, so it is shown from its own thread.
+pub fn show() {
+    if OPEN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    thread::spawn(|| {
+        run(Locale::current());
+        OPEN.store(false, Ordering::SeqCst);
+    });
+}
+
+fn run(locale: Locale) {
+    let wnd = gui::WindowMain::new(gui::WindowMainOpts {
+        title: locale.history_title(),
+        size: (840, 420),
+        ..Default::default()
+    });
+    let from = gui::DateTimePicker::new(
+        &wnd,
+        gui::DateTimePickerOpts {
+            position: (20, 20),
+            width: 200,
+            ..Default::default()
+        },
+    );
+    let to = gui::DateTimePicker::new(
+        &wnd,
+        gui::DateTimePickerOpts {
+            position: (230, 20),
+            width: 200,
+            ..Default::default()
+        },
+    );
+    let filter = gui::Button::new(
+        &wnd,
+        gui::ButtonOpts {
+            text: locale.history_filter(),
+            position: (440, 19),
+            width: 80,
+            ..Default::default()
+        },
+    );
+    let widths = [130, 120, 110, 110, 110, 200];
+    let list = gui::ListView::new(
+        &wnd,
+        gui::ListViewOpts {
+            position: (20, 60),
+            size: (800, 340),
+            columns: locale.history_columns().into_iter().zip(widths).collect(),
+            ..Default::default()
+        },
+    );
+
+    {
+        let (from, list) = (from.clone(), list.clone());
+        wnd.on().wm_create(move |_| {
+            let today = Local::now().date_naive();
+            from.set_date(&system_time(today - Duration::days(DEFAULT_DAYS)));
+            fill(&list, locale, today - Duration::days(DEFAULT_DAYS), today);
+            Ok(0)
+        });
+    }
+    filter.on().bn_clicked(move || {
+        let mut time = SYSTEMTIME::default();
+        from.date(&mut time);
+        let first = naive_date(&time);
+        to.date(&mut time);
+        let last = naive_date(&time);
+        if let (Some(first), Some(last)) = (first, last) {
+            fill(&list, locale, first, last);
+        }
+        Ok(())
+    });
+    if let Err(e) = wnd.run_main(None) {
+        log::warn!("history window failed: {e}");
+    }
+}
+
+//boots between `first` and `last`, both included, newest first
+fn fill(list: &gui::ListView, locale: Locale, first: NaiveDate, last: NaiveDate) {
+    let boots = audit::boots().unwrap_or_else(|e| {
+        log::warn!("unable to read audit records: {e:#}");
+        Vec::new()
+    });
+    //one query for every boot listed, since each takes a while
+    let first_time = first
+        .and_hms_opt(0, 0, 0)
+        .and_then(|time| time.and_local_timezone(Local).earliest())
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let events = reliability::events_since(first_time).unwrap_or_else(|e| {
+        log::warn!("unable to read reliability records: {e:#}");
+        Vec::new()
+    });
+    list.items().delete_all();
+    for boot in boots.iter().rev() {
+        let time = boot.time.with_timezone(&Local);
+        if time.date_naive() < first || time.date_naive() > last {
+            continue;
+        }
+        let action = match &boot.action {
+            Some(action) => locale.history_action(action, boot.acted()),
+            None => String::new(),
+        };
+        list.items().add(
+            &[
+                i18n::format_time(time),
+                boot.classification.clone(),
+                boot.cause.clone().unwrap_or_default(),
+                boot.uptime_before
+                    .map(|uptime| locale.format_duration(uptime))
+                    .unwrap_or_default(),
+                action,
+                reliability::summary(
+                    locale,
+                    reliability::around(&events, boot.boot_time.unwrap_or(boot.time)),
+                ),
+            ],
+            None,
+        );
+    }
+}
+
+fn system_time(date: NaiveDate) -> SYSTEMTIME {
+    SYSTEMTIME {
+        wYear: date.year() as u16,
+        wMonth: date.month() as u16,
+        wDay: date.day() as u16,
+        ..Default::default()
+    }
+}
+
+fn naive_date(time: &SYSTEMTIME) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(time.wYear.into(), time.wMonth.into(), time.wDay.into())
+}
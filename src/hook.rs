@@ -0,0 +1,48 @@
+use std::{
+    os::windows::process::CommandExt,
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+
+//keeps cmd from flashing a console window
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+//run user command(e.g. a script flushing sync clients) before shutting down.
+//error is returned if it fails, exits with non-zero code or doesn't finish within timeout.
+pub fn run_pre_shutdown(command: &str, timeout: Duration) -> anyhow::Result<()> {
+    run("pre-shutdown command", command, timeout, &[])
+}
+
+//run `command` through cmd with `envs` added to the environment. `what` names it in errors.
+pub fn run(
+    what: &str,
+    command: &str,
+    timeout: Duration,
+    envs: &[(&str, &str)],
+) -> anyhow::Result<()> {
+    log::info!("running {what}: {command}");
+    let mut child = Command::new("cmd.exe")
+        .arg("/C")
+        .raw_arg(command)
+        .envs(envs.iter().copied())
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()?;
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                bail!("{what} exited with {status}");
+            }
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            bail!("{what} didn't finish in {}s", timeout.as_secs());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
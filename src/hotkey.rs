@@ -0,0 +1,99 @@
+use std::{fmt, io, str::FromStr, sync::mpsc, thread};
+
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    RegisterHotKey, HOT_KEY_MODIFIERS, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+    VK_F1,
+};
+use winsafe::{co, MSG};
+
+//global key combination such as "Ctrl+Alt+Shift+C"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    modifiers: HOT_KEY_MODIFIERS,
+    //virtual-key code
+    key: u32,
+    //as configured, for logging
+    name: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid hotkey `{0}`(expected e.g. Ctrl+Alt+Shift+C: modifiers Ctrl, Alt, Shift, Win and a letter, digit or F1-F24)")]
+pub struct ParseHotkeyError(String);
+
+impl FromStr for Hotkey {
+    type Err = ParseHotkeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseHotkeyError(s.to_owned());
+        let mut parts: Vec<String> = s
+            .split('+')
+            .map(|part| part.trim().to_ascii_uppercase())
+            .collect();
+        let key = parts.pop().ok_or_else(error)?;
+        let mut modifiers = 0;
+        for part in &parts {
+            modifiers |= match part.as_str() {
+                "CTRL" | "CONTROL" => MOD_CONTROL,
+                "ALT" => MOD_ALT,
+                "SHIFT" => MOD_SHIFT,
+                "WIN" => MOD_WIN,
+                _ => return Err(error()),
+            };
+        }
+        //key without modifier would be taken away from every application
+        if modifiers == 0 {
+            return Err(error());
+        }
+        let key = match key.as_bytes() {
+            //virtual-key codes of letters and digits are their ascii codes
+            [c] if c.is_ascii_alphanumeric() => u32::from(*c),
+            [b'F', digits @ ..] => match std::str::from_utf8(digits)
+                .ok()
+                .and_then(|n| n.parse::<u32>().ok())
+            {
+                Some(n @ 1..=24) => u32::from(VK_F1) + n - 1,
+                _ => return Err(error()),
+            },
+            _ => return Err(error()),
+        };
+        Ok(Self {
+            modifiers,
+            key,
+            name: s.trim().to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+//call `handler` whenever `hotkey` is pressed, whichever window has focus. fails if another
+//application has already registered the combination.
+pub fn listen<F>(hotkey: Hotkey, handler: F) -> io::Result<()>
+where
+    F: Fn() + Send + 'static,
+{
+    let (registered_tx, registered_rx) = mpsc::channel();
+    thread::spawn(move || {
+        //hotkey without window posts WM_HOTKEY to the queue of the registering thread
+        let registered =
+            unsafe { RegisterHotKey(0, 1, hotkey.modifiers | MOD_NOREPEAT, hotkey.key) };
+        if registered == 0 {
+            let _ = registered_tx.send(Err(io::Error::last_os_error()));
+            return;
+        }
+        let _ = registered_tx.send(Ok(()));
+        let mut msg = MSG::default();
+        while let Ok(true) = winsafe::GetMessage(&mut msg, None, 0, 0) {
+            if msg.message == co::WM::HOTKEY {
+                handler();
+            }
+        }
+    });
+    registered_rx
+        .recv()
+        .unwrap_or_else(|_| Err(io::Error::other("hotkey thread stopped")))
+}
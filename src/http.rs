@@ -0,0 +1,12 @@
+use std::{sync::Arc, time::Duration};
+
+static TIMEOUT: Duration = Duration::from_secs(10);
+
+//http client for outgoing reports. requests must never hang the caller indefinitely.
+pub fn agent() -> anyhow::Result<ureq::Agent> {
+    Ok(ureq::AgentBuilder::new()
+        .tls_connector(Arc::new(native_tls::TlsConnector::new()?))
+        .timeout(TIMEOUT)
+        .user_agent(concat!("restart-fix/", env!("CARGO_PKG_VERSION")))
+        .build())
+}
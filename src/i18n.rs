@@ -0,0 +1,700 @@
+use std::{fs, mem, ptr, sync::OnceLock, time::Duration};
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+use windows_sys::Win32::{
+    Foundation::{LPARAM, SYSTEMTIME},
+    Globalization::{
+        GetDateFormatEx, GetTimeFormatEx, GetUserDefaultUILanguage, LCIDToLocaleName,
+        DATE_SHORTDATE,
+    },
+    Graphics::Gdi::{
+        EnumFontFamiliesExW, GetDC, ReleaseDC, FONT_CHARSET, HANGUL_CHARSET, LOGFONTW, TEXTMETRICW,
+    },
+};
+
+use crate::{countdown_window, paths, policy::Action, reliability, weekly_summary::Summary};
+
+type Bundle = FluentBundle<FluentResource>;
+
+// Catalogs built into the executable, by language tag. Catalogs in paths::locale_dirs() override
+// their messages or add languages.
+static BUILT_IN: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.ftl")),
+    ("ko", include_str!("../locales/ko.ftl")),
+];
+// Catalog every other one falls back to for messages it doesn't have.
+static ENGLISH: OnceLock<Bundle> = OnceLock::new();
+// Catalog of the display language of the user. It only changes on the next sign-in.
+static USER: OnceLock<&'static Bundle> = OnceLock::new();
+
+// Whether a font with hangul is installed. Checked once, since fonts are hardly ever removed.
+static HANGUL_FONT: OnceLock<bool> = OnceLock::new();
+
+//longest date or time the locale apis return, null included
+const MAX_FORMATTED: usize = 80;
+//longest locale name, null included(LOCALE_NAME_MAX_LENGTH)
+const MAX_LOCALE_NAME: usize = 85;
+
+//`time` in the short date and time format of the user's regional settings, e.g. "2024-03-05
+//오후 3:04:05" or "3/5/2024 3:04:05 PM". falls back to iso 8601 if windows can't format it.
+pub fn format_time(time: DateTime<Local>) -> String {
+    let system_time = SYSTEMTIME {
+        wYear: time.year() as u16,
+        wMonth: time.month() as u16,
+        wDayOfWeek: time.weekday().num_days_from_sunday() as u16,
+        wDay: time.day() as u16,
+        wHour: time.hour() as u16,
+        wMinute: time.minute() as u16,
+        wSecond: time.second() as u16,
+        wMilliseconds: 0,
+    };
+    let mut date = [0u16; MAX_FORMATTED];
+    let mut clock = [0u16; MAX_FORMATTED];
+    //null locale name is the one of the user
+    let (date_len, clock_len) = unsafe {
+        (
+            GetDateFormatEx(
+                ptr::null(),
+                DATE_SHORTDATE,
+                &system_time,
+                ptr::null(),
+                date.as_mut_ptr(),
+                date.len() as i32,
+                ptr::null(),
+            ),
+            GetTimeFormatEx(
+                ptr::null(),
+                0,
+                &system_time,
+                ptr::null(),
+                clock.as_mut_ptr(),
+                clock.len() as i32,
+            ),
+        )
+    };
+    if date_len <= 0 || clock_len <= 0 {
+        return time.format("%Y-%m-%d %H:%M:%S").to_string();
+    }
+    //lengths include the null
+    format!(
+        "{} {}",
+        String::from_utf16_lossy(&date[..date_len as usize - 1]),
+        String::from_utf16_lossy(&clock[..clock_len as usize - 1])
+    )
+}
+
+//korean text shows up as boxes on a system without a font that has hangul(e.g. a trimmed down
+//image), which is worse than english
+fn can_show_korean() -> bool {
+    *HANGUL_FONT.get_or_init(|| {
+        let found = has_font(HANGUL_CHARSET);
+        if !found {
+            log::warn!("no font with hangul is installed. text is shown in english instead");
+        }
+        found
+    })
+}
+
+//whether any installed font covers `charset`. taken as true if fonts can't be listed, so the
+//language of the user is only given up for a reason.
+fn has_font(charset: FONT_CHARSET) -> bool {
+    let screen = unsafe { GetDC(0) };
+    if screen == 0 {
+        return true;
+    }
+    let mut font: LOGFONTW = unsafe { mem::zeroed() };
+    font.lfCharSet = charset;
+    let mut found = false;
+    //empty face name lists one font of each family
+    unsafe {
+        EnumFontFamiliesExW(
+            screen,
+            &font,
+            Some(on_font),
+            &mut found as *mut bool as LPARAM,
+            0,
+        );
+        ReleaseDC(0, screen);
+    }
+    found
+}
+
+unsafe extern "system" fn on_font(
+    _font: *const LOGFONTW,
+    _metric: *const TEXTMETRICW,
+    _font_type: u32,
+    found: LPARAM,
+) -> i32 {
+    *(found as *mut bool) = true;
+    //one is enough
+    0
+}
+
+//display language of the user as a language tag, e.g. "ko-KR"
+fn user_language() -> String {
+    let lang_id = unsafe { GetUserDefaultUILanguage() };
+    let mut name = [0u16; MAX_LOCALE_NAME];
+    //a language id is a locale id with default sorting
+    let len = unsafe { LCIDToLocaleName(lang_id.into(), name.as_mut_ptr(), name.len() as i32, 0) };
+    if len <= 0 {
+        return "en".to_owned();
+    }
+    String::from_utf16_lossy(&name[..len as usize - 1])
+}
+
+//catalog of `tag`, or of its primary language("ko" of "ko-KR") if there is none of it. english if
+//there is neither.
+fn catalog(tag: &str) -> &'static Bundle {
+    let primary = tag.split('-').next().unwrap_or(tag);
+    if primary.eq_ignore_ascii_case("ko") && !can_show_korean() {
+        return english();
+    }
+    for candidate in [tag, primary] {
+        if candidate.eq_ignore_ascii_case("en") {
+            return english();
+        }
+        if let Some(bundle) = load(candidate) {
+            return Box::leak(Box::new(bundle));
+        }
+    }
+    english()
+}
+
+fn english() -> &'static Bundle {
+    ENGLISH.get_or_init(|| load("en").expect("english catalog is built in"))
+}
+
+//built-in catalog of `tag` with messages of its files in paths::locale_dirs() on top, the first
+//directory winning. none if there is neither.
+fn load(tag: &str) -> Option<Bundle> {
+    let built_in = BUILT_IN
+        .iter()
+        .find(|(built_in, _)| built_in.eq_ignore_ascii_case(tag))
+        .map(|(_, source)| parse((*source).to_owned(), tag));
+    let files: Vec<FluentResource> = paths::locale_dirs()
+        .into_iter()
+        .rev()
+        .filter_map(|dir| {
+            let path = dir.join(format!("{tag}.ftl"));
+            let source = fs::read_to_string(&path).ok()?;
+            log::info!("translation is loaded from {}", path.display());
+            Some(parse(source, &path.display().to_string()))
+        })
+        .collect();
+    if built_in.is_none() && files.is_empty() {
+        return None;
+    }
+    let language: LanguageIdentifier = tag.parse().unwrap_or_default();
+    let mut bundle = FluentBundle::new_concurrent(vec![language]);
+    //unicode isolation marks show up as boxes in message boxes of older windows
+    bundle.set_use_isolating(false);
+    for resource in built_in.into_iter().chain(files) {
+        bundle.add_resource_overriding(resource);
+    }
+    Some(bundle)
+}
+
+//messages of `source` that parse. the others are left out, so they fall back to english.
+fn parse(source: String, name: &str) -> FluentResource {
+    FluentResource::try_new(source).unwrap_or_else(|(resource, errors)| {
+        log::warn!("errors in translation {name}: {errors:?}");
+        resource
+    })
+}
+
+//`action` as the $action selector of catalogs
+fn action_key(action: Action) -> &'static str {
+    match action {
+        Action::Hibernate => "hibernate",
+        Action::RebootToFirmware => "firmware",
+        _ => "shutdown",
+    }
+}
+
+//language of user-facing text. texts are messages of fluent catalogs(locales/*.ftl).
+#[derive(Clone, Copy)]
+pub struct Locale {
+    bundle: &'static Bundle,
+}
+
+impl Locale {
+    //follow display language of current user
+    pub fn current() -> Self {
+        Locale {
+            bundle: USER.get_or_init(|| catalog(&user_language())),
+        }
+    }
+
+    //for text that isn't shown to the user, e.g. in logs
+    pub fn english() -> Self {
+        Locale { bundle: english() }
+    }
+
+    //message `id` with `args`, or of the english catalog if this one doesn't have it
+    fn format<const N: usize>(self, id: &str, args: [(&str, FluentValue); N]) -> String {
+        let found = [self.bundle, english()].into_iter().find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value()?;
+            Some((bundle, pattern))
+        });
+        let Some((bundle, pattern)) = found else {
+            log::warn!("no translation has message {id}");
+            return id.to_owned();
+        };
+        let args = FluentArgs::from_iter(args);
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, Some(&args), &mut errors);
+        if !errors.is_empty() {
+            log::warn!("unable to format message {id}: {errors:?}");
+        }
+        //edit controls and message boxes only break lines on \r\n
+        text.replace("\r\n", "\n").replace('\n', "\r\n")
+    }
+
+    fn text(self, id: &str) -> String {
+        self.format(id, [])
+    }
+
+    //format duration as "1 minute 30 seconds" / "1분 30초". zero units are omitted.
+    pub fn format_duration(self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        let units = [
+            (secs / 3600, "duration-hours"),
+            ((secs / 60) % 60, "duration-minutes"),
+            (secs % 60, "duration-seconds"),
+        ];
+        let parts: Vec<String> = units
+            .into_iter()
+            .filter(|(value, _)| *value > 0)
+            .map(|(value, id)| self.format(id, [("count", value.into())]))
+            .collect();
+        if parts.is_empty() {
+            return self.format("duration-seconds", [("count", 0.into())]);
+        }
+        parts.join(" ")
+    }
+
+    pub fn shutdown_title(self) -> String {
+        self.text("shutdown-title")
+    }
+
+    //dialog offers snoozing if `snooze` is given
+    pub fn countdown_text(
+        self,
+        action: Action,
+        timeout: Duration,
+        snooze: Option<Duration>,
+        extend: Option<Duration>,
+    ) -> String {
+        self.format(
+            "countdown-text",
+            [
+                ("action", action_key(action).into()),
+                ("timeout", self.format_duration(timeout).into()),
+                ("hint", self.cancel_hint(snooze, extend).into()),
+            ],
+        )
+    }
+
+    //spoken when the countdown starts
+    pub fn announcement(self, action: Action, timeout: Duration) -> String {
+        self.format(
+            "announcement",
+            [
+                ("action", action_key(action).into()),
+                ("timeout", self.format_duration(timeout).into()),
+            ],
+        )
+    }
+
+    //shown in sessions of other users, who didn't start the countdown. `can_cancel` tells whether
+    //they can cancel it for everyone.
+    pub fn other_session_text(self, action: Action, timeout: Duration, can_cancel: bool) -> String {
+        let hint = if can_cancel {
+            "other-session-hint"
+        } else {
+            "other-session-warning"
+        };
+        format!(
+            "{}\r\n{}",
+            self.announcement(action, timeout),
+            self.text(hint)
+        )
+    }
+
+    //shutdown was aborted since `apps` didn't close when asked to
+    pub fn apps_open_text(self, apps: &str) -> String {
+        self.format("apps-open-text", [("apps", apps.into())])
+    }
+
+    pub fn confirm_text(self, action: Action) -> String {
+        self.format("confirm-text", [("action", action_key(action).into())])
+    }
+
+    //buttons of the enlarged countdown, named by what they do so screen readers say so.
+    //letters after & are their keyboard shortcuts.
+    pub fn cancel_button(self) -> String {
+        self.text("cancel-button")
+    }
+
+    pub fn snooze_button(self, snooze: Duration) -> String {
+        self.format(
+            "snooze-button",
+            [("duration", self.format_duration(snooze).into())],
+        )
+    }
+
+    pub fn extend_button(self, extend: Duration) -> String {
+        self.format(
+            "extend-button",
+            [("duration", self.format_duration(extend).into())],
+        )
+    }
+
+    pub fn act_now_button(self, action: Action) -> String {
+        self.format("act-now-button", [("action", action_key(action).into())])
+    }
+
+    pub fn time_left(self, left: Duration) -> String {
+        self.format("time-left", [("left", self.format_duration(left).into())])
+    }
+
+    //extension is only offered along with snoozing
+    pub fn cancel_hint(self, snooze: Option<Duration>, extend: Option<Duration>) -> String {
+        //buttons of the enlarged countdown say what they do
+        if countdown_window::is_used() {
+            return self.text("cancel-hint-window");
+        }
+        let Some(snooze) = snooze else {
+            return self.text("cancel-hint-ok");
+        };
+        let snooze = self.format_duration(snooze);
+        match extend {
+            Some(extend) => self.format(
+                "cancel-hint-extend",
+                [
+                    ("snooze", snooze.into()),
+                    ("extend", self.format_duration(extend).into()),
+                ],
+            ),
+            None => self.format("cancel-hint-snooze", [("snooze", snooze.into())]),
+        }
+    }
+
+    //shown on the screen of apps preventing shutdown while the heartbeat is saved
+    pub fn shutdown_block_reason(self) -> String {
+        self.text("shutdown-block-reason")
+    }
+
+    pub fn restart_notice_title(self) -> String {
+        self.text("restart-notice-title")
+    }
+
+    pub fn restart_notice_text(self) -> String {
+        self.text("restart-notice-text")
+    }
+
+    pub fn pending_reboot_title(self) -> String {
+        self.text("pending-reboot-title")
+    }
+
+    pub fn pending_reboot_text(self) -> String {
+        self.text("pending-reboot-text")
+    }
+
+    pub fn scheduled_task_text(self, task: &str) -> String {
+        self.format("scheduled-task-text", [("task", task.into())])
+    }
+
+    pub fn peer_notice_text(self, from: &str) -> String {
+        self.format("peer-notice-text", [("from", from.into())])
+    }
+
+    pub fn dry_run_text(self, action: Action) -> String {
+        self.format("dry-run-text", [("action", action_key(action).into())])
+    }
+
+    pub fn tray_tip(self) -> String {
+        self.text("tray-tip")
+    }
+
+    pub fn show_status(self) -> String {
+        self.text("show-status")
+    }
+
+    pub fn show_history(self) -> String {
+        self.text("show-history")
+    }
+
+    pub fn history_title(self) -> String {
+        self.text("history-title")
+    }
+
+    pub fn history_filter(self) -> String {
+        self.text("history-filter")
+    }
+
+    //column headers: time, classification, cause, uptime before restart, action
+    pub fn history_columns(self) -> [String; 6] {
+        [
+            "started",
+            "boot",
+            "cause",
+            "uptime",
+            "action",
+            "reliability",
+        ]
+        .map(|column| self.text(&format!("history-column-{column}")))
+    }
+
+    pub fn reliability_event(self, kind: reliability::Kind, count: usize) -> String {
+        use reliability::Kind;
+        let kind = match kind {
+            Kind::UpdateInstalled => "update-installed",
+            Kind::UpdateFailed => "update-failed",
+            Kind::SystemCrash => "system-crash",
+            Kind::AppCrash => "app-crash",
+            Kind::AppHang => "app-hang",
+        };
+        self.format(
+            "reliability-event",
+            [("kind", kind.into()), ("count", count.into())],
+        )
+    }
+
+    //whether restart-fix powered the computer off after the boot
+    pub fn history_action(self, action: &str, acted: bool) -> String {
+        self.format(
+            "history-action",
+            [
+                ("action", action.into()),
+                ("acted", if acted { "yes" } else { "no" }.into()),
+            ],
+        )
+    }
+
+    pub fn status_title(self) -> String {
+        self.text("status-title")
+    }
+
+    //status of an instance showing the prompts of the system service
+    pub fn agent_status_text(self, connected: bool) -> String {
+        self.text(if connected {
+            "agent-status-connected"
+        } else {
+            "agent-status-disconnected"
+        })
+    }
+
+    pub fn status_text(self, status: &crate::status_window::Status) -> String {
+        let monitoring = match (status.paused, status.countdown_pending) {
+            (true, _) => "paused",
+            (false, true) => "countdown",
+            (false, false) => "armed",
+        };
+        let heartbeat = status
+            .last_heartbeat
+            .map_or_else(|| self.text("status-no-heartbeat"), format_time);
+        let detection = match &status.detection {
+            Some(detection) => self.format(
+                "status-detection",
+                [
+                    (
+                        "classification",
+                        serde_json::to_value(detection.classification)
+                            .ok()
+                            .and_then(|value| value.as_str().map(str::to_owned))
+                            .unwrap_or_default()
+                            .into(),
+                    ),
+                    (
+                        "cause",
+                        detection
+                            .cause
+                            .map_or(String::new(), |cause| {
+                                self.format(
+                                    "status-detection-cause",
+                                    [("cause", cause.as_str().into())],
+                                )
+                            })
+                            .into(),
+                    ),
+                    (
+                        "time",
+                        format_time(detection.at.with_timezone(&Local)).into(),
+                    ),
+                ],
+            ),
+            None => self.text("status-not-detected"),
+        };
+        let (active_hours, hours) = match status.active_hours {
+            Some(Some(hours)) if hours.contains_now() => ("now", hours.to_string()),
+            Some(Some(hours)) => ("not-now", hours.to_string()),
+            Some(None) => ("unset", String::new()),
+            None => ("ignored", String::new()),
+        };
+        let fast_startup = match status.fast_startup {
+            Some(true) => "on",
+            Some(false) => "off",
+            None => "unknown",
+        };
+        self.format(
+            "status-text",
+            [
+                (
+                    "monitoring",
+                    self.format("status-monitoring", [("state", monitoring.into())])
+                        .into(),
+                ),
+                ("uptime", self.format_duration(status.uptime).into()),
+                ("heartbeat", heartbeat.into()),
+                ("detection", detection.into()),
+                (
+                    "active_hours",
+                    self.format(
+                        "status-active-hours",
+                        [("state", active_hours.into()), ("hours", hours.into())],
+                    )
+                    .into(),
+                ),
+                (
+                    "fast_startup",
+                    self.format("status-fast-startup", [("state", fast_startup.into())])
+                        .into(),
+                ),
+            ],
+        )
+    }
+
+    pub fn wizard_title(self) -> String {
+        self.text("wizard-title")
+    }
+
+    pub fn wizard_intro(self) -> String {
+        self.text("wizard-intro")
+    }
+
+    pub fn wizard_threshold(self) -> String {
+        self.text("wizard-threshold")
+    }
+
+    pub fn wizard_seconds(self, secs: u64) -> String {
+        self.format_duration(Duration::from_secs(secs))
+    }
+
+    pub fn wizard_action(self) -> String {
+        self.text("wizard-action")
+    }
+
+    //in the order of setup_wizard::ACTIONS
+    pub fn wizard_actions(self) -> [String; 3] {
+        ["shutdown", "hibernate", "notify"]
+            .map(|action| self.text(&format!("wizard-action-{action}")))
+    }
+
+    pub fn wizard_autostart(self) -> String {
+        self.text("wizard-autostart")
+    }
+
+    pub fn config_tampered_title(self) -> String {
+        self.text("config-tampered-title")
+    }
+
+    pub fn config_tampered_text(self) -> String {
+        self.text("config-tampered-text")
+    }
+
+    pub fn wizard_telemetry(self) -> String {
+        self.text("wizard-telemetry")
+    }
+
+    pub fn wizard_start(self) -> String {
+        self.text("wizard-start")
+    }
+
+    pub fn wizard_exit(self) -> String {
+        self.text("wizard-exit")
+    }
+
+    pub fn autostart_error_title(self) -> String {
+        self.text("autostart-error-title")
+    }
+
+    pub fn autostart_error_text(self, error: &anyhow::Error) -> String {
+        self.format(
+            "autostart-error-text",
+            [("error", error.to_string().into())],
+        )
+    }
+
+    pub fn watchdog_error_title(self) -> String {
+        self.text("watchdog-error-title")
+    }
+
+    pub fn watchdog_error_text(self, error: &anyhow::Error) -> String {
+        self.format("watchdog-error-text", [("error", error.to_string().into())])
+    }
+
+    pub fn fatal_error_title(self) -> String {
+        self.text("fatal-error-title")
+    }
+
+    pub fn fatal_error_text(self, error: &crate::error::Error) -> String {
+        self.format("fatal-error-text", [("error", error.to_string().into())])
+    }
+
+    pub fn panic_text(self, panic: &str) -> String {
+        self.format("panic-text", [("panic", panic.into())])
+    }
+
+    pub fn update_title(self) -> String {
+        self.text("update-title")
+    }
+
+    //`installed` if the update was downloaded and replaces the program on next start
+    pub fn update_text(self, version: &str, url: &str, installed: bool) -> String {
+        self.format(
+            "update-text",
+            [
+                ("version", version.into()),
+                ("url", url.into()),
+                ("installed", if installed { "yes" } else { "no" }.into()),
+            ],
+        )
+    }
+
+    pub fn weekly_summary_title(self) -> String {
+        self.text("weekly-summary-title")
+    }
+
+    pub fn weekly_summary_text(self, summary: &Summary) -> String {
+        let causes = summary
+            .causes
+            .iter()
+            .map(|(count, cause)| format!("{cause} {count}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.format(
+            "weekly-summary-text",
+            [
+                ("restarts", summary.restarts.into()),
+                ("previous", summary.previous.into()),
+                ("causes", causes.into()),
+                ("shutdowns", summary.shutdowns.into()),
+                (
+                    "notified",
+                    summary.restarts.saturating_sub(summary.shutdowns).into(),
+                ),
+            ],
+        )
+    }
+
+    pub fn recovery_title(self) -> String {
+        self.text("recovery-title")
+    }
+
+    pub fn recovery_text(self, failed: u32) -> String {
+        self.format("recovery-text", [("failed", failed.into())])
+    }
+}
@@ -0,0 +1,208 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    mem,
+    os::windows::io::{FromRawHandle, RawHandle},
+    ptr,
+    str::FromStr,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use windows_sys::Win32::{
+    Foundation::{ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE},
+    Security::{
+        Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1},
+        SECURITY_ATTRIBUTES,
+    },
+    Storage::FileSystem::PIPE_ACCESS_DUPLEX,
+    System::Memory::LocalFree,
+    System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    },
+};
+
+pub const PIPE_NAME: &str = r"\\.\pipe\restart-fix";
+
+const BUFFER_SIZE: u32 = 4096;
+const CONNECT_RETRIES: u32 = 20;
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+//commands accepted by the control pipe. one command per connection, terminated by newline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Status,
+    //status as json object for monitoring scripts
+    StatusJson,
+    Tail,
+    Pause,
+    Resume,
+    CancelShutdown,
+    TriggerTest,
+}
+
+impl Command {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Command::Status => "status",
+            Command::StatusJson => "status-json",
+            Command::Tail => "tail",
+            Command::Pause => "pause",
+            Command::Resume => "resume",
+            Command::CancelShutdown => "cancel-shutdown",
+            Command::TriggerTest => "trigger-test",
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "status" => Ok(Command::Status),
+            "status-json" => Ok(Command::StatusJson),
+            "tail" => Ok(Command::Tail),
+            "pause" => Ok(Command::Pause),
+            "resume" => Ok(Command::Resume),
+            "cancel-shutdown" => Ok(Command::CancelShutdown),
+            "trigger-test" => Ok(Command::TriggerTest),
+            _ => Err(anyhow!("Unknown command: {s}")),
+        }
+    }
+}
+
+//spawn server thread that answers commands sent to the control pipe.
+//handler receives parsed command and returns response text sent back to the client.
+//`sddl` gives access to the pipe, e.g. to users other than the one serving it.
+pub fn serve<F>(sddl: Option<&'static str>, mut handler: F) -> JoinHandle<()>
+where
+    F: FnMut(Command) -> String + Send + 'static,
+{
+    thread::spawn(move || {
+        let security = match sddl.map(PipeSecurity::new).transpose() {
+            Ok(security) => security,
+            Err(e) => {
+                log::warn!("unable to secure control pipe. commands are not answered: {e:#}");
+                return;
+            }
+        };
+        loop {
+            let pipe = match accept_on(PIPE_NAME, security.as_ref().map(|s| &s.attributes)) {
+                Ok(pipe) => pipe,
+                //pipe can't be created(e.g. name is taken by another instance). give up serving.
+                Err(_) => return,
+            };
+            //failure of single client must not stop the server
+            let _ = handle_client(&pipe, &mut handler);
+        }
+    })
+}
+
+//send command to running instance and return its response
+pub fn send(command: Command) -> anyhow::Result<String> {
+    let mut pipe = connect()?;
+    writeln!(pipe, "{}", command.as_str())?;
+    let mut response = String::new();
+    pipe.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+fn handle_client<F>(mut pipe: &File, handler: &mut F) -> anyhow::Result<()>
+where
+    F: FnMut(Command) -> String,
+{
+    let mut line = String::new();
+    BufReader::new(pipe).read_line(&mut line)?;
+    let response = match line.trim().parse::<Command>() {
+        Ok(command) => handler(command),
+        Err(e) => format!("error: {e}"),
+    };
+    pipe.write_all(response.as_bytes())?;
+    //wait until client has read the response before the pipe is closed
+    pipe.sync_all()?;
+    Ok(())
+}
+
+//without `security`, the pipe gets the default access(creator and administrators only)
+pub fn accept_on(pipe_name: &str, security: Option<&SECURITY_ATTRIBUTES>) -> anyhow::Result<File> {
+    let name: Vec<u16> = pipe_name.encode_utf16().chain(Some(0)).collect();
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            security.map_or(ptr::null(), |security| security as *const _),
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error().into());
+    }
+    //file takes ownership of the handle and closes it on drop
+    let pipe = unsafe { File::from_raw_handle(handle as RawHandle) };
+    if unsafe { ConnectNamedPipe(handle, ptr::null_mut()) } == 0 {
+        let err = io::Error::last_os_error();
+        //client connected between CreateNamedPipe and ConnectNamedPipe
+        if err.raw_os_error() != Some(ERROR_PIPE_CONNECTED as i32) {
+            return Err(err.into());
+        }
+    }
+    Ok(pipe)
+}
+
+//security attributes of a pipe, given as sddl. the descriptor is freed on drop.
+pub struct PipeSecurity {
+    pub attributes: SECURITY_ATTRIBUTES,
+}
+
+impl PipeSecurity {
+    pub fn new(sddl: &str) -> anyhow::Result<Self> {
+        let sddl: Vec<u16> = sddl.encode_utf16().chain(Some(0)).collect();
+        let mut descriptor = ptr::null_mut();
+        let converted = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                ptr::null_mut(),
+            )
+        };
+        if converted == 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(PipeSecurity {
+            attributes: SECURITY_ATTRIBUTES {
+                nLength: mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+                lpSecurityDescriptor: descriptor,
+                bInheritHandle: 0,
+            },
+        })
+    }
+}
+
+impl Drop for PipeSecurity {
+    fn drop(&mut self) {
+        unsafe { LocalFree(self.attributes.lpSecurityDescriptor as isize) };
+    }
+}
+
+fn connect() -> anyhow::Result<File> {
+    for _ in 0..CONNECT_RETRIES {
+        match OpenOptions::new().read(true).write(true).open(PIPE_NAME) {
+            Ok(pipe) => return Ok(pipe),
+            //server is busy answering another client
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) => {
+                thread::sleep(CONNECT_RETRY_DELAY)
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => bail!("restart-fix is not running"),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    bail!("restart-fix control pipe is busy")
+}
@@ -0,0 +1,128 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use chrono::{Local, SecondsFormat, Utc};
+use log::{LevelFilter, Log, Metadata, Record};
+
+//number of recent lines kept in memory for `restart-fix status --tail`
+const RECENT_CAPACITY: usize = 500;
+//end of the log read on startup to continue numbering. longer than any single line.
+const TAIL_BYTES: u64 = 16 * 1024;
+
+//recent lines make quick triage possible without locating the log file on disk
+static RECENT: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// Whether logging was started with `--console`, which keeps debug records on.
+static CONSOLE: AtomicBool = AtomicBool::new(false);
+
+//appends log records to a file. program runs without console, so the file is the only place logs can go.
+//each line is "#seq utc local level message". clock and timezone change under this program, so
+//records are ordered by the sequence number, which keeps counting across runs.
+struct FileLogger {
+    output: Mutex<Output>,
+    //lines are also printed to the console opened with `--console`
+    console: bool,
+}
+
+struct Output {
+    file: File,
+    //sequence number of the last line written
+    seq: u64,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let now = Utc::now();
+        let line = {
+            let mut output = self.output.lock().unwrap();
+            output.seq += 1;
+            let line = format!(
+                "#{} {} {} {:<5} {}",
+                output.seq,
+                now.to_rfc3339_opts(SecondsFormat::Secs, true),
+                now.with_timezone(&Local)
+                    .to_rfc3339_opts(SecondsFormat::Secs, false),
+                record.level(),
+                record.args()
+            );
+            //failure to log must never take down the program
+            let _ = writeln!(output.file, "{line}");
+            if self.console {
+                let _ = writeln!(io::stdout(), "{line}");
+            }
+            line
+        };
+
+        let mut recent = RECENT.lock().unwrap();
+        if recent.len() == RECENT_CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(line);
+    }
+
+    fn flush(&self) {
+        let _ = self.output.lock().unwrap().file.flush();
+    }
+}
+
+//`console` also prints records to stdout, with at least debug level for interactive debugging
+pub fn init(path: impl AsRef<Path>, level: LevelFilter, console: bool) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    //lines written before numbering was introduced count as 0
+    let seq = last_seq(&mut file).unwrap_or(0);
+    log::set_boxed_logger(Box::new(FileLogger {
+        output: Mutex::new(Output { file, seq }),
+        console,
+    }))?;
+    CONSOLE.store(console, Ordering::Relaxed);
+    set_level(level);
+    Ok(())
+}
+
+//applies `log_level` of a reloaded config without restarting
+pub fn set_level(level: LevelFilter) {
+    if CONSOLE.load(Ordering::Relaxed) {
+        log::set_max_level(level.max(LevelFilter::Debug));
+    } else {
+        log::set_max_level(level);
+    }
+}
+
+//last lines written to the log, oldest first
+pub fn recent_lines() -> Vec<String> {
+    RECENT.lock().unwrap().iter().cloned().collect()
+}
+
+//sequence number of the last numbered line in the log
+fn last_seq(file: &mut File) -> io::Result<u64> {
+    let len = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    //line cut off by power loss or at the start of the tail is skipped
+    let seq = String::from_utf8_lossy(&tail)
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix('#')?.split(' ').next()?.parse().ok())
+        .unwrap_or(0);
+    Ok(seq)
+}
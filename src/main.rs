@@ -1,165 +1,1605 @@
 #![windows_subsystem = "windows"]
 
+mod action;
+mod active_hours;
+mod activity;
+mod agent;
+mod alert;
+mod analyze;
 mod app_close_handler;
+mod app_state;
+mod audit;
+mod autostart;
+mod boot;
+mod clean_shutdown;
+mod cli;
+mod clock_change;
+mod close_apps;
+mod config;
+mod config_watch;
+mod console;
+mod countdown_window;
+mod crash;
+mod decide;
+mod diag;
+mod dialog_placement;
+mod doctor;
+mod elevation;
+mod email;
+mod error;
+mod event_log;
+mod explain;
+mod fast_startup;
+mod file_lock;
+mod fleet;
+mod fullscreen;
+mod health_ping;
+mod heartbeat_key;
+mod heartbeat_mirror;
+mod heartbeat_store;
+mod history;
+mod history_window;
+mod hook;
+mod hotkey;
+mod http;
+mod i18n;
+mod ipc;
+mod logger;
+mod metrics;
+mod minidump;
+mod mqtt;
+mod notifier;
+mod overlay_window;
+mod paths;
+mod pause_marker;
+mod peers;
+mod pending_reboot;
+mod policy;
+mod pool_timer;
+mod power;
+mod prevention;
+mod process_list;
+mod provision;
+mod registry;
+mod reliability;
+mod remote_assist;
+mod report;
+mod responder;
+mod scheduled_task;
+mod self_test;
+mod service;
+mod session;
+mod session_arbiter;
+mod session_lock;
+mod setup_wizard;
+mod shutdown_block;
+mod shutdown_log;
+mod shutdown_privilege;
+mod shutdown_reason;
+mod shutdown_watch;
+mod single_instance;
+mod snooze;
+mod startup_guard;
+mod status_window;
+mod taskbar;
+mod telemetry;
+mod template;
+mod toast;
+mod tray;
+mod uninstall;
+mod updater;
+mod vdi;
+mod wake_on_lan;
+mod watchdog_task;
+mod webhook;
+mod websocket;
+mod weekly_summary;
+mod windows_update;
+mod wmi;
+mod write_filter;
 
 use std::{
-    fs::{File, OpenOptions},
-    io::{Read, Write},
-    os::windows::prelude::FileExt,
-    sync::{
-        atomic::{self, AtomicBool},
-        Arc, Mutex,
-    },
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom},
+    os::windows::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    process,
+    sync::{Arc, Mutex, PoisonError, TryLockError},
     thread::{self},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use anyhow::anyhow;
-use chrono::{self, DateTime, TimeZone, Utc};
-use flume::{select::SelectError, Selector};
-use native_dialog::MessageDialog;
-
-use app_close_handler::AppCloseHandler;
-
-// Define a threshold duration used to determine if the system should initiate a shutdown sequence.
-// This constant sets a time limit of 100 seconds. If the duration since the last recorded update 
-// (as read from a file) is less than this threshold, it indicates an unexpected restart or a similar
-// event. In such a case, the system will consider initiating a shutdown sequence to handle this situation.
-static THRESHOLD: Duration = Duration::from_secs(100);
-
-// Specify the timeout duration for the shutdown process. This constant defines a period of 20 seconds
-// during which the application will wait after notifying the user of an impending shutdown. If the
-// user does not cancel the shutdown within this timeframe, the system will proceed to shut down.
-// This timeout provides a brief window for any last-minute user intervention or to abort the shutdown
-// process if it was triggered unintentionally.
-static SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(20);
-
-fn main() -> anyhow::Result<()> {
-    let file = Arc::new(Mutex::new(
-        OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open("./last_updated")?,
-    ));
-
-    let (shutdown_tx, shutdown_rx) = flume::bounded::<()>(1);
-    let (cleanup_tx, cleanup_rx) = flume::bounded::<()>(1);
-
-    //determines weather to shutdown or not.
-    //it is not safe to directly call shutdown() inside background worker. all resource(including file) must be released before calling shutdown().
-    let shutdown_signal = Arc::new(AtomicBool::new(false));
-
-    //spawn background worker thread that periodically writes current time to file.
-    let background_worker = {
-        let file = Arc::clone(&file);
-        let shutdown_signal = Arc::clone(&shutdown_signal);
-        thread::spawn(move || loop {
-            let result = Selector::new()
-                .recv(&shutdown_rx, |result| {
-                    if result.is_ok() {
-                        shutdown_signal.store(true, atomic::Ordering::SeqCst)
-                    }
-                })
-                .recv(&cleanup_rx, |_| {})
-                .wait_timeout(Duration::from_secs(1));
-            match result {
-                Ok(_) => {
-                    //shutdown or cleanup signal
-                    return;
-                }
-                Err(SelectError::Timeout) => {
-                    //timeout expired. update time.
-                    write_last_updated(&file.lock().unwrap()).unwrap();
+use chrono::{DateTime, Local, Utc};
+use clap::Parser;
+use serde_json::json;
+use windows_sys::Win32::{
+    Foundation::HWND,
+    Storage::FileSystem::FILE_FLAG_WRITE_THROUGH,
+    System::Threading::{GetCurrentProcess, SetProcessWorkingSetSize},
+};
+
+use action::{ActionSettings, Frontend};
+use app_close_handler::{AppCloseHandler, EndSession};
+use app_state::AppState;
+use boot::{BootClassification, SimulatedClock, SystemClock};
+use clean_shutdown::CleanShutdownMarker;
+use cli::Cli;
+use config::Config;
+use config_watch::LiveConfig;
+use error::Error;
+use heartbeat_store::{HeartbeatStore, RegistryHeartbeat};
+use history::History;
+use i18n::Locale;
+use pause_marker::PauseMarker;
+use policy::{Action, Cause, HeartbeatBackend, Rule, WarningStyle};
+use restart_fix_core::{
+    parse_record, CancellationToken, Clock, Command as EngineCommand, Decision,
+    DoubleBufferedHeartbeat, Event, Heartbeat, HeartbeatSource, HeartbeatStorage, HeartbeatWorker,
+    MemoryHeartbeat, Origin, RestartDetector, RestartPolicy, StopReason, Stopper, WorkerOptions,
+};
+use shutdown_block::ShutdownBlock;
+use shutdown_log::ShutdownLog;
+use shutdown_reason::ShutdownRecord;
+use single_instance::SingleInstance;
+use snooze::Snooze;
+use startup_guard::StartupGuard;
+
+// Number of consecutive heartbeat write failures tolerated before the background worker gives up.
+// Transient failures(e.g. antivirus or backup tool briefly locking the file) are retried on the
+// next tick, but a heartbeat that can't be written for this long makes detection meaningless.
+static MAX_HEARTBEAT_FAILURES: u32 = 10;
+
+// How a background worker that gave up or crashed is started again, with the heartbeat file opened
+// anew. Detection keeps running through a storage hiccup instead of taking the whole program down.
+static WORKER_RESTARTS: RestartPolicy = RestartPolicy {
+    max_restarts: 5,
+    initial_backoff: Duration::from_secs(10),
+    max_backoff: Duration::from_secs(300),
+};
+
+// Allowed difference between the boot time reported by WMI and the time of the last heartbeat.
+// Heartbeat may be stored in whole seconds and the reported boot time is only as exact as the clock
+// was while booting.
+static BOOT_TIME_TOLERANCE: Duration = Duration::from_secs(5);
+
+//heartbeat is written to these state files in turn
+const HEARTBEAT_FILES: [&str; 2] = ["last_updated.a", "last_updated.b"];
+//state file written before restarting into firmware setup, so the restart isn't acted on
+const FIRMWARE_REBOOT_MARKER: &str = "firmware_reboot";
+
+fn main() {
+    //arguments are given only when launched from command line
+    if std::env::args_os().len() > 1 {
+        cli::attach_parent_console();
+    }
+    let cli = Cli::parse();
+    if cli.service {
+        if let Err(e) = service::dispatch(cli.is_dry_run()) {
+            error::print(
+                cli.error_format,
+                "service",
+                error::EXIT_FAILURE,
+                &format!("{e:#}"),
+            );
+            process::exit(error::EXIT_FAILURE);
+        }
+        return;
+    }
+    if let Some(command) = cli.command.clone() {
+        let result = cli::run(command);
+        if let Some(path) = &cli.elevated_output {
+            elevation::write_output(path, &result);
+        }
+        match result {
+            Ok(response) => println!("{}", response.trim_end()),
+            Err(e) => {
+                error::print(
+                    cli.error_format,
+                    "command",
+                    error::EXIT_FAILURE,
+                    &format!("{e:#}"),
+                );
+                process::exit(error::EXIT_FAILURE);
+            }
+        }
+        return;
+    }
+    if cli.console {
+        console::open();
+    }
+    minidump::install(paths::data_dir());
+    error::install_panic_hook();
+
+    //startup that never finishes counts as failed. bad configuration must not keep restarts from
+    //being noticed, so monitor falls back to recovery mode after a few of them.
+    let guard = StartupGuard::new(
+        paths::data_dir().join("startup"),
+        Config::file_hash(paths::config_path()),
+    );
+    let failed = guard.begin();
+    let recovery = failed >= startup_guard::MAX_FAILED_STARTUPS;
+    match run(&cli, &guard, recovery.then_some(failed)) {
+        Ok(()) if !recovery => guard.succeeded(),
+        Ok(()) => {}
+        Err(e @ Error::AlreadyRunning) => {
+            if !recovery {
+                guard.succeeded();
+            }
+            error::print(cli.error_format, e.kind(), e.exit_code(), &e.to_string());
+            process::exit(e.exit_code());
+        }
+        Err(e) => {
+            error::report(&e);
+            error::print(cli.error_format, e.kind(), e.exit_code(), &e.to_string());
+            process::exit(e.exit_code());
+        }
+    }
+}
+
+//`recovery` is the number of failed startups if monitor runs in recovery mode
+fn run(cli: &Cli, guard: &StartupGuard, recovery: Option<u32>) -> Result<(), Error> {
+    //earlier versions kept files in working directory, which depends on how restart-fix was launched
+    let config_migration = paths::migrate_legacy_file(paths::CONFIG_FILE, &paths::data_dir());
+    //nothing drastic happens before the user knows what restart-fix does
+    if recovery.is_none() && is_first_run() {
+        match setup_wizard::run(Locale::current()) {
+            Some(choices) => {
+                if let Err(e) = choices.save(&paths::config_path()) {
+                    notifier::notify(&Locale::current().fatal_error_title(), &format!("{e:#}"));
                 }
             }
+            None => return Ok(()),
+        }
+    }
+    let mut config = match recovery {
+        Some(_) => Config::recovery(),
+        None => Config::load(paths::config_path()).map_err(Error::Config)?,
+    };
+    if cli.fast_forward {
+        config.startup_grace = Duration::ZERO;
+        config.logon_grace = Duration::ZERO;
+    }
+    let state_dir =
+        paths::create_state_dir(&config.state_dir).map_err(|source| Error::StateFile {
+            path: config.state_dir.clone(),
+            source,
+        })?;
+    let unusable_state_dir = (state_dir != config.state_dir)
+        .then(|| std::mem::replace(&mut config.state_dir, state_dir));
+    let config = Arc::new(config);
+
+    logger::init(
+        config.state_file("restart-fix.log"),
+        config.log_level,
+        cli.console,
+    )
+    .map_err(Error::Logger)?;
+    if let Some(dir) = unusable_state_dir {
+        log::warn!(
+            "state directory {} can't be created. state is kept in {} instead",
+            dir.display(),
+            config.state_dir.display()
+        );
+    }
+    if config.tampered {
+        config::warn_tampered();
+    }
+    log::info!(
+        "restart-fix started{}",
+        if cli.is_dry_run() {
+            " in dry-run mode"
+        } else {
+            ""
+        }
+    );
+    if cli.is_simulated() {
+        log::info!(
+            "simulation: restart {}, time {}, grace {}",
+            if cli.simulate_restart {
+                "simulated"
+            } else {
+                "actual"
+            },
+            cli.fake_now
+                .map_or_else(|| "actual".to_owned(), |now| now.to_rfc3339()),
+            if cli.fast_forward {
+                "skipped"
+            } else {
+                "as configured"
+            }
+        );
+    }
+    if let Some(failed) = recovery {
+        log::warn!(
+            "{failed} startups in a row have failed. running in recovery mode(notification only, configuration ignored) until configuration is changed"
+        );
+        show_recovery_warning(failed);
+    }
+
+    //update downloaded by the last instance takes over before anything is acquired
+    match updater::apply_pending() {
+        Ok(true) => {
+            log::info!("update installed. updated executable started");
+            return Ok(());
+        }
+        Ok(false) => {}
+        Err(e) => log::warn!("unable to install downloaded update: {e:#}"),
+    }
+
+    //watchdog task relaunches restart-fix periodically. only one monitor may run at a time.
+    let _instance = match SingleInstance::acquire() {
+        Ok(Some(instance)) => instance,
+        Ok(None) => {
+            log::info!("another instance is already running");
+            return Err(Error::AlreadyRunning);
+        }
+        Err(e) => return Err(Error::SingleInstance(e)),
+    };
+
+    //system service owns the heartbeat and the shutdown while it runs. this instance only shows
+    //its prompts in the session of the user.
+    if service::is_running() {
+        log::info!("restart-fix service is running. prompts of the service are shown");
+        agent::run();
+        return Ok(());
+    }
+
+    log_migration(paths::CONFIG_FILE, config_migration);
+    for name in ["last_updated", "paused"] {
+        log_migration(name, paths::migrate_legacy_file(name, &config.state_dir));
+    }
+    warn_write_filter(&config);
+    if config.vdi_mode {
+        match vdi::reset_if_cloned(&config) {
+            Ok(true) => log::info!(
+                "state was written on another machine(e.g. golden image). state is reset"
+            ),
+            Ok(false) => {}
+            Err(e) => log::warn!("unable to check machine identity: {e:#}"),
+        }
+    }
+
+    let state = Arc::new(AppState::new(config.concurrent_prompts));
+    audit::init(config.state_file("audit.jsonl"), &state);
+    countdown_window::set_text_scale(config.accessibility.text_scale);
+    countdown_window::set_custom_buttons(config.has_custom_buttons());
+    overlay_window::set_enabled(config.warning_style == WarningStyle::Overlay);
+
+    //monitoring stays paused until resumed or until the next boot
+    let pause_marker = Arc::new(PauseMarker::new(config.state_file("paused")));
+    state.set_paused(pause_marker.is_set());
+    if state.is_paused() {
+        log::info!("monitoring is paused until next boot");
+    }
+
+    let hostname = winsafe::GetComputerName().unwrap_or_default();
+    let heartbeat = Arc::new(Mutex::new(heartbeat_writer(&config, &hostname)?));
+
+    //read before background worker overwrites it. elapsed time is measured at startup even if
+    //evaluation is delayed by startup grace.
+    let last_heartbeat = {
+        let mut heartbeat = heartbeat.lock().unwrap();
+        let last_heartbeat = last_heartbeat(&config, &mut heartbeat);
+        if let Some(origin) = heartbeat.last_origin() {
+            log::info!(
+                "last heartbeat was written by restart-fix {}(pid {}) on {}",
+                origin.version,
+                origin.pid,
+                origin.hostname
+            );
+            if origin.hostname != hostname {
+                log::warn!("last heartbeat was written on another computer(copied state directory or cloned image?)");
+            }
+        }
+        last_heartbeat
+    };
+    let started = if cli.is_simulated() {
+        SimulatedClock {
+            now: cli.fake_now,
+            restart: cli.simulate_restart,
+        }
+        .now()
+    } else {
+        SystemClock.now()
+    };
+    let simulated = cli.is_simulated();
+    //simulated restart is a genuine one
+    let clean_shutdown = CleanShutdownMarker::new(config.state_file("clean_shutdown"));
+    let shut_down_cleanly = clean_shutdown.take() || cli.simulate_restart;
+
+    let worker = start_heartbeat(
+        &heartbeat,
+        &config,
+        &hostname,
+        MonitorHeartbeat {
+            state: Arc::clone(&state),
+            starting_since: Some(Instant::now()),
+            guard: recovery.is_none().then(|| guard.clone()),
+        },
+    );
+
+    autostart::spawn_watchdog();
+    updater::spawn(config.update_check, config.state_file("update_notified"));
+    if config.warn_pending_reboot {
+        pending_reboot::spawn(config.state_file("pending_reboot_warned"));
+    }
+    if config.weekly_summary {
+        weekly_summary::spawn(
+            History::new(config.state_file("history")),
+            ShutdownLog::new(config.state_file("shutdowns")),
+            config.email.clone(),
+            config.state_file("summary_sent"),
+        );
+    }
+    if config.telemetry {
+        telemetry::spawn(
+            config.telemetry_url.clone(),
+            config.state_file("telemetry_sent"),
+        );
+    }
+    watchdog_task::verify();
+
+    //configuration edited while running applies to what happens next, so changing a setting doesn't
+    //take a restart of restart-fix, which would look like a restart itself
+    let live_config = Arc::new(LiveConfig::new(Arc::clone(&config)));
+    if recovery.is_none() {
+        config_watch::watch(Arc::clone(&live_config), paths::config_path());
+    }
+    if let Some(endpoint) = config.fleet_endpoint.clone() {
+        //policy pushed before this start applies to the restart detected now
+        let policy_path = config.state_file("fleet_policy.json");
+        if let Some(policy) = fleet::PushedPolicy::load(&policy_path) {
+            live_config.push(policy);
+        }
+        fleet::spawn_agent(endpoint, &state, Arc::clone(&live_config), policy_path);
+    }
+
+    //heartbeat is recorded the moment the computer starts going down, not up to an interval before
+    let _shutdown_watch = {
+        let heartbeat = Arc::clone(&heartbeat);
+        let state = Arc::clone(&state);
+        shutdown_watch::watch(Arc::clone(&live_config), move || {
+            record_heartbeat(
+                &mut heartbeat.lock().unwrap_or_else(PoisonError::into_inner),
+                &state,
+            )
         })
+        .map_err(|e| log::warn!("unable to watch the event log for shutdowns: {e:#}"))
+        .ok()
     };
 
-    //if pc is restarted within specified threshold, show shutdown dialog
-    if let Ok(duration) = duration_since_shutdown(&file.lock().unwrap()) {
-        if duration < THRESHOLD {
-            show_shutdown_dialog(SHUTDOWN_TIMEOUT, shutdown_tx);
+    //serve control commands sent by client mode
+    {
+        let state = Arc::clone(&state);
+        let pause_marker = Arc::clone(&pause_marker);
+        let config = Arc::clone(&live_config);
+        ipc::serve(None, move |command| {
+            handle_command(
+                command,
+                &config.get(),
+                &state,
+                &pause_marker,
+                &action::Desktop,
+            )
+        });
+    }
+    if let Some(port) = config.websocket_port {
+        let handler_state = Arc::clone(&state);
+        let pause_marker = Arc::clone(&pause_marker);
+        let handler_config = Arc::clone(&live_config);
+        let served = websocket::serve(port, Arc::clone(&state), move |command| {
+            handle_command(
+                command,
+                &handler_config.get(),
+                &handler_state,
+                &pause_marker,
+                &action::Desktop,
+            )
+        });
+        match served {
+            Ok(()) => log::info!("websocket endpoint listening on localhost:{port}"),
+            Err(e) => log::warn!("unable to listen on localhost:{port}: {e}"),
         }
     }
 
-    let (close_handler_tx, close_handler_rx) = oneshot::channel();
+    if let Some(port) = config.metrics_port {
+        match metrics::serve(port, Arc::clone(&state)) {
+            Ok(()) => log::info!("metrics served on localhost:{port}/metrics"),
+            Err(e) => log::warn!("unable to listen on localhost:{port}: {e}"),
+        }
+    }
+    if let Some(dir) = config.heartbeat_mirror.clone() {
+        heartbeat_mirror::spawn(dir, &hostname, config.timestamp_format, Arc::clone(&state));
+    }
+    if let Some(url) = config.ping_url.clone() {
+        health_ping::spawn(url, config.ping_interval, Arc::clone(&state));
+    }
+    if let Some(broker) = config.mqtt_broker.clone() {
+        let topic = config.mqtt_topic.clone().unwrap_or_else(|| {
+            format!(
+                "restart-fix/{}",
+                winsafe::GetComputerName().unwrap_or_default()
+            )
+        });
+        let settings = mqtt::Settings {
+            broker,
+            topic,
+            discovery: config.mqtt_discovery,
+            history: History::new(config.state_file("history")),
+        };
+        let handler_state = Arc::clone(&state);
+        let pause_marker = Arc::clone(&pause_marker);
+        let handler_config = Arc::clone(&live_config);
+        mqtt::spawn(settings, Arc::clone(&state), move |command| {
+            handle_command(
+                command,
+                &handler_config.get(),
+                &handler_state,
+                &pause_marker,
+                &action::Desktop,
+            )
+        });
+    }
+
+    if config.peers.listen_port.is_some() {
+        let live_config = Arc::clone(&live_config);
+        let state = Arc::clone(&state);
+        let stopper = worker.stopper();
+        let dry_run = cli.is_dry_run();
+        let listened = peers::listen(&config.peers, move |request, from, cause| match request {
+            _ if state.is_paused() => {
+                log::info!("monitoring is paused. request of peer is ignored")
+            }
+            peers::Request::Shutdown => {
+                let config = live_config.get();
+                let settings = action_settings(&config, cause, config.shutdown_timeout, dry_run);
+                action::take(
+                    Action::Shutdown,
+                    &settings,
+                    &state,
+                    stopper.clone(),
+                    &action::Desktop,
+                );
+            }
+            peers::Request::Notify => show_peer_notice(from.to_owned()),
+            peers::Request::Nothing => {}
+        });
+        match listened {
+            Ok(port) => log::info!("accepting requests of peers on port {port}"),
+            Err(e) => log::warn!("unable to accept requests of peers: {e:#}"),
+        }
+    }
+
+    if let Some(hotkey) = config.cancel_hotkey.clone() {
+        let state = Arc::clone(&state);
+        let listened = hotkey::listen(hotkey.clone(), move || {
+            if state.execute(EngineCommand::CancelCountdown) {
+                log::info!("shutdown cancelled with hotkey");
+            }
+        });
+        match listened {
+            Ok(()) => log::info!("{hotkey} cancels the shutdown countdown"),
+            Err(e) => log::warn!("unable to register hotkey {hotkey}: {e}"),
+        }
+    }
+
+    //decision is made in background so that close handler is installed during startup grace
+    {
+        let startup = Startup {
+            last_heartbeat,
+            started,
+            simulated,
+            shut_down_cleanly,
+            dry_run: cli.is_dry_run(),
+        };
+        let live_config = Arc::clone(&live_config);
+        let state = Arc::clone(&state);
+        let stopper = worker.stopper();
+        thread::spawn(move || detect(startup, &live_config, &state, stopper, &action::Desktop));
+    }
+
+    //cancelled once the program is safe to exit, which releases a pending end of session
+    let exit = CancellationToken::new();
+
+    if cli.console {
+        let stopper = worker.stopper();
+        console::on_ctrl_c(move || {
+            log::info!("Ctrl+C pressed, stopping");
+            stopper.stop(StopReason::Close);
+        });
+    }
 
     //install wm_close and wm_endsession handler
     //I can't use ctrlc handler because I'm working on gui mode instead of console mode
-    AppCloseHandler::new().on_app_close(move || {
-        //send cancel signal to background worker thread
-        let _ = cleanup_tx.send(());
-        //wait for program exit
-        let _ = close_handler_rx.recv();
-    });
+    let stopper = worker.stopper();
+    let heartbeat_1 = Arc::clone(&heartbeat);
+    let state_1 = Arc::clone(&state);
+    let state_2 = Arc::clone(&state);
+    let live_config_1 = Arc::clone(&live_config);
+    AppCloseHandler::new()
+        .track_session_lock()
+        .with_tray(move || status_window::show(&state_2, &live_config_1.get()))
+        .with_history(history_window::show)
+        .on_query_end_session(move |hwnd, end| {
+            log::info!("session is ending({end:?})");
+            final_heartbeat(hwnd, &heartbeat_1, &state_1);
+        })
+        //a logoff isn't followed by a restart, and the query can still be vetoed
+        .on_session_ended(move |end| {
+            if end == EndSession::Shutdown {
+                if let Err(e) = clean_shutdown.set() {
+                    log::warn!("unable to write clean shutdown marker: {e:#}");
+                }
+            }
+        })
+        .on_app_close({
+            let exit = exit.clone();
+            move || {
+                //send cancel signal to background worker thread
+                stopper.stop(StopReason::Close);
+                //wait for program exit
+                exit.wait();
+            }
+        });
 
     //wait for thread to finish
-    background_worker.join().unwrap();
+    let stop_reason = match worker.wait() {
+        Ok(reason) => reason,
+        Err(e) => {
+            //release handler before reporting so that pending end session is not blocked by dialog
+            exit.cancel();
+            return Err(Error::Worker(e));
+        }
+    };
 
     //at this point, file should be flushed and programe is safe to exit.
 
-    //check if shutdown signal is set
-    if shutdown_signal.load(atomic::Ordering::SeqCst) {
-        //shut down computer
-        system_shutdown::shutdown().unwrap();
+    if stop_reason != StopReason::Close {
+        if let Err(e) = power_off(stop_reason, cli.is_dry_run(), &live_config.get()) {
+            exit.cancel();
+            return Err(e);
+        }
     }
 
     //release handler
-    let _ = close_handler_tx.send(());
+    exit.cancel();
     Ok(())
 }
 
-fn show_shutdown_dialog(timeout: Duration, shutdown: flume::Sender<()>) {
-    thread::spawn(move || {
-        let (cancel_tx, cancel_rx) = oneshot::channel();
-        start_shutdown_timeout_thread(timeout, cancel_rx, shutdown);
-        MessageDialog::new()
-            .set_title("컴퓨터 종료 알림")
-            .set_text(&format!(
-                "자동 재시작을 감지했습니다. {}초 후 컴퓨터가 종료됩니다.\r\n취소하려면 확인을 누르세요.",
-                timeout.as_secs()
-            ))
-            .show_alert()
-            .expect("unable to display dialog box");
-        cancel_tx
-            .send(())
-            .expect("unable to cancel shutdown timeout thread.");
+//heartbeat written by the monitor
+struct MonitorHeartbeat {
+    state: Arc<AppState>,
+    //None once startup is over
+    starting_since: Option<Instant>,
+    //startup succeeds once stable. None in recovery mode.
+    guard: Option<StartupGuard>,
+}
+
+impl HeartbeatSource for MonitorHeartbeat {
+    fn next(&mut self) -> Option<Heartbeat> {
+        //the worker already wakes up regularly, so startup is ended here instead of on a thread of
+        //its own
+        if self
+            .starting_since
+            .is_some_and(|since| since.elapsed() >= startup_guard::STABLE_AFTER)
+        {
+            self.starting_since = None;
+            if let Some(guard) = self.guard.take() {
+                guard.succeeded();
+            }
+            trim_working_set();
+        }
+        //stale heartbeat keeps restarts during pause from being detected
+        (!self.state.is_paused()).then(|| SystemClock.now())
+    }
+
+    fn written(&mut self, heartbeat: Heartbeat) {
+        self.state.set_last_updated(heartbeat.time);
+        metrics::heartbeat_written();
+    }
+
+    fn failed(&mut self, attempts: u32, error: &anyhow::Error) {
+        metrics::heartbeat_failed();
+        log::warn!("unable to write heartbeat({attempts} attempts): {error:#}");
+    }
+}
+
+//pages touched only during startup(config parsing, event log and wmi queries, ...) are returned
+//to the system. idle monitoring, which runs all day, only needs a few of them back.
+fn trim_working_set() {
+    if unsafe { SetProcessWorkingSetSize(GetCurrentProcess(), usize::MAX, usize::MAX) } == 0 {
+        log::debug!(
+            "unable to trim working set: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+//neither configured nor ever started. log file is written on every start, and without config file
+//the state directory is the default one. machines configured by administrators are no first run.
+fn is_first_run() -> bool {
+    !paths::config_path().exists()
+        && !paths::machine_config_path().exists()
+        && !paths::data_dir().join("restart-fix.log").exists()
+}
+
+//periodically write current time to the heartbeat on the thread pool.
+//it is not safe to directly call shutdown() while writing. all resource(including file) must be released before calling shutdown().
+fn start_heartbeat(
+    heartbeat: &Arc<Mutex<HeartbeatStore>>,
+    config: &Arc<Config>,
+    hostname: &str,
+    source: MonitorHeartbeat,
+) -> HeartbeatWorker {
+    let interval = config
+        .heartbeat_interval
+        .max(config::MIN_HEARTBEAT_INTERVAL);
+    let (worker, mut ticks) = HeartbeatWorker::ticked(
+        Arc::clone(heartbeat),
+        WorkerOptions {
+            interval,
+            max_failures: MAX_HEARTBEAT_FAILURES,
+        },
+        source,
+        WORKER_RESTARTS,
+        reopen_heartbeat(Arc::clone(config), hostname.to_owned()),
+    );
+    //heartbeats are compared to the second, so they aren't coalesced
+    pool_timer::start(interval, Duration::ZERO, move || match ticks.tick() {
+        Some(next) => pool_timer::Next::After(next),
+        None => pool_timer::Next::Stop,
     });
+    worker
 }
 
-fn start_shutdown_timeout_thread(
-    timeout: Duration,
-    cancel: oneshot::Receiver<()>,
-    shutdown: flume::Sender<()>,
+//opens heartbeat storage again for a restarted background worker. the old store is closed first,
+//so the heartbeat file isn't locked against itself.
+fn reopen_heartbeat(
+    config: Arc<Config>,
+    hostname: String,
+) -> impl FnMut(&mut HeartbeatStore) -> anyhow::Result<()> + Send + 'static {
+    move |store| {
+        *store = HeartbeatStore::Memory(MemoryHeartbeat::default());
+        *store = heartbeat_writer(&config, &hostname)?;
+        log::info!("heartbeat storage is reopened");
+        Ok(())
+    }
+}
+
+fn heartbeat_writer(config: &Config, hostname: &str) -> Result<HeartbeatStore, Error> {
+    Ok(match config.heartbeat_store {
+        HeartbeatBackend::File => HeartbeatStore::File(file_heartbeat(config, hostname)?),
+        HeartbeatBackend::Registry => {
+            HeartbeatStore::Registry(RegistryHeartbeat::new(config.timestamp_format))
+        }
+        HeartbeatBackend::Memory => {
+            log::warn!("heartbeat is kept in memory only. no restart can be detected");
+            HeartbeatStore::Memory(MemoryHeartbeat::default())
+        }
+    })
+}
+
+//heartbeat is written to both files in turn, so one of them survives a torn write
+fn file_heartbeat(config: &Config, hostname: &str) -> Result<DoubleBufferedHeartbeat<File>, Error> {
+    let [a, b] = open_heartbeat(config)?;
+    let heartbeat =
+        DoubleBufferedHeartbeat::new(a, b, config.timestamp_format).with_origin(Origin {
+            pid: process::id(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            hostname: hostname.to_owned(),
+        });
+    if !config.heartbeat_hmac {
+        return Ok(heartbeat);
+    }
+    match heartbeat_key::load_or_create(&config.state_file("heartbeat.key")) {
+        Ok(key) => Ok(heartbeat.with_key(key)),
+        Err(e) => {
+            log::warn!("unable to load heartbeat key. heartbeat is not authenticated: {e:#}");
+            Ok(heartbeat)
+        }
+    }
+}
+
+//heartbeat files in state directory. if they can't be opened there(e.g. locked by antivirus), they
+//are kept in a fallback directory rather than not monitoring at all.
+fn open_heartbeat(config: &Config) -> Result<[File; 2], Error> {
+    let open = |dir: &Path| -> Result<[File; 2], Error> {
+        let open = |name: &str| {
+            let path = dir.join(name);
+            let mut options = OpenOptions::new();
+            options.read(true).write(true).create(true).truncate(false);
+            if config.durable_heartbeat {
+                //every write reaches the disk before it returns, not only the cache
+                options.custom_flags(FILE_FLAG_WRITE_THROUGH);
+            }
+            let file = options.open(&path).map_err(|source| Error::StateFile {
+                path: path.clone(),
+                source,
+            })?;
+            //nothing else may interleave writes with the heartbeat
+            file_lock::lock_exclusive(&file)
+                .map_err(|source| Error::StateFileLocked { path, source })?;
+            Ok(file)
+        };
+        Ok([open(HEARTBEAT_FILES[0])?, open(HEARTBEAT_FILES[1])?])
+    };
+    let error = match open(&config.state_dir) {
+        Ok(files) => return Ok(files),
+        Err(e) => e,
+    };
+    for dir in paths::fallback_state_dirs() {
+        if dir == config.state_dir || fs::create_dir_all(&dir).is_err() {
+            continue;
+        }
+        if let Ok(files) = open(&dir) {
+            log::warn!("{error}. heartbeat is kept in {} instead", dir.display());
+            return Ok(files);
+        }
+    }
+    Err(error)
+}
+
+//heartbeat of the last run. earlier versions kept it in a single file, which is read once.
+fn last_heartbeat(config: &Config, heartbeat: &mut HeartbeatStore) -> anyhow::Result<Heartbeat> {
+    let legacy_path = config.state_file("last_updated");
+    let legacy = match fs::read_to_string(&legacy_path) {
+        Ok(legacy) => legacy,
+        Err(_) => {
+            return heartbeat
+                .read()
+                .or_else(|e| recover_heartbeat(config, heartbeat, e))
+        }
+    };
+    if let Err(e) = fs::remove_file(&legacy_path) {
+        log::warn!("unable to remove {}: {e}", legacy_path.display());
+    }
+    heartbeat.read().or_else(|_| parse_record(&legacy))
+}
+
+//heartbeat files hold no valid record(torn write, garbage or tampering). bad files are kept for
+//inspection and the heartbeat starts over. their modification time is the last sign of life, which
+//is then checked against the boot time as usual.
+fn recover_heartbeat(
+    config: &Config,
+    heartbeat: &mut HeartbeatStore,
+    error: anyhow::Error,
+) -> anyhow::Result<Heartbeat> {
+    let mut modified = None;
+    for (mut file, name) in heartbeat.files().iter().zip(HEARTBEAT_FILES) {
+        let metadata = file.metadata()?;
+        //created just now, on first run
+        if metadata.len() == 0 {
+            continue;
+        }
+        if modified.is_none() {
+            log::warn!("heartbeat is corrupted: {error:#}");
+        }
+        modified = modified.max(metadata.modified().ok());
+        let quarantine = config.state_file(&format!("{name}.corrupt"));
+        let mut content = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut content)?;
+        match fs::write(&quarantine, content) {
+            Ok(()) => log::info!("corrupted {name} was copied to {}", quarantine.display()),
+            Err(e) => log::warn!("unable to quarantine corrupted {name}: {e}"),
+        }
+        file.set_len(0)?;
+    }
+    match modified {
+        Some(modified) => {
+            log::info!(
+                "heartbeat is reinitialized. modification time of heartbeat files is used instead"
+            );
+            Ok(Heartbeat {
+                time: modified.into(),
+                uptime: None,
+                boot_id: None,
+            })
+        }
+        None => Err(error),
+    }
+}
+
+//records heartbeat once more right before the session ends, so it isn't up to an interval stale.
+//fast logoff must not cut a heartbeat write short, so a write in progress is waited for.
+fn final_heartbeat(hwnd: HWND, heartbeat: &Mutex<HeartbeatStore>, state: &Arc<AppState>) {
+    let (mut writer, _block) = match heartbeat.try_lock() {
+        Ok(writer) => (writer, None),
+        Err(TryLockError::WouldBlock) => {
+            let block = ShutdownBlock::create(hwnd, &Locale::current().shutdown_block_reason())
+                .map_err(|e| log::warn!("unable to block shutdown during heartbeat write: {e}"))
+                .ok();
+            (
+                heartbeat.lock().unwrap_or_else(PoisonError::into_inner),
+                block,
+            )
+        }
+        Err(TryLockError::Poisoned(e)) => (e.into_inner(), None),
+    };
+    record_heartbeat(&mut writer, state);
+}
+
+//write heartbeat now and flush it to disk
+fn record_heartbeat(writer: &mut HeartbeatStore, state: &Arc<AppState>) {
+    let mut source = MonitorHeartbeat {
+        state: Arc::clone(state),
+        starting_since: None,
+        guard: None,
+    };
+    if let Some(heartbeat) = source.next() {
+        match writer.write(heartbeat) {
+            Ok(()) => source.written(heartbeat),
+            Err(e) => source.failed(1, &e),
+        }
+    }
+    for file in writer.files() {
+        if let Err(e) = file.sync_data() {
+            log::warn!("unable to flush heartbeat: {e}");
+        }
+    }
+}
+
+//readings taken at startup, before the background worker overwrites the last heartbeat
+struct Startup {
+    last_heartbeat: anyhow::Result<Heartbeat>,
+    started: Heartbeat,
+    simulated: bool,
+    shut_down_cleanly: bool,
+    dry_run: bool,
+}
+
+//if pc is restarted within specified threshold, takes action once startup grace has passed. used
+//by the monitor and the service alike, which only differ in `frontend`.
+fn detect(
+    startup: Startup,
+    live_config: &LiveConfig,
+    state: &Arc<AppState>,
+    stopper: Stopper,
+    frontend: &dyn Frontend,
 ) {
-    thread::spawn(move || {
-        if let Err(oneshot::RecvTimeoutError::Timeout) = cancel.recv_timeout(timeout) {
-            //send shutdown signal
-            let _ = shutdown.send(());
+    let startup_grace = live_config.get().startup_grace;
+    if !startup_grace.is_zero() {
+        //login, sync and update finalization settle before event log is consulted
+        log::info!("restart detection begins in {}s", startup_grace.as_secs());
+        thread::sleep(startup_grace);
+    }
+    //threshold, countdown and action of the day and hour of the detection
+    let now = Local::now();
+    let config = live_config.get();
+    if let Some(schedule) = config.schedule_at(now) {
+        log::info!("schedule for {schedule} applies");
+    }
+    let config = config.scheduled(now);
+    //read once for both the bypass list and the cause
+    let previous_shutdown =
+        shutdown_reason::previous_shutdown(boot::boot_time()).unwrap_or_else(|e| {
+            log::warn!("unable to read previous shutdown reason: {e:#}");
+            None
+        });
+    let (classification, elapsed) = classify_boot(
+        &config,
+        startup.last_heartbeat,
+        startup.started,
+        startup.simulated,
+        startup.shut_down_cleanly,
+        previous_shutdown.as_ref(),
+        state,
+    );
+    let (cause, initiator) = restart_cause(classification, previous_shutdown.as_ref());
+    status_window::record(status_window::Detection {
+        classification,
+        cause,
+        at: Utc::now(),
+    });
+    if let Some(endpoint) = &config.fleet_endpoint {
+        fleet::send_online(
+            endpoint.clone(),
+            Config::file_hash(paths::config_path()),
+            classification,
+        );
+    }
+    if let Some(cause) = cause {
+        if let Some(tier) = config.tier_for(elapsed) {
+            log::info!(
+                "restart came back after {}s. tier within {}s applies",
+                elapsed.as_secs(),
+                tier.within.as_secs()
+            );
+        }
+        let config = config.tiered(elapsed);
+        let restart = Restart {
+            cause,
+            initiator,
+            elapsed,
+        };
+        respond(restart, &config, state, stopper, startup.dry_run, frontend);
+    }
+}
+
+//restart detected within threshold
+struct Restart {
+    cause: Cause,
+    initiator: Initiator,
+    //time since the last heartbeat
+    elapsed: Duration,
+}
+
+//takes action the policy defines for a restart detected within threshold. blocks while action is deferred.
+fn respond(
+    restart: Restart,
+    config: &Config,
+    state: &Arc<AppState>,
+    stopper: Stopper,
+    dry_run: bool,
+    frontend: &dyn Frontend,
+) {
+    let Restart {
+        cause,
+        initiator,
+        elapsed,
+    } = restart;
+    let mut rule = initiator.rule(config, cause);
+    //decide.rhai of advanced users decides what the policy can't express
+    let context = decide::Context {
+        cause,
+        elapsed,
+        action: rule.action,
+    };
+    if let Some(action) = decide::decide(&paths::decide_script_path(), &context) {
+        rule = config.allowed(Rule { action, ..rule });
+    }
+    log::info!(
+        "automatic restart detected(cause: {}, action: {:?})",
+        cause.as_str(),
+        rule.action
+    );
+    metrics::restart_detected();
+    audit::detection(cause, rule.action);
+    email::send_detected(&config.email, cause, rule.action);
+    webhook::send_detected(&config.webhooks, cause, rule.action);
+    //machines depending on this one went down with it
+    wake_on_lan::wake(&config.wake_on_lan);
+    peers::tell(&config.peers, cause);
+    if let Some(endpoint) = &config.fleet_endpoint {
+        fleet::send_detection(endpoint, cause, rule.action);
+    }
+    responder::spawn(
+        &config.responses,
+        responder::Context {
+            cause,
+            action: rule.action,
+            dry_run,
+        },
+    );
+    if let Some(count) = restart_loop(config, cause) {
+        //shutting down a machine stuck in a loop only powers it off before the next restart
+        log::error!(
+            "RESTART LOOP: {count} restarts detected within {}s. shutdown dialog is skipped{}",
+            config.restart_loop.window.as_secs(),
+            if config.restart_loop.hibernate {
+                ", hibernating"
+            } else {
+                ""
+            }
+        );
+        if config.restart_loop.hibernate {
+            action::hibernate(dry_run);
         }
+        return;
+    }
+    let mut settings = action_settings(
+        config,
+        cause,
+        rule.timeout.unwrap_or(config.shutdown_timeout),
+        dry_run,
+    );
+    settings.task = initiator.task;
+    let delay = logon_grace_left(config);
+    if !delay.is_zero() {
+        //user who just logged on must not watch the computer announce it will power off
+        log::info!(
+            "user logged on recently. action is deferred for {}s",
+            delay.as_secs()
+        );
+        thread::sleep(delay);
+    }
+    //technician working on the machine after the restart must not be cut off
+    remote_assist::wait_until_inactive(&config.remote_assist_processes);
+    //countdown popping up over a game or a live presentation is hostile
+    fullscreen::wait_until_inactive(config.fullscreen_deadline);
+    //backup, download or render in progress would be thrown away
+    activity::wait_until_idle(&config.activity);
+    if let Some(remaining) = settings.snooze.remaining() {
+        //user snoozed the shutdown before this restart of monitor or computer
+        log::info!(
+            "shutdown is snoozed. action is deferred for {}s",
+            remaining.as_secs()
+        );
+        thread::sleep(remaining);
+    }
+    if state.is_paused() {
+        log::info!("monitoring was paused. deferred action is dropped");
+        return;
+    }
+    action::take(rule.action, &settings, state, stopper, frontend);
+}
+
+fn action_settings(
+    config: &Config,
+    cause: Cause,
+    timeout: Duration,
+    dry_run: bool,
+) -> ActionSettings {
+    ActionSettings {
+        cause,
+        prompt_mode: config.prompt_mode,
+        timeout,
+        silent_delay: config.silent_delay,
+        extend_by: config.extend_by,
+        dialog_buttons: config.dialog_buttons(),
+        screen_reader_timeout: config.accessibility.screen_reader_timeout,
+        locked_session: config.locked_session,
+        alert_sound: config.alert_sound.clone(),
+        announce: config.announce,
+        dialog_title: config.dialog_title.clone(),
+        dialog_text: config.dialog_text.clone(),
+        pre_shutdown_cmd: config.pre_shutdown_cmd.clone(),
+        pre_shutdown_timeout: config.pre_shutdown_timeout,
+        close_apps: config.close_apps.clone(),
+        close_apps_timeout: config.close_apps_timeout,
+        abort_if_apps_open: config.abort_if_apps_open,
+        battery: config.battery,
+        respect_active_hours: config.respect_active_hours,
+        quiet_hours: config.quiet_hours,
+        active_input_window: config.active_input_window,
+        cancel_on_input: config.cancel_on_input,
+        pause_on_input: config.pause_on_input,
+        remote_session: config.remote_session,
+        other_sessions: config.other_sessions,
+        snooze: Snooze::new(config.state_file("snoozed")),
+        shutdown_limit: config.shutdown_limit,
+        shutdowns: ShutdownLog::new(config.state_file("shutdowns")),
+        email: config.email.clone(),
+        webhooks: config.webhooks.clone(),
+        task: None,
+        dry_run,
+    }
+}
+
+//another machine detected a restart and asks this one's user to check it
+fn show_peer_notice(from: String) {
+    thread::spawn(move || {
+        let locale = Locale::current();
+        notifier::notify(
+            &locale.restart_notice_title(),
+            &locale.peer_notice_text(&from),
+        );
+    });
+}
+
+fn show_recovery_warning(failed: u32) {
+    thread::spawn(move || {
+        let locale = Locale::current();
+        notifier::notify(&locale.recovery_title(), &locale.recovery_text(failed));
     });
 }
 
-fn duration_since_shutdown(file: &File) -> anyhow::Result<Duration> {
+//shut down or restart into firmware setup once the heartbeat is released
+fn power_off(reason: StopReason, dry_run: bool, config: &Config) -> Result<(), Error> {
+    let firmware = reason == StopReason::RebootToFirmware;
+    if dry_run {
+        let locale = Locale::current();
+        let text = if firmware {
+            log::info!("dry run: would have restarted into firmware setup now");
+            locale.dry_run_text(Action::RebootToFirmware)
+        } else {
+            log::info!("dry run: would have shut down now");
+            locale.dry_run_text(Action::Shutdown)
+        };
+        notifier::notify(&locale.shutdown_title(), &text);
+        return Ok(());
+    }
+    shutdown_privilege::enable().map_err(Error::ShutdownPrivilege)?;
+    let closing = power::Closing {
+        app_close: config.app_close,
+        timeout: config.app_close_timeout,
+    };
+    if firmware {
+        log::info!("restarting into firmware setup");
+        if let Err(e) = fs::write(config.state_file(FIRMWARE_REBOOT_MARKER), "") {
+            log::warn!("unable to record restart into firmware setup: {e}");
+        }
+        power::reboot_to_firmware(closing).map_err(Error::Shutdown)
+    } else {
+        log::info!("shutting down");
+        power::shutdown(config.shutdown_type, closing).map_err(Error::Shutdown)
+    }
+}
+
+fn log_migration(name: &str, result: anyhow::Result<Option<PathBuf>>) {
+    match result {
+        Ok(Some(legacy)) => log::info!("migrated {} to data directory", legacy.display()),
+        Ok(None) => {}
+        Err(e) => log::warn!("unable to migrate {name} to data directory: {e:#}"),
+    }
+}
+
+#[tracing::instrument(
+    level = "debug",
+    skip(config, last_heartbeat, previous_shutdown, state),
+    ret
+)]
+fn classify_boot(
+    config: &Config,
+    last_heartbeat: anyhow::Result<Heartbeat>,
+    now: Heartbeat,
+    simulated: bool,
+    shut_down_cleanly: bool,
+    previous_shutdown: Option<&ShutdownRecord>,
+    state: &AppState,
+) -> (BootClassification, Duration) {
+    let last_heartbeat = match last_heartbeat {
+        Ok(last_heartbeat) => last_heartbeat,
+        Err(e) => {
+            log::warn!("unable to read last heartbeat: {e}");
+            audit::unreadable_heartbeat(&e);
+            return (BootClassification::Unknown, Duration::ZERO);
+        }
+    };
+    //marker is consumed by the first start after it was written, whatever is detected then
+    let rebooted_to_firmware = fs::remove_file(config.state_file(FIRMWARE_REBOOT_MARKER)).is_ok();
+    let detector = RestartDetector::new(config.detection_threshold());
+    let mut decision = detector.evaluate(last_heartbeat, now);
+    match wmi::last_boot_up_time() {
+        //actual boot time would contradict the simulated readings
+        Ok(_) if simulated => {}
+        Ok(boot_time) => {
+            log::info!("computer was booted at {}", boot_time.to_rfc3339());
+            decision = confirm_with_boot_time(&detector, decision, last_heartbeat, now, boot_time);
+        }
+        Err(e) => log::warn!("unable to query boot time: {e:#}"),
+    }
+    if !simulated {
+        decision = account_for_clock_corrections(&detector, decision, last_heartbeat);
+    }
+    log::info!(
+        "{:.3}s elapsed since last heartbeat(threshold: {:.3}s)",
+        decision.elapsed().as_secs_f64(),
+        detector.threshold().as_secs_f64()
+    );
+    state.publish(Event::Evaluated(decision));
+    let classification = match decision {
+        Decision::Normal { .. } => BootClassification::Normal,
+        Decision::SameBoot { .. } => {
+            log::info!("last heartbeat is from the current boot. restart-fix itself was restarted");
+            BootClassification::MonitorRestarted
+        }
+        Decision::ClockChanged { .. } => {
+            log::warn!("clock was changed since last heartbeat. restart detection is skipped");
+            BootClassification::ClockChanged
+        }
+        Decision::Restarted { .. } if rebooted_to_firmware => {
+            log::info!(
+                "computer was restarted into firmware setup by restart-fix. detection is skipped"
+            );
+            BootClassification::Bypassed
+        }
+        Decision::Restarted { .. } if state.is_paused() => BootClassification::Paused,
+        Decision::Restarted { .. } => match dirty_shutdown() {
+            Some(classification) => classification,
+            //windows never ended the session. only restart-fix was killed and relaunched.
+            None if !shut_down_cleanly => {
+                log::info!("no clean shutdown was recorded since last heartbeat. restart-fix itself was restarted");
+                BootClassification::MonitorRestarted
+            }
+            None if is_bypassed(config, previous_shutdown) => BootClassification::Bypassed,
+            None => BootClassification::UnexpectedRestart,
+        },
+    };
+    audit::evaluation(
+        last_heartbeat,
+        now,
+        detector.threshold(),
+        decision,
+        classification,
+    );
+    (classification, decision.elapsed())
+}
+
+//boot time recorded by windows confirms restarts, and makes them detectable even if restart-fix
+//is started long after boot(e.g. on a late logon)
+fn confirm_with_boot_time(
+    detector: &RestartDetector,
+    decision: Decision,
+    last_heartbeat: Heartbeat,
+    now: Heartbeat,
+    boot_time: DateTime<Utc>,
+) -> Decision {
+    let tolerance = chrono::Duration::from_std(BOOT_TIME_TOLERANCE).unwrap();
+    match decision {
+        Decision::Restarted { elapsed } if last_heartbeat.time > boot_time + tolerance => {
+            //heartbeat written after boot. only restart-fix was restarted.
+            Decision::SameBoot { elapsed }
+        }
+        Decision::Normal { .. }
+            if last_heartbeat.time <= boot_time
+                && (now.time - boot_time).to_std().unwrap_or_default() >= detector.threshold() =>
+        {
+            //time until restart-fix was started doesn't count. compare with boot instead.
+            let boot = Heartbeat {
+                time: boot_time,
+                uptime: Some(Duration::ZERO),
+                boot_id: now.boot_id,
+            };
+            detector.evaluate(last_heartbeat, boot)
+        }
+        decision => decision,
+    }
+}
+
+//a restart measured across a correction of the wall clock(e.g. a time service sync on boot that
+//fixed a drifting hardware clock) may only look quick
+fn account_for_clock_corrections(
+    detector: &RestartDetector,
+    decision: Decision,
+    last_heartbeat: Heartbeat,
+) -> Decision {
+    if !matches!(decision, Decision::Restarted { .. }) {
+        return decision;
+    }
+    let corrections = match clock_change::corrections_since(last_heartbeat.time) {
+        Ok(corrections) => corrections,
+        Err(e) => {
+            log::warn!("unable to query clock corrections: {e:#}");
+            return decision;
+        }
+    };
+    if corrections.is_empty() {
+        return decision;
+    }
+    let shift = corrections
+        .iter()
+        .fold(chrono::Duration::zero(), |sum, correction| {
+            sum + correction.shift()
+        });
+    for correction in &corrections {
+        log::info!(
+            "clock was changed from {} to {} since last heartbeat",
+            correction.old_time.to_rfc3339(),
+            correction.new_time.to_rfc3339()
+        );
+    }
+    let corrected = detector.corrected(decision, shift);
+    if corrected != decision {
+        log::warn!(
+            "restart straddles a clock correction of {:.3}s. it is not counted as a restart",
+            shift.num_milliseconds() as f64 / 1000.0
+        );
+    }
+    corrected
+}
+
+//crash or power loss is told apart from planned restarts, so it can be handled differently
+fn dirty_shutdown() -> Option<BootClassification> {
+    match crash::previous_dirty_shutdown(boot::boot_time()) {
+        Ok(Some(dirty)) => {
+            let classification = match dirty.bugcheck_code {
+                Some(code) => {
+                    log::info!("previous session ended with bugcheck {code:#x}");
+                    BootClassification::Crash
+                }
+                None => {
+                    log::info!("previous session ended without clean shutdown(power loss or hang)");
+                    BootClassification::PowerLoss
+                }
+            };
+            Some(classification)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            log::warn!("unable to check for dirty shutdown: {e:#}");
+            None
+        }
+    }
+}
+
+//None if boot is not a restart within threshold that policy applies to
+//cause of the restart, and the scheduled task and the process that initiated it, if known
+fn restart_cause(
+    classification: BootClassification,
+    record: Option<&ShutdownRecord>,
+) -> (Option<Cause>, Initiator) {
+    match classification {
+        BootClassification::UnexpectedRestart => {
+            let task = record.and_then(|record| {
+                scheduled_task::initiating_task(record).unwrap_or_else(|e| {
+                    log::warn!("unable to read task scheduler history: {e:#}");
+                    None
+                })
+            });
+            let process = record
+                .map(|record| record.process_name().to_owned())
+                .filter(|process| !process.is_empty());
+            if let Some(process) = &process {
+                log::info!("restart was initiated by process {process}");
+            }
+            let cause = match &task {
+                Some(task) => {
+                    log::info!("restart was initiated by scheduled task {task}");
+                    Cause::of_task(task)
+                }
+                None => Cause::of_shutdown(record),
+            };
+            (Some(cause), Initiator { task, process })
+        }
+        BootClassification::Crash => (Some(Cause::Crash), Initiator::default()),
+        BootClassification::PowerLoss => (Some(Cause::PowerLoss), Initiator::default()),
+        _ => (None, Initiator::default()),
+    }
+}
+
+//what initiated a restart, as far as the event log tells
+#[derive(Debug, Default)]
+struct Initiator {
+    task: Option<String>,
+    //file name, e.g. "MusNotification.exe"
+    process: Option<String>,
+}
+
+impl Initiator {
+    //rule of the process that initiated the restart, if the policy gives one, or of `cause`
+    fn rule(&self, config: &Config, cause: Cause) -> Rule {
+        config.allowed(config.policy.rule_for(cause, self.process.as_deref()))
+    }
+}
+
+//records detection and returns number of detections within window if computer is in a restart loop
+fn restart_loop(config: &Config, cause: Cause) -> Option<usize> {
+    let history = History::new(config.state_file("history"));
     let now = Utc::now();
-    let last_updated = read_last_updated(file)?;
-    let duration = (now - last_updated).abs();
-    Ok(duration.to_std()?)
+    match history.record(cause) {
+        Ok(detection) => log::info!("detection #{} recorded in history", detection.seq),
+        Err(e) => log::warn!("unable to record detection in history: {e:#}"),
+    }
+    let restart_loop = config.restart_loop;
+    if restart_loop.count == 0 {
+        return None;
+    }
+    let since = chrono::Duration::from_std(restart_loop.window)
+        .ok()
+        .and_then(|window| now.checked_sub_signed(window))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    match history.since(since) {
+        Ok(detections) if detections.len() >= restart_loop.count as usize => Some(detections.len()),
+        Ok(_) => None,
+        Err(e) => {
+            log::warn!("unable to read history: {e:#}");
+            None
+        }
+    }
 }
 
-fn read_last_updated(mut file: &File) -> anyhow::Result<DateTime<Utc>> {
-    let mut time = String::new();
-    file.read_to_string(&mut time)?;
-    let time = time.parse::<i64>()?;
-    Utc.timestamp_opt(time, 0)
-        .single()
-        .ok_or_else(|| anyhow!("Invalid timestamp: {time}"))
+//restarts whose reason code is in the bypass list are sanctioned and never trigger shutdown
+fn is_bypassed(config: &Config, previous_shutdown: Option<&ShutdownRecord>) -> bool {
+    match previous_shutdown {
+        Some(record) if config.bypass_reason_codes.contains(&record.reason_code) => {
+            log::info!(
+                "previous shutdown at {} by {}({}) has reason {:#x}({}) in bypass list. detection is skipped",
+                record.time.to_rfc3339(),
+                record.process,
+                record.user,
+                record.reason_code,
+                record.reason
+            );
+            true
+        }
+        _ => false,
+    }
 }
 
-fn write_last_updated(mut file: &File) -> anyhow::Result<()> {
-    let time = Utc::now().timestamp();
-    file.seek_write(time.to_string().as_bytes(), 0)?;
-    file.flush()?;
-    Ok(())
+//heartbeat on a write-protected volume is discarded on reboot, which makes restarts undetectable
+fn warn_write_filter(config: &Config) {
+    if write_filter::detect().is_empty() {
+        return;
+    }
+    if !matches!(
+        write_filter::is_on_system_volume(&config.state_dir),
+        Ok(false)
+    ) {
+        log::warn!(
+            "write filter is active and state directory may be protected. \
+            heartbeat may not survive a reboot. run `restart-fix doctor` for details."
+        );
+    }
+}
+
+fn handle_command(
+    command: ipc::Command,
+    config: &Config,
+    state: &AppState,
+    pause_marker: &PauseMarker,
+    frontend: &dyn Frontend,
+) -> String {
+    log::info!("received control command: {}", command.as_str());
+    match command {
+        ipc::Command::Status => {
+            let engine_state = state.state();
+            let last_updated = engine_state
+                .last_heartbeat
+                .map_or_else(|| "none".to_owned(), |time| time.to_rfc3339());
+            let fast_startup = fast_startup::is_enabled()
+                .map_or_else(|e| format!("unknown({e})"), |enabled| enabled.to_string());
+            format!(
+                "paused: {}\ncountdown pending: {}\nlast heartbeat: {last_updated}\nfast startup: {fast_startup}",
+                engine_state.paused, engine_state.countdown_pending,
+            )
+        }
+        ipc::Command::StatusJson => {
+            let engine_state = state.state();
+            json!({
+                "running": true,
+                "paused": engine_state.paused,
+                "countdown_pending": engine_state.countdown_pending,
+                "last_heartbeat": engine_state.last_heartbeat.map(|time| time.to_rfc3339()),
+                "uptime_secs": boot::uptime().as_secs(),
+                "boot_time": boot::boot_time().to_rfc3339(),
+                "fast_startup": fast_startup::is_enabled().ok(),
+                "config": {
+                    "path": paths::config_path(),
+                    "sha256": Config::file_hash(paths::config_path()),
+                    "threshold_secs": config.threshold.as_secs(),
+                    "shutdown_timeout_secs": config.shutdown_timeout.as_secs(),
+                    "prompt_mode": config.prompt_mode,
+                    "state_dir": config.state_dir,
+                },
+            })
+            .to_string()
+        }
+        ipc::Command::Tail => logger::recent_lines().join("\n"),
+        ipc::Command::Pause => {
+            state.execute(EngineCommand::Pause);
+            match pause_marker.set() {
+                Ok(()) => "monitoring paused until resumed or next boot".to_owned(),
+                Err(e) => format!(
+                    "monitoring paused, but pause won't survive restart of restart-fix: {e}"
+                ),
+            }
+        }
+        ipc::Command::Resume => {
+            state.execute(EngineCommand::Resume);
+            match pause_marker.clear() {
+                Ok(()) => "monitoring resumed".to_owned(),
+                Err(e) => format!("monitoring resumed, but pause marker can't be removed: {e}"),
+            }
+        }
+        ipc::Command::CancelShutdown => {
+            if state.execute(EngineCommand::CancelCountdown) {
+                "shutdown cancelled".to_owned()
+            } else {
+                "no pending shutdown".to_owned()
+            }
+        }
+        ipc::Command::TriggerTest => {
+            if state.is_countdown_pending() {
+                return "countdown is already pending".to_owned();
+            }
+            //test countdown shuts nothing down
+            frontend.test_prompt(config.shutdown_timeout, config.pause_on_input, state);
+            "test countdown started".to_owned()
+        }
+    }
+}
+
+//part of logon_grace that hasn't passed yet
+fn logon_grace_left(config: &Config) -> Duration {
+    if config.logon_grace.is_zero() {
+        return Duration::ZERO;
+    }
+    match session::time_since_logon() {
+        Ok(elapsed) => config.logon_grace.saturating_sub(elapsed),
+        Err(e) => {
+            log::warn!("unable to query logon time: {e}");
+            Duration::ZERO
+        }
+    }
 }
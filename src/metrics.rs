@@ -0,0 +1,138 @@
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::Utc;
+
+use crate::app_state::AppState;
+
+//counters since restart-fix was started. prometheus handles the reset on restart.
+static HEARTBEATS_WRITTEN: AtomicU64 = AtomicU64::new(0);
+static HEARTBEAT_FAILURES: AtomicU64 = AtomicU64::new(0);
+static DETECTIONS: AtomicU64 = AtomicU64::new(0);
+static SHUTDOWNS: AtomicU64 = AtomicU64::new(0);
+//scraper that stops reading must not keep a thread forever
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub fn heartbeat_written() {
+    HEARTBEATS_WRITTEN.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn heartbeat_failed() {
+    HEARTBEAT_FAILURES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn restart_detected() {
+    DETECTIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+//automatic shutdown or hibernation carried out
+pub fn shutdown_performed() {
+    SHUTDOWNS.fetch_add(1, Ordering::Relaxed);
+}
+
+//spawn server thread answering GET /metrics on localhost in prometheus text format
+pub fn serve(port: u16, state: Arc<AppState>) -> io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            //scrapes are rare and answered at once, so one is handled at a time
+            if let Err(e) = handle_client(stream, &state) {
+                log::debug!("unable to answer metrics request: {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_client(mut stream: TcpStream, state: &AppState) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    //headers are not needed, but are read so the client doesn't see the connection reset
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render(state)),
+        (Some("GET"), _) => ("404 Not Found", String::from("not found\n")),
+        _ => (
+            "405 Method Not Allowed",
+            String::from("method not allowed\n"),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+fn render(state: &AppState) -> String {
+    let mut text = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+        let _ = write!(
+            text,
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+        );
+    };
+    let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed).to_string();
+    metric(
+        "restart_fix_heartbeats_written_total",
+        "counter",
+        "Heartbeats written to the state directory.",
+        load(&HEARTBEATS_WRITTEN),
+    );
+    metric(
+        "restart_fix_heartbeat_write_failures_total",
+        "counter",
+        "Heartbeat writes that failed.",
+        load(&HEARTBEAT_FAILURES),
+    );
+    metric(
+        "restart_fix_detections_total",
+        "counter",
+        "Restarts detected within the threshold.",
+        load(&DETECTIONS),
+    );
+    metric(
+        "restart_fix_shutdowns_total",
+        "counter",
+        "Automatic shutdowns and hibernations carried out.",
+        load(&SHUTDOWNS),
+    );
+    if let Some(last) = state.last_updated() {
+        let age = (Utc::now() - last).num_milliseconds().max(0) as f64 / 1000.0;
+        metric(
+            "restart_fix_seconds_since_last_heartbeat",
+            "gauge",
+            "Seconds since the last heartbeat was written.",
+            age.to_string(),
+        );
+    }
+    metric(
+        "restart_fix_paused",
+        "gauge",
+        "1 while monitoring is paused.",
+        u8::from(state.is_paused()).to_string(),
+    );
+    text
+}
@@ -0,0 +1,89 @@
+use std::{
+    backtrace::Backtrace,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    os::windows::io::AsRawHandle,
+    panic,
+    path::PathBuf,
+    ptr,
+    sync::OnceLock,
+};
+
+use chrono::Local;
+use windows_sys::Win32::System::{
+    Diagnostics::Debug::{
+        MiniDumpWithThreadInfo, MiniDumpWriteDump, SetUnhandledExceptionFilter, EXCEPTION_POINTERS,
+        MINIDUMP_EXCEPTION_INFORMATION,
+    },
+    Threading::{GetCurrentProcess, GetCurrentProcessId, GetCurrentThreadId},
+};
+
+//let windows go on terminating the process after the dump is written
+const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+//directory crash.log and crash.dmp are written to
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+//program has no console, so crashes would go unnoticed. record panics and unhandled exceptions
+//with a minidump of the latest one. exceptions handled by the code raising them(e.g. inside com)
+//never reach the unhandled exception filter, unlike a vectored handler.
+pub fn install(dir: PathBuf) {
+    let _ = fs::create_dir_all(&dir);
+    let _ = DIR.set(dir);
+    unsafe { SetUnhandledExceptionFilter(Some(unhandled_exception)) };
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        write_crash_log(&format!("{info}\n{}", Backtrace::force_capture()));
+        write_dump(None);
+        default_hook(info);
+    }));
+}
+
+unsafe extern "system" fn unhandled_exception(pointers: *const EXCEPTION_POINTERS) -> i32 {
+    let code = (*(*pointers).ExceptionRecord).ExceptionCode;
+    write_crash_log(&format!("unhandled exception {:#010x}", code as u32));
+    write_dump(Some(pointers));
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+//logger may be what crashed, so crashes go to a file of their own
+fn write_crash_log(text: &str) {
+    let Some(dir) = DIR.get() else {
+        return;
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join("crash.log"));
+    if let Ok(mut file) = file {
+        let _ = writeln!(file, "{} {text}", Local::now().to_rfc3339());
+    }
+}
+
+//only the latest dump is kept. dumps are large and the first crash usually explains the rest.
+fn write_dump(pointers: Option<*const EXCEPTION_POINTERS>) {
+    let Some(dir) = DIR.get() else {
+        return;
+    };
+    let Ok(file) = File::create(dir.join("crash.dmp")) else {
+        return;
+    };
+    let exception = pointers.map(|pointers| MINIDUMP_EXCEPTION_INFORMATION {
+        ThreadId: unsafe { GetCurrentThreadId() },
+        ExceptionPointers: pointers.cast_mut(),
+        ClientPointers: 0,
+    });
+    unsafe {
+        MiniDumpWriteDump(
+            GetCurrentProcess(),
+            GetCurrentProcessId(),
+            file.as_raw_handle() as _,
+            MiniDumpWithThreadInfo,
+            exception
+                .as_ref()
+                .map_or(ptr::null(), |exception| exception),
+            ptr::null(),
+            ptr::null(),
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    str::FromStr,
+    sync::{
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail};
+use chrono::{Local, Utc};
+use restart_fix_core::Event;
+use serde_json::json;
+
+use crate::{app_state::AppState, history::History, ipc::Command, websocket};
+
+const DEFAULT_PORT: u16 = 1883;
+//broker drops the connection if nothing is sent for 1.5 times this long
+const KEEP_ALIVE: Duration = Duration::from_secs(120);
+//ping is sent when nothing else was sent for this long
+const PING_INTERVAL: Duration = Duration::from_secs(60);
+//heartbeat is written every second. publishing each of them would flood the broker.
+const HEARTBEAT_PUBLISH_INTERVAL: Duration = Duration::from_secs(60);
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+//only short commands are subscribed to. anything longer is not meant for this client.
+const MAX_PACKET: usize = 64 * 1024;
+//topic prefix home assistant watches for discovery configs
+const DISCOVERY_PREFIX: &str = "homeassistant";
+
+const PACKET_CONNECT: u8 = 0x10;
+const PACKET_CONNACK: u8 = 0x20;
+const PACKET_PUBLISH: u8 = 0x30;
+const PACKET_SUBSCRIBE: u8 = 0x82;
+const FLAG_RETAIN: u8 = 0x01;
+
+//broker given as `mqtt://[user:password@]host[:port]`
+#[derive(Clone, PartialEq, Eq)]
+pub struct Broker {
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid mqtt broker `{0}`(expected mqtt://[user:password@]host[:port])")]
+pub struct ParseBrokerError(String);
+
+impl FromStr for Broker {
+    type Err = ParseBrokerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rest = s.strip_prefix("mqtt://").unwrap_or(s);
+        let (credentials, address) = match rest.rsplit_once('@') {
+            Some((credentials, address)) => {
+                let (user, password) = credentials.split_once(':').unwrap_or((credentials, ""));
+                (Some((user.to_owned(), password.to_owned())), address)
+            }
+            None => (None, rest),
+        };
+        let address = address.trim_end_matches('/');
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse().map_err(|_| ParseBrokerError(s.to_owned()))?,
+            ),
+            None => (address, DEFAULT_PORT),
+        };
+        if host.is_empty() {
+            return Err(ParseBrokerError(s.to_owned()));
+        }
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+            credentials,
+        })
+    }
+}
+
+//credentials are left out so that they never reach the log
+impl fmt::Display for Broker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+impl fmt::Debug for Broker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Broker({self})")
+    }
+}
+
+//what is published and where
+pub struct Settings {
+    pub broker: Broker,
+    pub topic: String,
+    //announce the machine to home assistant as a device with sensors and a pause switch
+    pub discovery: bool,
+    //detected restarts counted by the sensors
+    pub history: History,
+}
+
+type Handler = Arc<dyn Fn(Command) -> String + Send + Sync>;
+
+//publish liveness, restart detections and shutdown actions to the broker in background.
+//messages go to `{topic}/status`("online" or "offline"), `{topic}/heartbeat`, `{topic}/event`,
+//`{topic}/paused`("ON" or "OFF"), `{topic}/last_restart` and `{topic}/restarts_today`.
+//with discovery, "ON" or "OFF" sent to `{topic}/paused/set` pauses or resumes monitoring.
+pub fn spawn<F>(settings: Settings, state: Arc<AppState>, handler: F)
+where
+    F: Fn(Command) -> String + Send + Sync + 'static,
+{
+    let handler: Handler = Arc::new(handler);
+    let events = state.subscribe();
+    thread::spawn(move || loop {
+        match publish_events(&settings, &state, &events, &handler) {
+            Ok(()) => return,
+            Err(e) => log::warn!(
+                "mqtt connection to {} lost, reconnecting: {e:#}",
+                settings.broker
+            ),
+        }
+        thread::sleep(RECONNECT_DELAY);
+    });
+}
+
+//returns Ok once no more events can be published
+fn publish_events(
+    settings: &Settings,
+    state: &AppState,
+    events: &Receiver<Event>,
+    handler: &Handler,
+) -> anyhow::Result<()> {
+    let topic = settings.topic.as_str();
+    let mut stream = connect(settings, handler)?;
+    log::info!(
+        "publishing to mqtt broker {} under {topic}",
+        settings.broker
+    );
+    publish(&mut stream, &format!("{topic}/status"), b"online", true)?;
+    if settings.discovery {
+        announce(&mut stream, topic)?;
+    }
+    publish_paused(&mut stream, topic, state.is_paused())?;
+    publish_restarts(&mut stream, topic, &settings.history)?;
+    //heartbeat is republished right away after a reconnect
+    let mut last_published: Option<Instant> = None;
+    let mut last_sent = Instant::now();
+    loop {
+        let timeout = PING_INTERVAL.saturating_sub(last_sent.elapsed());
+        match events.recv_timeout(timeout) {
+            Ok(Event::HeartbeatWritten(time)) => {
+                if last_published.is_some_and(|at| at.elapsed() < HEARTBEAT_PUBLISH_INTERVAL) {
+                    continue;
+                }
+                last_published = Some(Instant::now());
+                let message = json!({ "time": time.to_rfc3339() }).to_string();
+                publish(
+                    &mut stream,
+                    &format!("{topic}/heartbeat"),
+                    message.as_bytes(),
+                    true,
+                )?;
+            }
+            Ok(event) => {
+                let Some(message) = websocket::event_message(&event) else {
+                    continue;
+                };
+                publish(
+                    &mut stream,
+                    &format!("{topic}/event"),
+                    message.to_string().as_bytes(),
+                    false,
+                )?;
+                match event {
+                    Event::Paused => publish_paused(&mut stream, topic, true)?,
+                    Event::Resumed => publish_paused(&mut stream, topic, false)?,
+                    _ => {}
+                }
+            }
+            //detection is recorded in history after it is published, and the count of today
+            //starts over at midnight, so restarts are refreshed instead of pinging
+            Err(RecvTimeoutError::Timeout) => {
+                publish_restarts(&mut stream, topic, &settings.history)?
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        last_sent = Instant::now();
+    }
+}
+
+fn publish_paused(stream: &mut TcpStream, topic: &str, paused: bool) -> io::Result<()> {
+    let message: &[u8] = if paused { b"ON" } else { b"OFF" };
+    publish(stream, &format!("{topic}/paused"), message, true)
+}
+
+//time of the last detected restart and number of restarts detected since local midnight
+fn publish_restarts(stream: &mut TcpStream, topic: &str, history: &History) -> io::Result<()> {
+    let detections = match history.read() {
+        Ok(detections) => detections,
+        Err(e) => {
+            log::warn!("unable to read history: {e:#}");
+            return Ok(());
+        }
+    };
+    if let Some(last) = detections.last() {
+        publish(
+            stream,
+            &format!("{topic}/last_restart"),
+            last.time.to_rfc3339().as_bytes(),
+            true,
+        )?;
+    }
+    let midnight = Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map_or(Utc::now(), |midnight| midnight.with_timezone(&Utc));
+    let today = detections
+        .iter()
+        .filter(|detection| detection.time >= midnight)
+        .count();
+    publish(
+        stream,
+        &format!("{topic}/restarts_today"),
+        today.to_string().as_bytes(),
+        true,
+    )
+}
+
+//home assistant discovery(https://www.home-assistant.io/integrations/mqtt/#mqtt-discovery).
+//configs are retained, so the device appears without any manual yaml.
+fn announce(stream: &mut TcpStream, topic: &str) -> io::Result<()> {
+    let name = winsafe::GetComputerName().unwrap_or_default();
+    //node id may only contain these characters
+    let node: String = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '_',
+        })
+        .collect();
+    let device = json!({
+        "identifiers": [format!("restart-fix-{node}")],
+        "name": name,
+        "manufacturer": "restart-fix",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let entities = [
+        (
+            "sensor",
+            "last_restart",
+            json!({
+                "name": "Last unexpected restart",
+                "state_topic": format!("{topic}/last_restart"),
+                "device_class": "timestamp",
+                "icon": "mdi:restart-alert",
+            }),
+        ),
+        (
+            "sensor",
+            "restarts_today",
+            json!({
+                "name": "Restart count today",
+                "state_topic": format!("{topic}/restarts_today"),
+                "state_class": "measurement",
+                "icon": "mdi:counter",
+            }),
+        ),
+        (
+            "switch",
+            "paused",
+            json!({
+                "name": "Pause monitoring",
+                "state_topic": format!("{topic}/paused"),
+                "command_topic": format!("{topic}/paused/set"),
+                "payload_on": "ON",
+                "payload_off": "OFF",
+                "icon": "mdi:pause-circle",
+            }),
+        ),
+    ];
+    for (component, object, mut config) in entities {
+        config["unique_id"] = json!(format!("restart-fix_{node}_{object}"));
+        config["availability_topic"] = json!(format!("{topic}/status"));
+        config["payload_available"] = json!("online");
+        config["payload_not_available"] = json!("offline");
+        config["device"] = device.clone();
+        publish(
+            stream,
+            &format!("{DISCOVERY_PREFIX}/{component}/{node}/{object}/config"),
+            config.to_string().as_bytes(),
+            true,
+        )?;
+    }
+    Ok(())
+}
+
+//open session whose last will marks the monitor offline if it goes away without saying so
+fn connect(settings: &Settings, handler: &Handler) -> anyhow::Result<TcpStream> {
+    let Settings { broker, topic, .. } = settings;
+    let address = (broker.host.as_str(), broker.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("{} can't be resolved", broker.host))?;
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    //stalled broker is noticed instead of blocking forever
+    stream.set_write_timeout(Some(CONNECT_TIMEOUT))?;
+
+    let client_id = format!(
+        "restart-fix-{}",
+        winsafe::GetComputerName().unwrap_or_default()
+    );
+    //clean session, last will retained with qos 0
+    let mut flags = 0x02 | 0x04 | 0x20;
+    let mut payload = Vec::new();
+    put_str(&mut payload, client_id.as_bytes());
+    put_str(&mut payload, format!("{topic}/status").as_bytes());
+    put_str(&mut payload, b"offline");
+    if let Some((user, password)) = &broker.credentials {
+        flags |= 0x80 | 0x40;
+        put_str(&mut payload, user.as_bytes());
+        put_str(&mut payload, password.as_bytes());
+    }
+    let mut body = Vec::new();
+    put_str(&mut body, b"MQTT");
+    body.push(4); //protocol level of mqtt 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&(KEEP_ALIVE.as_secs() as u16).to_be_bytes());
+    body.extend_from_slice(&payload);
+    write_packet(&mut stream, PACKET_CONNECT, &body)?;
+
+    stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+    let (header, connack) = read_packet(&mut stream)?;
+    if header != PACKET_CONNACK || connack.len() < 2 {
+        bail!("broker answered connect with packet {header:#x}");
+    }
+    match connack[1] {
+        0 => {}
+        4 | 5 => bail!("broker refused the credentials"),
+        code => bail!("broker refused connection with code {code}"),
+    }
+
+    let command_topic = format!("{topic}/paused/set");
+    if settings.discovery {
+        let mut body = Vec::new();
+        body.extend_from_slice(&1u16.to_be_bytes()); //packet identifier
+        put_str(&mut body, command_topic.as_bytes());
+        body.push(0); //qos 0
+        write_packet(&mut stream, PACKET_SUBSCRIBE, &body)?;
+    }
+
+    //broker sends commands of the switch and answers to pings. reading them also keeps its send
+    //buffer from filling up.
+    let mut reader = stream.try_clone()?;
+    reader.set_read_timeout(None)?;
+    let handler = Arc::clone(handler);
+    thread::spawn(move || {
+        while let Ok((header, body)) = read_packet(&mut reader) {
+            if header & 0xf0 != PACKET_PUBLISH {
+                continue;
+            }
+            let Some((topic, message)) = parse_publish(header, &body) else {
+                continue;
+            };
+            if topic != command_topic {
+                continue;
+            }
+            let command = match message {
+                b"ON" => Command::Pause,
+                b"OFF" => Command::Resume,
+                _ => continue,
+            };
+            log::info!("mqtt: {}: {}", command.as_str(), handler(command));
+        }
+    });
+    Ok(stream)
+}
+
+fn read_packet(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut byte = [0; 1];
+    stream.read_exact(&mut byte)?;
+    let header = byte[0];
+    let mut len = 0;
+    for shift in (0..28).step_by(7) {
+        stream.read_exact(&mut byte)?;
+        len |= ((byte[0] & 0x7f) as usize) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    if len > MAX_PACKET {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body)?;
+    Ok((header, body))
+}
+
+//topic and message of a received publish packet
+fn parse_publish(header: u8, body: &[u8]) -> Option<(String, &[u8])> {
+    let len = u16::from_be_bytes(body.get(..2)?.try_into().ok()?) as usize;
+    let topic = String::from_utf8(body.get(2..2 + len)?.to_vec()).ok()?;
+    //packet identifier follows the topic if qos is above 0
+    let start = if header & 0x06 != 0 { 4 + len } else { 2 + len };
+    Some((topic, body.get(start..)?))
+}
+
+fn publish(stream: &mut TcpStream, topic: &str, message: &[u8], retain: bool) -> io::Result<()> {
+    let mut body = Vec::with_capacity(2 + topic.len() + message.len());
+    put_str(&mut body, topic.as_bytes());
+    body.extend_from_slice(message);
+    let header = if retain {
+        PACKET_PUBLISH | FLAG_RETAIN
+    } else {
+        PACKET_PUBLISH
+    };
+    write_packet(stream, header, &body)
+}
+
+fn write_packet(stream: &mut TcpStream, header: u8, body: &[u8]) -> io::Result<()> {
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    packet.push(header);
+    //remaining length is encoded 7 bits at a time, low bits first
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    stream.write_all(&packet)
+}
+
+//length prefixed string
+fn put_str(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.extend_from_slice(&(s.len() as u16).to_be_bytes());
+    buf.extend_from_slice(s);
+}
@@ -0,0 +1,41 @@
+use winsafe::{co, prelude::*, HWND};
+
+use crate::{toast, tray};
+
+//show notice on the first way that works in this session: a toast, a dialog, a balloon from the
+//icon in the notification area, or the log. toasts are unavailable e.g. on windows server or where
+//notifications are turned off, dialogs fail on a desktop that doesn't take windows, and there is no
+//icon before the hidden window is created or in the service. blocks while a dialog is shown.
+pub fn notify(title: &str, text: &str) {
+    let toast_error = match toast::show(title, text, &[], |_| {}) {
+        Ok(_) => return,
+        Err(e) => e,
+    };
+    let Err(dialog_error) = message_box(title, text) else {
+        log::debug!("unable to show toast({toast_error:#}). dialog shown instead");
+        return;
+    };
+    match tray::balloon(title, text) {
+        Ok(()) => log::debug!(
+            "unable to show toast({toast_error:#}) or dialog({dialog_error}). balloon shown instead"
+        ),
+        Err(e) => log::warn!(
+            "unable to show notice(toast: {toast_error:#}, dialog: {dialog_error}, balloon: {e}): {title}: {}",
+            text.replace("\r\n", " ")
+        ),
+    }
+}
+
+//message box with an ok button. blocks until it is closed.
+pub fn message_box(title: &str, text: &str) -> winsafe::SysResult<()> {
+    HWND::NULL
+        .MessageBox(text, title, co::MB::OK | co::MB::ICONINFORMATION)
+        .map(|_| ())
+}
+
+//message box with yes and no buttons. returns true for yes, false for no or when it is closed.
+pub fn ask(title: &str, text: &str) -> winsafe::SysResult<bool> {
+    HWND::NULL
+        .MessageBox(text, title, co::MB::YESNO | co::MB::ICONQUESTION)
+        .map(|answer| answer == co::DLGID::YES)
+}
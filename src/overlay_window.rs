@@ -0,0 +1,133 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use winsafe::{co, gui, msg, prelude::*, HwndPlace, COLORREF, POINT, SIZE};
+
+use crate::{countdown_window, dialog_placement, i18n::Locale};
+
+const TIMER_ID: usize = 1;
+//out of 255. the desktop shows through, so it is clear what the warning is about.
+const OPACITY: u8 = 220;
+
+// Countdown is shown as a full-screen overlay(warning_style = "overlay"). Set on startup.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+//translucent window covering the primary screen, with the time left in huge digits and a single
+//cancel button, for users who never notice the message box before the computer turns off.
+//enter, escape and closing the window all cancel. blocks until cancelled.
+pub fn show(title: &str, text: &str, deadline: Arc<Mutex<Instant>>) -> anyhow::Result<()> {
+    let locale = Locale::current();
+    let (cx, cy) = (
+        winsafe::GetSystemMetrics(co::SM::CXSCREEN),
+        winsafe::GetSystemMetrics(co::SM::CYSCREEN),
+    );
+    let wnd = gui::WindowMain::new(gui::WindowMainOpts {
+        title: title.to_owned(),
+        size: (cx as u32, cy as u32),
+        style: co::WS::POPUP | co::WS::CLIPCHILDREN | co::WS::VISIBLE,
+        ex_style: co::WS_EX::TOPMOST | co::WS_EX::LAYERED,
+        ..Default::default()
+    });
+    let message = gui::Label::new(
+        &wnd,
+        gui::LabelOpts {
+            text: format!("{title}\r\n{text}"),
+            position: (cx / 10, cy / 8),
+            size: (cx as u32 * 8 / 10, cy as u32 / 5),
+            label_style: co::SS::CENTER,
+            ..Default::default()
+        },
+    );
+    let remaining = gui::Label::new(
+        &wnd,
+        gui::LabelOpts {
+            text: locale.time_left(countdown_window::time_left(&deadline)),
+            position: (0, cy * 3 / 8),
+            size: (cx as u32, cy as u32 / 4),
+            label_style: co::SS::CENTER,
+            ..Default::default()
+        },
+    );
+    let cancel = gui::Button::new(
+        &wnd,
+        gui::ButtonOpts {
+            text: locale.cancel_button(),
+            position: (cx / 2 - cx / 8, cy * 3 / 4),
+            width: cx as u32 / 4,
+            height: cy as u32 / 10,
+            button_style: co::BS::DEFPUSHBUTTON,
+            ..Default::default()
+        },
+    );
+
+    let text_font = countdown_window::font_of_height(cy / 30)?;
+    let countdown_font = countdown_window::font_of_height(cy / 6)?;
+    {
+        let wnd2 = wnd.clone();
+        let (message, remaining, cancel) = (message.clone(), remaining.clone(), cancel.clone());
+        let (text_font, countdown_font) =
+            unsafe { (text_font.raw_copy(), countdown_font.raw_copy()) };
+        wnd.on().wm_create(move |_| {
+            let hwnd = wnd2.hwnd();
+            hwnd.SetLayeredWindowAttributes(COLORREF::new(0, 0, 0), OPACITY, co::LWA::ALPHA)?;
+            //window is centered in the work area otherwise, which leaves the taskbar uncovered
+            hwnd.SetWindowPos(
+                HwndPlace::Place(co::HWND_PLACE::TOPMOST),
+                POINT::new(0, 0),
+                SIZE::new(cx, cy),
+                co::SWP::SHOWWINDOW,
+            )?;
+            for (control, font) in [
+                (message.hwnd(), &text_font),
+                (cancel.hwnd(), &text_font),
+                (remaining.hwnd(), &countdown_font),
+            ] {
+                control.SendMessage(msg::wm::SetFont {
+                    hfont: unsafe { font.raw_copy() },
+                    redraw: true,
+                });
+            }
+            cancel.focus();
+            hwnd.SetTimer(TIMER_ID, 1000, None)?;
+            dialog_placement::bring_to_front(hwnd.ptr() as _);
+            Ok(0)
+        });
+    }
+    wnd.on().wm_timer(TIMER_ID, move || {
+        remaining.set_text(&locale.time_left(countdown_window::time_left(&deadline)));
+        Ok(())
+    });
+    {
+        let wnd = wnd.clone();
+        cancel.on().bn_clicked(move || {
+            wnd.hwnd().PostMessage(msg::wm::Close {})?;
+            Ok(())
+        });
+    }
+    //escape, sent by the dialog manager as IDCANCEL
+    {
+        let wnd = wnd.clone();
+        wnd.clone()
+            .on()
+            .wm_command_accel_menu(co::DLGID::CANCEL.raw(), move || {
+                wnd.hwnd().PostMessage(msg::wm::Close {})?;
+                Ok(())
+            });
+    }
+    wnd.run_main(None).map_err(|e| anyhow::anyhow!("{e}"))?;
+    drop((text_font, countdown_font));
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use winsafe::co;
+
+const APP_DIR: &str = "restart-fix";
+pub const CONFIG_FILE: &str = "restart-fix.toml";
+const DECIDE_SCRIPT: &str = "decide.rhai";
+
+// Set by the system service, which belongs to no user.
+static MACHINE_WIDE: AtomicBool = AtomicBool::new(false);
+
+//keep data in the machine-wide directory from now on. called first thing by the service.
+pub fn use_machine_dir() {
+    MACHINE_WIDE.store(true, Ordering::Relaxed);
+}
+
+//per-user data directory(%LOCALAPPDATA%\restart-fix), or %ProgramData%\restart-fix for the service.
+//unlike working directory, it doesn't depend on how restart-fix was launched(startup folder, task scheduler, ...).
+pub fn data_dir() -> PathBuf {
+    dir_of(MACHINE_WIDE.load(Ordering::Relaxed))
+}
+
+fn dir_of(machine_wide: bool) -> PathBuf {
+    let (folder, var) = if machine_wide {
+        (&co::KNOWNFOLDERID::ProgramData, "ProgramData")
+    } else {
+        (&co::KNOWNFOLDERID::LocalAppData, "LOCALAPPDATA")
+    };
+    let base = winsafe::SHGetKnownFolderPath(folder, co::KF::DEFAULT, None)
+        .map(PathBuf::from)
+        .or_else(|_| env::var(var).map(PathBuf::from))
+        //last resort. behave like earlier versions.
+        .unwrap_or_else(|_| PathBuf::from("."));
+    base.join(APP_DIR)
+}
+
+//where state is kept if the configured state directory can't be used(read-only, locked or on a
+//missing drive). tried in order.
+pub fn fallback_state_dirs() -> [PathBuf; 2] {
+    [data_dir(), env::temp_dir().join(APP_DIR)]
+}
+
+//`dir`, or the first fallback state directory that can be created
+pub fn create_state_dir(dir: &Path) -> io::Result<PathBuf> {
+    let error = match fs::create_dir_all(dir) {
+        Ok(()) => return Ok(dir.to_owned()),
+        Err(e) => e,
+    };
+    fallback_state_dirs()
+        .into_iter()
+        .find(|fallback| fs::create_dir_all(fallback).is_ok())
+        .ok_or(error)
+}
+
+pub fn config_path() -> PathBuf {
+    data_dir().join(CONFIG_FILE)
+}
+
+//script deciding the action of a detected restart, next to the config file
+pub fn decide_script_path() -> PathBuf {
+    data_dir().join(DECIDE_SCRIPT)
+}
+
+//config file of administrators(%ProgramData%\restart-fix), which per-user config files override.
+//it is the config file of the service.
+pub fn machine_config_path() -> PathBuf {
+    dir_of(true).join(CONFIG_FILE)
+}
+
+//directories of translations(<language tag>.ftl), the first one winning: the data directory, so a
+//user can fix the text of their language, then the one of the executable, so a translation can be
+//installed along with restart-fix
+pub fn locale_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![data_dir().join("locales")];
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("locales")))
+    {
+        dirs.push(dir);
+    }
+    dirs
+}
+
+//move file of earlier versions into target directory. existing file in target directory is kept.
+//returns path of migrated file.
+pub fn migrate_legacy_file(name: &str, target_dir: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let target = target_dir.join(name);
+    if target.exists() {
+        return Ok(None);
+    }
+    let Some(legacy) = legacy_dirs()
+        .into_iter()
+        .map(|dir| dir.join(name))
+        .find(|legacy| legacy.is_file())
+    else {
+        return Ok(None);
+    };
+    fs::create_dir_all(target_dir)?;
+    //rename fails across volumes
+    if fs::rename(&legacy, &target).is_err() {
+        fs::copy(&legacy, &target)?;
+        fs::remove_file(&legacy)?;
+    }
+    Ok(Some(legacy))
+}
+
+//earlier versions stored files in working directory, which usually was the executable's directory
+fn legacy_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(dir) = env::current_dir() {
+        dirs.push(dir);
+    }
+    if let Some(dir) = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_owned))
+    {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+    dirs
+}
@@ -0,0 +1,62 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::boot::boot_time;
+
+// Boot time is derived from wall clock and tick count, so two readings taken during the
+// same boot may differ slightly. Markers whose boot time is within this tolerance are
+// considered to be written during the current boot.
+static BOOT_TIME_TOLERANCE: Duration = Duration::from_secs(60);
+
+//persists pause state until monitoring is resumed or computer is restarted.
+//marker file stores boot time of the session in which monitoring was paused.
+pub struct PauseMarker {
+    path: PathBuf,
+}
+
+impl PauseMarker {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    //returns true if monitoring was paused during current boot
+    pub fn is_set(&self) -> bool {
+        match self.read() {
+            Ok(paused_boot) => {
+                let delta = (boot_time() - paused_boot).abs();
+                delta
+                    .to_std()
+                    .is_ok_and(|delta| delta < BOOT_TIME_TOLERANCE)
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn set(&self) -> anyhow::Result<()> {
+        fs::write(&self.path, boot_time().timestamp().to_string())?;
+        Ok(())
+    }
+
+    pub fn clear(&self) -> anyhow::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<DateTime<Utc>> {
+        let time = fs::read_to_string(&self.path)?.trim().parse::<i64>()?;
+        Utc.timestamp_opt(time, 0)
+            .single()
+            .ok_or_else(|| anyhow!("Invalid timestamp: {time}"))
+    }
+}
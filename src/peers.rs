@@ -0,0 +1,257 @@
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::policy::Cause;
+
+//requests are a single short line. anything longer is not a peer.
+const MAX_REQUEST: u64 = 4096;
+const TIMEOUT: Duration = Duration::from_secs(5);
+//requests older than this are refused, so a recorded one can't be replayed later
+const MAX_AGE: i64 = 120;
+//signatures of requests accepted within MAX_AGE, to refuse replays within it
+const SEEN_CAPACITY: usize = 64;
+//a short secret could be guessed from a single recorded request
+const MIN_SECRET_LEN: usize = 16;
+
+//what a peer is asked to do about a restart detected on another machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Request {
+    //start the shutdown countdown, which its user can still cancel
+    Shutdown,
+    //only show a notice
+    #[default]
+    Notify,
+    Nothing,
+}
+
+impl Request {
+    fn as_str(self) -> &'static str {
+        match self {
+            Request::Shutdown => "shutdown",
+            Request::Notify => "notify",
+            Request::Nothing => "nothing",
+        }
+    }
+}
+
+//other restart-fix instances on the network([peers] section)
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Peers {
+    //shared by all peers. requests are signed with it and unsigned ones are refused.
+    pub secret: Option<String>,
+    //port on which requests of peers are accepted. none doesn't listen.
+    pub listen_port: Option<u16>,
+    //"host:port" of peers told about restarts detected here
+    pub addresses: Vec<String>,
+    //what they are asked to do
+    pub request: Request,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Message {
+    request: Request,
+    cause: String,
+    //computer name of the sender
+    from: String,
+    //unix timestamp
+    time: i64,
+    signature: String,
+}
+
+impl Message {
+    fn signature(&self, secret: &str) -> String {
+        let signed = format!(
+            "{} {} {} {}",
+            self.request.as_str(),
+            self.cause,
+            self.from,
+            self.time
+        );
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("hmac takes keys of any length");
+        mac.update(signed.as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+fn secret(peers: &Peers) -> anyhow::Result<&str> {
+    match peers.secret.as_deref() {
+        Some(secret) if secret.len() >= MIN_SECRET_LEN => Ok(secret),
+        Some(_) => bail!("peer secret must be at least {MIN_SECRET_LEN} characters"),
+        None => bail!("peer secret is not set"),
+    }
+}
+
+//tell every peer about restart detected here in background
+pub fn tell(peers: &Peers, cause: Cause) {
+    if peers.addresses.is_empty() || peers.request == Request::Nothing {
+        return;
+    }
+    let peers = peers.clone();
+    thread::spawn(move || {
+        for (address, result) in send(&peers, peers.request, cause) {
+            match result {
+                Ok(()) => log::info!("asked peer {address} to {}", peers.request.as_str()),
+                Err(e) => log::warn!("unable to reach peer {address}: {e:#}"),
+            }
+        }
+    });
+}
+
+//send `request` to every peer. returns the outcome for each address.
+pub fn send(peers: &Peers, request: Request, cause: Cause) -> Vec<(String, anyhow::Result<()>)> {
+    let secret = match secret(peers) {
+        Ok(secret) => secret,
+        Err(e) => {
+            let e = e.to_string();
+            return peers
+                .addresses
+                .iter()
+                .map(|address| (address.clone(), Err(anyhow!("{e}"))))
+                .collect();
+        }
+    };
+    let mut message = Message {
+        request,
+        cause: cause.as_str().to_owned(),
+        from: winsafe::GetComputerName().unwrap_or_default(),
+        time: Utc::now().timestamp(),
+        signature: String::new(),
+    };
+    message.signature = message.signature(secret);
+    let line = serde_json::to_string(&message).unwrap_or_default();
+    peers
+        .addresses
+        .iter()
+        .map(|address| (address.clone(), send_to(address, &line)))
+        .collect()
+}
+
+fn send_to(address: &str, line: &str) -> anyhow::Result<()> {
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("{address} can't be resolved"))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    writeln!(stream, "{line}")?;
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        reply => bail!("peer answered: {reply}"),
+    }
+}
+
+//accept requests of peers. `on_request` is given the request, computer name of the peer and the
+//cause of the restart it detected.
+pub fn listen<F>(peers: &Peers, on_request: F) -> anyhow::Result<u16>
+where
+    F: Fn(Request, &str, Cause) + Send + Sync + 'static,
+{
+    let Some(port) = peers.listen_port else {
+        bail!("listen_port is not set");
+    };
+    let secret = secret(peers)?.to_owned();
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))?;
+    let on_request = Arc::new(on_request);
+    let seen = Arc::new(Mutex::new(VecDeque::new()));
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let secret = secret.clone();
+            let on_request = Arc::clone(&on_request);
+            let seen = Arc::clone(&seen);
+            thread::spawn(move || {
+                let peer = stream
+                    .peer_addr()
+                    .map(|address| address.to_string())
+                    .unwrap_or_default();
+                if let Err(e) = handle(stream, &secret, &seen, &*on_request) {
+                    log::warn!("refused request of peer {peer}: {e:#}");
+                }
+            });
+        }
+    });
+    Ok(port)
+}
+
+fn handle(
+    mut stream: TcpStream,
+    secret: &str,
+    seen: &Mutex<VecDeque<String>>,
+    on_request: &(dyn Fn(Request, &str, Cause) + Send + Sync),
+) -> anyhow::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST)).read_line(&mut line)?;
+    let result = verify(&line, secret, seen);
+    match &result {
+        Ok(_) => writeln!(stream, "ok")?,
+        Err(e) => writeln!(stream, "error: {e}")?,
+    }
+    let (message, cause) = result?;
+    log::info!(
+        "peer {} detected a restart({}) and asks to {}",
+        message.from,
+        cause.as_str(),
+        message.request.as_str()
+    );
+    on_request(message.request, &message.from, cause);
+    Ok(())
+}
+
+fn verify(
+    line: &str,
+    secret: &str,
+    seen: &Mutex<VecDeque<String>>,
+) -> anyhow::Result<(Message, Cause)> {
+    let message: Message = serde_json::from_str(line.trim())?;
+    if !constant_time_eq(
+        message.signature.as_bytes(),
+        message.signature(secret).as_bytes(),
+    ) {
+        bail!("signature doesn't match. peers must share the same secret");
+    }
+    if (Utc::now().timestamp() - message.time).abs() > MAX_AGE {
+        bail!("request is too old or clocks of the peers disagree");
+    }
+    let mut seen = seen.lock().unwrap();
+    if seen.contains(&message.signature) {
+        bail!("request was already received");
+    }
+    if seen.len() == SEEN_CAPACITY {
+        seen.pop_front();
+    }
+    seen.push_back(message.signature.clone());
+    let cause = Cause::parse(&message.cause).unwrap_or(Cause::Unknown);
+    Ok((message, cause))
+}
+
+//comparison taking the same time wherever the first difference is, so the signature can't be
+//guessed byte by byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
@@ -0,0 +1,80 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use winsafe::{co, prelude::*, RegistryValue, HKEY};
+
+use crate::{boot, i18n::Locale, notifier, pool_timer};
+
+// Time between checks. Windows schedules its restart hours to days after a reboot became pending,
+// so a warning a few minutes late still comes well before it.
+static CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+//keys that exist while windows waits for a restart to finish servicing or updates
+const PENDING_KEYS: [(&str, &str); 2] = [
+    (
+        "Component Based Servicing",
+        r"SOFTWARE\Microsoft\Windows\CurrentVersion\Component Based Servicing\RebootPending",
+    ),
+    (
+        "Windows Update",
+        r"SOFTWARE\Microsoft\Windows\CurrentVersion\WindowsUpdate\Auto Update\RebootRequired",
+    ),
+];
+//files installers replace on the next boot
+const SESSION_MANAGER_KEY: &str = r"SYSTEM\CurrentControlSet\Control\Session Manager";
+
+//markers of a pending restart that are present, e.g. ["Windows Update"]
+pub fn markers() -> Vec<&'static str> {
+    let mut found: Vec<&'static str> = PENDING_KEYS
+        .iter()
+        .filter(|(_, key)| {
+            HKEY::LOCAL_MACHINE
+                .RegOpenKeyEx(Some(key), co::REG_OPTION::default(), co::KEY::READ)
+                .is_ok()
+        })
+        .map(|(name, _)| *name)
+        .collect();
+    if matches!(
+        HKEY::LOCAL_MACHINE.RegGetValue(Some(SESSION_MANAGER_KEY), Some("PendingFileRenameOperations")),
+        Ok(RegistryValue::MultiSz(files)) if files.iter().any(|file| !file.is_empty())
+    ) {
+        found.push("PendingFileRenameOperations");
+    }
+    found
+}
+
+//warn the user once per boot that windows intends to restart, before it does. `warned` records
+//the boot id the warning was shown in, so restarting restart-fix doesn't repeat it.
+pub fn spawn(warned: PathBuf) {
+    pool_timer::every(CHECK_INTERVAL, move || {
+        let markers = markers();
+        if !markers.is_empty() {
+            warn(&markers, &warned);
+        }
+    });
+}
+
+fn warn(markers: &[&str], warned: &Path) {
+    let boot_id = match boot::boot_id() {
+        Ok(boot_id) => boot_id.to_string(),
+        Err(e) => {
+            log::warn!("unable to read boot id: {e:#}");
+            return;
+        }
+    };
+    if fs::read_to_string(warned).is_ok_and(|last| last.trim() == boot_id) {
+        return;
+    }
+    log::info!("restart is pending({})", markers.join(", "));
+    if let Err(e) = fs::write(warned, &boot_id) {
+        log::warn!("unable to record pending restart warning: {e}");
+    }
+    let locale = Locale::current();
+    notifier::notify(
+        &locale.pending_reboot_title(),
+        &locale.pending_reboot_text(),
+    );
+}
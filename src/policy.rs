@@ -0,0 +1,572 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Datelike, Local, Timelike, Weekday};
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::{
+    active_hours::ActiveHours,
+    config::{deserialize_optional, deserialize_secs},
+    shutdown_reason::ShutdownRecord,
+};
+
+//processes that restart the computer to install updates
+const UPDATE_PROCESSES: [&str; 4] = [
+    "trustedinstaller.exe",
+    "mousocoreworker.exe",
+    "usoclient.exe",
+    "wuauclt.exe",
+];
+//folder of the tasks windows update schedules its restarts with
+const UPDATE_TASK_FOLDER: &str = r"\microsoft\windows\updateorchestrator\";
+//processes through which a user at the keyboard restarts the computer
+const USER_PROCESSES: [&str; 3] = ["explorer.exe", "runtimebroker.exe", "shutdown.exe"];
+
+//SHTDN_REASON_MAJOR_OPERATINGSYSTEM
+const REASON_MAJOR_OPERATING_SYSTEM: u32 = 0x0002_0000;
+//SHTDN_REASON_MINOR_UPGRADE, _SERVICEPACK, _HOTFIX, _SECURITYFIX
+const REASON_MINOR_UPDATES: [u32; 4] = [0x03, 0x10, 0x11, 0x12];
+
+//what caused a restart detected within threshold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cause {
+    WindowsUpdate,
+    Crash,
+    UserInitiated,
+    //restart initiated by a process a scheduled task started
+    ScheduledTask,
+    PowerLoss,
+    Unknown,
+}
+
+impl Cause {
+    //classify restart initiated by previous shutdown record(event 1074)
+    pub fn of_shutdown(record: Option<&ShutdownRecord>) -> Self {
+        let Some(record) = record else {
+            return Cause::Unknown;
+        };
+        let process = record.process.to_lowercase();
+        let initiated_by = |names: &[&str]| names.iter().any(|name| process.contains(name));
+        let major = record.reason_code & 0x00ff_0000;
+        let minor = record.reason_code & 0xffff;
+        if initiated_by(&UPDATE_PROCESSES)
+            || (major == REASON_MAJOR_OPERATING_SYSTEM && REASON_MINOR_UPDATES.contains(&minor))
+        {
+            Cause::WindowsUpdate
+        } else if initiated_by(&USER_PROCESSES) {
+            Cause::UserInitiated
+        } else {
+            Cause::Unknown
+        }
+    }
+
+    //classify restart initiated by scheduled task `task`
+    pub fn of_task(task: &str) -> Self {
+        if task.to_lowercase().starts_with(UPDATE_TASK_FOLDER) {
+            Cause::WindowsUpdate
+        } else {
+            Cause::ScheduledTask
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Cause::WindowsUpdate => "windows-update",
+            Cause::Crash => "crash",
+            Cause::UserInitiated => "user-initiated",
+            Cause::ScheduledTask => "scheduled-task",
+            Cause::PowerLoss => "power-loss",
+            Cause::Unknown => "unknown",
+        }
+    }
+
+    //inverse of as_str
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Cause::WindowsUpdate,
+            Cause::Crash,
+            Cause::UserInitiated,
+            Cause::ScheduledTask,
+            Cause::PowerLoss,
+            Cause::Unknown,
+        ]
+        .into_iter()
+        .find(|cause| cause.as_str() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Shutdown,
+    Hibernate,
+    //restart into uefi firmware setup, for users who want to check the bios after a restart
+    RebootToFirmware,
+    //lock the workstation and turn the monitors off, for a computer that shouldn't sit there lit
+    //after it restarted itself at night. keeps running, so it isn't prompted for.
+    LockScreen,
+    //show notice without countdown
+    NotifyOnly,
+    Nothing,
+}
+
+impl Action {
+    //name used in the config file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Shutdown => "shutdown",
+            Action::Hibernate => "hibernate",
+            Action::RebootToFirmware => "reboot-to-firmware",
+            Action::LockScreen => "lock-screen",
+            Action::NotifyOnly => "notify-only",
+            Action::Nothing => "nothing",
+        }
+    }
+
+    //inverse of as_str
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Action::Shutdown,
+            Action::Hibernate,
+            Action::RebootToFirmware,
+            Action::LockScreen,
+            Action::NotifyOnly,
+            Action::Nothing,
+        ]
+        .into_iter()
+        .find(|action| action.as_str() == name)
+    }
+
+    //action takes the computer away from the user, so it is prompted for
+    pub fn powers_off(self) -> bool {
+        matches!(
+            self,
+            Action::Shutdown | Action::Hibernate | Action::RebootToFirmware
+        )
+    }
+}
+
+//what happens while someone is connected over remote desktop. powering off cuts them off until
+//someone has physical access to the machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RemoteSession {
+    //take no action
+    Skip,
+    //show restart notice only
+    #[default]
+    Notify,
+    //prompt as usual. the monitor of the remote user shows the dialog in the remote session.
+    Dialog,
+}
+
+//what users logged on to other sessions of a shared computer get to see of the countdown. their
+//own restart-fix, if it runs, prompts them anyway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OtherSessions {
+    //nothing
+    #[default]
+    Ignore,
+    //warning of the countdown
+    Warn,
+    //warning that lets any of them cancel the shutdown
+    Object,
+}
+
+//how a prompt is handled while the workstation is locked. nobody can read the dialog then.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LockedSession {
+    //prompt as if unlocked
+    #[default]
+    Dialog,
+    //prompt once the workstation is unlocked
+    Wait,
+    //act without dialog as in silent prompt mode
+    Silent,
+    //hibernate without dialog, whatever the action is
+    Hibernate,
+}
+
+//what is done when a newer release is published on github
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateCheck {
+    #[default]
+    Off,
+    //tell the user about it
+    Notify,
+    //also download it. it replaces the executable on next start.
+    Install,
+}
+
+//how the computer is powered off when the action is shutdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShutdownType {
+    //cold boot next time, like `shutdown /s /full`
+    #[default]
+    Full,
+    //kernel is saved to the hiberfile for fast startup, like the start menu does
+    Hybrid,
+}
+
+//how applications still open are closed when the computer is shut down or restarted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AppClose {
+    //applications not responding are closed forcibly, others may ask to save their work
+    #[default]
+    ForceIfHung,
+    //every application is closed forcibly. unsaved work is lost.
+    Force,
+    //applications are asked to close and may keep the computer from shutting down
+    Graceful,
+}
+
+//where the heartbeat is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HeartbeatBackend {
+    //two files in the state directory, written in turn
+    #[default]
+    File,
+    //value of the per-user key of restart-fix, for state directories that can't be written often
+    Registry,
+    //nothing survives a restart, so none is detected. for trying out the configuration.
+    Memory,
+}
+
+//how the countdown is presented
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningStyle {
+    #[default]
+    Dialog,
+    //translucent full-screen window with a huge countdown, for users who miss the dialog
+    Overlay,
+}
+
+//choice of the countdown dialog(dialog_buttons)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DialogButton {
+    Cancel,
+    //prompt again in 1 hour, also after restart-fix is restarted
+    Snooze,
+    //prompt again after extend_by
+    Extend,
+    //carry out the action right away
+    ActNow,
+    //hibernate right away instead of carrying out the action
+    Hibernate,
+}
+
+//how shutdown or hibernation is announced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PromptMode {
+    //dialog with countdown that carries out the action unless cancelled
+    #[default]
+    Countdown,
+    //no dialog. action is carried out after silent_delay. for machines nobody sits in front of.
+    Silent,
+    //dialog without countdown. action is carried out only if the user confirms it.
+    Confirm,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub action: Action,
+    //countdown in seconds. shutdown_timeout is used if not given.
+    #[serde(default, deserialize_with = "deserialize_timeout")]
+    pub timeout: Option<Duration>,
+}
+
+impl Rule {
+    const fn new(action: Action) -> Self {
+        Self {
+            action,
+            timeout: None,
+        }
+    }
+}
+
+//action taken for each cause of restart
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Policy {
+    windows_update: Rule,
+    crash: Rule,
+    user_initiated: Rule,
+    scheduled_task: Rule,
+    power_loss: Rule,
+    unknown: Rule,
+    //rules by file name of the process that initiated the restart, e.g. "explorer.exe". they take
+    //precedence over the rule of the cause.
+    processes: HashMap<String, Rule>,
+}
+
+impl Default for Policy {
+    //crashes and power losses are not the kind of restart shutdown is meant for
+    fn default() -> Self {
+        Self {
+            windows_update: Rule::new(Action::Shutdown),
+            crash: Rule::new(Action::Nothing),
+            user_initiated: Rule::new(Action::Shutdown),
+            scheduled_task: Rule::new(Action::Shutdown),
+            power_loss: Rule::new(Action::Nothing),
+            unknown: Rule::new(Action::Shutdown),
+            processes: HashMap::new(),
+        }
+    }
+}
+
+impl Policy {
+    //recovery mode only tells the user about restarts
+    pub fn notify_only() -> Self {
+        let rule = Rule::new(Action::NotifyOnly);
+        Self {
+            windows_update: rule,
+            crash: rule,
+            user_initiated: rule,
+            scheduled_task: rule,
+            power_loss: rule,
+            unknown: rule,
+            processes: HashMap::new(),
+        }
+    }
+
+    pub fn rule(&self, cause: Cause) -> Rule {
+        match cause {
+            Cause::WindowsUpdate => self.windows_update,
+            Cause::Crash => self.crash,
+            Cause::UserInitiated => self.user_initiated,
+            Cause::ScheduledTask => self.scheduled_task,
+            Cause::PowerLoss => self.power_loss,
+            Cause::Unknown => self.unknown,
+        }
+    }
+
+    //rule for a restart initiated by `process`, or the rule of `cause` if none is given for it
+    pub fn rule_for(&self, cause: Cause, process: Option<&str>) -> Rule {
+        process
+            .and_then(|process| {
+                self.processes
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(process))
+            })
+            .map_or_else(|| self.rule(cause), |(_, rule)| *rule)
+    }
+
+    //`action` for every cause that is acted on. causes ignored by the policy stay ignored.
+    pub fn with_action(mut self, action: Action) -> Self {
+        for rule in [
+            &mut self.windows_update,
+            &mut self.crash,
+            &mut self.user_initiated,
+            &mut self.scheduled_task,
+            &mut self.power_loss,
+            &mut self.unknown,
+        ]
+        .into_iter()
+        .chain(self.processes.values_mut())
+        {
+            if rule.action != Action::Nothing {
+                rule.action = action;
+            }
+        }
+        self
+    }
+}
+
+//settings overridden on some days and hours, e.g. a long countdown while someone is at work
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    //"mon" to "sun", "weekdays" or "weekends". every day if not given.
+    #[serde(default, deserialize_with = "deserialize_days")]
+    pub days: Vec<Weekday>,
+    //e.g. "08:00-22:00". all day if not given.
+    #[serde(default, deserialize_with = "deserialize_optional")]
+    pub hours: Option<ActiveHours>,
+    //in seconds
+    #[serde(default, deserialize_with = "deserialize_timeout")]
+    pub threshold: Option<Duration>,
+    //in seconds
+    #[serde(default, deserialize_with = "deserialize_timeout")]
+    pub shutdown_timeout: Option<Duration>,
+    pub action: Option<Action>,
+}
+
+impl Schedule {
+    //day is the one `now` falls on, also for hours past midnight
+    pub fn contains(&self, now: DateTime<Local>) -> bool {
+        (self.days.is_empty() || self.days.contains(&now.weekday()))
+            && self.hours.is_none_or(|hours| hours.contains(now.hour()))
+    }
+}
+
+//behavior while running on battery
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Battery {
+    //below this charge(in percent) on battery, user is only notified instead of powering off
+    pub min_percent: u8,
+    //hibernate instead of shutting down on battery, so unsaved work survives
+    pub prefer_hibernate: bool,
+}
+
+impl Default for Battery {
+    fn default() -> Self {
+        Self {
+            min_percent: 20,
+            prefer_hibernate: false,
+        }
+    }
+}
+
+//deferral of the action while the computer is busy with work of its own, e.g. a backup, a
+//download or a render that powering off would throw away
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Activity {
+    //in seconds. action is deferred for at most this long. 0 disables it.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub deadline: Duration,
+    //usage at or above any of these counts as busy. 0 ignores it.
+    pub cpu_percent: u8,
+    pub disk_mb_per_sec: u32,
+    pub network_mbit_per_sec: u32,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            deadline: Duration::ZERO,
+            cpu_percent: 80,
+            disk_mb_per_sec: 20,
+            network_mbit_per_sec: 20,
+        }
+    }
+}
+
+//escalation when the computer keeps restarting(boot loop). shutting it down is useless then.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RestartLoop {
+    //detections within window that make a loop. 0 disables it.
+    pub count: u32,
+    //in seconds
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub window: Duration,
+    //hibernate without dialog instead of doing nothing
+    pub hibernate: bool,
+}
+
+impl Default for RestartLoop {
+    fn default() -> Self {
+        Self {
+            count: 3,
+            window: Duration::from_secs(30 * 60),
+            hibernate: false,
+        }
+    }
+}
+
+//cap on automatic shutdowns and hibernations, so that a flaky clock, bad threshold or update storm
+//can't power the computer off again and again
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShutdownLimit {
+    //automatic shutdowns allowed within window. 0 disables the limit.
+    pub count: u32,
+    //in seconds
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub window: Duration,
+}
+
+impl Default for ShutdownLimit {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            window: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+//countdown for people using assistive technology([accessibility] section)
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Accessibility {
+    //text of the countdown dialog is enlarged by this factor, e.g. 1.5. above 1 the countdown is
+    //shown in restart-fix's own window, since the message box can't be enlarged.
+    pub text_scale: f64,
+    //countdown lasts at least this long while a screen reader is running. in seconds.
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub screen_reader_timeout: Duration,
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            text_scale: 1.0,
+            screen_reader_timeout: Duration::from_secs(120),
+        }
+    }
+}
+
+//response to restarts taking up to `within` to come back, e.g. a silent shutdown for quick ones
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tier {
+    //in seconds since the last heartbeat
+    #[serde(deserialize_with = "deserialize_secs")]
+    pub within: Duration,
+    pub action: Option<Action>,
+    pub prompt_mode: Option<PromptMode>,
+    //in seconds
+    #[serde(default, deserialize_with = "deserialize_timeout")]
+    pub shutdown_timeout: Option<Duration>,
+}
+
+//e.g. "Mon Tue 08:00-22:00"
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let days: Vec<String> = self.days.iter().map(Weekday::to_string).collect();
+        match (days.is_empty(), self.hours) {
+            (true, None) => write!(f, "every day"),
+            (true, Some(hours)) => write!(f, "{hours}"),
+            (false, None) => write!(f, "{}", days.join(" ")),
+            (false, Some(hours)) => write!(f, "{} {hours}", days.join(" ")),
+        }
+    }
+}
+
+fn deserialize_days<'de, D>(deserializer: D) -> Result<Vec<Weekday>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use Weekday::*;
+    let mut days = Vec::new();
+    for name in Vec::<String>::deserialize(deserializer)? {
+        match name.trim().to_lowercase().as_str() {
+            "weekdays" => days.extend([Mon, Tue, Wed, Thu, Fri]),
+            "weekends" => days.extend([Sat, Sun]),
+            day => days.push(
+                day.parse()
+                    .map_err(|_| de::Error::custom(format!("invalid day `{name}`")))?,
+            ),
+        }
+    }
+    Ok(days)
+}
+
+fn deserialize_timeout<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_secs))
+}
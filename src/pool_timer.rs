@@ -0,0 +1,116 @@
+use std::{
+    ffi::c_void,
+    io,
+    panic::{self, AssertUnwindSafe},
+    ptr, thread,
+    time::Duration,
+};
+
+use windows_sys::Win32::{
+    Foundation::FILETIME,
+    System::Threading::{
+        CloseThreadpoolTimer, CreateThreadpoolTimer, SetThreadpoolTimer, PTP_CALLBACK_INSTANCE,
+        PTP_TIMER,
+    },
+};
+
+// Time windows may delay a callback by to coalesce it with other wakeups. Checks running every few
+// minutes or hours don't mind.
+static COALESCING_WINDOW: Duration = Duration::from_secs(10);
+
+//what a timer does after its callback returned
+pub enum Next {
+    //call it again after this long
+    After(Duration),
+    Stop,
+}
+
+type Callback = Box<dyn FnMut() -> Next + Send>;
+
+//context of a timer, freed once its callback stops it
+struct Timer {
+    handle: PTP_TIMER,
+    //time a call may be delayed by
+    window: Duration,
+    callback: Callback,
+}
+
+//call `callback` right away and then every `interval` on the thread pool of the process, until
+//the process exits. checks that only run now and then don't keep a thread of their own waiting
+//for them all day.
+pub fn every(interval: Duration, mut callback: impl FnMut() + Send + 'static) {
+    let window = COALESCING_WINDOW.min(interval / 10);
+    start(Duration::ZERO, window, move || {
+        //panicking checks are still run again next time
+        let _ = panic::catch_unwind(AssertUnwindSafe(&mut callback));
+        Next::After(interval)
+    });
+}
+
+//call `callback` after `delay` on the thread pool, and again for as long as it asks to. the timer
+//is armed again only once a call returned, so calls never overlap however long one takes. a
+//thread of its own makes the calls if the timer can't be created.
+pub fn start(delay: Duration, window: Duration, callback: impl FnMut() -> Next + Send + 'static) {
+    let context = Box::into_raw(Box::new(Timer {
+        handle: 0,
+        window,
+        callback: Box::new(callback),
+    }));
+    let handle = unsafe { CreateThreadpoolTimer(Some(on_timer), context.cast(), ptr::null()) };
+    if handle == 0 {
+        log::warn!(
+            "unable to create thread-pool timer({}). using a thread",
+            io::Error::last_os_error()
+        );
+        let mut timer = unsafe { Box::from_raw(context) };
+        thread::spawn(move || {
+            let mut delay = delay;
+            loop {
+                thread::sleep(delay);
+                match call(&mut timer.callback) {
+                    Next::After(next) => delay = next,
+                    Next::Stop => return,
+                }
+            }
+        });
+        return;
+    }
+    unsafe {
+        (*context).handle = handle;
+        arm(&*context, delay);
+    }
+}
+
+unsafe fn arm(timer: &Timer, delay: Duration) {
+    //negative due times are relative, in 100 ns units
+    let due = -((delay.as_nanos() / 100).clamp(1, i64::MAX as u128) as i64);
+    let due = FILETIME {
+        dwLowDateTime: due as u32,
+        dwHighDateTime: (due >> 32) as u32,
+    };
+    let window = timer.window.as_millis().min(u32::MAX as u128) as u32;
+    //one-shot. the callback arms it again.
+    SetThreadpoolTimer(timer.handle, &due, 0, window);
+}
+
+fn call(callback: &mut Callback) -> Next {
+    //unwinding into the thread pool aborts the process. the panic hook has reported it already,
+    //and the timer stops.
+    panic::catch_unwind(AssertUnwindSafe(callback)).unwrap_or(Next::Stop)
+}
+
+unsafe extern "system" fn on_timer(
+    _instance: PTP_CALLBACK_INSTANCE,
+    context: *mut c_void,
+    _timer: PTP_TIMER,
+) {
+    let timer = context as *mut Timer;
+    match call(&mut (*timer).callback) {
+        //nothing touches the context after arming, since the next call may start right away
+        Next::After(delay) => arm(&*timer, delay),
+        Next::Stop => {
+            let timer = Box::from_raw(timer);
+            CloseThreadpoolTimer(timer.handle);
+        }
+    }
+}
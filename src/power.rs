@@ -0,0 +1,198 @@
+use std::{io, marker::PhantomData, thread, time::Duration};
+
+use windows_sys::Win32::{
+    System::{
+        Power::{
+            GetSystemPowerStatus, SetThreadExecutionState, ES_CONTINUOUS, ES_DISPLAY_REQUIRED,
+            ES_SYSTEM_REQUIRED, SYSTEM_POWER_STATUS,
+        },
+        Shutdown::{ExitWindowsEx, LockWorkStation, EWX_HYBRID_SHUTDOWN, EWX_REBOOT, EWX_SHUTDOWN},
+        WindowsProgramming::{GetFirmwareEnvironmentVariableW, SetFirmwareEnvironmentVariableW},
+    },
+    UI::WindowsAndMessaging::{
+        PostMessageW, EWX_BOOTOPTIONS, EWX_FORCE, EWX_FORCEIFHUNG, SC_MONITORPOWER, WM_SYSCOMMAND,
+    },
+};
+
+use crate::{
+    policy::{AppClose, ShutdownType},
+    shutdown_privilege, shutdown_reason,
+};
+
+//EFI_GLOBAL_VARIABLE, namespace of OsIndications
+const EFI_GLOBAL_VARIABLE: &str = "{8BE4DF61-93CA-11D2-AA0D-00E098032B8C}";
+//EFI_OS_INDICATIONS_BOOT_TO_FW_UI bit of OsIndications
+const BOOT_TO_FIRMWARE_UI: u64 = 0x1;
+
+//top-level windows, one of which(the desktop) turns the monitors off for SC_MONITORPOWER
+const HWND_BROADCAST: isize = 0xffff;
+//lParam of SC_MONITORPOWER that powers the monitors off, rather than putting them on standby
+const MONITOR_OFF: isize = 2;
+
+//ACLineStatus and BatteryLifePercent report 255 when unknown
+const UNKNOWN: u8 = 255;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PowerStatus {
+    pub on_battery: bool,
+    //None if there is no battery or charge is unknown
+    pub battery_percent: Option<u8>,
+}
+
+pub fn status() -> io::Result<PowerStatus> {
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(PowerStatus {
+        //ACLineStatus is 0 when offline, 1 when online
+        on_battery: status.ACLineStatus == 0,
+        battery_percent: Some(status.BatteryLifePercent).filter(|percent| *percent != UNKNOWN),
+    })
+}
+
+//keeps the computer from sleeping and the display from turning off while alive.
+//execution state belongs to the calling thread, so it can't be sent to another one.
+pub struct KeepAwake(PhantomData<*const ()>);
+
+impl KeepAwake {
+    pub fn new() -> Self {
+        let flags = ES_CONTINUOUS | ES_SYSTEM_REQUIRED | ES_DISPLAY_REQUIRED;
+        if unsafe { SetThreadExecutionState(flags) } == 0 {
+            log::warn!("unable to keep the computer awake during the countdown");
+        }
+        Self(PhantomData)
+    }
+}
+
+impl Drop for KeepAwake {
+    fn drop(&mut self) {
+        unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+    }
+}
+
+//how applications are closed on shutdown and restart
+#[derive(Debug, Clone, Copy)]
+pub struct Closing {
+    pub app_close: AppClose,
+    //graceful closing turns forcible after this long. zero waits as long as it takes.
+    pub timeout: Duration,
+}
+
+//power off the computer. SeShutdownPrivilege must be enabled.
+pub fn shutdown(shutdown_type: ShutdownType, closing: Closing) -> io::Result<()> {
+    let flags = match shutdown_type {
+        ShutdownType::Full => EWX_SHUTDOWN,
+        ShutdownType::Hybrid => EWX_SHUTDOWN | EWX_HYBRID_SHUTDOWN,
+    };
+    exit_windows(flags, closing)
+}
+
+//restart into uefi firmware setup, like `shutdown /r /fw`. SeShutdownPrivilege must be enabled.
+//asking the firmware for its setup takes administrator rights. without them, or on legacy bios,
+//windows restarts into its boot options menu, which offers "UEFI Firmware Settings" if available.
+pub fn reboot_to_firmware(closing: Closing) -> io::Result<()> {
+    match request_firmware_ui() {
+        Ok(()) => exit_windows(EWX_REBOOT, closing),
+        Err(e) => {
+            log::warn!("unable to ask firmware for its setup. restarting into boot options: {e}");
+            exit_windows(EWX_REBOOT | EWX_BOOTOPTIONS, closing)
+        }
+    }
+}
+
+fn request_firmware_ui() -> io::Result<()> {
+    shutdown_privilege::enable_system_environment()?;
+    let guid = to_wide(EFI_GLOBAL_VARIABLE);
+    let supported = firmware_variable("OsIndicationsSupported", &guid)?;
+    if supported & BOOT_TO_FIRMWARE_UI == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "firmware has no setup to boot into",
+        ));
+    }
+    //variable doesn't exist until something was requested
+    let indications = firmware_variable("OsIndications", &guid).unwrap_or(0) | BOOT_TO_FIRMWARE_UI;
+    let name = to_wide("OsIndications");
+    let bytes = indications.to_le_bytes();
+    if unsafe {
+        SetFirmwareEnvironmentVariableW(
+            name.as_ptr(),
+            guid.as_ptr(),
+            bytes.as_ptr().cast(),
+            bytes.len() as u32,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn firmware_variable(name: &str, guid: &[u16]) -> io::Result<u64> {
+    let name = to_wide(name);
+    let mut bytes = [0u8; 8];
+    let size = unsafe {
+        GetFirmwareEnvironmentVariableW(
+            name.as_ptr(),
+            guid.as_ptr(),
+            bytes.as_mut_ptr().cast(),
+            bytes.len() as u32,
+        )
+    };
+    if size == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(u64::from_le_bytes(bytes))
+}
+
+//lock the workstation of the calling session and power off the monitors. the computer keeps
+//running, so nothing is lost, but it doesn't sit there lit with the desktop open.
+pub fn lock_and_turn_off_displays() -> io::Result<()> {
+    if unsafe { LockWorkStation() } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    //posted, since a hung window would keep a sent broadcast from returning
+    if unsafe {
+        PostMessageW(
+            HWND_BROADCAST,
+            WM_SYSCOMMAND,
+            SC_MONITORPOWER as _,
+            MONITOR_OFF,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+//in graceful mode this returns only once applications still open are closed forcibly, unless the
+//session ends first.
+fn exit_windows(flags: u32, closing: Closing) -> io::Result<()> {
+    let force = match closing.app_close {
+        AppClose::ForceIfHung => EWX_FORCEIFHUNG,
+        AppClose::Force => EWX_FORCE,
+        AppClose::Graceful => 0,
+    };
+    let reason = shutdown_reason::RESTART_FIX_REASON_CODE;
+    if unsafe { ExitWindowsEx(flags | force, reason) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if closing.app_close == AppClose::Graceful && !closing.timeout.is_zero() {
+        //applications with unsaved work keep the session alive until they are closed
+        thread::sleep(closing.timeout);
+        log::warn!(
+            "applications are still open after {}s. closing them forcibly",
+            closing.timeout.as_secs()
+        );
+        if unsafe { ExitWindowsEx(flags | EWX_FORCE, reason) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
@@ -0,0 +1,75 @@
+use winsafe::{co, prelude::*, RegistryValue, HKEY};
+
+//machine policies of windows update, as set by group policy "Configure Automatic Updates"
+const POLICY_KEY: &str = r"SOFTWARE\Policies\Microsoft\Windows\WindowsUpdate\AU";
+
+//"auto download and schedule the install". updates keep being installed, which
+//NoAutoRebootWithLoggedOnUsers only applies with.
+const AU_OPTIONS: u32 = 4;
+
+//values set by `apply()`, removed again by `restore()`
+const VALUES: [(&str, u32); 2] = [
+    ("AUOptions", AU_OPTIONS),
+    ("NoAutoRebootWithLoggedOnUsers", 1),
+];
+
+//keep windows update from restarting the computer while a user is logged on. they are asked to
+//restart instead. needs administrator rights.
+pub fn apply() -> anyhow::Result<()> {
+    let result = HKEY::LOCAL_MACHINE
+        .RegCreateKeyEx(
+            POLICY_KEY,
+            None,
+            co::REG_OPTION::NON_VOLATILE,
+            co::KEY::SET_VALUE,
+            None,
+        )
+        .and_then(|(key, _)| {
+            VALUES.iter().try_for_each(|(name, value)| {
+                key.RegSetValueEx(Some(name), RegistryValue::Dword(*value))
+            })
+        });
+    access_checked(result)
+}
+
+//remove the values set by `apply()`, so windows update behaves as it does without policy
+pub fn restore() -> anyhow::Result<()> {
+    let key = match HKEY::LOCAL_MACHINE.RegOpenKeyEx(
+        Some(POLICY_KEY),
+        co::REG_OPTION::default(),
+        co::KEY::SET_VALUE,
+    ) {
+        Ok(key) => key,
+        Err(co::ERROR::FILE_NOT_FOUND) => return Ok(()),
+        Err(e) => return access_checked(Err(e)),
+    };
+    let result = VALUES
+        .iter()
+        .try_for_each(|(name, _)| match key.RegDeleteValue(Some(name)) {
+            Err(co::ERROR::FILE_NOT_FOUND) => Ok(()),
+            result => result,
+        });
+    access_checked(result)
+}
+
+//both values are set as `apply()` sets them
+pub fn is_applied() -> bool {
+    VALUES.iter().all(|(name, value)| {
+        matches!(
+            HKEY::LOCAL_MACHINE.RegGetValue(Some(POLICY_KEY), Some(name)),
+            Ok(RegistryValue::Dword(set)) if set == *value
+        )
+    })
+}
+
+fn access_checked(result: Result<(), co::ERROR>) -> anyhow::Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(co::ERROR::ACCESS_DENIED) => {
+            anyhow::bail!(
+                "access denied. run `restart-fix prevent` from an elevated command prompt"
+            )
+        }
+        Err(e) => Err(e.into()),
+    }
+}
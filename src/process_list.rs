@@ -0,0 +1,91 @@
+use std::{io, mem};
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, ERROR_NO_MORE_FILES, INVALID_HANDLE_VALUE},
+    System::{
+        Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+            TH32CS_SNAPPROCESS,
+        },
+        RemoteDesktop::ProcessIdToSessionId,
+    },
+};
+
+//name of the first running process whose executable name is in `names`. compared case-insensitively.
+pub fn find_running(names: &[impl AsRef<str>]) -> io::Result<Option<String>> {
+    Ok(running_names()?.into_iter().find(|running| {
+        names
+            .iter()
+            .any(|name| name.as_ref().eq_ignore_ascii_case(running))
+    }))
+}
+
+//executable names of all running processes(e.g. "explorer.exe")
+pub fn running_names() -> io::Result<Vec<String>> {
+    Ok(processes()?.into_iter().map(|(_, name)| name).collect())
+}
+
+//sessions running a process whose executable name is `name`. compared case-insensitively.
+pub fn sessions_running(name: &str) -> io::Result<Vec<u32>> {
+    let mut sessions: Vec<u32> = processes()?
+        .into_iter()
+        .filter(|(_, running)| running.eq_ignore_ascii_case(name))
+        //processes that exited meanwhile are left out
+        .filter_map(|(pid, _)| session_of(pid))
+        .collect();
+    sessions.sort_unstable();
+    sessions.dedup();
+    Ok(sessions)
+}
+
+//process ids and names of processes in the session of restart-fix whose executable name is in
+//`names`. compared case-insensitively.
+pub fn running_in_session(names: &[impl AsRef<str>]) -> io::Result<Vec<(u32, String)>> {
+    let own = session_of(std::process::id());
+    Ok(processes()?
+        .into_iter()
+        .filter(|(pid, running)| {
+            names
+                .iter()
+                .any(|name| name.as_ref().eq_ignore_ascii_case(running))
+                && own.is_some()
+                && session_of(*pid) == own
+        })
+        .collect())
+}
+
+//none once the process has exited
+fn session_of(pid: u32) -> Option<u32> {
+    let mut session_id = 0;
+    (unsafe { ProcessIdToSessionId(pid, &mut session_id) } != 0).then_some(session_id)
+}
+
+//process ids and executable names of all running processes
+fn processes() -> io::Result<Vec<(u32, String)>> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    let mut entry: PROCESSENTRY32W = unsafe { mem::zeroed() };
+    entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as u32;
+    let mut processes = Vec::new();
+    let mut ok = unsafe { Process32FirstW(snapshot, &mut entry) };
+    while ok != 0 {
+        let len = entry
+            .szExeFile
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(entry.szExeFile.len());
+        processes.push((
+            entry.th32ProcessID,
+            String::from_utf16_lossy(&entry.szExeFile[..len]),
+        ));
+        ok = unsafe { Process32NextW(snapshot, &mut entry) };
+    }
+    let error = io::Error::last_os_error();
+    unsafe { CloseHandle(snapshot) };
+    if error.raw_os_error() != Some(ERROR_NO_MORE_FILES as i32) {
+        return Err(error);
+    }
+    Ok(processes)
+}
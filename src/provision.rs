@@ -0,0 +1,61 @@
+use winsafe::{co, prelude::*, RegistryValue, HKEY};
+
+//event log source of restart-fix. registering it needs administrator rights,
+//so it is done once by `restart-fix provision` instead of failing at runtime.
+const EVENT_SOURCE_KEY: &str =
+    r"SYSTEM\CurrentControlSet\Services\EventLog\Application\restart-fix";
+
+//generic message table shipped with windows. event ids 1-1000 render the inserted string as is.
+const EVENT_MESSAGE_FILE: &str = r"%SystemRoot%\System32\EventCreate.exe";
+
+//EVENTLOG_ERROR_TYPE | EVENTLOG_WARNING_TYPE | EVENTLOG_INFORMATION_TYPE
+const TYPES_SUPPORTED: u32 = 0x7;
+
+pub fn register_event_source() -> anyhow::Result<()> {
+    let result = HKEY::LOCAL_MACHINE
+        .RegCreateKeyEx(
+            EVENT_SOURCE_KEY,
+            None,
+            co::REG_OPTION::NON_VOLATILE,
+            co::KEY::SET_VALUE,
+            None,
+        )
+        .and_then(|(key, _)| {
+            key.RegSetValueEx(
+                Some("EventMessageFile"),
+                RegistryValue::ExpandSz(EVENT_MESSAGE_FILE.to_owned()),
+            )?;
+            key.RegSetValueEx(
+                Some("TypesSupported"),
+                RegistryValue::Dword(TYPES_SUPPORTED),
+            )
+        });
+    match result {
+        Ok(()) => Ok(()),
+        Err(co::ERROR::ACCESS_DENIED) => {
+            anyhow::bail!(
+                "access denied. run `restart-fix provision` from an elevated command prompt"
+            )
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn is_event_source_registered() -> bool {
+    HKEY::LOCAL_MACHINE
+        .RegGetValue(Some(EVENT_SOURCE_KEY), Some("EventMessageFile"))
+        .is_ok()
+}
+
+//undo `register_event_source()`. events already logged are kept, but render without the source.
+pub fn unregister_event_source() -> anyhow::Result<()> {
+    match HKEY::LOCAL_MACHINE.RegDeleteKey(EVENT_SOURCE_KEY) {
+        Ok(()) | Err(co::ERROR::FILE_NOT_FOUND) => Ok(()),
+        Err(co::ERROR::ACCESS_DENIED) => {
+            anyhow::bail!(
+                "access denied. run `restart-fix uninstall` from an elevated command prompt"
+            )
+        }
+        Err(e) => Err(e.into()),
+    }
+}
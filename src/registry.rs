@@ -0,0 +1,79 @@
+use winsafe::{co, prelude::*, RegistryValue, HKEY};
+
+//per-user key holding what has been installed by restart-fix
+pub const APP_KEY: &str = r"Software\restart-fix";
+
+pub fn set_value(root: &HKEY, key: &str, name: &str, value: RegistryValue) -> anyhow::Result<()> {
+    let (key, _) = root.RegCreateKeyEx(
+        key,
+        None,
+        co::REG_OPTION::NON_VOLATILE,
+        co::KEY::SET_VALUE,
+        None,
+    )?;
+    key.RegSetValueEx(Some(name), value)?;
+    Ok(())
+}
+
+//string value, None if it isn't there
+pub fn string_value(root: &HKEY, key: &str, name: &str) -> anyhow::Result<Option<String>> {
+    match root.RegGetValue(Some(key), Some(name)) {
+        Ok(RegistryValue::Sz(value)) => Ok(Some(value)),
+        Ok(_) => anyhow::bail!("{name} is not a string"),
+        Err(co::ERROR::FILE_NOT_FOUND) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//values of `key` under `root`. no values if it isn't there.
+pub fn values(root: &HKEY, key: &str) -> anyhow::Result<Vec<(String, RegistryValue)>> {
+    let key = match root.RegOpenKeyEx(Some(key), co::REG_OPTION::default(), co::KEY::READ) {
+        Ok(key) => key,
+        Err(co::ERROR::FILE_NOT_FOUND) => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let names = key
+        .RegEnumValue()?
+        .map(|value| value.map(|(name, _)| name))
+        .collect::<Result<Vec<_>, _>>()?;
+    names
+        .into_iter()
+        .map(|name| {
+            let value = key.RegGetValue(None, Some(&name))?;
+            Ok((name, value))
+        })
+        .collect()
+}
+
+//flags record what install has set up, so its disappearance can be told apart from never being installed
+pub fn set_flag(name: &str) -> anyhow::Result<()> {
+    set_value(&HKEY::CURRENT_USER, APP_KEY, name, RegistryValue::Dword(1))
+}
+
+pub fn is_flag_set(name: &str) -> bool {
+    matches!(
+        HKEY::CURRENT_USER.RegGetValue(Some(APP_KEY), Some(name)),
+        Ok(RegistryValue::Dword(1))
+    )
+}
+
+//value that isn't there is already deleted
+pub fn delete_value(root: &HKEY, key: &str, name: &str) -> anyhow::Result<()> {
+    let key = match root.RegOpenKeyEx(Some(key), co::REG_OPTION::default(), co::KEY::SET_VALUE) {
+        Ok(key) => key,
+        Err(co::ERROR::FILE_NOT_FOUND) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    match key.RegDeleteValue(Some(name)) {
+        Ok(()) | Err(co::ERROR::FILE_NOT_FOUND) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//forget everything install has set up, flags included
+pub fn remove_app_key() -> anyhow::Result<()> {
+    match HKEY::CURRENT_USER.RegDeleteTree(Some(APP_KEY)) {
+        Ok(()) | Err(co::ERROR::FILE_NOT_FOUND) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
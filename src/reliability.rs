@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    i18n::Locale,
+    wmi::{self, ReliabilityRecord},
+};
+
+//time before a boot in which events may have led to it. updates installed before the restart
+//they asked for are usually within it.
+const BEFORE_BOOT_SECS: i64 = 60 * 60;
+//updates finish installing and report the result after the restart
+const AFTER_BOOT_SECS: i64 = 15 * 60;
+
+//what reliability monitor shows that tells why a computer restarted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    UpdateInstalled,
+    UpdateFailed,
+    //windows itself crashed(blue screen)
+    SystemCrash,
+    AppCrash,
+    AppHang,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::UpdateInstalled => "update installed",
+            Kind::UpdateFailed => "update failed",
+            Kind::SystemCrash => "windows crashed",
+            Kind::AppCrash => "app crashed",
+            Kind::AppHang => "app stopped responding",
+        }
+    }
+
+    fn of(record: &ReliabilityRecord) -> Option<Self> {
+        Some(match (record.source.as_str(), record.event_id) {
+            ("Microsoft-Windows-WindowsUpdateClient", 19) => Kind::UpdateInstalled,
+            ("Microsoft-Windows-WindowsUpdateClient", 20) => Kind::UpdateFailed,
+            ("Microsoft-Windows-WER-SystemErrorReporting", 1001) => Kind::SystemCrash,
+            ("Application Error", 1000) => Kind::AppCrash,
+            ("Application Hang", 1002) => Kind::AppHang,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub time: DateTime<Utc>,
+    pub kind: Kind,
+    //e.g. title of the update or name of the crashed application
+    pub product: String,
+}
+
+//events of reliability monitor around boots since `since`. entries that don't tell why a computer
+//restarted are left out.
+pub fn events_since(since: DateTime<Utc>) -> anyhow::Result<Vec<Event>> {
+    let since = since - chrono::Duration::seconds(BEFORE_BOOT_SECS);
+    let mut events: Vec<Event> = wmi::reliability_records_since(since)?
+        .into_iter()
+        .filter_map(|record| {
+            Some(Event {
+                time: record.time,
+                kind: Kind::of(&record)?,
+                product: record.product,
+            })
+        })
+        .collect();
+    events.sort_by_key(|event| event.time);
+    Ok(events)
+}
+
+//events of `events` close enough to the boot at `boot_time` to explain it
+pub fn around(events: &[Event], boot_time: DateTime<Utc>) -> impl Iterator<Item = &Event> {
+    let from = boot_time - chrono::Duration::seconds(BEFORE_BOOT_SECS);
+    let to = boot_time + chrono::Duration::seconds(AFTER_BOOT_SECS);
+    events
+        .iter()
+        .filter(move |event| event.time >= from && event.time <= to)
+}
+
+//events counted by kind, e.g. "2 updates installed, 1 app crashed"
+pub fn summary<'a>(locale: Locale, events: impl Iterator<Item = &'a Event>) -> String {
+    let mut counts: Vec<(Kind, usize)> = Vec::new();
+    for event in events {
+        match counts.iter_mut().find(|(kind, _)| *kind == event.kind) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((event.kind, 1)),
+        }
+    }
+    counts.sort();
+    counts
+        .into_iter()
+        .map(|(kind, count)| locale.reliability_event(kind, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,57 @@
+use std::{io, thread, time::Duration};
+
+use crate::process_list;
+
+// Time between checks while a remote assistance session is active. Technicians usually stay
+// for minutes, so checking more often only costs a process snapshot for nothing.
+static RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+//processes that run only while someone is connected to the machine, not merely installed
+pub fn default_processes() -> Vec<String> {
+    [
+        //quick assist
+        "QuickAssist.exe",
+        //windows remote assistance
+        "msra.exe",
+        //shadow agent of remote desktop shadowing(mstsc /shadow)
+        "RdpSa.exe",
+        //spawned by teamviewer for each incoming session
+        "TeamViewer_Desktop.exe",
+    ]
+    .map(str::to_owned)
+    .to_vec()
+}
+
+//name of the first running process from the list. names are compared case-insensitively.
+pub fn active_tool(processes: &[String]) -> io::Result<Option<String>> {
+    if processes.is_empty() {
+        return Ok(None);
+    }
+    process_list::find_running(processes)
+}
+
+//block while a technician is working on the machine. failure to list processes counts as no session.
+pub fn wait_until_inactive(processes: &[String]) {
+    let mut logged = false;
+    loop {
+        match active_tool(processes) {
+            Ok(Some(tool)) => {
+                if !logged {
+                    log::info!("remote assistance session is active({tool}). action is deferred");
+                    logged = true;
+                }
+                thread::sleep(RECHECK_INTERVAL);
+            }
+            Ok(None) => {
+                if logged {
+                    log::info!("remote assistance session has ended");
+                }
+                return;
+            }
+            Err(e) => {
+                log::warn!("unable to check for remote assistance session: {e}");
+                return;
+            }
+        }
+    }
+}
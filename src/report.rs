@@ -0,0 +1,63 @@
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::{policy::Cause, shutdown_reason};
+
+//number of events of each kind looked at
+const MAX_EVENTS: usize = 500;
+
+//how the computer was shut down or restarted recently, aggregated by cause and initiator.
+//gives users the data to fix the underlying cause of unwanted restarts.
+pub fn shutdown_statistics() -> anyhow::Result<String> {
+    let shutdowns = shutdown_reason::recent_shutdowns(MAX_EVENTS)?;
+    let dirty = shutdown_reason::recent_dirty_shutdowns(MAX_EVENTS)?;
+
+    let mut causes: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut initiators: BTreeMap<&str, usize> = BTreeMap::new();
+    for record in &shutdowns {
+        *causes
+            .entry(Cause::of_shutdown(Some(record)).as_str())
+            .or_default() += 1;
+        //process is logged as "C:\Windows\system32\winlogon.exe (HOSTNAME)"
+        let process = record.process.split(" (").next().unwrap_or_default();
+        *initiators.entry(process).or_default() += 1;
+    }
+
+    let oldest = shutdowns
+        .iter()
+        .map(|record| record.time)
+        .chain(dirty.iter().copied())
+        .min();
+    let mut report = String::new();
+    match oldest {
+        Some(oldest) => writeln!(report, "shutdowns since {}:", oldest.to_rfc3339())?,
+        None => {
+            writeln!(report, "no shutdowns recorded in the System event log")?;
+            return Ok(report);
+        }
+    }
+    for (count, cause) in by_count(causes) {
+        writeln!(report, "{count:>5} {cause}")?;
+    }
+    if !dirty.is_empty() {
+        writeln!(
+            report,
+            "{:>5} dirty(power loss, crash or hang)",
+            dirty.len()
+        )?;
+    }
+    writeln!(report, "initiated by:")?;
+    for (count, process) in by_count(initiators) {
+        writeln!(report, "{count:>5} {process}")?;
+    }
+    Ok(report)
+}
+
+//most frequent first
+pub fn by_count(counts: BTreeMap<&str, usize>) -> Vec<(usize, &str)> {
+    let mut counts: Vec<_> = counts
+        .into_iter()
+        .map(|(key, count)| (count, key))
+        .collect();
+    counts.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+    counts
+}
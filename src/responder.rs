@@ -0,0 +1,238 @@
+use std::{
+    ffi::{c_char, CString},
+    io,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use windows_sys::Win32::{
+    Foundation::FARPROC,
+    System::LibraryLoader::{GetProcAddress, LoadLibraryW},
+};
+
+use crate::{
+    config::deserialize_secs,
+    hook,
+    policy::{Action, Cause},
+};
+
+//exports of a plugin library. both take the cause and the action as nul-terminated utf-8 and the
+//first returns 0 on success. rollback is optional.
+const PLUGIN_EXECUTE: &[u8] = b"restart_fix_execute\0";
+const PLUGIN_ROLLBACK: &[u8] = b"restart_fix_rollback\0";
+
+// Time a response command may take by default. Long enough for a script archiving logs.
+static DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+type PluginFn = unsafe extern "system" fn(cause: *const c_char, action: *const c_char) -> i32;
+
+//what a restart detected within threshold is responded with, besides the action of the policy
+#[derive(Debug, Clone, Copy)]
+pub struct Context {
+    pub cause: Cause,
+    pub action: Action,
+    pub dry_run: bool,
+}
+
+//response to a detected restart, built in or added through the config([[responses]] sections)
+pub trait Responder: Send + Sync {
+    fn name(&self) -> &str;
+    fn execute(&self, context: &Context) -> anyhow::Result<()>;
+    //undo after a response that ran later failed. most responses can't be undone.
+    fn rollback(&self, _context: &Context) {}
+}
+
+//response in the config. exactly one of `command` and `library` is given.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Response {
+    pub name: String,
+    //run through `cmd /C` with RESTART_FIX_CAUSE and RESTART_FIX_ACTION set
+    pub command: Option<String>,
+    //plugin dll exporting restart_fix_execute and optionally restart_fix_rollback
+    pub library: Option<PathBuf>,
+    //causes responded to. empty responds to all of them.
+    #[serde(default)]
+    pub causes: Vec<Cause>,
+    #[serde(default = "default_timeout", deserialize_with = "deserialize_secs")]
+    pub timeout: Duration,
+}
+
+fn default_timeout() -> Duration {
+    DEFAULT_RESPONSE_TIMEOUT
+}
+
+//responses in the order they run, each with the causes it responds to
+#[derive(Default)]
+pub struct Registry {
+    responders: Vec<(Vec<Cause>, Box<dyn Responder>)>,
+}
+
+impl Registry {
+    //registry of the responses in the config. ones that can't be set up are logged and left out.
+    pub fn with_responses(responses: &[Response]) -> Self {
+        let mut registry = Self::default();
+        for response in responses {
+            let responder: Box<dyn Responder> = match (&response.command, &response.library) {
+                (Some(command), None) => Box::new(Script {
+                    name: response.name.clone(),
+                    command: command.clone(),
+                    timeout: response.timeout,
+                }),
+                (None, Some(library)) => match Plugin::load(&response.name, library) {
+                    Ok(plugin) => Box::new(plugin),
+                    Err(e) => {
+                        log::warn!(
+                            "unable to load plugin {} for response {}: {e}",
+                            library.display(),
+                            response.name
+                        );
+                        continue;
+                    }
+                },
+                _ => {
+                    log::warn!(
+                        "response {} needs either command or library. ignored",
+                        response.name
+                    );
+                    continue;
+                }
+            };
+            registry.register(responder, response.causes.clone());
+        }
+        registry
+    }
+
+    pub fn register(&mut self, responder: Box<dyn Responder>, causes: Vec<Cause>) {
+        self.responders.push((causes, responder));
+    }
+
+    //run the responses for `context.cause` in order. once one fails, those that ran are rolled
+    //back in reverse and the rest don't run.
+    pub fn respond(&self, context: &Context) {
+        let mut done = Vec::new();
+        for (causes, responder) in &self.responders {
+            if !causes.is_empty() && !causes.contains(&context.cause) {
+                continue;
+            }
+            if context.dry_run {
+                log::info!("dry run: would have run response {}", responder.name());
+                continue;
+            }
+            match responder.execute(context) {
+                Ok(()) => {
+                    log::info!("response {} done", responder.name());
+                    done.push(responder);
+                }
+                Err(e) => {
+                    log::error!("response {} failed: {e:#}", responder.name());
+                    for responder in done.into_iter().rev() {
+                        log::info!("rolling back response {}", responder.name());
+                        responder.rollback(context);
+                    }
+                    return;
+                }
+            }
+        }
+    }
+}
+
+//respond in the background, so slow responses don't hold the prompt back
+pub fn spawn(responses: &[Response], context: Context) {
+    if responses.is_empty() {
+        return;
+    }
+    let responses = responses.to_vec();
+    thread::spawn(move || Registry::with_responses(&responses).respond(&context));
+}
+
+struct Script {
+    name: String,
+    command: String,
+    timeout: Duration,
+}
+
+impl Responder for Script {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, context: &Context) -> anyhow::Result<()> {
+        hook::run(
+            &format!("response {}", self.name),
+            &self.command,
+            self.timeout,
+            &[
+                ("RESTART_FIX_CAUSE", context.cause.as_str()),
+                ("RESTART_FIX_ACTION", context.action.as_str()),
+            ],
+        )
+    }
+}
+
+//library stays loaded until restart-fix exits, since its functions are called from other threads
+struct Plugin {
+    name: String,
+    execute: PluginFn,
+    rollback: Option<PluginFn>,
+}
+
+impl Plugin {
+    fn load(name: &str, library: &Path) -> io::Result<Self> {
+        let path: Vec<u16> = library
+            .as_os_str()
+            .to_string_lossy()
+            .encode_utf16()
+            .chain(Some(0))
+            .collect();
+        let module = unsafe { LoadLibraryW(path.as_ptr()) };
+        if module == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let function = |export: &[u8]| -> Option<PluginFn> {
+            let address: FARPROC = unsafe { GetProcAddress(module, export.as_ptr()) };
+            address.map(|address| unsafe { std::mem::transmute(address) })
+        };
+        let Some(execute) = function(PLUGIN_EXECUTE) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "library doesn't export restart_fix_execute",
+            ));
+        };
+        Ok(Self {
+            name: name.to_owned(),
+            execute,
+            rollback: function(PLUGIN_ROLLBACK),
+        })
+    }
+
+    fn call(function: PluginFn, context: &Context) -> i32 {
+        let cause = CString::new(context.cause.as_str()).unwrap();
+        let action = CString::new(context.action.as_str()).unwrap();
+        unsafe { function(cause.as_ptr(), action.as_ptr()) }
+    }
+}
+
+impl Responder for Plugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, context: &Context) -> anyhow::Result<()> {
+        match Self::call(self.execute, context) {
+            0 => Ok(()),
+            code => anyhow::bail!("plugin returned {code}"),
+        }
+    }
+
+    fn rollback(&self, context: &Context) {
+        if let Some(rollback) = self.rollback {
+            let code = Self::call(rollback, context);
+            if code != 0 {
+                log::warn!("rollback of response {} returned {code}", self.name);
+            }
+        }
+    }
+}
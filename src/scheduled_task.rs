@@ -0,0 +1,52 @@
+use chrono::Duration;
+
+use crate::{event_log, shutdown_reason::ShutdownRecord};
+
+//task scheduler records the tasks it starts while its history is enabled("Enable All Tasks History")
+const CHANNEL: &str = "Microsoft-Windows-TaskScheduler/Operational";
+//task process created(129) and action started(200)
+const TASK_STARTED_QUERY: &str = "*[System[(EventID=129 or EventID=200)]]";
+//tasks start all the time, so a few events per hour before the shutdown are looked at
+const MAX_EVENTS: usize = 200;
+//task initiating shutdown was started at most this long before it, e.g. a script running a while
+const MAX_DELAY_MINUTES: i64 = 10;
+
+//path of the scheduled task whose process initiated the shutdown of `record`,
+//e.g. "\Microsoft\Windows\UpdateOrchestrator\Reboot_AC"
+pub fn initiating_task(record: &ShutdownRecord) -> anyhow::Result<Option<String>> {
+    let Some(process) = file_name(process_path(&record.process)) else {
+        return Ok(None);
+    };
+    let earliest = record.time - Duration::minutes(MAX_DELAY_MINUTES);
+    let events = event_log::query_newest(CHANNEL, TASK_STARTED_QUERY, MAX_EVENTS)?;
+    Ok(events
+        .iter()
+        .filter(|event| {
+            event
+                .time_created()
+                .is_some_and(|time| time <= record.time && time >= earliest)
+        })
+        .find(|event| {
+            //event 129 names the executable as Path, event 200 as ActionName
+            ["Path", "ActionName"].iter().any(|name| {
+                event
+                    .data(name)
+                    .is_some_and(|path| file_name(&path).as_deref() == Some(process.as_str()))
+            })
+        })
+        .and_then(|event| event.data("TaskName")))
+}
+
+//"C:\Windows\system32\shutdown.exe (HOSTNAME)" is logged with the computer name
+fn process_path(process: &str) -> &str {
+    process
+        .rsplit_once(" (")
+        .map_or(process, |(path, _)| path)
+        .trim()
+}
+
+//lowercase, e.g. "shutdown.exe"
+fn file_name(path: &str) -> Option<String> {
+    let name = path.trim().trim_matches('"').rsplit(['\\', '/']).next()?;
+    (!name.is_empty()).then(|| name.to_lowercase())
+}
@@ -0,0 +1,81 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use restart_fix_core::{
+    Event, Heartbeat, HeartbeatSource, HeartbeatWorker, MemoryHeartbeat, WorkerOptions,
+};
+
+use crate::{
+    action, alert,
+    app_state::AppState,
+    config::Config,
+    countdown_window,
+    i18n::Locale,
+    notifier, overlay_window,
+    policy::{Action, Cause, WarningStyle},
+};
+
+// Time the stand-in heartbeat worker waits for a stop request. It never writes anything.
+static IDLE_INTERVAL: Duration = Duration::from_secs(3600);
+
+//worker only there to be stopped when the test countdown expires
+struct NoHeartbeat;
+
+impl HeartbeatSource for NoHeartbeat {
+    fn next(&mut self) -> Option<Heartbeat> {
+        None
+    }
+}
+
+//notice of a detected restart the way users get it: localized, as a dialog, a balloon or in the
+//log, with the alert sound of the config
+pub fn notification(config: &Config) -> String {
+    let mut response = String::new();
+    if let Some(sound) = &config.alert_sound {
+        match alert::play(sound) {
+            Ok(()) => response.push_str(&format!("{sound} played\n")),
+            Err(e) => response.push_str(&format!("unable to play {sound}: {e}\n")),
+        }
+    }
+    let locale = Locale::current();
+    notifier::notify(
+        &locale.restart_notice_title(),
+        &locale.restart_notice_text(),
+    );
+    response.push_str("notice shown");
+    response
+}
+
+//shutdown prompt for a detected restart the way the config shows it, with its wording, warning
+//style, placement, sound and announcement, but never shutting down. blocks until it is answered or
+//expires.
+pub fn shutdown_dialog(config: &Config) -> String {
+    countdown_window::set_text_scale(config.accessibility.text_scale);
+    countdown_window::set_custom_buttons(config.has_custom_buttons());
+    overlay_window::set_enabled(config.warning_style == WarningStyle::Overlay);
+    let state = Arc::new(AppState::new(config.concurrent_prompts));
+    let events = state.subscribe();
+    let worker = HeartbeatWorker::spawn(
+        Arc::new(Mutex::new(MemoryHeartbeat::default())),
+        WorkerOptions {
+            interval: IDLE_INTERVAL,
+            max_failures: 1,
+        },
+        NoHeartbeat,
+    );
+    let settings = crate::action_settings(config, Cause::Unknown, config.shutdown_timeout, true);
+    action::prompt(Action::Shutdown, &settings, &state, worker.stopper());
+    let outcome = events
+        .into_iter()
+        .find_map(|event| match event {
+            Event::CountdownCancelled => Some("cancelled"),
+            Event::CountdownSnoozed { .. } => Some("snoozed"),
+            Event::CountdownExpired => Some("expired. the computer would have been shut down"),
+            _ => None,
+        })
+        .unwrap_or("ended");
+    let _ = worker.stop(Duration::from_secs(1));
+    format!("test countdown {outcome}")
+}
@@ -0,0 +1,643 @@
+use std::{
+    env,
+    ffi::c_void,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    mem, ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicIsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use flume::{Receiver, Sender};
+use restart_fix_core::{CancellationToken, Clock, PromptId, StopReason, Stopper};
+use windows_sys::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{
+            ERROR_CALL_NOT_IMPLEMENTED, ERROR_SERVICE_DOES_NOT_EXIST, ERROR_SERVICE_EXISTS,
+            ERROR_SERVICE_SPECIFIC_ERROR, NO_ERROR,
+        },
+        Security::SC_HANDLE,
+        Storage::FileSystem::DELETE,
+        System::Services::{
+            ChangeServiceConfig2W, CloseServiceHandle, ControlService, CreateServiceW,
+            DeleteService, OpenSCManagerW, OpenServiceW, QueryServiceStatus,
+            RegisterServiceCtrlHandlerExW, SetServiceStatus, StartServiceCtrlDispatcherW,
+            StartServiceW, SC_MANAGER_CONNECT, SC_MANAGER_CREATE_SERVICE,
+            SERVICE_ACCEPT_PRESHUTDOWN, SERVICE_ACCEPT_SHUTDOWN, SERVICE_ACCEPT_STOP,
+            SERVICE_ALL_ACCESS, SERVICE_AUTO_START, SERVICE_CONFIG_DESCRIPTION,
+            SERVICE_CONTROL_INTERROGATE, SERVICE_CONTROL_PRESHUTDOWN, SERVICE_CONTROL_SHUTDOWN,
+            SERVICE_CONTROL_STOP, SERVICE_DESCRIPTIONW, SERVICE_ERROR_NORMAL, SERVICE_QUERY_STATUS,
+            SERVICE_RUNNING, SERVICE_STATUS, SERVICE_STATUS_HANDLE, SERVICE_STOP, SERVICE_STOPPED,
+            SERVICE_TABLE_ENTRYW, SERVICE_WIN32_OWN_PROCESS,
+        },
+    },
+};
+
+use crate::{
+    action::{self, ActionSettings, Frontend, Prompt},
+    app_state::AppState,
+    audit,
+    boot::SystemClock,
+    clean_shutdown::CleanShutdownMarker,
+    config::Config,
+    config_watch::LiveConfig,
+    error::Error,
+    event_log,
+    i18n::Locale,
+    ipc::{self, PipeSecurity},
+    logger, paths,
+    pause_marker::PauseMarker,
+    policy::{Action, PromptMode},
+    session, MonitorHeartbeat,
+};
+
+pub const NAME: &str = "restart-fix";
+const DISPLAY_NAME: &str = "Restart Fix";
+const DESCRIPTION: &str =
+    "Detects unintended restarts, also before anyone logs on, and shuts the computer down.";
+
+//agents of the logged on users connect to this pipe to show the prompts of the service
+pub const AGENT_PIPE: &str = r"\\.\pipe\restart-fix-service";
+//access to the pipes of the service. system and administrators have full access. interactive
+//users may read and write, but not create instances(FILE_GENERIC_READ | FILE_GENERIC_WRITE without
+//FILE_CREATE_PIPE_INSTANCE), so nobody but the service can pose as it.
+const PIPE_SDDL: &str = "D:(A;;GA;;;SY)(A;;GA;;;BA)(A;;0x12019b;;;IU)";
+
+// Stopping the service is waited for at most STOP_POLLS * STOP_POLL_INTERVAL.
+static STOP_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const STOP_POLLS: u32 = 20;
+
+// Controls of the service control manager, forwarded to the monitor. Set once by `service_main`.
+static CONTROLS: OnceLock<Sender<Control>> = OnceLock::new();
+// Handle the status of the service is reported with.
+static STATUS_HANDLE: AtomicIsize = AtomicIsize::new(0);
+// Whether the service only pretends to power off(--dry-run). Set once by `dispatch`.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Control {
+    Stop,
+    //computer shuts down. heartbeat stops for a reason, so the next boot isn't a restart.
+    Shutdown,
+}
+
+//run as the service. blocks until the service is stopped, called when launched by the service
+//control manager with --service.
+pub fn dispatch(dry_run: bool) -> anyhow::Result<()> {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+    //the service belongs to no user, so its state is kept machine-wide
+    paths::use_machine_dir();
+    let mut name = to_wide(NAME);
+    let table = [
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: name.as_mut_ptr(),
+            lpServiceProc: Some(service_main),
+        },
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: ptr::null_mut(),
+            lpServiceProc: None,
+        },
+    ];
+    if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } == 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+    let (controls_tx, controls_rx) = flume::unbounded();
+    let _ = CONTROLS.set(controls_tx);
+    let name = to_wide(NAME);
+    let handle = RegisterServiceCtrlHandlerExW(name.as_ptr(), Some(handle_control), ptr::null());
+    if handle == 0 {
+        return;
+    }
+    STATUS_HANDLE.store(handle, Ordering::Relaxed);
+    report_status(SERVICE_RUNNING, 0);
+    let exit_code = match monitor(&controls_rx) {
+        Ok(()) => 0,
+        Err(e) => {
+            log::error!("{e}");
+            let _ = event_log::report_error(&e.to_string());
+            e.exit_code()
+        }
+    };
+    report_status(SERVICE_STOPPED, exit_code as u32);
+}
+
+unsafe extern "system" fn handle_control(
+    control: u32,
+    _event_type: u32,
+    _event_data: *mut c_void,
+    _context: *mut c_void,
+) -> u32 {
+    let control = match control {
+        SERVICE_CONTROL_STOP => Control::Stop,
+        SERVICE_CONTROL_SHUTDOWN | SERVICE_CONTROL_PRESHUTDOWN => Control::Shutdown,
+        SERVICE_CONTROL_INTERROGATE => return NO_ERROR,
+        _ => return ERROR_CALL_NOT_IMPLEMENTED,
+    };
+    if let Some(controls) = CONTROLS.get() {
+        let _ = controls.send(control);
+    }
+    NO_ERROR
+}
+
+//`exit_code` is one of the exit codes of the process, 0 if the service didn't fail
+fn report_status(state: u32, exit_code: u32) {
+    let status = SERVICE_STATUS {
+        dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+        dwCurrentState: state,
+        dwControlsAccepted: match state {
+            SERVICE_RUNNING => {
+                SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN | SERVICE_ACCEPT_PRESHUTDOWN
+            }
+            _ => 0,
+        },
+        dwWin32ExitCode: if exit_code != 0 {
+            ERROR_SERVICE_SPECIFIC_ERROR
+        } else {
+            NO_ERROR
+        },
+        dwServiceSpecificExitCode: exit_code,
+        dwCheckPoint: 0,
+        dwWaitHint: 0,
+    };
+    let handle: SERVICE_STATUS_HANDLE = STATUS_HANDLE.load(Ordering::Relaxed);
+    unsafe { SetServiceStatus(handle, &status) };
+}
+
+//heartbeat, detection and shutdown of the service. prompts are shown by the agents of the logged
+//on users. without any, the action is carried out once the countdown ends.
+fn monitor(controls: &Receiver<Control>) -> Result<(), Error> {
+    let mut config = Config::load(paths::config_path()).map_err(Error::Config)?;
+    config.state_dir =
+        paths::create_state_dir(&config.state_dir).map_err(|source| Error::StateFile {
+            path: config.state_dir.clone(),
+            source,
+        })?;
+    let config = Arc::new(config);
+    logger::init(
+        config.state_file("restart-fix.log"),
+        config.log_level,
+        false,
+    )
+    .map_err(Error::Logger)?;
+    let dry_run = DRY_RUN.load(Ordering::Relaxed);
+    log::info!(
+        "restart-fix service started{}",
+        if dry_run { " in dry-run mode" } else { "" }
+    );
+
+    let state = Arc::new(AppState::new(config.concurrent_prompts));
+    audit::init(config.state_file("audit.jsonl"), &state);
+    let pause_marker = Arc::new(PauseMarker::new(config.state_file("paused")));
+    state.set_paused(pause_marker.is_set());
+
+    let hostname = winsafe::GetComputerName().unwrap_or_default();
+    let heartbeat = Arc::new(Mutex::new(crate::heartbeat_writer(&config, &hostname)?));
+    let clean_shutdown = CleanShutdownMarker::new(config.state_file("clean_shutdown"));
+    let startup = crate::Startup {
+        last_heartbeat: crate::last_heartbeat(&config, &mut heartbeat.lock().unwrap()),
+        started: SystemClock.now(),
+        simulated: false,
+        shut_down_cleanly: clean_shutdown.take(),
+        dry_run,
+    };
+    let worker = crate::start_heartbeat(
+        &heartbeat,
+        &config,
+        &hostname,
+        MonitorHeartbeat {
+            state: Arc::clone(&state),
+            starting_since: Some(Instant::now()),
+            guard: None,
+        },
+    );
+    let agents = Agents::serve();
+
+    //control commands are sent from the sessions of the users, like to the monitor
+    {
+        let config = Arc::clone(&config);
+        let state = Arc::clone(&state);
+        let pause_marker = Arc::clone(&pause_marker);
+        let agents = Arc::clone(&agents);
+        ipc::serve(Some(PIPE_SDDL), move |command| {
+            crate::handle_command(command, &config, &state, &pause_marker, &agents)
+        });
+    }
+    {
+        let live_config = LiveConfig::new(Arc::clone(&config));
+        let state = Arc::clone(&state);
+        let stopper = worker.stopper();
+        thread::spawn(move || crate::detect(startup, &live_config, &state, stopper, &agents));
+    }
+    {
+        let stopper = worker.stopper();
+        let controls = controls.clone();
+        thread::spawn(move || {
+            if let Ok(control) = controls.recv() {
+                log::info!("service received {control:?}");
+                if control == Control::Shutdown {
+                    if let Err(e) = clean_shutdown.set() {
+                        log::warn!("unable to write clean shutdown marker: {e:#}");
+                    }
+                }
+                stopper.stop(StopReason::Close);
+            }
+        });
+    }
+
+    let reason = worker.wait().map_err(Error::Worker)?;
+    if reason != StopReason::Close {
+        crate::power_off(reason, dry_run, &config)?;
+    }
+    log::info!("restart-fix service stopped");
+    Ok(())
+}
+
+//how a prompt shown by the agents ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Answer {
+    Expired,
+    Cancelled,
+    Confirmed,
+}
+
+//connections of the agents. a prompt is sent to every agent, and the first one to answer decides.
+struct Agents {
+    connected: Mutex<Vec<File>>,
+    //prompt line of the countdown running, sent to agents connecting meanwhile, and its token
+    pending: Mutex<Option<(String, CancellationToken)>>,
+    //whether the pending prompt was confirmed. held while an answer cancels the token, so it is
+    //set by the time the prompt wakes up.
+    confirmed: Mutex<bool>,
+}
+
+impl Agents {
+    fn serve() -> Arc<Self> {
+        let agents = Arc::new(Agents {
+            connected: Mutex::new(Vec::new()),
+            pending: Mutex::new(None),
+            confirmed: Mutex::new(false),
+        });
+        let accepting = Arc::clone(&agents);
+        thread::spawn(move || {
+            let security = match PipeSecurity::new(PIPE_SDDL) {
+                Ok(security) => security,
+                Err(e) => {
+                    log::warn!("unable to secure agent pipe. prompts are not shown: {e:#}");
+                    return;
+                }
+            };
+            loop {
+                match ipc::accept_on(AGENT_PIPE, Some(&security.attributes)) {
+                    Ok(pipe) => accepting.add(pipe),
+                    Err(e) => {
+                        log::warn!("unable to accept agents. prompts are not shown: {e:#}");
+                        return;
+                    }
+                }
+            }
+        });
+        agents
+    }
+
+    fn add(self: &Arc<Self>, pipe: File) {
+        let reader = match pipe.try_clone() {
+            Ok(reader) => reader,
+            Err(e) => {
+                log::warn!("unable to read from agent: {e}");
+                return;
+            }
+        };
+        let mut connected = self.connected.lock().unwrap();
+        if let Some((prompt, _)) = &*self.pending.lock().unwrap() {
+            let _ = (&pipe).write_all(prompt.as_bytes());
+        }
+        connected.push(pipe);
+        let agents = Arc::clone(self);
+        thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                match line.as_deref().map(str::trim) {
+                    Ok("cancel") => agents.answer(false),
+                    Ok("confirm") => agents.answer(true),
+                    Ok(_) => {}
+                    Err(_) => return,
+                }
+            }
+        });
+    }
+
+    fn is_empty(&self) -> bool {
+        self.connected.lock().unwrap().is_empty()
+    }
+
+    //agents that can't be written to have disconnected
+    fn send(&self, line: &str) {
+        self.connected
+            .lock()
+            .unwrap()
+            .retain(|mut pipe| pipe.write_all(line.as_bytes()).is_ok());
+    }
+
+    //only the first answer counts. answers to a prompt that ended do nothing.
+    fn answer(&self, confirmed: bool) {
+        let Some((_, countdown)) = self.pending.lock().unwrap().clone() else {
+            return;
+        };
+        let mut answer = self.confirmed.lock().unwrap();
+        if countdown.cancel() {
+            *answer = confirmed;
+        }
+    }
+
+    //shows `line` on the screens of the agents until `countdown` is cancelled, or until `timeout`
+    //passed if there is one
+    fn show(
+        &self,
+        line: String,
+        countdown: &CancellationToken,
+        timeout: Option<Duration>,
+    ) -> Answer {
+        *self.confirmed.lock().unwrap() = false;
+        {
+            //agents connecting meanwhile get the line from `add` instead
+            let mut connected = self.connected.lock().unwrap();
+            *self.pending.lock().unwrap() = Some((line.clone(), countdown.clone()));
+            connected.retain(|mut pipe| pipe.write_all(line.as_bytes()).is_ok());
+        }
+        let ran_out = match timeout {
+            Some(timeout) => !countdown.wait_timeout(timeout),
+            None => {
+                countdown.wait();
+                false
+            }
+        };
+        let expired = ran_out && countdown.cancel();
+        *self.pending.lock().unwrap() = None;
+        self.send("done\n");
+        match *self.confirmed.lock().unwrap() {
+            _ if expired => Answer::Expired,
+            true => Answer::Confirmed,
+            false => Answer::Cancelled,
+        }
+    }
+
+    //show prompt `id`, then those queued behind it as long as they are cancelled. the one that
+    //expires or is confirmed is carried out.
+    fn prompts(&self, mut id: PromptId, mut prompt: Prompt, state: &AppState) {
+        loop {
+            match self.ask(&prompt, state) {
+                Some(true) => {
+                    state.countdown_ended(false);
+                    if state.controller().expired(id) {
+                        action::carry_out(prompt.action, &prompt.settings, &prompt.stopper);
+                    }
+                    return;
+                }
+                Some(false) => {
+                    state.countdown_ended(true);
+                    log::info!("shutdown cancelled");
+                }
+                //ends like a cancelled prompt, without a countdown to report
+                None => log::info!("nothing is done"),
+            }
+            match action::next_prompt(id, state) {
+                Some(next) => (id, prompt) = next,
+                None => return,
+            }
+        }
+    }
+
+    //whether the action of `prompt` is carried out, or None if it couldn't be prompted for
+    fn ask(&self, prompt: &Prompt, state: &AppState) -> Option<bool> {
+        let (action, settings) = (prompt.action, &prompt.settings);
+        let carry_out = match settings.prompt_mode {
+            PromptMode::Silent => {
+                log::warn!(
+                    "silent mode: {action:?} in {}s without dialog(cause: {})",
+                    settings.silent_delay.as_secs(),
+                    settings.cause.as_str()
+                );
+                let countdown = state.start_countdown(Some(settings.silent_delay));
+                !countdown.wait_timeout(settings.silent_delay) && countdown.cancel()
+            }
+            PromptMode::Countdown => {
+                let countdown = state.start_countdown(Some(settings.timeout));
+                if self.is_empty() {
+                    prompt_console(action, settings.timeout, countdown.clone());
+                }
+                let line = format!(
+                    "prompt {} {}\n",
+                    action.as_str(),
+                    settings.timeout.as_secs()
+                );
+                self.show(line, &countdown, Some(settings.timeout)) == Answer::Expired
+            }
+            //nobody may be there. only a user who says yes is powered off.
+            PromptMode::Confirm => {
+                if self.is_empty() {
+                    log::info!("no agent is connected to confirm {action:?}");
+                    return None;
+                }
+                let countdown = state.start_countdown(None);
+                let line = format!("confirm {}\n", action.as_str());
+                self.show(line, &countdown, None) == Answer::Confirmed
+            }
+        };
+        Some(carry_out)
+    }
+}
+
+//prompts of the service are shown by the agents, and the service powers off once they end
+impl Frontend for Arc<Agents> {
+    fn lock_screen(&self, dry_run: bool) {
+        if dry_run {
+            log::info!(
+                "dry run: would have locked the workstation and turned the displays off now"
+            );
+            return;
+        }
+        self.send("lock\n");
+    }
+
+    fn show_restart_notice(&self) {
+        self.send("notice\n");
+    }
+
+    fn prompt(
+        &self,
+        action: Action,
+        settings: &ActionSettings,
+        state: &Arc<AppState>,
+        stopper: Stopper,
+    ) {
+        let prompt = Prompt {
+            action,
+            settings: settings.clone(),
+            stopper,
+        };
+        action::request_prompt(prompt, state, |id, prompt| self.prompts(id, prompt, state));
+    }
+
+    fn test_prompt(&self, timeout: Duration, _pause_on_input: Duration, state: &AppState) {
+        let countdown = state.start_countdown(Some(timeout));
+        let line = format!(
+            "prompt {} {}\n",
+            Action::Shutdown.as_str(),
+            timeout.as_secs()
+        );
+        let agents = Arc::clone(self);
+        thread::spawn(move || agents.show(line, &countdown, Some(timeout)));
+    }
+}
+
+//without an agent, e.g. autostart is disabled or it hasn't started yet, the countdown is shown in
+//the console session by windows itself
+fn prompt_console(action: Action, timeout: Duration, countdown: CancellationToken) {
+    let Some(session_id) = session::console_user_session() else {
+        log::info!(
+            "no user is logged on to cancel. {action:?} in {}s",
+            timeout.as_secs()
+        );
+        return;
+    };
+    let locale = Locale::current();
+    let text = locale.other_session_text(action, timeout, true);
+    log::info!("no agent is connected. countdown is shown in console session {session_id}");
+    thread::spawn(move || {
+        match session::send_message(session_id, &locale.shutdown_title(), &text, timeout, true) {
+            Ok(true) => {
+                countdown.cancel();
+            }
+            Ok(false) => {}
+            Err(e) => log::warn!("unable to show countdown in console session: {e}"),
+        }
+    });
+}
+
+//install the service for this executable and start it. needs administrator rights.
+pub fn install() -> anyhow::Result<()> {
+    let manager = ServiceHandle::manager(SC_MANAGER_CREATE_SERVICE)?;
+    let command = to_wide(&format!("\"{}\" --service", env::current_exe()?.display()));
+    let (name, display_name) = (to_wide(NAME), to_wide(DISPLAY_NAME));
+    let service = unsafe {
+        CreateServiceW(
+            manager.0,
+            name.as_ptr(),
+            display_name.as_ptr(),
+            SERVICE_ALL_ACCESS,
+            SERVICE_WIN32_OWN_PROCESS,
+            SERVICE_AUTO_START,
+            SERVICE_ERROR_NORMAL,
+            command.as_ptr(),
+            ptr::null(),
+            ptr::null_mut(),
+            ptr::null(),
+            //LocalSystem
+            ptr::null(),
+            ptr::null(),
+        )
+    };
+    let service = ServiceHandle::checked(service).map_err(|e| match e.raw_os_error() {
+        Some(code) if code == ERROR_SERVICE_EXISTS as i32 => {
+            anyhow::anyhow!("service is already installed")
+        }
+        _ => e.into(),
+    })?;
+    let mut description = to_wide(DESCRIPTION);
+    let description = SERVICE_DESCRIPTIONW {
+        lpDescription: description.as_mut_ptr(),
+    };
+    unsafe {
+        ChangeServiceConfig2W(
+            service.0,
+            SERVICE_CONFIG_DESCRIPTION,
+            &description as *const _ as *const c_void,
+        )
+    };
+    if unsafe { StartServiceW(service.0, 0, ptr::null()) } == 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+//stop and remove the service. needs administrator rights.
+pub fn uninstall() -> anyhow::Result<()> {
+    let service = match ServiceHandle::service(SERVICE_STOP | SERVICE_QUERY_STATUS | DELETE) {
+        Ok(service) => service,
+        Err(e) if e.raw_os_error() == Some(ERROR_SERVICE_DOES_NOT_EXIST as i32) => {
+            anyhow::bail!("service is not installed")
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut status = unsafe { mem::zeroed::<SERVICE_STATUS>() };
+    //already stopped if it fails
+    if unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) } != 0 {
+        //heartbeat files stay locked until the process is gone
+        for _ in 0..STOP_POLLS {
+            thread::sleep(STOP_POLL_INTERVAL);
+            let queried = unsafe { QueryServiceStatus(service.0, &mut status) };
+            if queried == 0 || status.dwCurrentState == SERVICE_STOPPED {
+                break;
+            }
+        }
+    }
+    if unsafe { DeleteService(service.0) } == 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+pub fn is_installed() -> bool {
+    ServiceHandle::service(SERVICE_QUERY_STATUS).is_ok()
+}
+
+//an instance started at logon becomes an agent of the running service
+pub fn is_running() -> bool {
+    let Ok(service) = ServiceHandle::service(SERVICE_QUERY_STATUS) else {
+        return false;
+    };
+    let mut status = unsafe { mem::zeroed::<SERVICE_STATUS>() };
+    let queried = unsafe { QueryServiceStatus(service.0, &mut status) };
+    queried != 0 && status.dwCurrentState == SERVICE_RUNNING
+}
+
+//closed on drop
+struct ServiceHandle(SC_HANDLE);
+
+impl ServiceHandle {
+    fn checked(handle: SC_HANDLE) -> io::Result<Self> {
+        match handle {
+            0 => Err(io::Error::last_os_error()),
+            handle => Ok(ServiceHandle(handle)),
+        }
+    }
+
+    fn manager(access: u32) -> io::Result<Self> {
+        Self::checked(unsafe { OpenSCManagerW(ptr::null(), ptr::null(), access) })
+    }
+
+    //restart-fix service
+    fn service(access: u32) -> io::Result<Self> {
+        let manager = Self::manager(SC_MANAGER_CONNECT)?;
+        let name = to_wide(NAME);
+        Self::checked(unsafe { OpenServiceW(manager.0, name.as_ptr(), access) })
+    }
+}
+
+impl Drop for ServiceHandle {
+    fn drop(&mut self) {
+        unsafe { CloseServiceHandle(self.0) };
+    }
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(Some(0)).collect()
+}
@@ -0,0 +1,195 @@
+use std::{io, ptr, time::Duration};
+
+use windows_sys::Win32::{
+    System::{
+        RemoteDesktop::{
+            WTSActive, WTSClientProtocolType, WTSEnumerateSessionsW, WTSFreeMemory,
+            WTSGetActiveConsoleSessionId, WTSQuerySessionInformationW, WTSSendMessageW,
+            WTSSessionInfo, WTSUserName, WTSINFOW, WTS_CURRENT_SERVER_HANDLE, WTS_CURRENT_SESSION,
+            WTS_SESSION_INFOW,
+        },
+        SystemInformation::GetTickCount,
+    },
+    UI::{
+        Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+        WindowsAndMessaging::{
+            IDYES, MB_ICONWARNING, MB_OK, MB_SETFOREGROUND, MB_TOPMOST, MB_YESNO,
+        },
+    },
+};
+
+//WTS_PROTOCOL_TYPE_RDP
+const PROTOCOL_RDP: u16 = 2;
+
+//information of the session restart-fix runs in
+fn session_info() -> io::Result<WTSINFOW> {
+    let mut buffer = ptr::null_mut();
+    let mut len = 0;
+    let ok = unsafe {
+        WTSQuerySessionInformationW(
+            WTS_CURRENT_SERVER_HANDLE,
+            WTS_CURRENT_SESSION,
+            WTSSessionInfo,
+            &mut buffer,
+            &mut len,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let info = unsafe { *buffer.cast::<WTSINFOW>() };
+    unsafe { WTSFreeMemory(buffer.cast()) };
+    Ok(info)
+}
+
+//time since the user logged on to the current session
+pub fn time_since_logon() -> io::Result<Duration> {
+    let info = session_info()?;
+    //both are FILETIME in 100ns units taken from the same clock
+    let elapsed = info.CurrentTime.saturating_sub(info.LogonTime).max(0) as u64;
+    Ok(Duration::from_nanos(elapsed * 100))
+}
+
+//time since the last keyboard or mouse input in the current session
+pub fn time_since_input() -> io::Result<Duration> {
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    if unsafe { GetLastInputInfo(&mut info) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    //both are tick counts in milliseconds, which wrap around after 49.7 days
+    let elapsed = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+    Ok(Duration::from_millis(elapsed.into()))
+}
+
+//id of an active session that is connected over remote desktop, if any
+pub fn active_remote_session() -> io::Result<Option<u32>> {
+    let mut sessions: *mut WTS_SESSION_INFOW = ptr::null_mut();
+    let mut count = 0;
+    let ok = unsafe {
+        WTSEnumerateSessionsW(WTS_CURRENT_SERVER_HANDLE, 0, 1, &mut sessions, &mut count)
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let found = unsafe { std::slice::from_raw_parts(sessions, count as usize) }
+        .iter()
+        .filter(|session| session.State == WTSActive)
+        .map(|session| session.SessionId)
+        .find(|&id| protocol(id).is_ok_and(|protocol| protocol == PROTOCOL_RDP));
+    unsafe { WTSFreeMemory(sessions.cast()) };
+    Ok(found)
+}
+
+fn protocol(session_id: u32) -> io::Result<u16> {
+    let mut buffer = ptr::null_mut();
+    let mut len = 0;
+    let ok = unsafe {
+        WTSQuerySessionInformationW(
+            WTS_CURRENT_SERVER_HANDLE,
+            session_id,
+            WTSClientProtocolType,
+            &mut buffer,
+            &mut len,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let protocol = unsafe { *buffer.cast::<u16>() };
+    unsafe { WTSFreeMemory(buffer.cast()) };
+    Ok(protocol)
+}
+
+//active sessions that a user is logged on to, except the one restart-fix runs in
+pub fn other_user_sessions() -> io::Result<Vec<u32>> {
+    let own = session_info()?.SessionId;
+    let mut sessions: *mut WTS_SESSION_INFOW = ptr::null_mut();
+    let mut count = 0;
+    let ok = unsafe {
+        WTSEnumerateSessionsW(WTS_CURRENT_SERVER_HANDLE, 0, 1, &mut sessions, &mut count)
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let found = unsafe { std::slice::from_raw_parts(sessions, count as usize) }
+        .iter()
+        .filter(|session| session.State == WTSActive && session.SessionId != own)
+        .map(|session| session.SessionId)
+        .filter(|&id| user_name(id).is_ok_and(|name| !name.is_empty()))
+        .collect();
+    unsafe { WTSFreeMemory(sessions.cast()) };
+    Ok(found)
+}
+
+//session at the physical console, if a user is logged on to it(not at the logon screen)
+pub fn console_user_session() -> Option<u32> {
+    let session_id = unsafe { WTSGetActiveConsoleSessionId() };
+    //no session is attached to the console, e.g. while sessions are switched
+    if session_id == u32::MAX {
+        return None;
+    }
+    user_name(session_id)
+        .is_ok_and(|name| !name.is_empty())
+        .then_some(session_id)
+}
+
+fn user_name(session_id: u32) -> io::Result<String> {
+    let mut buffer = ptr::null_mut();
+    let mut len = 0;
+    let ok = unsafe {
+        WTSQuerySessionInformationW(
+            WTS_CURRENT_SERVER_HANDLE,
+            session_id,
+            WTSUserName,
+            &mut buffer,
+            &mut len,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    //length in bytes, including the terminating null
+    let chars = unsafe { std::slice::from_raw_parts(buffer, len as usize / 2) };
+    let name = String::from_utf16_lossy(chars)
+        .trim_end_matches('\0')
+        .to_owned();
+    unsafe { WTSFreeMemory(buffer.cast()) };
+    Ok(name)
+}
+
+//message box shown in another session by a service, which has no desktop of its own. blocks until
+//it is answered or `timeout` passes. with `ask` it has yes and no, and returns true only if yes was
+//pressed. dismissing it or letting it time out never counts as an answer.
+pub fn send_message(
+    session_id: u32,
+    title: &str,
+    text: &str,
+    timeout: Duration,
+    ask: bool,
+) -> io::Result<bool> {
+    let title: Vec<u16> = title.encode_utf16().collect();
+    let text: Vec<u16> = text.encode_utf16().collect();
+    let mut response = 0;
+    let ok = unsafe {
+        WTSSendMessageW(
+            WTS_CURRENT_SERVER_HANDLE,
+            session_id,
+            title.as_ptr(),
+            (title.len() * 2) as u32,
+            text.as_ptr(),
+            (text.len() * 2) as u32,
+            if ask { MB_YESNO } else { MB_OK } | MB_ICONWARNING | MB_TOPMOST | MB_SETFOREGROUND,
+            //zero would wait for an answer forever
+            timeout.as_secs().clamp(1, u32::MAX as u64) as u32,
+            &mut response,
+            1,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(response == IDYES)
+}
@@ -0,0 +1,92 @@
+use std::{io, ptr};
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0},
+    Security::{
+        Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1},
+        PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES,
+    },
+    System::{
+        Memory::LocalFree,
+        Threading::{
+            CreateEventExW, SetEvent, WaitForSingleObject, CREATE_EVENT_MANUAL_RESET,
+            EVENT_MODIFY_STATE, INFINITE, SYNCHRONIZATION_SYNCHRONIZE,
+        },
+    },
+};
+
+//full access for system and administrators. interactive users may wait on and set the event.
+const SECURITY_DESCRIPTOR: &str = "D:(A;;GA;;;SY)(A;;GA;;;BA)(A;;0x100002;;;IU)";
+
+//with fast user switching every logged on user runs own monitor, and each of them prompts.
+//countdowns of the same boot share a global event, so the first user who cancels in any session
+//cancels shutdown for all of them instead of whichever dialog happens to be answered last.
+pub struct SessionArbiter {
+    event: HANDLE,
+}
+
+unsafe impl Send for SessionArbiter {}
+unsafe impl Sync for SessionArbiter {}
+
+impl SessionArbiter {
+    pub fn open(boot_id: u32) -> io::Result<Self> {
+        let name: Vec<u16> = format!(r"Global\restart-fix-cancelled-{boot_id}")
+            .encode_utf16()
+            .chain(Some(0))
+            .collect();
+        let sddl: Vec<u16> = SECURITY_DESCRIPTOR.encode_utf16().chain(Some(0)).collect();
+        let mut descriptor: PSECURITY_DESCRIPTOR = ptr::null_mut();
+        let converted = unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                sddl.as_ptr(),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                ptr::null_mut(),
+            )
+        };
+        if converted == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let attributes = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: descriptor,
+            bInheritHandle: 0,
+        };
+        //event created by other session is opened with the same call
+        let event = unsafe {
+            CreateEventExW(
+                &attributes,
+                name.as_ptr(),
+                CREATE_EVENT_MANUAL_RESET,
+                SYNCHRONIZATION_SYNCHRONIZE | EVENT_MODIFY_STATE,
+            )
+        };
+        let result = if event == 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(Self { event })
+        };
+        unsafe { LocalFree(descriptor as isize) };
+        result
+    }
+
+    //true if shutdown of this boot was cancelled in any session
+    pub fn is_cancelled(&self) -> bool {
+        unsafe { WaitForSingleObject(self.event, 0) == WAIT_OBJECT_0 }
+    }
+
+    pub fn cancel(&self) {
+        unsafe { SetEvent(self.event) };
+    }
+
+    //block until shutdown is cancelled in any session
+    pub fn wait_cancelled(&self) {
+        unsafe { WaitForSingleObject(self.event, INFINITE) };
+    }
+}
+
+impl Drop for SessionArbiter {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.event) };
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    io, ptr,
+    sync::{Condvar, Mutex},
+};
+
+use windows_sys::Win32::{
+    Foundation::HWND,
+    System::RemoteDesktop::{
+        WTSFreeMemory, WTSQuerySessionInformationW, WTSRegisterSessionNotification,
+        WTSSessionInfoEx, NOTIFY_FOR_THIS_SESSION, WTSINFOEXW, WTS_CURRENT_SERVER_HANDLE,
+        WTS_CURRENT_SESSION, WTS_SESSIONSTATE_LOCK,
+    },
+};
+
+//wParam of WM_WTSSESSION_CHANGE
+const WTS_SESSION_LOCK: usize = 0x7;
+const WTS_SESSION_UNLOCK: usize = 0x8;
+
+//whether the workstation is locked. kept up to date by the hidden window.
+static LOCKED: Mutex<bool> = Mutex::new(false);
+static CHANGED: Condvar = Condvar::new();
+
+//subscribe `hwnd` to lock and unlock of the current session. window then receives
+//WM_WTSSESSION_CHANGE, which is passed to changed().
+pub fn register(hwnd: HWND) -> io::Result<()> {
+    //monitor may be relaunched by watchdog while the workstation is locked
+    match query_locked() {
+        Ok(locked) => set(locked),
+        Err(e) => log::warn!("unable to query whether workstation is locked: {e}"),
+    }
+    if unsafe { WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+//handle wParam of WM_WTSSESSION_CHANGE
+pub fn changed(event: usize) {
+    match event {
+        WTS_SESSION_LOCK => set(true),
+        WTS_SESSION_UNLOCK => set(false),
+        _ => {}
+    }
+}
+
+pub fn is_locked() -> bool {
+    *LOCKED.lock().unwrap()
+}
+
+pub fn wait_until_unlocked() {
+    let locked = LOCKED.lock().unwrap();
+    drop(CHANGED.wait_while(locked, |locked| *locked));
+}
+
+fn set(locked: bool) {
+    *LOCKED.lock().unwrap() = locked;
+    CHANGED.notify_all();
+}
+
+fn query_locked() -> io::Result<bool> {
+    let mut buffer = ptr::null_mut();
+    let mut len = 0;
+    let ok = unsafe {
+        WTSQuerySessionInformationW(
+            WTS_CURRENT_SERVER_HANDLE,
+            WTS_CURRENT_SESSION,
+            WTSSessionInfoEx,
+            &mut buffer,
+            &mut len,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let info = unsafe { *buffer.cast::<WTSINFOEXW>() };
+    unsafe { WTSFreeMemory(buffer.cast()) };
+    let flags = unsafe { info.Data.WTSInfoExLevel1.SessionFlags };
+    Ok(flags as u32 == WTS_SESSIONSTATE_LOCK)
+}
@@ -0,0 +1,185 @@
+use std::{cell::Cell, fs, path::Path, rc::Rc, thread, time::Duration};
+
+use winsafe::{gui, msg, prelude::*};
+
+use crate::{autostart, i18n::Locale};
+
+//offered thresholds in seconds. 100s is the default of `threshold`.
+const THRESHOLDS: [u64; 4] = [60, 100, 300, 600];
+const DEFAULT_THRESHOLD: u32 = 1;
+//offered actions, as policy names them
+const ACTIONS: [&str; 3] = ["shutdown", "hibernate", "notify-only"];
+
+//essential settings picked by the user on first run
+pub struct Choices {
+    pub threshold: Duration,
+    pub action: &'static str,
+    pub autostart: bool,
+    //opted in to anonymous telemetry. unchecked unless the user checks it.
+    pub telemetry: bool,
+}
+
+impl Choices {
+    //write initial config file. crashes and power losses keep their default of taking no action.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut config = format!(
+            "# written by the setup wizard. every setting is described in the README.\r\nthreshold = {}\r\ntelemetry = {}\r\n",
+            self.threshold.as_secs(),
+            self.telemetry
+        );
+        for cause in [
+            "windows-update",
+            "user-initiated",
+            "scheduled-task",
+            "unknown",
+        ] {
+            config.push_str(&format!(
+                "\r\n[policy.{cause}]\r\naction = \"{}\"\r\n",
+                self.action
+            ));
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, config)?;
+        if self.autostart {
+            autostart::register()?;
+        }
+        Ok(())
+    }
+}
+
+//explain what restart-fix does before it may power off the computer and ask for the essential
+//settings. returns None if the user chose to exit.
+pub fn run(locale: Locale) -> Option<Choices> {
+    //window runs its own message loop, apart from the hidden window of the monitor
+    thread::spawn(move || show(locale)).join().unwrap_or(None)
+}
+
+fn show(locale: Locale) -> Option<Choices> {
+    let wnd = gui::WindowMain::new(gui::WindowMainOpts {
+        title: locale.wizard_title(),
+        size: (460, 335),
+        ..Default::default()
+    });
+    let _intro = gui::Label::new(
+        &wnd,
+        gui::LabelOpts {
+            text: locale.wizard_intro(),
+            position: (20, 20),
+            size: (420, 100),
+            ..Default::default()
+        },
+    );
+    let _threshold_label = gui::Label::new(
+        &wnd,
+        gui::LabelOpts {
+            text: locale.wizard_threshold(),
+            position: (20, 133),
+            size: (220, 20),
+            ..Default::default()
+        },
+    );
+    let threshold = gui::ComboBox::new(
+        &wnd,
+        gui::ComboBoxOpts {
+            position: (250, 130),
+            width: 190,
+            items: THRESHOLDS
+                .iter()
+                .map(|secs| locale.wizard_seconds(*secs))
+                .collect(),
+            selected_item: Some(DEFAULT_THRESHOLD),
+            ..Default::default()
+        },
+    );
+    let _action_label = gui::Label::new(
+        &wnd,
+        gui::LabelOpts {
+            text: locale.wizard_action(),
+            position: (20, 168),
+            size: (220, 20),
+            ..Default::default()
+        },
+    );
+    let action = gui::ComboBox::new(
+        &wnd,
+        gui::ComboBoxOpts {
+            position: (250, 165),
+            width: 190,
+            items: locale.wizard_actions().to_vec(),
+            selected_item: Some(0),
+            ..Default::default()
+        },
+    );
+    let autostart = gui::CheckBox::new(
+        &wnd,
+        gui::CheckBoxOpts {
+            text: locale.wizard_autostart(),
+            position: (20, 205),
+            size: (420, 20),
+            check_state: gui::CheckState::Checked,
+            ..Default::default()
+        },
+    );
+    let telemetry = gui::CheckBox::new(
+        &wnd,
+        gui::CheckBoxOpts {
+            text: locale.wizard_telemetry(),
+            position: (20, 235),
+            size: (420, 20),
+            check_state: gui::CheckState::Unchecked,
+            ..Default::default()
+        },
+    );
+    let start = gui::Button::new(
+        &wnd,
+        gui::ButtonOpts {
+            text: locale.wizard_start(),
+            position: (220, 280),
+            width: 130,
+            ..Default::default()
+        },
+    );
+    let exit = gui::Button::new(
+        &wnd,
+        gui::ButtonOpts {
+            text: locale.wizard_exit(),
+            position: (360, 280),
+            width: 80,
+            ..Default::default()
+        },
+    );
+
+    let choices = Rc::new(Cell::new(None));
+    {
+        let wnd = wnd.clone();
+        let choices = Rc::clone(&choices);
+        start.on().bn_clicked(move || {
+            let threshold = threshold
+                .items()
+                .selected_index()
+                .unwrap_or(DEFAULT_THRESHOLD);
+            let action = action.items().selected_index().unwrap_or(0);
+            choices.set(Some(Choices {
+                threshold: Duration::from_secs(THRESHOLDS[threshold as usize]),
+                action: ACTIONS[action as usize],
+                autostart: autostart.is_checked(),
+                telemetry: telemetry.is_checked(),
+            }));
+            wnd.hwnd().PostMessage(msg::wm::Close {})?;
+            Ok(())
+        });
+    }
+    {
+        let wnd = wnd.clone();
+        exit.on().bn_clicked(move || {
+            wnd.hwnd().PostMessage(msg::wm::Close {})?;
+            Ok(())
+        });
+    }
+    if let Err(e) = wnd.run_main(None) {
+        log::warn!("setup wizard failed: {e}");
+    }
+    choices.take()
+}
@@ -0,0 +1,28 @@
+use std::{io, iter};
+
+use windows_sys::Win32::{
+    Foundation::HWND,
+    System::Shutdown::{ShutdownBlockReasonCreate, ShutdownBlockReasonDestroy},
+};
+
+//keeps windows from ending the session while alive. reason is listed on the screen of apps
+//preventing shutdown, should the user look at it.
+pub struct ShutdownBlock {
+    hwnd: HWND,
+}
+
+impl ShutdownBlock {
+    pub fn create(hwnd: HWND, reason: &str) -> io::Result<Self> {
+        let reason: Vec<u16> = reason.encode_utf16().chain(iter::once(0)).collect();
+        if unsafe { ShutdownBlockReasonCreate(hwnd, reason.as_ptr()) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { hwnd })
+    }
+}
+
+impl Drop for ShutdownBlock {
+    fn drop(&mut self) {
+        unsafe { ShutdownBlockReasonDestroy(self.hwnd) };
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use restart_fix_core::parse_timestamp;
+
+// Number of shutdowns kept in the file. Far more than any sensible limit counts.
+static MAX_ENTRIES: usize = 100;
+
+//automatic shutdowns and hibernations carried out by restart-fix, oldest first.
+//each line is the unix timestamp of one of them.
+#[derive(Clone)]
+pub struct ShutdownLog {
+    path: PathBuf,
+}
+
+impl ShutdownLog {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    pub fn record(&self, time: DateTime<Utc>) -> anyhow::Result<()> {
+        let mut times = self.read()?;
+        if times.len() >= MAX_ENTRIES {
+            times.push(time);
+            let text: String = times[times.len() - MAX_ENTRIES..]
+                .iter()
+                .map(|time| format!("{}\n", time.timestamp()))
+                .collect();
+            fs::write(&self.path, text)?;
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", time.timestamp())?;
+        Ok(())
+    }
+
+    //shutdowns at or after `time`. shutdowns recorded in the future(clock turned back since) count
+    //as well, so a clock that jumps around never lifts the limit early.
+    pub fn since(&self, time: DateTime<Utc>) -> anyhow::Result<usize> {
+        Ok(self.read()?.into_iter().filter(|t| *t >= time).count())
+    }
+
+    //missing file is empty. unreadable lines(e.g. cut off by power loss) are skipped.
+    fn read(&self) -> anyhow::Result<Vec<DateTime<Utc>>> {
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(text
+            .lines()
+            .filter_map(|line| parse_timestamp(line.trim()).ok())
+            .collect())
+    }
+}
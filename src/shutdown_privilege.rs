@@ -0,0 +1,68 @@
+use std::{io, mem, ptr};
+
+use windows_sys::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::{CloseHandle, GetLastError, ERROR_NOT_ALL_ASSIGNED, LUID},
+        Security::{
+            AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES,
+            SE_PRIVILEGE_ENABLED, SE_SHUTDOWN_NAME, SE_SYSTEM_ENVIRONMENT_NAME,
+            TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+        },
+        System::Threading::{GetCurrentProcess, OpenProcessToken},
+    },
+};
+
+//enable SeShutdownPrivilege of this process, which shutting down and hibernating require.
+//interactive users hold it disabled. accounts denied the "Shut down the system" user right by
+//policy don't hold it at all, which fails with ERROR_NOT_ALL_ASSIGNED.
+pub fn enable() -> io::Result<()> {
+    enable_privilege(SE_SHUTDOWN_NAME)
+}
+
+//enable SeSystemEnvironmentPrivilege, which writing uefi variables requires. only administrators
+//hold it.
+pub fn enable_system_environment() -> io::Result<()> {
+    enable_privilege(SE_SYSTEM_ENVIRONMENT_NAME)
+}
+
+fn enable_privilege(name: PCWSTR) -> io::Result<()> {
+    let mut token = 0;
+    if unsafe {
+        OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+            &mut token,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let result = adjust(token, name);
+    unsafe { CloseHandle(token) };
+    result
+}
+
+fn adjust(token: isize, name: PCWSTR) -> io::Result<()> {
+    let mut luid: LUID = unsafe { mem::zeroed() };
+    if unsafe { LookupPrivilegeValueW(ptr::null(), name, &mut luid) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let privileges = TOKEN_PRIVILEGES {
+        PrivilegeCount: 1,
+        Privileges: [LUID_AND_ATTRIBUTES {
+            Luid: luid,
+            Attributes: SE_PRIVILEGE_ENABLED,
+        }],
+    };
+    if unsafe { AdjustTokenPrivileges(token, 0, &privileges, 0, ptr::null_mut(), ptr::null_mut()) }
+        == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    //succeeds even if the privilege isn't held. only the last error tells.
+    match unsafe { GetLastError() } {
+        ERROR_NOT_ALL_ASSIGNED => Err(io::Error::from_raw_os_error(ERROR_NOT_ALL_ASSIGNED as i32)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use windows_sys::Win32::System::Shutdown::{
+    SHTDN_REASON_FLAG_PLANNED, SHTDN_REASON_MAJOR_APPLICATION, SHTDN_REASON_MINOR_OTHER,
+};
+
+use crate::event_log::{self, Event};
+
+//User32 logs event 1074 whenever a process initiates shutdown or restart
+pub const SHUTDOWN_INITIATED_QUERY: &str = "*[System[Provider[@Name='User32'] and EventID=1074]]";
+//EventLog service logs event 6008 on boot when the previous shutdown was unexpected
+const DIRTY_SHUTDOWN_QUERY: &str = "*[System[Provider[@Name='EventLog'] and EventID=6008]]";
+//reason of shutdowns initiated by restart-fix, "Application: Other (Planned)"(0x80040000).
+//event log and reliability monitor record it as an application decision rather than an update.
+pub const RESTART_FIX_REASON_CODE: u32 =
+    SHTDN_REASON_MAJOR_APPLICATION | SHTDN_REASON_MINOR_OTHER | SHTDN_REASON_FLAG_PLANNED;
+//shutdowns initiated in the current boot are skipped, so a few more events are looked at
+const MAX_EVENTS: usize = 10;
+
+//shutdown or restart initiated before the current boot
+#[derive(Debug)]
+pub struct ShutdownRecord {
+    pub time: DateTime<Utc>,
+    //image path of initiating process, e.g. "C:\Windows\system32\winlogon.exe (HOSTNAME)"
+    pub process: String,
+    pub reason: String,
+    //SHTDN_REASON_* code passed to InitiateSystemShutdownEx
+    pub reason_code: u32,
+    pub user: String,
+}
+
+impl ShutdownRecord {
+    //record of event 1074
+    pub fn of_event(event: &Event) -> Option<Self> {
+        Some(Self {
+            time: event.time_created()?,
+            process: event.data("param1").unwrap_or_default(),
+            reason: event.data("param3").unwrap_or_default(),
+            reason_code: parse_reason_code(&event.data("param4")?)?,
+            user: event.data("param7").unwrap_or_default(),
+        })
+    }
+
+    //file name of initiating process, e.g. "winlogon.exe"
+    pub fn process_name(&self) -> &str {
+        let path = self.process.split(" (").next().unwrap_or_default();
+        path.rsplit('\\').next().unwrap_or(path).trim()
+    }
+}
+
+//returns the last shutdown initiated before the computer was started
+pub fn previous_shutdown(boot_time: DateTime<Utc>) -> anyhow::Result<Option<ShutdownRecord>> {
+    Ok(recent_shutdowns(MAX_EVENTS)?
+        .into_iter()
+        .find(|record| record.time < boot_time))
+}
+
+//shutdowns and restarts initiated by processes, newest first
+pub fn recent_shutdowns(max: usize) -> anyhow::Result<Vec<ShutdownRecord>> {
+    let events = event_log::query_newest("System", SHUTDOWN_INITIATED_QUERY, max)?;
+    Ok(events.iter().filter_map(ShutdownRecord::of_event).collect())
+}
+
+//times of sessions that ended without clean shutdown(crash, power loss or hang), newest first
+pub fn recent_dirty_shutdowns(max: usize) -> anyhow::Result<Vec<DateTime<Utc>>> {
+    let events = event_log::query_newest("System", DIRTY_SHUTDOWN_QUERY, max)?;
+    Ok(events
+        .iter()
+        .filter_map(|event| event.time_created())
+        .collect())
+}
+
+//reason code is logged as hex string, e.g. "0x80020003"
+fn parse_reason_code(code: &str) -> Option<u32> {
+    let code = code.trim();
+    let hex = code
+        .strip_prefix("0x")
+        .or_else(|| code.strip_prefix("0X"))
+        .unwrap_or(code);
+    u32::from_str_radix(hex, 16).ok()
+}
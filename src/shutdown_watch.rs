@@ -0,0 +1,81 @@
+use std::{io, ptr, sync::Arc};
+
+use windows_sys::Win32::System::Shutdown::AbortSystemShutdownW;
+
+use crate::{
+    config_watch::LiveConfig,
+    event_log::{self, Subscription},
+    policy::Cause,
+    shutdown_privilege,
+    shutdown_reason::{self, ShutdownRecord, RESTART_FIX_REASON_CODE},
+};
+
+//EventLog logs event 6006 when it stops, which is the last thing a shutdown does before going down
+const EVENT_LOG_STOPPED_QUERY: &str = "*[System[Provider[@Name='EventLog'] and EventID=6006]]";
+
+//keeps watching until dropped
+pub struct ShutdownWatch {
+    _subscriptions: [Subscription; 2],
+}
+
+//react to shutdowns and restarts as they are initiated, not only after the next boot: the
+//initiator is logged, `on_shutdown` is called(e.g. to record a final heartbeat) and restarts of
+//windows update are aborted with abort_update_restarts. shutdowns of restart-fix itself are left
+//alone.
+pub fn watch(
+    config: Arc<LiveConfig>,
+    on_shutdown: impl Fn() + Send + Sync + 'static,
+) -> anyhow::Result<ShutdownWatch> {
+    let on_shutdown = Arc::new(on_shutdown);
+    let initiated = {
+        let on_shutdown = Arc::clone(&on_shutdown);
+        event_log::subscribe(
+            "System",
+            shutdown_reason::SHUTDOWN_INITIATED_QUERY,
+            move |event| {
+                let Some(record) = ShutdownRecord::of_event(&event) else {
+                    return;
+                };
+                if record.reason_code == RESTART_FIX_REASON_CODE {
+                    return;
+                }
+                initiated(&record, config.get().abort_update_restarts);
+                on_shutdown();
+            },
+        )?
+    };
+    let stopping = event_log::subscribe("System", EVENT_LOG_STOPPED_QUERY, move |_| {
+        log::info!("event log is stopping. computer is going down");
+        on_shutdown();
+    })?;
+    Ok(ShutdownWatch {
+        _subscriptions: [initiated, stopping],
+    })
+}
+
+fn initiated(record: &ShutdownRecord, abort_update_restarts: bool) {
+    let cause = Cause::of_shutdown(Some(record));
+    log::warn!(
+        "shutdown or restart initiated by {}(user: {}, reason: {}, cause: {})",
+        record.process,
+        record.user,
+        record.reason,
+        cause.as_str()
+    );
+    if !abort_update_restarts || cause != Cause::WindowsUpdate {
+        return;
+    }
+    match abort() {
+        Ok(()) => log::warn!("restart of windows update is aborted"),
+        //shutdowns without grace period(e.g. ExitWindowsEx) can't be aborted
+        Err(e) => log::warn!("unable to abort restart of windows update: {e}"),
+    }
+}
+
+fn abort() -> io::Result<()> {
+    shutdown_privilege::enable()?;
+    if unsafe { AbortSystemShutdownW(ptr::null()) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
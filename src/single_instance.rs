@@ -0,0 +1,35 @@
+use std::io;
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, HANDLE},
+    System::Threading::CreateMutexW,
+};
+
+const MUTEX_NAME: &str = r"Local\restart-fix";
+
+//named mutex owned by the running monitor. released when dropped.
+pub struct SingleInstance {
+    mutex: HANDLE,
+}
+
+impl SingleInstance {
+    //returns None if another monitor is already running in this session
+    pub fn acquire() -> io::Result<Option<Self>> {
+        let name: Vec<u16> = MUTEX_NAME.encode_utf16().chain(Some(0)).collect();
+        let mutex = unsafe { CreateMutexW(std::ptr::null(), 0, name.as_ptr()) };
+        if mutex == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { GetLastError() } == ERROR_ALREADY_EXISTS {
+            unsafe { CloseHandle(mutex) };
+            return Ok(None);
+        }
+        Ok(Some(Self { mutex }))
+    }
+}
+
+impl Drop for SingleInstance {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.mutex) };
+    }
+}
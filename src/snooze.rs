@@ -0,0 +1,55 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, TimeZone, Utc};
+
+// Length of "Snooze 1 hour" in the shutdown dialog. Long enough to finish a meeting or a download,
+// short enough that pending update restarts are not forgotten.
+pub static SNOOZE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+//persists snoozed shutdown across restarts of the monitor and the computer.
+//file stores unix timestamp at which the snooze ends.
+#[derive(Clone)]
+pub struct Snooze {
+    path: PathBuf,
+}
+
+impl Snooze {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    pub fn set(&self, until: DateTime<Utc>) -> anyhow::Result<()> {
+        fs::write(&self.path, until.timestamp().to_string())?;
+        Ok(())
+    }
+
+    //time left until snooze ends. None if nothing is snoozed.
+    pub fn remaining(&self) -> Option<Duration> {
+        let until = self.read().ok()?;
+        let remaining = (until - Utc::now()).to_std().ok()?;
+        //clock turned back must not stretch the snooze
+        Some(remaining.min(SNOOZE_DURATION))
+    }
+
+    pub fn clear(&self) -> anyhow::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn read(&self) -> anyhow::Result<DateTime<Utc>> {
+        let time = fs::read_to_string(&self.path)?.trim().parse::<i64>()?;
+        Utc.timestamp_opt(time, 0)
+            .single()
+            .ok_or_else(|| anyhow!("Invalid timestamp: {time}"))
+    }
+}
@@ -0,0 +1,50 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+// Failed startups in a row after which the monitor starts in recovery mode. A single crash may be
+// bad luck, but three in a row are a broken configuration or environment.
+pub static MAX_FAILED_STARTUPS: u32 = 3;
+
+// Time the monitor must run before its startup counts as successful. Crashes caused by the
+// configuration happen while setting up, well within this time.
+pub static STABLE_AFTER: Duration = Duration::from_secs(60);
+
+//counts startups in a row that ended before monitoring ran for a while(crash, fatal error, killed).
+//file stores "count config_hash". changing the configuration resets the count, so that a fixed
+//configuration is tried again.
+#[derive(Clone)]
+pub struct StartupGuard {
+    path: PathBuf,
+    config_hash: String,
+}
+
+impl StartupGuard {
+    pub fn new(path: impl AsRef<Path>, config_hash: Option<String>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            config_hash: config_hash.unwrap_or_else(|| "-".to_owned()),
+        }
+    }
+
+    //registers a startup and returns number of failed startups right before it.
+    //failure to write is ignored. guard must never keep the monitor from starting.
+    pub fn begin(&self) -> u32 {
+        let failed = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|text| {
+                let (count, hash) = text.trim().split_once(' ')?;
+                (hash == self.config_hash).then(|| count.parse().ok())?
+            })
+            .unwrap_or(0);
+        let _ = fs::create_dir_all(self.path.parent().unwrap_or(Path::new(".")));
+        let _ = fs::write(&self.path, format!("{} {}", failed + 1, self.config_hash));
+        failed
+    }
+
+    pub fn succeeded(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
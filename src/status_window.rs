@@ -0,0 +1,83 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, Utc};
+use winsafe::{co, prelude::*, HWND};
+
+use crate::{
+    active_hours::ActiveHours, app_state::AppState, boot, boot::BootClassification, config::Config,
+    dialog_placement, fast_startup, i18n::Locale, policy::Cause,
+};
+
+//classification of the current boot, once restart detection has run
+static DETECTION: Mutex<Option<Detection>> = Mutex::new(None);
+//only one status window is open at a time
+static OPEN: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Detection {
+    pub classification: BootClassification,
+    //cause of a restart the policy applies to
+    pub cause: Option<Cause>,
+    pub at: DateTime<Utc>,
+}
+
+//what the status window shows
+pub struct Status {
+    pub paused: bool,
+    pub countdown_pending: bool,
+    pub uptime: Duration,
+    pub last_heartbeat: Option<DateTime<Local>>,
+    pub detection: Option<Detection>,
+    //None if they are not respected
+    pub active_hours: Option<Option<ActiveHours>>,
+    //None if it can't be read
+    pub fast_startup: Option<bool>,
+}
+
+pub fn record(detection: Detection) {
+    *DETECTION.lock().unwrap() = Some(detection);
+}
+
+//show whether the monitor is alive and armed. window stays open until dismissed, so it is shown
+//from its own thread.
+pub fn show(state: &AppState, config: &Config) {
+    if OPEN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let engine = state.state();
+    let active_hours = config.respect_active_hours.then(|| {
+        ActiveHours::read().unwrap_or_else(|e| {
+            log::warn!("unable to read active hours: {e:#}");
+            None
+        })
+    });
+    let status = Status {
+        paused: engine.paused,
+        countdown_pending: engine.countdown_pending,
+        uptime: boot::uptime(),
+        last_heartbeat: engine.last_heartbeat.map(|time| time.with_timezone(&Local)),
+        detection: *DETECTION.lock().unwrap(),
+        active_hours,
+        fast_startup: fast_startup::is_enabled()
+            .inspect_err(|e| log::warn!("unable to read fast startup setting: {e:#}"))
+            .ok(),
+    };
+    thread::spawn(move || {
+        let locale = Locale::current();
+        let _ = dialog_placement::in_front(|| {
+            HWND::NULL.MessageBox(
+                &locale.status_text(&status),
+                &locale.status_title(),
+                co::MB::OK | co::MB::ICONINFORMATION | co::MB::SETFOREGROUND,
+            )
+        });
+        OPEN.store(false, Ordering::SeqCst);
+    });
+}
@@ -0,0 +1,126 @@
+use std::{
+    ffi::c_void,
+    io, mem, ptr,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use windows_sys::{
+    core::{GUID, HRESULT},
+    Win32::{
+        Foundation::HWND,
+        System::Com::{
+            CoCreateInstance, CoInitializeEx, CoUninitialize, CLSCTX_INPROC_SERVER,
+            COINIT_APARTMENTTHREADED,
+        },
+        UI::{
+            Shell::{TaskbarList, TBPF_ERROR, TBPF_NORMAL},
+            WindowsAndMessaging::{
+                FlashWindowEx, IsWindow, FLASHWINFO, FLASHW_ALL, FLASHW_TIMERNOFG,
+            },
+        },
+    },
+};
+
+use crate::countdown_window;
+
+const IID_ITASKBARLIST3: GUID = GUID::from_u128(0xea1afb91_9e28_4b86_90e9_9e9f8a5eefaf);
+
+// Time between updates of the progress indicator. Smooth enough for a countdown of minutes.
+static PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+//progress turns red for the last part of the countdown, out of 10
+const RED_TENTHS: u64 = 1;
+
+//ITaskbarList3 up to SetProgressState, the rest isn't called. windows-sys has no com interfaces.
+#[repr(C)]
+struct TaskbarListVtbl {
+    query_interface: usize,
+    add_ref: usize,
+    release: unsafe extern "system" fn(this: *mut TaskbarList3) -> u32,
+    hr_init: unsafe extern "system" fn(this: *mut TaskbarList3) -> HRESULT,
+    add_tab: usize,
+    delete_tab: usize,
+    activate_tab: usize,
+    set_active_alt: usize,
+    mark_fullscreen_window: usize,
+    set_progress_value: unsafe extern "system" fn(
+        this: *mut TaskbarList3,
+        hwnd: HWND,
+        done: u64,
+        total: u64,
+    ) -> HRESULT,
+    set_progress_state:
+        unsafe extern "system" fn(this: *mut TaskbarList3, hwnd: HWND, state: i32) -> HRESULT,
+}
+
+#[repr(C)]
+struct TaskbarList3 {
+    vtbl: *const TaskbarListVtbl,
+}
+
+//flash the taskbar button of the countdown `hwnd` until it is brought to the foreground, and drain
+//its progress indicator as the countdown runs, so it is noticed even behind other windows. stops
+//once the window is closed.
+pub fn track(hwnd: HWND, deadline: Arc<Mutex<Instant>>, timeout: Duration) {
+    let flash = FLASHWINFO {
+        cbSize: mem::size_of::<FLASHWINFO>() as u32,
+        hwnd,
+        dwFlags: FLASHW_ALL | FLASHW_TIMERNOFG,
+        uCount: 0,
+        dwTimeout: 0,
+    };
+    unsafe { FlashWindowEx(&flash) };
+    thread::spawn(move || {
+        if let Err(e) = show_progress(hwnd, &deadline, timeout) {
+            log::warn!("unable to show countdown progress on the taskbar: {e}");
+        }
+    });
+}
+
+fn show_progress(hwnd: HWND, deadline: &Mutex<Instant>, timeout: Duration) -> io::Result<()> {
+    unsafe { CoInitializeEx(ptr::null(), COINIT_APARTMENTTHREADED) };
+    let result = unsafe { drain(hwnd, deadline, timeout) };
+    unsafe { CoUninitialize() };
+    result
+}
+
+unsafe fn drain(hwnd: HWND, deadline: &Mutex<Instant>, timeout: Duration) -> io::Result<()> {
+    let mut taskbar: *mut c_void = ptr::null_mut();
+    check(CoCreateInstance(
+        &TaskbarList,
+        ptr::null_mut(),
+        CLSCTX_INPROC_SERVER,
+        &IID_ITASKBARLIST3,
+        &mut taskbar,
+    ))?;
+    let taskbar = taskbar as *mut TaskbarList3;
+    let vtbl = &*(*taskbar).vtbl;
+    let result = (|| {
+        check((vtbl.hr_init)(taskbar))?;
+        let total = timeout.as_millis().max(1) as u64;
+        while IsWindow(hwnd) != 0 {
+            //held while the user types, so it may exceed the timeout
+            let left = (countdown_window::time_left(deadline).as_millis() as u64).min(total);
+            let state = if left * 10 <= total * RED_TENTHS {
+                TBPF_ERROR
+            } else {
+                TBPF_NORMAL
+            };
+            check((vtbl.set_progress_state)(taskbar, hwnd, state))?;
+            check((vtbl.set_progress_value)(taskbar, hwnd, left, total))?;
+            thread::sleep(PROGRESS_INTERVAL);
+        }
+        Ok(())
+    })();
+    (vtbl.release)(taskbar);
+    result
+}
+
+fn check(result: HRESULT) -> io::Result<()> {
+    if result < 0 {
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    Ok(())
+}
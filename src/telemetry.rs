@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    audit::{self, Boot},
+    http, pool_timer, weekly_summary,
+};
+
+// Time between checks whether a week has passed, as for the weekly summary.
+static CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+//endpoint of release builds. builds without it only log what would have been sent.
+const DEFAULT_URL: Option<&str> = option_env!("RESTART_FIX_TELEMETRY_URL");
+
+//counters of the week that ended now. nothing in it tells machines, users or times apart.
+#[derive(Debug, Serialize)]
+struct Report {
+    version: &'static str,
+    detections: usize,
+    //detections by cause, e.g. {"windows-update": 2}
+    causes: BTreeMap<String, usize>,
+    prompts: usize,
+    cancelled: usize,
+}
+
+impl Report {
+    fn of(boots: &[Boot], now: DateTime<Utc>) -> Self {
+        let week_ago = now - chrono::Duration::seconds(weekly_summary::WEEK_SECS);
+        let mut report = Report {
+            version: env!("CARGO_PKG_VERSION"),
+            detections: 0,
+            causes: BTreeMap::new(),
+            prompts: 0,
+            cancelled: 0,
+        };
+        for boot in boots.iter().filter(|boot| boot.time >= week_ago) {
+            let Some(cause) = &boot.cause else {
+                continue;
+            };
+            report.detections += 1;
+            *report.causes.entry(cause.clone()).or_default() += 1;
+            if boot.response.is_some() {
+                report.prompts += 1;
+            }
+            if boot.response.as_deref() == Some("cancelled") {
+                report.cancelled += 1;
+            }
+        }
+        report
+    }
+}
+
+//report aggregate counters once a week if the user opted in. `url` empty falls back to the
+//endpoint of the build. `sent` stores when the last report was sent.
+pub fn spawn(url: String, sent: PathBuf) {
+    let url = Some(url)
+        .filter(|url| !url.is_empty())
+        .or(DEFAULT_URL.map(str::to_owned));
+    pool_timer::every(CHECK_INTERVAL, move || {
+        if let Err(e) = check(url.as_deref(), &sent) {
+            log::debug!("unable to send telemetry: {e:#}");
+        }
+    });
+}
+
+fn check(url: Option<&str>, sent: &Path) -> anyhow::Result<()> {
+    let now = Utc::now();
+    if !weekly_summary::is_due(sent, now)? {
+        return Ok(());
+    }
+    let report = Report::of(&audit::boots()?, now);
+    //what leaves the computer is always in the log
+    log::info!("telemetry: {}", serde_json::to_string(&report)?);
+    if let Some(url) = url {
+        http::agent()?.post(url).send_json(&report)?;
+    }
+    weekly_summary::mark_sent(sent, now)?;
+    Ok(())
+}
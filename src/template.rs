@@ -0,0 +1,95 @@
+use std::{str::FromStr, time::Duration};
+
+use chrono::{DateTime, Local, Utc};
+
+use crate::{i18n, policy::Cause};
+
+//dialog wording written by the organization in the config file.
+//`{name}` is replaced by the value of placeholder `name`. `{{` and `}}` are literal braces.
+#[derive(Debug, Clone)]
+pub struct Template(String);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid template `{template}`: {reason}(placeholders are {{timeout}}, {{timeout_secs}}, {{detected_cause}} and {{boot_time}})")]
+pub struct ParseTemplateError {
+    template: String,
+    reason: String,
+}
+
+//what placeholders are replaced with
+pub struct Values {
+    pub timeout: Duration,
+    pub cause: Cause,
+    pub boot_time: DateTime<Utc>,
+}
+
+impl Values {
+    fn get(&self, name: &str) -> Option<String> {
+        match name {
+            "timeout" => Some(i18n::Locale::current().format_duration(self.timeout)),
+            "timeout_secs" => Some(self.timeout.as_secs().to_string()),
+            "detected_cause" => Some(self.cause.as_str().to_owned()),
+            "boot_time" => Some(i18n::format_time(self.boot_time.with_timezone(&Local))),
+            _ => None,
+        }
+    }
+}
+
+impl Template {
+    pub fn render(&self, values: &Values) -> String {
+        //placeholders were checked when parsing
+        expand(&self.0, |name| values.get(name)).unwrap_or_else(|_| self.0.clone())
+    }
+}
+
+impl FromStr for Template {
+    type Err = ParseTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = Values {
+            timeout: Duration::ZERO,
+            cause: Cause::Unknown,
+            boot_time: Utc::now(),
+        };
+        match expand(s, |name| values.get(name)) {
+            Ok(_) => Ok(Self(s.to_owned())),
+            Err(reason) => Err(ParseTemplateError {
+                template: s.to_owned(),
+                reason,
+            }),
+        }
+    }
+}
+
+fn expand(template: &str, value: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                expanded.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                expanded.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("unclosed `{`".to_owned()),
+                    }
+                }
+                let value = value(name.trim())
+                    .ok_or_else(|| format!("unknown placeholder `{{{name}}}`"))?;
+                expanded.push_str(&value);
+            }
+            '}' => return Err("unmatched `}`".to_owned()),
+            c => expanded.push(c),
+        }
+    }
+    Ok(expanded)
+}
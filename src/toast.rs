@@ -0,0 +1,122 @@
+use anyhow::Context;
+use windows::{
+    core::{IInspectable, Interface, HSTRING},
+    Data::Xml::Dom::XmlDocument,
+    Foundation::TypedEventHandler,
+    UI::Notifications::{
+        NotificationSetting, ToastActivatedEventArgs, ToastNotification, ToastNotificationManager,
+        ToastNotifier,
+    },
+};
+use winsafe::{co, prelude::*, RegistryValue, HKEY};
+
+use crate::registry;
+
+// Application user model ID toasts are shown under. Unpackaged programs have none unless it is
+// registered under this key.
+static APP_ID: &str = "restart-fix";
+static APP_ID_KEY: &str = r"Software\Classes\AppUserModelId\restart-fix";
+
+//toast shown by show(). hidden once it isn't needed anymore, e.g. when its countdown ended.
+pub struct Toast {
+    notifier: ToastNotifier,
+    notification: ToastNotification,
+}
+
+impl Toast {
+    pub fn hide(&self) {
+        if let Err(e) = self.notifier.Hide(&self.notification) {
+            log::debug!("unable to hide toast: {e}");
+        }
+    }
+}
+
+//show windows notification with `buttons`, whose index is given to `on_button` when one is
+//pressed while restart-fix runs. fails where toasts are unavailable, e.g. in the service, on
+//windows server without desktop experience or when notifications are turned off.
+pub fn show(
+    title: &str,
+    text: &str,
+    buttons: &[String],
+    on_button: impl Fn(usize) + Send + 'static,
+) -> anyhow::Result<Toast> {
+    register()?;
+    let notifier = ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?;
+    let setting = notifier.Setting()?;
+    if setting != NotificationSetting::Enabled {
+        anyhow::bail!("notifications are disabled({})", setting.0);
+    }
+    let document = XmlDocument::new()?;
+    document.LoadXml(&HSTRING::from(xml(title, text, buttons)))?;
+    let notification = ToastNotification::CreateToastNotification(&document)?;
+    notification.Activated(&TypedEventHandler::new(
+        move |_: &Option<ToastNotification>, args: &Option<IInspectable>| {
+            //clicking the toast itself has no arguments
+            let pressed = args
+                .as_ref()
+                .and_then(|args| args.cast::<ToastActivatedEventArgs>().ok())
+                .and_then(|args| args.Arguments().ok())
+                .and_then(|arguments| arguments.to_string().parse().ok());
+            if let Some(button) = pressed {
+                on_button(button);
+            }
+            Ok(())
+        },
+    ))?;
+    notifier.Show(&notification)?;
+    Ok(Toast {
+        notifier,
+        notification,
+    })
+}
+
+//toasts of unpackaged programs need their id registered with a display name
+fn register() -> anyhow::Result<()> {
+    registry::set_value(
+        &HKEY::CURRENT_USER,
+        APP_ID_KEY,
+        "DisplayName",
+        RegistryValue::Sz(APP_ID.to_owned()),
+    )
+    .context("unable to register notification sender")
+}
+
+pub fn unregister() -> anyhow::Result<()> {
+    match HKEY::CURRENT_USER.RegDeleteTree(Some(APP_ID_KEY)) {
+        Ok(()) | Err(co::ERROR::FILE_NOT_FOUND) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+//toasts with buttons stay on screen until answered, like the dialog they stand in for
+fn xml(title: &str, text: &str, buttons: &[String]) -> String {
+    let scenario = if buttons.is_empty() {
+        ""
+    } else {
+        r#" scenario="reminder""#
+    };
+    //toasts show up to three texts, so the lines share one
+    let text = escape(&text.replace("\r\n", "\n"));
+    let actions: String = buttons
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            format!(
+                r#"<action content="{}" arguments="{index}" activationType="foreground"/>"#,
+                //toasts have no keyboard shortcuts
+                escape(&label.replace('&', ""))
+            )
+        })
+        .collect();
+    format!(
+        r#"<toast{scenario}><visual><binding template="ToastGeneric"><text>{}</text><text>{text}</text></binding></visual><actions>{actions}</actions></toast>"#,
+        escape(title)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
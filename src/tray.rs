@@ -0,0 +1,140 @@
+use std::{
+    io, mem, ptr,
+    sync::atomic::{AtomicIsize, Ordering},
+};
+
+use windows_sys::Win32::{
+    Foundation::{HWND, POINT},
+    UI::{
+        Shell::{
+            Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO, NIM_ADD,
+            NIM_DELETE, NIM_MODIFY, NOTIFYICONDATAW,
+        },
+        WindowsAndMessaging::{
+            AppendMenuW, CreatePopupMenu, DestroyMenu, GetCursorPos, LoadIconW,
+            SetForegroundWindow, TrackPopupMenu, IDI_APPLICATION, MF_STRING, TPM_RETURNCMD,
+            TPM_RIGHTBUTTON, WM_APP, WM_CONTEXTMENU, WM_LBUTTONDBLCLK, WM_RBUTTONUP,
+        },
+    },
+};
+
+//sent to the window of the icon when it is clicked. lParam holds the original mouse message.
+pub const WM_TRAY: u32 = WM_APP + 1;
+const ICON_ID: u32 = 1;
+const SHOW_STATUS: usize = 1;
+const SHOW_HISTORY: usize = 2;
+
+// Window of the icon while it is shown. Balloons are shown from its icon.
+static ICON_WINDOW: AtomicIsize = AtomicIsize::new(0);
+
+//what the user picked from the icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    ShowStatus,
+    ShowHistory,
+}
+
+//add icon of the monitor to the notification area. its messages go to `hwnd`.
+pub fn add(hwnd: HWND, tip: &str) -> io::Result<()> {
+    let mut data = data(hwnd);
+    data.uFlags = NIF_MESSAGE | NIF_ICON | NIF_TIP;
+    data.uCallbackMessage = WM_TRAY;
+    data.hIcon = unsafe { LoadIconW(0, IDI_APPLICATION) };
+    copy_wide(&mut data.szTip, tip);
+    if unsafe { Shell_NotifyIconW(NIM_ADD, &data) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    ICON_WINDOW.store(hwnd, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn remove(hwnd: HWND) {
+    let _ = ICON_WINDOW.compare_exchange(hwnd, 0, Ordering::Relaxed, Ordering::Relaxed);
+    unsafe { Shell_NotifyIconW(NIM_DELETE, &data(hwnd)) };
+}
+
+//show balloon from the icon. windows 10 and later show it as a notification.
+pub fn balloon(title: &str, text: &str) -> io::Result<()> {
+    let hwnd = ICON_WINDOW.load(Ordering::Relaxed);
+    if hwnd == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "notification area icon isn't shown",
+        ));
+    }
+    let mut data = data(hwnd);
+    data.uFlags = NIF_INFO;
+    data.dwInfoFlags = NIIF_INFO;
+    copy_wide(&mut data.szInfoTitle, title);
+    copy_wide(&mut data.szInfo, text);
+    if unsafe { Shell_NotifyIconW(NIM_MODIFY, &data) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+//truncated, leaving room for the terminating nul
+fn copy_wide(dst: &mut [u16], src: &str) {
+    let room = dst.len() - 1;
+    for (dst, src) in dst.iter_mut().zip(src.encode_utf16().take(room)) {
+        *dst = src;
+    }
+}
+
+//handle lParam of WM_TRAY. double click asks for the status window, the context menu offers it and
+//the history window if `show_history` is given.
+pub fn clicked(
+    hwnd: HWND,
+    event: u32,
+    show_status: &str,
+    show_history: Option<&str>,
+) -> Option<Command> {
+    match event {
+        WM_LBUTTONDBLCLK => Some(Command::ShowStatus),
+        WM_RBUTTONUP | WM_CONTEXTMENU => match context_menu(hwnd, show_status, show_history) {
+            SHOW_STATUS => Some(Command::ShowStatus),
+            SHOW_HISTORY => Some(Command::ShowHistory),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn context_menu(hwnd: HWND, show_status: &str, show_history: Option<&str>) -> usize {
+    let wide = |text: &str| -> Vec<u16> { text.encode_utf16().chain(Some(0)).collect() };
+    let show_status = wide(show_status);
+    let show_history = show_history.map(wide);
+    let mut cursor = POINT { x: 0, y: 0 };
+    unsafe {
+        let menu = CreatePopupMenu();
+        if menu == 0 {
+            return 0;
+        }
+        AppendMenuW(menu, MF_STRING, SHOW_STATUS, show_status.as_ptr());
+        if let Some(show_history) = &show_history {
+            AppendMenuW(menu, MF_STRING, SHOW_HISTORY, show_history.as_ptr());
+        }
+        GetCursorPos(&mut cursor);
+        //menu is dismissed by clicking elsewhere only if its window is in the foreground
+        SetForegroundWindow(hwnd);
+        let command = TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_RIGHTBUTTON,
+            cursor.x,
+            cursor.y,
+            0,
+            hwnd,
+            ptr::null(),
+        );
+        DestroyMenu(menu);
+        command as usize
+    }
+}
+
+fn data(hwnd: HWND) -> NOTIFYICONDATAW {
+    let mut data: NOTIFYICONDATAW = unsafe { mem::zeroed() };
+    data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+    data.hWnd = hwnd;
+    data.uID = ICON_ID;
+    data
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs, io,
+    os::windows::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    autostart, config::Config, elevation, paths, provision, registry, service, toast, watchdog_task,
+};
+
+const HISTORY_FILE: &str = "history";
+//keeps taskkill from flashing a console window
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+// Time for stopped instances to release the files they held open.
+static RELEASE_DELAY: Duration = Duration::from_secs(1);
+
+//files restart-fix writes to a configured state directory. only these are removed there, since the
+//directory may hold files of others. rotated and quarantined copies share the prefix.
+const STATE_FILES: [&str; 16] = [
+    "restart-fix.log",
+    "audit.jsonl",
+    "last_updated",
+    "history",
+    "clean_shutdown",
+    "firmware_reboot",
+    "fleet_policy.json",
+    "heartbeat.key",
+    "machine",
+    "paused",
+    "pending_reboot_warned",
+    "shutdowns",
+    "snoozed",
+    "summary_sent",
+    "update_notified",
+    ".doctor",
+];
+
+//remove everything restart-fix has set up or written for this user, then the machine-wide parts.
+//returns what was removed, one line each.
+pub fn run(keep_history: bool) -> anyhow::Result<String> {
+    let mut removed = Vec::new();
+    //the watchdog would relaunch the instance stopped below
+    if watchdog_task::exists()? {
+        watchdog_task::unregister()?;
+        removed.push("watchdog task removed".to_owned());
+    }
+    autostart::unregister()?;
+    removed.push("autostart removed".to_owned());
+    if stop_instances()? {
+        removed.push("running instance stopped".to_owned());
+    }
+    for dir in state_dirs() {
+        if remove_state(&dir, keep_history)? {
+            removed.push(format!("{} removed", dir.display()));
+        }
+    }
+    registry::remove_app_key()?;
+    toast::unregister()?;
+    if service::is_installed() || provision::is_event_source_registered() {
+        let mut args = vec!["uninstall", "--machine-wide"];
+        if keep_history {
+            args.push("--keep-history");
+        }
+        match elevation::require(&args)? {
+            Some(output) => removed.push(output.trim_end().to_owned()),
+            None => removed.push(run_machine_wide(keep_history)?),
+        }
+    }
+    if keep_history {
+        removed.push("history kept".to_owned());
+    }
+    Ok(removed.join("\n"))
+}
+
+//service, its state in %ProgramData% and the event log source. needs administrator rights.
+pub fn run_machine_wide(keep_history: bool) -> anyhow::Result<String> {
+    let mut removed = Vec::new();
+    if service::is_installed() {
+        service::uninstall()?;
+        removed.push("service removed".to_owned());
+        paths::use_machine_dir();
+        for dir in state_dirs() {
+            if remove_state(&dir, keep_history)? {
+                removed.push(format!("{} removed", dir.display()));
+            }
+        }
+    }
+    if provision::is_event_source_registered() {
+        provision::unregister_event_source()?;
+        removed.push("event log source unregistered".to_owned());
+    }
+    Ok(removed.join("\n"))
+}
+
+//other instances of this user. returns true if any was running.
+fn stop_instances() -> anyhow::Result<bool> {
+    let exe = std::env::current_exe()?;
+    let Some(name) = exe.file_name() else {
+        return Ok(false);
+    };
+    let output = Command::new("taskkill.exe")
+        .arg("/F")
+        .arg("/IM")
+        .arg(name)
+        .arg("/FI")
+        .arg(format!("PID ne {}", process::id()))
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()?;
+    //taskkill exits with an error if nothing matched
+    let stopped = output.status.success();
+    if stopped {
+        thread::sleep(RELEASE_DELAY);
+    }
+    Ok(stopped)
+}
+
+//data directory, and the configured state directory if it is another one
+fn state_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![paths::data_dir()];
+    if let Ok(config) = Config::load(paths::config_path()) {
+        if !dirs.contains(&config.state_dir) {
+            dirs.push(config.state_dir);
+        }
+    }
+    dirs
+}
+
+//returns true if anything was removed. the data directory is restart-fix's own and is removed
+//entirely, other directories lose only the state files.
+fn remove_state(dir: &Path, keep_history: bool) -> io::Result<bool> {
+    if !dir.exists() {
+        return Ok(false);
+    }
+    let own = dir == paths::data_dir();
+    if own && !keep_history {
+        fs::remove_dir_all(dir)?;
+        return Ok(true);
+    }
+    let mut removed = false;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_state = own || STATE_FILES.iter().any(|file| name.starts_with(file));
+        if !is_state || (keep_history && name == HISTORY_FILE) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        } else {
+            fs::remove_file(entry.path())?;
+        }
+        removed = true;
+    }
+    Ok(removed)
+}
@@ -0,0 +1,163 @@
+use std::{
+    env, fs,
+    io::{ErrorKind, Read},
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{http, i18n::Locale, notifier, policy::UpdateCheck, pool_timer};
+
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/hasunwoo/Restart-Fix/releases/latest";
+
+// Time between update checks. Releases are rare, and the GitHub API limits anonymous requests.
+static CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+//executable is far smaller. anything larger is not a release of restart-fix.
+const MAX_DOWNLOAD: u64 = 64 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+    //"sha256:<hex>" on releases published since github started recording it
+    digest: Option<String>,
+}
+
+//check for a newer release once a day. `notified` stores the last version the user was told
+//about, so a version is announced only once.
+pub fn spawn(mode: UpdateCheck, notified: PathBuf) {
+    if mode == UpdateCheck::Off {
+        return;
+    }
+    pool_timer::every(CHECK_INTERVAL, move || {
+        if let Err(e) = check(mode, &notified) {
+            log::warn!("unable to check for updates: {e:#}");
+        }
+    });
+}
+
+fn check(mode: UpdateCheck, notified: &Path) -> anyhow::Result<()> {
+    let release: Release = http::agent()?.get(LATEST_RELEASE_URL).call()?.into_json()?;
+    let version = release.tag_name.trim_start_matches('v');
+    if !is_newer(version, env!("CARGO_PKG_VERSION")) {
+        return Ok(());
+    }
+    if fs::read_to_string(notified).is_ok_and(|last| last.trim() == version) {
+        return Ok(());
+    }
+    log::info!("restart-fix {version} is available: {}", release.html_url);
+    let installed = mode == UpdateCheck::Install && {
+        match download(&release) {
+            Ok(path) => {
+                log::info!(
+                    "update downloaded to {}. installed on next start",
+                    path.display()
+                );
+                true
+            }
+            Err(e) => {
+                log::warn!("unable to download update: {e:#}");
+                false
+            }
+        }
+    };
+    fs::write(notified, version)?;
+    let locale = Locale::current();
+    notifier::notify(
+        &locale.update_title(),
+        &locale.update_text(version, &release.html_url, installed),
+    );
+    Ok(())
+}
+
+//dotted numeric versions. pre-release suffix(e.g. "-beta") is ignored.
+fn is_newer(version: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split('-')
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(version) > parse(current)
+}
+
+//download executable of `release` next to the running one. it replaces the running one on next start.
+fn download(release: &Release) -> anyhow::Result<PathBuf> {
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name.to_ascii_lowercase().ends_with(".exe"))
+        .ok_or_else(|| anyhow!("release has no executable"))?;
+    let mut executable = Vec::new();
+    http::agent()?
+        .get(&asset.browser_download_url)
+        .call()?
+        .into_reader()
+        .take(MAX_DOWNLOAD)
+        .read_to_end(&mut executable)?;
+    if let Some(expected) = asset
+        .digest
+        .as_deref()
+        .and_then(|d| d.strip_prefix("sha256:"))
+    {
+        let actual: String = Sha256::digest(&executable)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        if !actual.eq_ignore_ascii_case(expected) {
+            bail!("checksum of {} doesn't match the release", asset.name);
+        }
+    }
+    let path = pending_path()?;
+    fs::write(&path, executable).with_context(|| format!("unable to write {}", path.display()))?;
+    Ok(path)
+}
+
+fn pending_path() -> anyhow::Result<PathBuf> {
+    Ok(env::current_exe()?.with_extension("exe.new"))
+}
+
+//swap in the executable downloaded by the last instance and start it with the same arguments.
+//returns true if it was started, in which case this instance must exit.
+pub fn apply_pending() -> anyhow::Result<bool> {
+    let current = env::current_exe()?;
+    let old = current.with_extension("exe.old");
+    //left over from the last update. only deletable once that instance has exited.
+    match fs::remove_file(&old) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            log::warn!("unable to remove {}: {e}", old.display())
+        }
+        _ => {}
+    }
+    let pending = pending_path()?;
+    if !pending.exists() {
+        return Ok(false);
+    }
+    //running executable can be renamed, though not overwritten
+    fs::rename(&current, &old)?;
+    if let Err(e) = fs::rename(&pending, &current) {
+        let _ = fs::rename(&old, &current);
+        return Err(e.into());
+    }
+    Command::new(&current)
+        .args(env::args_os().skip(1))
+        .spawn()?;
+    Ok(true)
+}
@@ -0,0 +1,90 @@
+use std::{fs, io::ErrorKind, path::Path, ptr};
+
+use windows_sys::Win32::{
+    Security::{Authorization::ConvertSidToStringSidW, LookupAccountNameW},
+    System::Memory::LocalFree,
+};
+
+use crate::config::Config;
+
+//state files that belong to the machine they were written on
+const MACHINE_STATE: [&str; 7] = [
+    "last_updated",
+    "last_updated.a",
+    "last_updated.b",
+    "paused",
+    "history",
+    "snoozed",
+    "shutdowns",
+];
+
+//clones of a golden image boot with its recent heartbeat, which looks like an unexpected restart.
+//in vdi mode, state written on another machine(different computer name or machine sid) is discarded.
+//returns true if state was reset.
+pub fn reset_if_cloned(config: &Config) -> anyhow::Result<bool> {
+    let identity_path = config.state_file("machine");
+    let identity = machine_identity()?;
+    let stored = match fs::read_to_string(&identity_path) {
+        Ok(stored) => Some(stored),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    //without stored identity, origin of existing state is unknown and can't be trusted either
+    let cloned = stored.as_deref().map(str::trim) != Some(identity.as_str());
+    if cloned {
+        for name in MACHINE_STATE {
+            remove_if_exists(&config.state_file(name))?;
+        }
+        fs::write(&identity_path, &identity)?;
+    }
+    Ok(cloned)
+}
+
+//computer name and machine sid. sysprep generalization changes both.
+fn machine_identity() -> anyhow::Result<String> {
+    let name = winsafe::GetComputerName()?;
+    Ok(format!("{name} {}", machine_sid(&name)?))
+}
+
+//sid of local computer account domain, which is the machine sid
+fn machine_sid(computer_name: &str) -> anyhow::Result<String> {
+    let name: Vec<u16> = computer_name.encode_utf16().chain(Some(0)).collect();
+    let mut sid = vec![0u8; 68];
+    let mut domain = vec![0u16; 256];
+    let mut sid_len = sid.len() as u32;
+    let mut domain_len = domain.len() as u32;
+    let mut sid_type = 0;
+    let found = unsafe {
+        LookupAccountNameW(
+            ptr::null(),
+            name.as_ptr(),
+            sid.as_mut_ptr().cast(),
+            &mut sid_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut sid_type,
+        )
+    };
+    if found == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let mut string_sid = ptr::null_mut();
+    if unsafe { ConvertSidToStringSidW(sid.as_mut_ptr().cast(), &mut string_sid) } == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    let len = (0..)
+        .take_while(|&i| unsafe { *string_sid.add(i) } != 0)
+        .count();
+    let result = String::from_utf16_lossy(unsafe { std::slice::from_raw_parts(string_sid, len) });
+    unsafe { LocalFree(string_sid as isize) };
+    Ok(result)
+}
+
+fn remove_if_exists(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    fmt, io,
+    net::{Ipv4Addr, UdpSocket},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use serde::{de, Deserialize, Deserializer};
+
+//port wake-on-lan listeners commonly use(discard protocol)
+const PORT: u16 = 9;
+//packets are sent a few times, since udp broadcast may be dropped
+const REPEAT: usize = 3;
+const REPEAT_DELAY: Duration = Duration::from_millis(100);
+
+//hardware address of a network adapter written as "aa:bb:cc:dd:ee:ff" or "aa-bb-cc-dd-ee-ff"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacAddress([u8; 6]);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid mac address `{0}`(expected e.g. aa:bb:cc:dd:ee:ff)")]
+pub struct ParseMacAddressError(String);
+
+impl FromStr for MacAddress {
+    type Err = ParseMacAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseMacAddressError(s.to_owned());
+        let mut address = [0; 6];
+        let mut parts = s.trim().split([':', '-']);
+        for byte in &mut address {
+            let part = parts
+                .next()
+                .filter(|part| part.len() == 2)
+                .ok_or_else(invalid)?;
+            *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Self(address))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl<'de> Deserialize<'de> for MacAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+//wake machines that went down with this one in background
+pub fn wake(addresses: &[MacAddress]) {
+    if addresses.is_empty() {
+        return;
+    }
+    let addresses = addresses.to_vec();
+    thread::spawn(move || match send(&addresses) {
+        Ok(()) => log::info!(
+            "sent wake-on-lan packets to {}",
+            addresses
+                .iter()
+                .map(MacAddress::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Err(e) => log::warn!("unable to send wake-on-lan packets: {e}"),
+    });
+}
+
+fn send(addresses: &[MacAddress]) -> io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    for attempt in 0..REPEAT {
+        if attempt > 0 {
+            thread::sleep(REPEAT_DELAY);
+        }
+        for address in addresses {
+            socket.send_to(&magic_packet(*address), (Ipv4Addr::BROADCAST, PORT))?;
+        }
+    }
+    Ok(())
+}
+
+//6 bytes of 0xff followed by the address repeated 16 times
+fn magic_packet(MacAddress(address): MacAddress) -> [u8; 102] {
+    let mut packet = [0xff; 102];
+    for chunk in packet[6..].chunks_mut(6) {
+        chunk.copy_from_slice(&address);
+    }
+    packet
+}
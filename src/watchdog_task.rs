@@ -0,0 +1,87 @@
+use std::{
+    os::windows::process::CommandExt,
+    process::{Command, Output},
+};
+
+use anyhow::bail;
+
+use crate::{autostart, i18n::Locale, notifier, registry};
+
+const TASK_NAME: &str = "restart-fix watchdog";
+const INSTALLED_FLAG: &str = "Watchdog";
+//keeps schtasks from flashing a console window
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+// Interval(in minutes) of the scheduled task that relaunches restart-fix. Task scheduler ignores
+// the trigger while the previous instance is still running, so restart-fix is only relaunched
+// after it has exited or crashed.
+static RELAUNCH_INTERVAL_MINUTES: u32 = 5;
+
+//create scheduled task that relaunches restart-fix if it exits or crashes
+pub fn register() -> anyhow::Result<()> {
+    let interval = RELAUNCH_INTERVAL_MINUTES.to_string();
+    let command = autostart::command_line()?;
+    let output = schtasks(&[
+        "/Create", "/F", "/TN", TASK_NAME, "/TR", &command, "/SC", "MINUTE", "/MO", &interval,
+    ])?;
+    if !output.status.success() {
+        bail!(
+            "schtasks failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    registry::set_flag(INSTALLED_FLAG)?;
+    Ok(())
+}
+
+pub fn unregister() -> anyhow::Result<()> {
+    let output = schtasks(&["/Delete", "/F", "/TN", TASK_NAME])?;
+    if !output.status.success() {
+        bail!(
+            "schtasks failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+pub fn is_installed() -> bool {
+    registry::is_flag_set(INSTALLED_FLAG)
+}
+
+pub fn exists() -> anyhow::Result<bool> {
+    Ok(schtasks(&["/Query", "/TN", TASK_NAME])?.status.success())
+}
+
+//startup health check. a restart watchdog that itself silently died provides false confidence.
+pub fn verify() {
+    if !is_installed() {
+        return;
+    }
+    match exists() {
+        Ok(true) => return,
+        Ok(false) => log::warn!("watchdog task is missing"),
+        Err(e) => {
+            log::warn!("unable to verify watchdog task: {e:#}");
+            return;
+        }
+    }
+    match register() {
+        Ok(()) => log::info!("watchdog task repaired"),
+        Err(e) => {
+            log::error!("unable to repair watchdog task: {e:#}");
+            let locale = Locale::current();
+            notifier::notify(
+                &locale.watchdog_error_title(),
+                &locale.watchdog_error_text(&e),
+            );
+        }
+    }
+}
+
+fn schtasks(args: &[&str]) -> anyhow::Result<Output> {
+    Ok(Command::new("schtasks.exe")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()?)
+}
@@ -0,0 +1,163 @@
+use std::thread;
+
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    http,
+    policy::{Action, Cause},
+};
+
+//embed color of discord messages(orange)
+const DISCORD_COLOR: u32 = 0xe6_7e_22;
+
+//format of the posted message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    //plain json for own integrations
+    #[default]
+    Generic,
+    Discord,
+    Slack,
+}
+
+//incoming webhook notified about detected restarts and automatic shutdowns([[webhooks]] section)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    #[serde(default)]
+    pub kind: Kind,
+    pub url: String,
+}
+
+//what webhooks are told about
+#[derive(Debug, Clone, Copy)]
+enum Notice {
+    Detected { cause: Cause, action: Action },
+    ShutDown { cause: Cause, action: Action },
+}
+
+impl Notice {
+    fn event(self) -> &'static str {
+        match self {
+            Notice::Detected { .. } => "restart-detected",
+            Notice::ShutDown { action, .. } => action.as_str(),
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Notice::Detected { .. } => "Unexpected restart detected",
+            Notice::ShutDown {
+                action: Action::Hibernate,
+                ..
+            } => "Computer hibernated automatically",
+            Notice::ShutDown {
+                action: Action::RebootToFirmware,
+                ..
+            } => "Computer rebooted into firmware setup automatically",
+            Notice::ShutDown { .. } => "Computer shut down automatically",
+        }
+    }
+
+    fn cause(self) -> Cause {
+        match self {
+            Notice::Detected { cause, .. } | Notice::ShutDown { cause, .. } => cause,
+        }
+    }
+
+    fn action(self) -> &'static str {
+        match self {
+            Notice::Detected { action, .. } | Notice::ShutDown { action, .. } => action.as_str(),
+        }
+    }
+}
+
+//notify about restart detected within threshold in background
+pub fn send_detected(webhooks: &[Webhook], cause: Cause, action: Action) {
+    if webhooks.is_empty() {
+        return;
+    }
+    let webhooks = webhooks.to_vec();
+    thread::spawn(move || send(&webhooks, Notice::Detected { cause, action }));
+}
+
+//notify about automatic shutdown, hibernation or reboot into firmware setup. blocks until sent,
+//since the computer is about to go down.
+pub fn send_shutdown(webhooks: &[Webhook], cause: Cause, action: Action) {
+    send(webhooks, Notice::ShutDown { cause, action });
+}
+
+fn send(webhooks: &[Webhook], notice: Notice) {
+    if webhooks.is_empty() {
+        return;
+    }
+    let agent = match http::agent() {
+        Ok(agent) => agent,
+        Err(e) => {
+            log::warn!("unable to notify webhooks: {e:#}");
+            return;
+        }
+    };
+    let hostname = winsafe::GetComputerName().unwrap_or_default();
+    for webhook in webhooks {
+        let message = message(webhook.kind, notice, &hostname);
+        //url carries the secret of the webhook, so only its kind is logged
+        match agent.post(&webhook.url).send_json(message) {
+            Ok(_) => log::info!("notified {:?} webhook of {}", webhook.kind, notice.event()),
+            Err(e) => log::warn!("unable to notify {:?} webhook: {e}", webhook.kind),
+        }
+    }
+}
+
+fn message(kind: Kind, notice: Notice, hostname: &str) -> Value {
+    let time = Utc::now().to_rfc3339();
+    let cause = notice.cause().as_str();
+    let action = notice.action();
+    match kind {
+        Kind::Generic => json!({
+            "event": notice.event(),
+            "hostname": hostname,
+            "cause": cause,
+            "action": action,
+            "time": time,
+        }),
+        Kind::Discord => json!({
+            "username": "restart-fix",
+            "embeds": [{
+                "title": notice.title(),
+                "color": DISCORD_COLOR,
+                "fields": [
+                    { "name": "Host", "value": hostname, "inline": true },
+                    { "name": "Cause", "value": cause, "inline": true },
+                    { "name": "Action", "value": action, "inline": true },
+                ],
+                "timestamp": time,
+            }],
+        }),
+        Kind::Slack => json!({
+            //shown in notifications, where blocks are not rendered
+            "text": format!("{}: {hostname}({cause})", notice.title()),
+            "blocks": [
+                {
+                    "type": "header",
+                    "text": { "type": "plain_text", "text": notice.title() },
+                },
+                {
+                    "type": "section",
+                    "fields": [
+                        { "type": "mrkdwn", "text": format!("*Host*\n{hostname}") },
+                        { "type": "mrkdwn", "text": format!("*Cause*\n{cause}") },
+                        { "type": "mrkdwn", "text": format!("*Action*\n{action}") },
+                    ],
+                },
+                {
+                    "type": "context",
+                    "elements": [{ "type": "mrkdwn", "text": time }],
+                },
+            ],
+        }),
+    }
+}
@@ -0,0 +1,247 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::{anyhow, bail};
+use base64::Engine as _;
+use restart_fix_core::{Decision, Event};
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+
+use crate::{app_state::AppState, ipc::Command};
+
+//appended to the key of the client to compute Sec-WebSocket-Accept(RFC 6455)
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+//commands are short words. anything longer is not a client of this endpoint.
+const MAX_PAYLOAD: u64 = 4096;
+
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+type Handler = Arc<dyn Fn(Command) -> String + Send + Sync>;
+
+//spawn server thread accepting websocket connections on localhost. every client receives state
+//changes as json messages and may send "pause", "resume", "cancel-shutdown" or "status".
+pub fn serve<F>(port: u16, state: Arc<AppState>, handler: F) -> io::Result<()>
+where
+    F: Fn(Command) -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    let handler: Handler = Arc::new(handler);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let state = Arc::clone(&state);
+            let handler = Arc::clone(&handler);
+            thread::spawn(move || {
+                //failure of single client must not stop the server
+                if let Err(e) = handle_client(stream, &state, &handler) {
+                    log::debug!("websocket client disconnected: {e:#}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+fn handle_client(stream: TcpStream, state: &AppState, handler: &Handler) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let writer = Arc::new(Mutex::new(stream));
+    handshake(&mut reader, &writer)?;
+
+    //subscribe before sending current state so that no change is missed in between
+    let events = state.subscribe();
+    let engine_state = state.state();
+    send_json(
+        &writer,
+        &json!({
+            "event": "state",
+            "armed": !engine_state.paused,
+            "countdown_pending": engine_state.countdown_pending,
+        }),
+    )?;
+    {
+        let writer = Arc::clone(&writer);
+        thread::spawn(move || {
+            for event in events {
+                let Some(message) = event_message(&event) else {
+                    continue;
+                };
+                if send_json(&writer, &message).is_err() {
+                    //dropping receiver unsubscribes
+                    return;
+                }
+            }
+        });
+    }
+
+    loop {
+        let (opcode, payload) = read_frame(&mut reader)?;
+        match opcode {
+            OPCODE_TEXT => {
+                let text = String::from_utf8(payload)?;
+                let response = match text.trim().parse::<Command>() {
+                    Ok(
+                        command @ (Command::Pause
+                        | Command::Resume
+                        | Command::CancelShutdown
+                        | Command::Status),
+                    ) => handler(command),
+                    Ok(command) => format!("error: {} is not accepted here", command.as_str()),
+                    Err(e) => format!("error: {e}"),
+                };
+                send_json(&writer, &json!({ "response": response }))?;
+            }
+            OPCODE_PING => write_frame(&mut *writer.lock().unwrap(), OPCODE_PONG, &payload)?,
+            OPCODE_PONG => {}
+            OPCODE_CLOSE => {
+                let _ = write_frame(&mut *writer.lock().unwrap(), OPCODE_CLOSE, &payload);
+                return Ok(());
+            }
+            opcode => bail!("unsupported opcode {opcode:#x}"),
+        }
+    }
+}
+
+//None for events not of interest to button integrations
+pub fn event_message(event: &Event) -> Option<Value> {
+    let message = match event {
+        Event::Evaluated(decision) => {
+            let decision = match decision {
+                Decision::Restarted { .. } => "restarted",
+                Decision::Normal { .. } => "normal",
+                Decision::SameBoot { .. } => "same-boot",
+                Decision::ClockChanged { .. } => "clock-changed",
+            };
+            json!({ "event": "evaluated", "decision": decision })
+        }
+        Event::HeartbeatWritten(_) => return None,
+        Event::Paused => json!({ "event": "paused" }),
+        Event::Resumed => json!({ "event": "armed" }),
+        Event::CountdownStarted { timeout } => {
+            json!({ "event": "countdown-started", "timeout": timeout.map(|timeout| timeout.as_secs()) })
+        }
+        Event::CountdownCancelled => json!({ "event": "cancelled" }),
+        Event::CountdownSnoozed { until } => {
+            json!({ "event": "snoozed", "until": until.to_rfc3339() })
+        }
+        Event::CountdownExpired => json!({ "event": "expired" }),
+    };
+    Some(message)
+}
+
+//answer the upgrade request. web pages of other sites must not control the computer, so requests
+//sent by a browser are only accepted from pages served by localhost.
+fn handshake(reader: &mut impl BufRead, writer: &Mutex<TcpStream>) -> anyhow::Result<()> {
+    let mut key = None;
+    let mut origin = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            bail!("connection closed during handshake");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "sec-websocket-key" => key = Some(value.trim().to_owned()),
+                "origin" => origin = Some(value.trim().to_owned()),
+                _ => {}
+            }
+        }
+    }
+    let mut writer = writer.lock().unwrap();
+    let Some(key) = key.filter(|_| origin.as_deref().is_none_or(is_local_origin)) else {
+        writer.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")?;
+        bail!("not a websocket upgrade from an allowed origin");
+    };
+    let accept = base64::engine::general_purpose::STANDARD
+        .encode(Sha1::digest(format!("{key}{ACCEPT_GUID}").as_bytes()));
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {accept}\r\n\r\n"
+    )?;
+    Ok(())
+}
+
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin
+        .split_once("://")
+        .map_or(origin, |(_, rest)| rest)
+        .split([':', '/'])
+        .next()
+        .unwrap_or_default();
+    matches!(host, "localhost" | "127.0.0.1")
+}
+
+//read one unfragmented frame sent by the client. client frames are always masked.
+fn read_frame(reader: &mut impl Read) -> anyhow::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header)?;
+    if header[0] & 0x80 == 0 {
+        bail!("fragmented messages are not supported");
+    }
+    let opcode = header[0] & 0x0f;
+    if header[1] & 0x80 == 0 {
+        bail!("client frame is not masked");
+    }
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u64::from(u16::from_be_bytes(len))
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => u64::from(len),
+    };
+    if len > MAX_PAYLOAD {
+        return Err(anyhow!("frame of {len} bytes is too large"));
+    }
+    let mut mask = [0; 4];
+    reader.read_exact(&mut mask)?;
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((opcode, payload))
+}
+
+//server frames are sent unmasked
+fn write_frame(writer: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)
+}
+
+fn send_json(writer: &Mutex<TcpStream>, message: &Value) -> io::Result<()> {
+    write_frame(
+        &mut *writer.lock().unwrap(),
+        OPCODE_TEXT,
+        message.to_string().as_bytes(),
+    )
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use restart_fix_core::parse_timestamp;
+
+use crate::{
+    email::{self, Email},
+    history::History,
+    i18n::Locale,
+    notifier, pool_timer, report,
+    shutdown_log::ShutdownLog,
+};
+
+// Time between checks whether a week has passed. The computer is often off or asleep when it does.
+static CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub const WEEK_SECS: i64 = 7 * 24 * 60 * 60;
+
+//detections of the week that ended now
+#[derive(Debug)]
+pub struct Summary {
+    pub restarts: usize,
+    //restarts of the week before, to tell whether things get better
+    pub previous: usize,
+    //most frequent first, e.g. [(2, "windows-update")]
+    pub causes: Vec<(usize, String)>,
+    //automatic shutdowns and hibernations. other restarts were cancelled or only notified about.
+    pub shutdowns: usize,
+}
+
+impl Summary {
+    pub fn of(
+        history: &History,
+        shutdowns: &ShutdownLog,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Self> {
+        let week_ago = now - chrono::Duration::seconds(WEEK_SECS);
+        let detections = history.since(now - chrono::Duration::seconds(2 * WEEK_SECS))?;
+        let (this_week, previous): (Vec<_>, Vec<_>) = detections
+            .into_iter()
+            .partition(|detection| detection.time >= week_ago);
+        let mut causes: BTreeMap<&str, usize> = BTreeMap::new();
+        for detection in &this_week {
+            *causes.entry(detection.cause.as_str()).or_default() += 1;
+        }
+        Ok(Self {
+            restarts: this_week.len(),
+            previous: previous.len(),
+            causes: report::by_count(causes)
+                .into_iter()
+                .map(|(count, cause)| (count, cause.to_owned()))
+                .collect(),
+            shutdowns: shutdowns.since(week_ago)?,
+        })
+    }
+}
+
+//sum up the detected restarts once a week. `sent` stores when the last summary was shown, so
+//restarting restart-fix doesn't start the week over.
+pub fn spawn(history: History, shutdowns: ShutdownLog, email: Email, sent: PathBuf) {
+    pool_timer::every(CHECK_INTERVAL, move || {
+        if let Err(e) = check(&history, &shutdowns, &email, &sent) {
+            log::warn!("unable to send weekly summary: {e:#}");
+        }
+    });
+}
+
+fn check(
+    history: &History,
+    shutdowns: &ShutdownLog,
+    email: &Email,
+    sent: &Path,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    if !is_due(sent, now)? {
+        return Ok(());
+    }
+    let summary = Summary::of(history, shutdowns, now)?;
+    mark_sent(sent, now)?;
+    log::info!(
+        "weekly summary: {} unexpected restarts({} the week before), {} automatic shutdowns",
+        summary.restarts,
+        summary.previous,
+        summary.shutdowns
+    );
+    email::send_summary(email, &summary);
+    let locale = Locale::current();
+    notifier::notify(
+        &locale.weekly_summary_title(),
+        &locale.weekly_summary_text(&summary),
+    );
+    Ok(())
+}
+
+//whether a week passed since the time stored in `sent`. first week starts now, and so does a
+//marker in the future(clock turned back since).
+pub fn is_due(sent: &Path, now: DateTime<Utc>) -> anyhow::Result<bool> {
+    let last = match fs::read_to_string(sent) {
+        Ok(text) => parse_timestamp(&text).ok(),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let Some(last) = last.filter(|last| *last <= now) else {
+        mark_sent(sent, now)?;
+        return Ok(false);
+    };
+    Ok((now - last).num_seconds() >= WEEK_SECS)
+}
+
+pub fn mark_sent(sent: &Path, now: DateTime<Utc>) -> anyhow::Result<()> {
+    fs::write(sent, now.timestamp().to_string())?;
+    Ok(())
+}
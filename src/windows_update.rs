@@ -0,0 +1,65 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::process_list;
+
+// Time between checks while updates are being installed.
+static RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Longest time shutdown waits for an installation. An installer that hangs must not keep the
+// computer running forever.
+static MAX_WAIT: Duration = Duration::from_secs(2 * 60 * 60);
+
+//processes that run only while updates are being installed
+const INSTALLER_PROCESSES: [&str; 3] = [
+    //windows modules installer worker, installs servicing stack packages
+    "TiWorker.exe",
+    //standalone update installer(.msu)
+    "wusa.exe",
+    //feature update setup
+    "SetupHost.exe",
+];
+
+//installer running now, if any. failure to list processes counts as none.
+pub fn running_installer() -> Option<String> {
+    process_list::find_running(&INSTALLER_PROCESSES)
+        .ok()
+        .flatten()
+}
+
+//powering off in the middle of an installation can corrupt the update.
+//blocks until no installer is running. failure to list processes counts as no installation.
+pub fn wait_until_installed() {
+    let started = Instant::now();
+    let mut logged = false;
+    loop {
+        match process_list::find_running(&INSTALLER_PROCESSES) {
+            Ok(Some(installer)) if started.elapsed() < MAX_WAIT => {
+                if !logged {
+                    log::info!("updates are being installed({installer}). shutdown is deferred");
+                    logged = true;
+                }
+                thread::sleep(RECHECK_INTERVAL);
+            }
+            Ok(Some(installer)) => {
+                log::warn!(
+                    "{installer} is still running after {}s. shutting down anyway",
+                    MAX_WAIT.as_secs()
+                );
+                return;
+            }
+            Ok(None) => {
+                if logged {
+                    log::info!("update installation has finished");
+                }
+                return;
+            }
+            Err(e) => {
+                log::warn!("unable to check for update installation: {e}");
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::thread;
+
+use ::wmi::{COMLibrary, WMIConnection, WMIDateTime};
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_OperatingSystem")]
+#[serde(rename_all = "PascalCase")]
+struct OperatingSystem {
+    last_boot_up_time: WMIDateTime,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_ReliabilityRecords")]
+#[serde(rename_all = "PascalCase")]
+struct ReliabilityRecordRow {
+    time_generated: WMIDateTime,
+    source_name: String,
+    event_identifier: u32,
+    product_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_ReliabilityStabilityMetrics")]
+#[serde(rename_all = "PascalCase")]
+struct StabilityMetricsRow {
+    time_generated: WMIDateTime,
+    system_stability_index: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_PerfRawData_PerfDisk_PhysicalDisk")]
+#[serde(rename_all = "PascalCase")]
+struct PhysicalDiskRow {
+    disk_bytes_persec: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Win32_PerfRawData_Tcpip_NetworkInterface")]
+#[serde(rename_all = "PascalCase")]
+struct NetworkInterfaceRow {
+    bytes_total_persec: u64,
+}
+
+//bytes moved since the computer was started. rates are told by two samples apart.
+#[derive(Debug, Clone, Copy)]
+pub struct IoCounters {
+    //read from and written to all physical disks
+    pub disk_bytes: u64,
+    //sent and received over all network interfaces
+    pub network_bytes: u64,
+}
+
+//entry of reliability monitor
+#[derive(Debug)]
+pub struct ReliabilityRecord {
+    pub time: DateTime<Utc>,
+    //event provider, e.g. "Application Error"
+    pub source: String,
+    pub event_id: u32,
+    //e.g. title of the installed update
+    pub product: String,
+}
+
+//boot time as recorded by the operating system. independent of when restart-fix was started.
+pub fn last_boot_up_time() -> anyhow::Result<DateTime<Utc>> {
+    //com is initialized on its own thread so apartment of the calling(gui) thread is left alone
+    thread::spawn(|| {
+        let connection = WMIConnection::new(COMLibrary::new()?)?;
+        let systems: Vec<OperatingSystem> =
+            connection.raw_query("SELECT LastBootUpTime FROM Win32_OperatingSystem")?;
+        let system = systems
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("Win32_OperatingSystem returned no instance"))?;
+        Ok(system.last_boot_up_time.0.with_timezone(&Utc))
+    })
+    .join()
+    .map_err(|_| anyhow!("wmi query panicked"))?
+}
+
+pub fn io_counters() -> anyhow::Result<IoCounters> {
+    thread::spawn(|| {
+        let connection = WMIConnection::new(COMLibrary::new()?)?;
+        let disks: Vec<PhysicalDiskRow> = connection.raw_query(
+            "SELECT DiskBytesPersec FROM Win32_PerfRawData_PerfDisk_PhysicalDisk \
+            WHERE Name = '_Total'",
+        )?;
+        let interfaces: Vec<NetworkInterfaceRow> = connection
+            .raw_query("SELECT BytesTotalPersec FROM Win32_PerfRawData_Tcpip_NetworkInterface")?;
+        Ok(IoCounters {
+            disk_bytes: disks.iter().map(|disk| disk.disk_bytes_persec).sum(),
+            network_bytes: interfaces
+                .iter()
+                .map(|interface| interface.bytes_total_persec)
+                .sum(),
+        })
+    })
+    .join()
+    .map_err(|_| anyhow!("wmi query panicked"))?
+}
+
+//reliability monitor entries logged by `source`(e.g. Microsoft-Windows-WindowsUpdateClient)
+pub fn reliability_records(source: &'static str) -> anyhow::Result<Vec<ReliabilityRecord>> {
+    query_reliability_records(format!("SourceName = '{source}'"))
+}
+
+//reliability monitor entries of every source logged since `since`
+pub fn reliability_records_since(since: DateTime<Utc>) -> anyhow::Result<Vec<ReliabilityRecord>> {
+    query_reliability_records(format!("TimeGenerated >= '{}'", wmi_time(since)))
+}
+
+//system stability index reliability monitor computed last before `time`, from 1(least stable) to
+//10. None if it computed none yet.
+pub fn stability_index(time: DateTime<Utc>) -> anyhow::Result<Option<f64>> {
+    thread::spawn(move || {
+        let connection = WMIConnection::new(COMLibrary::new()?)?;
+        let rows: Vec<StabilityMetricsRow> = connection.raw_query(format!(
+            "SELECT TimeGenerated, SystemStabilityIndex FROM Win32_ReliabilityStabilityMetrics \
+            WHERE TimeGenerated <= '{}'",
+            wmi_time(time)
+        ))?;
+        Ok(rows
+            .into_iter()
+            .max_by_key(|row| row.time_generated.0)
+            .map(|row| row.system_stability_index))
+    })
+    .join()
+    .map_err(|_| anyhow!("wmi query panicked"))?
+}
+
+fn query_reliability_records(condition: String) -> anyhow::Result<Vec<ReliabilityRecord>> {
+    thread::spawn(move || {
+        let connection = WMIConnection::new(COMLibrary::new()?)?;
+        let rows: Vec<ReliabilityRecordRow> = connection.raw_query(format!(
+            "SELECT TimeGenerated, SourceName, EventIdentifier, ProductName \
+            FROM Win32_ReliabilityRecords WHERE {condition}"
+        ))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| ReliabilityRecord {
+                time: row.time_generated.0.with_timezone(&Utc),
+                source: row.source_name,
+                event_id: row.event_identifier,
+                product: row.product_name.unwrap_or_default(),
+            })
+            .collect())
+    })
+    .join()
+    .map_err(|_| anyhow!("wmi query panicked"))?
+}
+
+//cim datetime in utc, e.g. 20240305150405.000000+000
+fn wmi_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%d%H%M%S.000000+000").to_string()
+}
@@ -0,0 +1,51 @@
+use std::{fmt, path::Path};
+
+use winsafe::{prelude::*, RegistryValue, HKEY};
+
+const SERVICES_KEY: &str = r"SYSTEM\CurrentControlSet\Services";
+//start type of disabled driver/service
+const SERVICE_DISABLED: u32 = 4;
+
+//write filters that discard changes to protected volumes on reboot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteFilter {
+    //unified write filter
+    Uwf,
+    //enhanced write filter
+    Ewf,
+}
+
+impl fmt::Display for WriteFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteFilter::Uwf => write!(f, "Unified Write Filter(UWF)"),
+            WriteFilter::Ewf => write!(f, "Enhanced Write Filter(EWF)"),
+        }
+    }
+}
+
+//returns write filters whose driver is installed and not disabled
+pub fn detect() -> Vec<WriteFilter> {
+    [("uwfvol", WriteFilter::Uwf), ("ewf", WriteFilter::Ewf)]
+        .into_iter()
+        .filter(|(service, _)| is_driver_enabled(service))
+        .map(|(_, filter)| filter)
+        .collect()
+}
+
+//write filters protect the system volume by default.
+//returns true if given path is on the same volume as windows itself.
+pub fn is_on_system_volume(path: &Path) -> anyhow::Result<bool> {
+    let path = std::path::absolute(path)?;
+    let volume = winsafe::GetVolumePathName(&path.to_string_lossy())?;
+    let system_volume = winsafe::GetVolumePathName(&winsafe::GetSystemDirectory()?)?;
+    Ok(volume.eq_ignore_ascii_case(&system_volume))
+}
+
+fn is_driver_enabled(service: &str) -> bool {
+    let key = format!(r"{SERVICES_KEY}\{service}");
+    matches!(
+        HKEY::LOCAL_MACHINE.RegGetValue(Some(&key), Some("Start")),
+        Ok(RegistryValue::Dword(start)) if start != SERVICE_DISABLED
+    )
+}